@@ -0,0 +1,98 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! This application allows the user to convert an Apple .ahap file to a Lofelt .haptic file, using lofelt-sdk/core/datamodel crate functions.
+//!
+//! This should be used internally in Lofelt only.
+
+use clap::{crate_authors, crate_version, App, AppSettings, Arg};
+use datamodel::ios::v1::Ahap;
+use std::{convert::TryFrom, fs::File, io::Write, path::Path};
+
+fn main() -> Result<(), String> {
+    let matches = App::new("ahap2haptic")
+        .version(crate_version!())
+        .author(crate_authors!("\n"))
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Input .ahap file to be converted to .haptic")
+                .required(true)
+                .index(1),
+        )
+        .arg(Arg::with_name("NO_MERGE").long("no-merge").short("n").help(
+            "Only convert the input AHAP file. By default, when converting a \
+             '<name>_continuous.ahap' file created by haptic2ahap, the matching \
+             '<name>_transients.ahap' file is merged into it first, so that the \
+             resulting '<name>.haptic' file contains the emphasis again.",
+        ))
+        .setting(AppSettings::ArgRequiredElseHelp)
+        .get_matches();
+
+    // Calling .unwrap() is safe here because "INPUT" is required (if "INPUT" wasn't
+    // required we could have used an 'if let' to conditionally get the value)
+    let input_file = matches.value_of("INPUT").unwrap();
+    let input_filename = input_file.strip_suffix(".ahap");
+    let merge = !matches.is_present("NO_MERGE");
+
+    //try load ahap file if file has .ahap extension
+    match input_filename {
+        Some(filename) => {
+            let mut ahap = load_ahap_from_file(input_file)?;
+            let mut output_filename = filename;
+
+            if merge {
+                if let Some(name) = filename.strip_suffix("_continuous") {
+                    let transients_file = [name, "_transients.ahap"].concat();
+                    let transients = if Path::new(&transients_file).exists() {
+                        Some(load_ahap_from_file(&transients_file)?)
+                    } else {
+                        None
+                    };
+                    ahap = Ahap::from_continuous_and_transients_ahaps(ahap, transients);
+                    output_filename = name;
+                }
+            }
+
+            let haptic_data = datamodel::latest::DataModel::try_from(ahap)
+                .map_err(|err| format!("Couldn't convert '{}': {}", input_file, err))?;
+            export_string_to_haptic_file(
+                output_filename,
+                &datamodel::DataModel::V1(haptic_data).to_json_pretty()?,
+            )?;
+        }
+        None => return Err(format!("Input '{}' should be an .ahap file", input_file)),
+    }
+
+    Ok(())
+}
+
+/// Loads AHAP data from file
+/// - path: File path to load AHAP data from
+fn load_ahap_from_file(path: &str) -> Result<Ahap, String> {
+    let path = std::fs::canonicalize(path)
+        .map_err(|err| format!("Error reading input from '{:?}': {}", path, err))?;
+    let ahap_json_string = std::fs::read_to_string(&path)
+        .map_err(|err| format!("Error reading input from '{:?}': {}", path, err))?;
+    serde_json::from_str::<Ahap>(&ahap_json_string)
+        .map_err(|err| format!("Error deserializing '{:?}': {}", path, err))
+}
+
+///Exports a string to `filename`.haptic file
+/// - filename: name of haptic file
+/// - data: String slice which contains data to be exported to file
+fn export_string_to_haptic_file(filename: &str, data: &str) -> Result<(), String> {
+    let output_file = format!("{}.haptic", filename);
+    let path = Path::new(&output_file);
+    let display = path.display();
+
+    // Open a file in write-only mode
+    match File::create(path) {
+        Err(e) => Err(format!("Couldn't create {}: {}", display, e)),
+        Ok(mut file) => {
+            // Write the haptic data string to `file`
+            match file.write_all(data.as_bytes()) {
+                Err(e) => Err(format!("Couldn't write to {}: {}", display, e)),
+                Ok(_) => Ok(()),
+            }
+        }
+    }
+}
@@ -6,7 +6,11 @@
 
 use clap::{crate_authors, crate_version, App, AppSettings, Arg};
 use datamodel::ios::v1::Ahap;
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 fn main() -> Result<(), String> {
     let matches = App::new("haptic2ahap")
@@ -15,9 +19,26 @@ fn main() -> Result<(), String> {
         .arg(
             Arg::with_name("INPUT")
                 .help("Input .haptic file to be converted to .ahap")
-                .required(true)
+                .required_unless("INPUT_DIR")
+                .conflicts_with("INPUT_DIR")
                 .index(1),
         )
+        .arg(
+            Arg::with_name("INPUT_DIR")
+                .long("input-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .requires("OUTPUT_DIR")
+                .help("Convert all .haptic files in DIR instead of a single INPUT file"),
+        )
+        .arg(
+            Arg::with_name("OUTPUT_DIR")
+                .long("output-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .requires("INPUT_DIR")
+                .help("Directory the .ahap files converted from --input-dir are written to"),
+        )
         .arg(
             Arg::with_name("NO_SPLIT")
                 .long("no-split")
@@ -31,44 +52,120 @@ fn main() -> Result<(), String> {
         .setting(AppSettings::ArgRequiredElseHelp)
         .get_matches();
 
-    // Calling .unwrap() is safe here because "INPUT" is required (if "INPUT" wasn't
-    // required we could have used an 'if let' to conditionally get the value)
+    let split = !matches.is_present("NO_SPLIT");
+
+    if let (Some(input_dir), Some(output_dir)) = (
+        matches.value_of("INPUT_DIR"),
+        matches.value_of("OUTPUT_DIR"),
+    ) {
+        return convert_directory(Path::new(input_dir), Path::new(output_dir), split);
+    }
+
+    // Calling .unwrap() is safe here because "INPUT" is required unless "INPUT_DIR" is
+    // given, which is handled above
     let input_file = matches.value_of("INPUT").unwrap();
     let input_filename = input_file.strip_suffix(".haptic");
-    let split = !matches.is_present("NO_SPLIT");
 
     //try load haptic file if file has .haptic extension
     match input_filename {
-        Some(filename) => {
-            let haptic_data = load_haptic_data_from_file(input_file)?;
-
-            if split {
-                let ahap_data =
-                    datamodel::ios::convert_to_transient_and_continuous_ahaps(haptic_data);
-
-                export_string_to_ahap_file(
-                    &[filename, "_continuous"].concat(),
-                    &datamodel::ios::v1::Ahap::to_string_pretty(&ahap_data.0)?,
-                )?;
-
-                if ahap_data.1.as_ref().is_some() {
-                    export_string_to_ahap_file(
-                        &[filename, "_transients"].concat(),
-                        &datamodel::ios::v1::Ahap::to_string_pretty(&ahap_data.1.unwrap())?,
-                    )?;
+        Some(filename) => convert_file(input_file, filename, split)?,
+        None => return Err(format!("Input '{}' should be a .haptic file", input_file)),
+    }
+
+    Ok(())
+}
+
+/// Converts a .haptic file to one or two .ahap files
+/// - input_file: path of the .haptic file
+/// - output_filename: path of the .ahap file(s) to create, without extension. When splitting,
+///   "_continuous" and "_transients" are appended.
+/// - split: whether to create two AHAPs with continuous and transient events respectively
+fn convert_file(input_file: &str, output_filename: &str, split: bool) -> Result<(), String> {
+    let haptic_data = load_haptic_data_from_file(input_file)?;
+
+    if split {
+        let ahap_data = datamodel::ios::convert_to_transient_and_continuous_ahaps(haptic_data);
+
+        export_string_to_ahap_file(
+            &[output_filename, "_continuous"].concat(),
+            &datamodel::ios::v1::Ahap::to_string_pretty(&ahap_data.0)?,
+        )?;
+
+        if ahap_data.1.as_ref().is_some() {
+            export_string_to_ahap_file(
+                &[output_filename, "_transients"].concat(),
+                &datamodel::ios::v1::Ahap::to_string_pretty(&ahap_data.1.unwrap())?,
+            )?;
+        }
+    } else {
+        let ahap = Ahap::from(haptic_data);
+        export_string_to_ahap_file(
+            output_filename,
+            &datamodel::ios::v1::Ahap::to_string_pretty(&ahap)?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Converts all .haptic files in `input_dir` and writes the .ahap files to `output_dir`,
+/// keeping the base filenames.
+///
+/// A failure to convert a single file doesn't stop the conversion of the remaining files. A
+/// summary is printed at the end, and an error is returned if any file failed to convert.
+fn convert_directory(input_dir: &Path, output_dir: &Path, split: bool) -> Result<(), String> {
+    let entries = std::fs::read_dir(input_dir)
+        .map_err(|err| format!("Error reading input directory '{:?}': {}", input_dir, err))?;
+    let mut input_files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && matches!(path.extension(), Some(ext) if ext == "haptic"))
+        .collect::<Vec<PathBuf>>();
+    input_files.sort();
+
+    std::fs::create_dir_all(output_dir).map_err(|err| {
+        format!(
+            "Error creating output directory '{:?}': {}",
+            output_dir, err
+        )
+    })?;
+
+    let mut failed_count = 0;
+    for input_file in &input_files {
+        let result = match (input_file.to_str(), input_file.file_stem()) {
+            (Some(input), Some(stem)) => {
+                let output_filename = output_dir.join(stem);
+                match output_filename.to_str() {
+                    Some(output_filename) => convert_file(input, output_filename, split),
+                    None => Err("Output path is not valid UTF-8".to_string()),
                 }
-            } else {
-                let ahap = Ahap::from(haptic_data);
-                export_string_to_ahap_file(
-                    &[filename, ""].concat(),
-                    &datamodel::ios::v1::Ahap::to_string_pretty(&ahap)?,
-                )?;
+            }
+            _ => Err("Input path is not valid UTF-8".to_string()),
+        };
+        match result {
+            Ok(()) => println!("OK     {}", input_file.display()),
+            Err(err) => {
+                failed_count += 1;
+                println!("FAILED {}: {}", input_file.display(), err);
             }
         }
-        None => return Err(format!("Input '{}' should be a .haptic file", input_file)),
     }
 
-    Ok(())
+    println!(
+        "Converted {} of {} files, {} failed",
+        input_files.len() - failed_count,
+        input_files.len(),
+        failed_count
+    );
+
+    if failed_count > 0 {
+        Err(format!(
+            "{} of {} files failed to convert",
+            failed_count,
+            input_files.len()
+        ))
+    } else {
+        Ok(())
+    }
 }
 
 /// Loads latest  haptic data from file
@@ -78,9 +175,7 @@ fn load_haptic_data_from_file(path: &str) -> Result<datamodel::latest::DataModel
         .map_err(|err| format!("Error reading input from '{:?}': {}", path, err))?;
     let haptic_json_string = std::fs::read_to_string(&path)
         .map_err(|err| format!("Error reading input from '{:?}': {}", path, err))?;
-    let data_model = datamodel::from_json(&haptic_json_string)?;
-    let (_, data_model) = datamodel::upgrade_to_latest(&data_model)?;
-    Ok(data_model)
+    datamodel::load_validated(&haptic_json_string).map_err(|err| err.to_string())
 }
 
 ///Exports a string to `filename`.ahap file
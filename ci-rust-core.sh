@@ -15,9 +15,20 @@ cargo clippy --all-targets -- -D warnings || exit_with_failure "Running clippy l
 echo "➡️ Building"
 cargo build || exit_with_failure "Building failed"
 
+echo "➡️ Building without std"
+# A target without std makes sure that nothing pulls in std by accident
+rustup target add thumbv7em-none-eabihf || exit_with_failure "Installing the target without std failed"
+cargo build -p utils -p datamodel --no-default-features --target thumbv7em-none-eabihf || exit_with_failure "Building without std failed"
+
 echo "➡️ Building benchmarks"
 cargo build --benches || exit_with_failure "Building benchmarks failed"
 
 echo "➡️ Running tests"
 cargo test || exit_with_failure "Running tests failed"
 
+echo "➡️ Running tests of the CSV importer"
+cargo test -p datamodel --features csv || exit_with_failure "Running tests of the CSV importer failed"
+
+echo "➡️ Running tests of the MessagePack support"
+cargo test -p datamodel --features msgpack || exit_with_failure "Running tests of the MessagePack support failed"
+
@@ -1,31 +1,129 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
+use crate::PlaybackState;
 use crossbeam_channel::{Receiver, Sender};
 use datamodel::{
     emphasis::{emphasize, EmphasisParameters},
     interpolation::{InterpolationParameters, Interpolator},
     latest,
-    v1::DataModel,
-    waveform::{Waveform, WaveformConversionParameters},
+    v1::{AmplitudeBreakpoint, DataModel},
+    waveform::{Waveform, WaveformConversionParameters, ANDROID_MAX_AMPLITUDE, OFF_THRESHOLD},
 };
-use std::thread::{self, JoinHandle};
-use utils::Error;
+use std::{
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+use utils::{Error, ErrorKind};
+
+/// Maximum loop count accepted by `Player::set_loop_count()`, other than 0 for infinite looping.
+///
+/// The Vibrator API can only repeat a waveform infinitely, so for a finite loop count the
+/// waveform is repeated in memory, see load_waveform().
+pub const MAX_LOOP_COUNT: u32 = 100;
+
+/// Parameters for converting a clip to a Waveform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AndroidWaveformConfig {
+    /// Quantization depth of the amplitude, in bits. Interpolated breakpoints that differ by
+    /// less than one quantization step are dropped.
+    pub q_bits: u32,
+
+    /// Minimum time between interpolated breakpoints, in seconds
+    pub min_time_step: f32,
+
+    /// Maximum number of entries of the Waveform, see `Waveform::decimate()`. Useful for
+    /// devices that fail to play long waveforms. None doesn't limit the number of entries.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for AndroidWaveformConfig {
+    fn default() -> Self {
+        AndroidWaveformConfig {
+            // Android amplitudes go from 0 to 255
+            q_bits: 8,
+
+            // The reason to use 25ms is to make sure we don’t add unnecessary
+            // breakpoints during the interpolation, thus avoiding to trigger the
+            // glitch bug (see Player::getPaddedEffect() in LofeltHaptics.java).
+            // Perceptually if you use less than 25ms, you can’t feel the difference
+            // on the interpolation.
+            min_time_step: 0.025,
+
+            max_entries: None,
+        }
+    }
+}
+
+impl AndroidWaveformConfig {
+    fn validate(&self) -> Result<(), Error> {
+        if self.q_bits == 0 || self.q_bits > 16 {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Invalid quantization depth of {} bits, must be between 1 and 16",
+                    self.q_bits
+                ),
+            ));
+        }
+        if !self.min_time_step.is_finite() || self.min_time_step <= 0.0 {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Invalid minimum time step {}, must be larger than 0",
+                    self.min_time_step
+                ),
+            ));
+        }
+        if self.max_entries == Some(0) {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                "Invalid maximum number of entries 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+type LoadCompositionCallback = dyn FnMut(&[CompositionPrimitive]) -> Result<(), Error> + Send;
+
+/// A part of a clip converted for playback with the `VibrationEffect.Composition` API.
+///
+/// The parts are played one after the other, starting at `start_ms` milliseconds from the
+/// start of the clip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositionPrimitive {
+    /// A `PRIMITIVE_CLICK`, with a `scale` from 0.0 to 1.0
+    Click { start_ms: i64, scale: f32 },
+
+    /// A part of the continuous signal, played with `VibrationEffect.createWaveform`
+    Waveform { start_ms: i64, waveform: Waveform },
+}
 
 // As the callbacks, the tests in this file use closures that capture and modify variables.
 // The callbacks need to be FnMut instead of Fn because the tests modify the captured variables.
 pub struct Callbacks {
+    /// Loads a waveform from its timings and amplitudes. The last argument is the index of the
+    /// entry from which the waveform is repeated infinitely, like the `repeat` argument of
+    /// `VibrationEffect.createWaveform()`, or None if the waveform is played once.
     #[allow(clippy::type_complexity)]
-    pub load_clip: Box<dyn FnMut(&[i64], &[i32], bool) -> Result<(), Error> + Send>,
+    pub load_clip: Box<dyn FnMut(&[i64], &[i32], Option<usize>) -> Result<(), Error> + Send>,
     pub play_clip: Box<dyn FnMut() -> Result<(), Error> + Send>,
     pub stop_clip: Box<dyn FnMut() -> Result<(), Error> + Send>,
     pub unload_clip: Box<dyn FnMut() -> Result<(), Error> + Send>,
     #[allow(clippy::type_complexity)]
     pub seek_clip: Box<dyn FnMut(&[i64], &[i32]) -> Result<(), Error> + Send>,
+    /// Called instead of `load_clip` and `seek_clip`, if set. Only set this on devices that
+    /// support `VibrationEffect.Composition` with `PRIMITIVE_CLICK`.
+    ///
+    /// The amplitude multiplication, a finite loop count and seeking are applied to the
+    /// composition. A frequency shift and infinite looping aren't supported with compositions,
+    /// see `Player::set_frequency_shift()` and `Player::set_loop_count()`.
+    pub load_composition: Option<Box<LoadCompositionCallback>>,
 }
 
 impl Callbacks {
     pub fn new(
-        load: impl FnMut(&[i64], &[i32], bool) -> Result<(), Error> + Send + 'static,
+        load: impl FnMut(&[i64], &[i32], Option<usize>) -> Result<(), Error> + Send + 'static,
         play: impl FnMut() -> Result<(), Error> + Send + 'static,
         stop: impl FnMut() -> Result<(), Error> + Send + 'static,
         unload: impl FnMut() -> Result<(), Error> + Send + 'static,
@@ -37,72 +135,237 @@ impl Callbacks {
             stop_clip: Box::new(stop),
             unload_clip: Box::new(unload),
             seek_clip: Box::new(seek),
+            load_composition: None,
         }
     }
 }
 
-fn convert_clip_to_waveform(clip: &DataModel) -> Waveform {
+/// Converts a clip to a Waveform, rendering the emphasis into the amplitude envelope.
+///
+/// The Vibrator API can't change the frequency of the motor, so `frequency_shift` changes the
+/// length of the rendered emphasis instead: A negative shift makes emphasis longer and heavier,
+/// a positive shift makes them shorter and crisper. With a shift of 0.0, emphasis have their
+/// default length.
+fn convert_clip_to_waveform(
+    clip: &DataModel,
+    frequency_shift: f32,
+    config: &AndroidWaveformConfig,
+) -> Waveform {
     let amplitude_breakpoints = &clip.signals.continuous.envelopes.amplitude;
+    let default_emphasis_parameters = EmphasisParameters::default();
+    let emphasis_length = default_emphasis_parameters
+        .emphasis_length
+        .mul_f32(1.0 - 0.5 * frequency_shift);
 
     // Android amplitudes go from 0 to 255. Use amplitude 1 for the ducking_amplitude
     // here, not amplitude 0. At amplitude 0, the motor is turned off, and turning on
     // the motor afterwards takes long and screws up the timings of the waveform.
-    // 1.1 is used here, not 1.0, to make sure the amplitude doesn't round down to
-    // 0.
+    // Slightly more than OFF_THRESHOLD (amplitude 1) is used here, to make sure the
+    // amplitude doesn't round down to 0.
     let amplitude_breakpoints = emphasize(
         amplitude_breakpoints,
         EmphasisParameters {
-            ducking_amplitude: 1.1 / 255.0,
-            ..Default::default()
+            ducking_amplitude: 1.1 * OFF_THRESHOLD,
+            emphasis_length,
+            // Always use full amplitude for the emphasis, so that it is distinct from the
+            // continuous signal on Android's motors
+            emphasis_amplitude: None,
+            ..default_emphasis_parameters
         },
     );
 
+    convert_breakpoints_to_waveform(&amplitude_breakpoints, config)
+}
+
+/// Converts amplitude breakpoints to a Waveform, ignoring any emphasis.
+fn convert_breakpoints_to_waveform(
+    amplitude_breakpoints: &[AmplitudeBreakpoint],
+    config: &AndroidWaveformConfig,
+) -> Waveform {
     //
     // Interpolate data
     //
 
-    const Q_BITS: u32 = 8;
-    let max_amplitude: i32 = 2_i32.pow(Q_BITS) - 1;
-
-    /// The reason to use 25ms is to make sure we don’t add unnecessary
-    /// breakpoints during the interpolation, thus avoiding to trigger the
-    /// glitch bug (see Player::getPaddedEffect() in LofeltHaptics.java).
-    /// Perceptually if you use less than 25ms, you can’t feel the difference
-    /// on the interpolation.
-    const MIN_TIME_STEP: f32 = 0.025;
-
-    let interpolator = Interpolator::new(InterpolationParameters::new(Q_BITS, MIN_TIME_STEP));
-    let amplitude_breakpoints = interpolator.process(&amplitude_breakpoints);
+    let interpolator = Interpolator::new(InterpolationParameters::new(
+        config.q_bits,
+        config.min_time_step,
+    ));
+    let amplitude_breakpoints = interpolator.process(amplitude_breakpoints);
 
     //
     // Convert to Waveform and return
     //
-    Waveform::from_breakpoints(
+    let mut waveform = Waveform::from_breakpoints(
         &amplitude_breakpoints,
-        WaveformConversionParameters { max_amplitude },
-    )
+        WaveformConversionParameters {
+            max_amplitude: ANDROID_MAX_AMPLITUDE,
+        },
+    );
+    waveform.coalesce();
+    if let Some(max_entries) = config.max_entries {
+        waveform.decimate(max_entries);
+    }
+    waveform
 }
 
-fn apply_amplitude_multiplication(
-    waveform: &Waveform,
+/// Converts a clip to a list of `CompositionPrimitive`s.
+///
+/// Each emphasis becomes a click primitive, scaled by the emphasis amplitude. The continuous
+/// signal is split into waveform parts at the emphasis, so that the clicks are played between
+/// the waveform parts. Unlike in `convert_clip_to_waveform()`, the continuous signal isn't
+/// ducked around the emphasis, since the click interrupts it anyway.
+///
+/// `amplitude_multiplication_factor` is applied to both the waveform parts and the scale of the
+/// clicks.
+fn convert_clip_to_composition(
+    clip: &DataModel,
     amplitude_multiplication_factor: f32,
-) -> Waveform {
-    if amplitude_multiplication_factor < 0.0 {
-        return (*waveform).clone();
-    }
-
-    Waveform {
-        timings: waveform.timings.clone(),
-        amplitudes: waveform
-            .amplitudes
-            .iter()
-            .map(|amplitude| {
-                ((*amplitude as f32 * amplitude_multiplication_factor).min(255.0) as i32)
-                    .min(255)
-                    .max(0)
+    config: &AndroidWaveformConfig,
+) -> Vec<CompositionPrimitive> {
+    let amplitude_breakpoints = &clip.signals.continuous.envelopes.amplitude;
+    let waveform = convert_breakpoints_to_waveform(amplitude_breakpoints, config)
+        .with_amplitude_multiplication(amplitude_multiplication_factor);
+
+    let clicks = amplitude_breakpoints
+        .iter()
+        .filter_map(|breakpoint| {
+            breakpoint.emphasis.map(|emphasis| {
+                let start_ms = (breakpoint.time * 1000.0).round() as i64;
+                let scale = emphasis.amplitude * amplitude_multiplication_factor;
+                (start_ms, scale.clamp(0.0, 1.0))
             })
-            .collect(),
+        })
+        .collect::<Vec<(i64, f32)>>();
+
+    let mut result = Vec::new();
+    let mut waveform_parts =
+        split_waveform(&waveform, clicks.iter().map(|(start_ms, _)| *start_ms));
+    for (start_ms, scale) in clicks {
+        // Add all waveform parts before the click
+        while let Some(part) = waveform_parts.first() {
+            if part.0 >= start_ms {
+                break;
+            }
+            let (start_ms, waveform) = waveform_parts.remove(0);
+            result.push(CompositionPrimitive::Waveform { start_ms, waveform });
+        }
+        result.push(CompositionPrimitive::Click { start_ms, scale });
+    }
+    result.extend(
+        waveform_parts
+            .into_iter()
+            .map(|(start_ms, waveform)| CompositionPrimitive::Waveform { start_ms, waveform }),
+    );
+    result
+}
+
+/// Converts `clip` to a composition that plays `loop_count` times, see
+/// convert_clip_to_composition().
+///
+/// Like in load_sought_waveform(), the first repetition starts at `seek_time`, and all further
+/// repetitions play the whole clip. A negative seek time delays all primitives. If the seek was
+/// beyond the end of the clip, the first repetition is skipped. A composition can't be repeated
+/// infinitely, so a loop count of 0 plays the clip once, see Player::set_loop_count().
+fn convert_clip_to_repeated_composition(
+    clip: &DataModel,
+    seek_time: Option<f32>,
+    amplitude_multiplication_factor: f32,
+    loop_count: u32,
+    config: &AndroidWaveformConfig,
+) -> Vec<CompositionPrimitive> {
+    let whole = convert_clip_to_composition(clip, amplitude_multiplication_factor, config);
+    let whole_duration_ms = clip.total_duration_ms();
+    let (mut composition, mut start_ms) = match seek_time {
+        None => (whole.clone(), whole_duration_ms),
+        Some(seek_time) => {
+            let delay_ms = seek_delay_ms(seek_time);
+            let mut clip_truncated = clip.clone();
+            // A truncation error means that there are no breakpoints after the seek offset value
+            match clip_truncated.truncate_before(seek_time.max(0.0)) {
+                Ok(()) => {
+                    let sought = convert_clip_to_composition(
+                        &clip_truncated,
+                        amplitude_multiplication_factor,
+                        config,
+                    );
+                    (
+                        delay_composition(&sought, delay_ms),
+                        delay_ms + clip_truncated.total_duration_ms(),
+                    )
+                }
+                Err(_) => (Vec::new(), 0),
+            }
+        }
+    };
+    for _ in 1..loop_count.max(1) {
+        composition.extend(delay_composition(&whole, start_ms));
+        start_ms += whole_duration_ms;
+    }
+    composition
+}
+
+/// Returns a copy of `composition` in which all primitives start `delay_ms` milliseconds later
+fn delay_composition(
+    composition: &[CompositionPrimitive],
+    delay_ms: i64,
+) -> Vec<CompositionPrimitive> {
+    composition
+        .iter()
+        .cloned()
+        .map(|mut primitive| {
+            match &mut primitive {
+                CompositionPrimitive::Click { start_ms, .. }
+                | CompositionPrimitive::Waveform { start_ms, .. } => *start_ms += delay_ms,
+            }
+            primitive
+        })
+        .collect()
+}
+
+/// Splits `waveform` at the given times, in milliseconds and in ascending order.
+///
+/// Returns the non-empty parts together with their start time in milliseconds.
+fn split_waveform(
+    waveform: &Waveform,
+    split_times_ms: impl Iterator<Item = i64>,
+) -> Vec<(i64, Waveform)> {
+    let mut split_times_ms = split_times_ms.peekable();
+    let mut parts = Vec::new();
+    let mut part = Waveform {
+        timings: Vec::new(),
+        amplitudes: Vec::new(),
+    };
+    let mut part_start_ms = 0;
+    let mut time_ms = 0;
+    for (timing, amplitude) in waveform.timings.iter().zip(&waveform.amplitudes) {
+        let end_ms = time_ms + timing;
+        while time_ms < end_ms {
+            // Split times at or before the current time have already been handled
+            while matches!(split_times_ms.peek(), Some(split_ms) if *split_ms <= time_ms) {
+                if !part.timings.is_empty() {
+                    parts.push((part_start_ms, part));
+                    part = Waveform {
+                        timings: Vec::new(),
+                        amplitudes: Vec::new(),
+                    };
+                }
+                part_start_ms = time_ms;
+                split_times_ms.next();
+            }
+
+            let segment_end_ms = match split_times_ms.peek() {
+                Some(split_ms) => end_ms.min(*split_ms),
+                None => end_ms,
+            };
+            part.timings.push(segment_end_ms - time_ms);
+            part.amplitudes.push(*amplitude);
+            time_ms = segment_end_ms;
+        }
+    }
+    if !part.timings.is_empty() {
+        parts.push((part_start_ms, part));
     }
+    parts
 }
 
 /// A command sent from the player thread to the haptic thread
@@ -114,10 +377,112 @@ enum PlayerCommand {
     Stop,
     Seek { seek_time: f32 },
     SetAmplitudeMultiplication { multiplication_factor: f32 },
-    Loop { enabled: bool },
+    SetFrequencyShift { shift: f32 },
+    SetWaveformConfig { config: AndroidWaveformConfig },
+    Loop { count: u32 },
     Quit,
 }
 
+/// Calls the load_clip callback with `waveform`, repeated `loop_count` times.
+///
+/// The Vibrator API can only repeat a waveform infinitely, so for a finite loop count the
+/// waveform itself is repeated. A loop count of 0 enables infinite looping. The loop count
+/// is limited to MAX_LOOP_COUNT by Player::set_loop_count().
+fn load_waveform(
+    callbacks: &mut Callbacks,
+    waveform: &Waveform,
+    loop_count: u32,
+) -> Result<(), Error> {
+    if loop_count > 1 {
+        let repetitions = loop_count as usize;
+        (callbacks.load_clip)(
+            &waveform.timings.repeat(repetitions),
+            &waveform.amplitudes.repeat(repetitions),
+            None,
+        )
+    } else {
+        let repeat_index = if loop_count == 0 { Some(0) } else { None };
+        (callbacks.load_clip)(&waveform.timings, &waveform.amplitudes, repeat_index)
+    }
+}
+
+/// Returns the delay before the clip starts after seeking to `seek_time`, in milliseconds.
+///
+/// Only a negative seek time delays the start, see Player::seek().
+fn seek_delay_ms(seek_time: f32) -> i64 {
+    (-seek_time * 1000.0).round().max(0.0) as i64
+}
+
+/// Converts the part of `clip` after `seek_time` to a Waveform, see Player::seek().
+///
+/// A negative seek time delays the start of playback. The Vibrator API has no way to delay
+/// playback, so a silent segment of that length is added before the waveform of the whole clip
+/// instead.
+///
+/// Returns None if there are no breakpoints after `seek_time`.
+fn convert_clip_to_sought_waveform(
+    clip: &DataModel,
+    seek_time: f32,
+    frequency_shift: f32,
+    amplitude_multiplication_factor: f32,
+    config: &AndroidWaveformConfig,
+) -> Option<Waveform> {
+    let delay_ms = seek_delay_ms(seek_time);
+    let mut clip_truncated = clip.clone();
+    // A truncation error means that there are no breakpoints after the seek offset value
+    clip_truncated.truncate_before(seek_time.max(0.0)).ok()?;
+
+    let mut waveform = convert_clip_to_waveform(&clip_truncated, frequency_shift, config)
+        .with_amplitude_multiplication(amplitude_multiplication_factor);
+    if delay_ms > 0 {
+        waveform.timings.insert(0, delay_ms);
+        waveform.amplitudes.insert(0, 0);
+    }
+    Some(waveform)
+}
+
+/// Calls the load_clip callback with a waveform that plays `loop_count` times, starting at a
+/// sought position.
+///
+/// Like on iOS, the first repetition plays `sought_waveform`, and all further repetitions play
+/// `whole_waveform`. With a loop count of 0, `sought_waveform` is repeated infinitely instead,
+/// without the silent lead-in added for a negative `seek_time`. If `sought_waveform` is None
+/// because the seek was beyond the end of the clip, the first repetition is skipped.
+///
+/// The loop count is limited to MAX_LOOP_COUNT by Player::set_loop_count().
+fn load_sought_waveform(
+    callbacks: &mut Callbacks,
+    sought_waveform: Option<&Waveform>,
+    whole_waveform: &Waveform,
+    seek_time: f32,
+    loop_count: u32,
+) -> Result<(), Error> {
+    if loop_count == 0 {
+        match sought_waveform {
+            Some(waveform) => {
+                // Repeat from the entry after the lead-in, see convert_clip_to_sought_waveform()
+                let has_lead_in = seek_delay_ms(seek_time) > 0 && waveform.timings.len() > 1;
+                let repeat_index = if has_lead_in { 1 } else { 0 };
+                (callbacks.load_clip)(&waveform.timings, &waveform.amplitudes, Some(repeat_index))
+            }
+            None => {
+                (callbacks.load_clip)(&whole_waveform.timings, &whole_waveform.amplitudes, Some(0))
+            }
+        }
+    } else {
+        let repetitions = loop_count as usize - 1;
+        let mut timings = Vec::new();
+        let mut amplitudes = Vec::new();
+        if let Some(sought_waveform) = sought_waveform {
+            timings.extend_from_slice(&sought_waveform.timings);
+            amplitudes.extend_from_slice(&sought_waveform.amplitudes);
+        }
+        timings.extend(whole_waveform.timings.repeat(repetitions));
+        amplitudes.extend(whole_waveform.amplitudes.repeat(repetitions));
+        (callbacks.load_clip)(&timings, &amplitudes, None)
+    }
+}
+
 /// The one function running in the haptic thread.
 ///
 /// This is an infinite loop that waits for the next PlayerCommand to be received
@@ -130,8 +495,14 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
     let mut original_clip: Option<latest::DataModel> = None;
     let mut original_waveform: Option<Waveform> = None;
 
+    // The time of the last seek, kept so that changing the loop count keeps the position. Reset
+    // whenever the whole clip is loaded again.
+    let mut last_seek_time: Option<f32> = None;
+
     let mut amplitude_multiplication_factor: f32 = 1.0;
-    let mut is_looping_enabled: bool = false;
+    let mut frequency_shift: f32 = 0.0;
+    let mut loop_count: u32 = 1;
+    let mut waveform_config = AndroidWaveformConfig::default();
 
     loop {
         match receiver.recv() {
@@ -143,22 +514,38 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
 
                 PlayerCommand::Load(data) => {
                     amplitude_multiplication_factor = 1.0;
-                    is_looping_enabled = false;
-                    original_clip = Some(data.clone());
-                    let waveform = convert_clip_to_waveform(&data);
-
-                    if let Err(error) = (callbacks.load_clip)(
-                        &waveform.timings,
-                        &waveform.amplitudes,
-                        is_looping_enabled,
-                    ) {
+                    frequency_shift = 0.0;
+                    loop_count = 1;
+                    last_seek_time = None;
+
+                    let load_result = match callbacks.load_composition.as_mut() {
+                        // Compositions are converted from the clip every time, so there is no
+                        // Waveform to keep
+                        Some(load_composition) => {
+                            original_waveform = None;
+                            load_composition(&convert_clip_to_composition(
+                                &data,
+                                amplitude_multiplication_factor,
+                                &waveform_config,
+                            ))
+                        }
+                        None => {
+                            let waveform =
+                                convert_clip_to_waveform(&data, frequency_shift, &waveform_config);
+                            let result = load_waveform(&mut callbacks, &waveform, loop_count);
+                            original_waveform = Some(waveform);
+                            result
+                        }
+                    };
+                    if let Err(error) = load_result {
                         log::error!("Failed to load clip: {}", error);
                     }
 
-                    original_waveform = Some(waveform);
+                    original_clip = Some(data);
                 }
 
                 PlayerCommand::Unload => {
+                    last_seek_time = None;
                     original_clip = None;
                     original_waveform = None;
 
@@ -180,32 +567,56 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
                 }
 
                 PlayerCommand::Seek { seek_time } => {
-                    // Negative seek times are currently unsupported on Android, so clamp to zero
-                    let seek_time = seek_time.max(0.0);
-                    if !is_looping_enabled {
-                        if let Some(clip) = &mut original_clip {
-                            let mut clip_truncated = clip.clone();
-
-                            let seek_result = match clip_truncated.truncate_before(seek_time) {
-                                Ok(_) => {
-                                    let waveform = convert_clip_to_waveform(&clip_truncated);
-                                    let waveform = apply_amplitude_multiplication(
-                                        &waveform,
-                                        amplitude_multiplication_factor,
-                                    );
+                    if let (Some(clip), Some(load_composition)) =
+                        (&original_clip, callbacks.load_composition.as_mut())
+                    {
+                        last_seek_time = Some(seek_time);
+                        let composition = convert_clip_to_repeated_composition(
+                            clip,
+                            Some(seek_time),
+                            amplitude_multiplication_factor,
+                            loop_count,
+                            &waveform_config,
+                        );
+                        // Like seek_clip, seeking a composition stops playback
+                        let seek_result =
+                            load_composition(&composition).and_then(|_| (callbacks.stop_clip)());
+                        if let Err(error) = seek_result {
+                            log::error!("Error seeking clip: {}", error);
+                        }
+                    } else if let (Some(clip), Some(original_waveform)) =
+                        (&original_clip, &original_waveform)
+                    {
+                        last_seek_time = Some(seek_time);
+                        let sought_waveform = convert_clip_to_sought_waveform(
+                            clip,
+                            seek_time,
+                            frequency_shift,
+                            amplitude_multiplication_factor,
+                            &waveform_config,
+                        );
+                        let seek_result = if loop_count == 1 {
+                            match sought_waveform {
+                                Some(waveform) => {
                                     (callbacks.seek_clip)(&waveform.timings, &waveform.amplitudes)
                                 }
-                                Err(_) => {
-                                    // A truncation error means that there are no breakpoints
-                                    // after the seek offset value. In this case, we don't want
-                                    // to raise an error but to play nothing.
-                                    (callbacks.seek_clip)(&[], &[])
-                                }
-                            };
-
-                            if let Err(error) = seek_result {
-                                log::error!("Error seeking clip: {}", error);
+                                // There are no breakpoints after the seek offset value. In this
+                                // case, we don't want to raise an error but to play nothing.
+                                None => (callbacks.seek_clip)(&[], &[]),
                             }
+                        } else {
+                            load_sought_waveform(
+                                &mut callbacks,
+                                sought_waveform.as_ref(),
+                                &original_waveform
+                                    .with_amplitude_multiplication(amplitude_multiplication_factor),
+                                seek_time,
+                                loop_count,
+                            )
+                        };
+
+                        if let Err(error) = seek_result {
+                            log::error!("Error seeking clip: {}", error);
                         }
                     }
                 }
@@ -213,18 +624,31 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
                 PlayerCommand::SetAmplitudeMultiplication {
                     multiplication_factor,
                 } => {
-                    if let Some(original_waveform) = &original_waveform {
+                    if let (Some(clip), Some(load_composition)) =
+                        (&original_clip, callbacks.load_composition.as_mut())
+                    {
                         amplitude_multiplication_factor = multiplication_factor;
-                        let waveform = apply_amplitude_multiplication(
-                            original_waveform,
+                        last_seek_time = None;
+                        let composition = convert_clip_to_repeated_composition(
+                            clip,
+                            None,
                             amplitude_multiplication_factor,
+                            loop_count,
+                            &waveform_config,
                         );
+                        if let Err(error) = load_composition(&composition) {
+                            log::error!(
+                                "Failed to load clip for changing amplitude multiplication: {}",
+                                error
+                            );
+                        }
+                    } else if let Some(original_waveform) = &original_waveform {
+                        amplitude_multiplication_factor = multiplication_factor;
+                        last_seek_time = None;
+                        let waveform = original_waveform
+                            .with_amplitude_multiplication(amplitude_multiplication_factor);
 
-                        if let Err(error) = (callbacks.load_clip)(
-                            &waveform.timings,
-                            &waveform.amplitudes,
-                            is_looping_enabled,
-                        ) {
+                        if let Err(error) = load_waveform(&mut callbacks, &waveform, loop_count) {
                             log::error!(
                                 "Failed to load clip for changing amplitude multiplication: {}",
                                 error
@@ -233,14 +657,71 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
                     }
                 }
 
-                PlayerCommand::Loop { enabled } => {
-                    is_looping_enabled = enabled;
-                    if let Some(original_waveform) = &original_waveform {
-                        if let Err(error) = (callbacks.load_clip)(
-                            &original_waveform.timings,
-                            &original_waveform.amplitudes,
-                            is_looping_enabled,
-                        ) {
+                PlayerCommand::SetFrequencyShift { shift } => {
+                    // Player::set_frequency_shift() doesn't send this command for compositions
+                    if let (Some(clip), None) = (&original_clip, &callbacks.load_composition) {
+                        frequency_shift = shift;
+                        last_seek_time = None;
+                        let waveform =
+                            convert_clip_to_waveform(clip, frequency_shift, &waveform_config);
+
+                        let waveform_multiplied =
+                            waveform.with_amplitude_multiplication(amplitude_multiplication_factor);
+                        if let Err(error) =
+                            load_waveform(&mut callbacks, &waveform_multiplied, loop_count)
+                        {
+                            log::error!(
+                                "Failed to load clip for changing frequency shift: {}",
+                                error
+                            );
+                        }
+
+                        original_waveform = Some(waveform);
+                    }
+                }
+
+                PlayerCommand::SetWaveformConfig { config } => {
+                    // Takes effect with the next load
+                    waveform_config = config;
+                }
+
+                PlayerCommand::Loop { count } => {
+                    loop_count = count;
+                    if let (Some(clip), Some(load_composition)) =
+                        (&original_clip, callbacks.load_composition.as_mut())
+                    {
+                        let composition = convert_clip_to_repeated_composition(
+                            clip,
+                            last_seek_time,
+                            amplitude_multiplication_factor,
+                            loop_count,
+                            &waveform_config,
+                        );
+                        if let Err(error) = load_composition(&composition) {
+                            log::error!("Failed to load clip for looping: {}", error);
+                        }
+                    } else if let (Some(clip), Some(original_waveform)) =
+                        (&original_clip, &original_waveform)
+                    {
+                        let load_result = match last_seek_time {
+                            Some(seek_time) => load_sought_waveform(
+                                &mut callbacks,
+                                convert_clip_to_sought_waveform(
+                                    clip,
+                                    seek_time,
+                                    frequency_shift,
+                                    amplitude_multiplication_factor,
+                                    &waveform_config,
+                                )
+                                .as_ref(),
+                                &original_waveform
+                                    .with_amplitude_multiplication(amplitude_multiplication_factor),
+                                seek_time,
+                                loop_count,
+                            ),
+                            None => load_waveform(&mut callbacks, original_waveform, loop_count),
+                        };
+                        if let Err(error) = load_result {
                             log::error!("Failed to load clip for looping: {}", error);
                         }
                     }
@@ -270,6 +751,62 @@ pub struct Player {
     join_handle: Option<JoinHandle<()>>,
 
     clip_loaded: bool,
+
+    /// True if clips are loaded with the load_composition callback, see Callbacks
+    uses_composition: bool,
+
+    /// Duration of the loaded clip in seconds, see DataModel::duration()
+    clip_duration: f32,
+
+    /// The Vibrator API has no callback for when playback finishes, so the playback state is
+    /// tracked here instead, based on the duration of the clip.
+    /// See playback_state().
+    playback_tracker: PlaybackTracker,
+}
+
+/// Tracks the playback state of a Player from the commands sent to the haptic thread
+struct PlaybackTracker {
+    /// Duration of the Waveform of the loaded clip in seconds. The Waveform ends with the
+    /// amplitude envelope, so this doesn't include a longer frequency envelope.
+    clip_duration: f32,
+
+    /// The position from which play() starts playing, in seconds. Negative if playback is
+    /// delayed by seeking to a negative time.
+    start_offset: f32,
+
+    /// How often the clip is played, 0 means infinitely
+    loop_count: u32,
+
+    /// The time play() was called, None if stopped
+    play_start: Option<Instant>,
+}
+
+impl Default for PlaybackTracker {
+    fn default() -> Self {
+        PlaybackTracker {
+            clip_duration: 0.0,
+            start_offset: 0.0,
+            loop_count: 1,
+            play_start: None,
+        }
+    }
+}
+
+impl PlaybackTracker {
+    fn playback_state(&self) -> PlaybackState {
+        match self.play_start {
+            None => PlaybackState::Stopped,
+            Some(play_start) => {
+                let remaining_duration = self.clip_duration - self.start_offset
+                    + self.loop_count.saturating_sub(1) as f32 * self.clip_duration;
+                if self.loop_count == 0 || play_start.elapsed().as_secs_f32() < remaining_duration {
+                    PlaybackState::Playing
+                } else {
+                    PlaybackState::Finished
+                }
+            }
+        }
+    }
 }
 
 impl Drop for Player {
@@ -290,68 +827,120 @@ impl Drop for Player {
 
 impl Player {
     pub fn new(callbacks: Callbacks) -> Result<Player, Error> {
+        let uses_composition = callbacks.load_composition.is_some();
         let (sender, receiver) = crossbeam_channel::unbounded();
         let join_handle = thread::Builder::new()
             .name("haptics".to_string())
             .spawn(move || command_loop(callbacks, receiver))
-            .map_err(|e| Error::new(&format!("Unable to start haptic thread: {}", e)))?;
+            .map_err(|e| {
+                Error::with_kind(
+                    ErrorKind::PlaybackFailed,
+                    &format!("Unable to start haptic thread: {}", e),
+                )
+            })?;
 
         Ok(Player {
             sender,
             join_handle: Some(join_handle),
             clip_loaded: false,
+            uses_composition,
+            clip_duration: 0.0,
+            playback_tracker: PlaybackTracker::default(),
         })
     }
 
+    /// Sets the parameters used for converting clips to Waveforms.
+    ///
+    /// The parameters are used for clips loaded after calling this, an already loaded clip
+    /// is not converted again.
+    pub fn set_waveform_config(&mut self, config: AndroidWaveformConfig) -> Result<(), Error> {
+        config.validate()?;
+        self.send_command(
+            PlayerCommand::SetWaveformConfig { config },
+            "SetWaveformConfig",
+        )
+    }
+
     fn send_command(&self, command: PlayerCommand, command_name: &str) -> Result<(), Error> {
         self.sender.send(command).map_err(|e| {
-            Error::new(&format!(
-                "Unable to send \"{}\" command to haptic thread: {}",
-                command_name, e
-            ))
+            Error::with_kind(
+                ErrorKind::PlaybackFailed,
+                &format!(
+                    "Unable to send \"{}\" command to haptic thread: {}",
+                    command_name, e
+                ),
+            )
         })
     }
 }
 
 impl crate::PreAuthoredClipPlayback for Player {
     fn load(&mut self, data_model: latest::DataModel) -> Result<(), Error> {
+        let clip_duration = data_model.duration();
+        let waveform_duration = utils::ms_to_seconds(data_model.total_duration_ms());
         self.send_command(PlayerCommand::Load(data_model), "Load")?;
         self.clip_loaded = true;
+        self.clip_duration = clip_duration;
+        self.playback_tracker = PlaybackTracker {
+            clip_duration: waveform_duration,
+            ..Default::default()
+        };
         Ok(())
     }
 
     fn play(&mut self) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to play, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to play, no clip loaded.",
+            ));
         }
-        self.send_command(PlayerCommand::Play, "Play")
+        self.send_command(PlayerCommand::Play, "Play")?;
+        self.playback_tracker.play_start = Some(Instant::now());
+        Ok(())
     }
 
     fn stop(&mut self) -> Result<(), Error> {
         if self.clip_loaded {
-            self.send_command(PlayerCommand::Stop, "Stop")
-        } else {
-            Ok(())
+            self.send_command(PlayerCommand::Stop, "Stop")?;
+            self.playback_tracker.play_start = None;
         }
+        Ok(())
     }
 
     fn unload(&mut self) -> Result<(), Error> {
         self.send_command(PlayerCommand::Unload, "Unload")?;
         self.clip_loaded = false;
+        self.clip_duration = 0.0;
+        self.playback_tracker = PlaybackTracker::default();
         Ok(())
     }
 
     fn seek(&mut self, seek_time: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to seek, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to seek, no clip loaded.",
+            ));
         }
 
-        self.send_command(PlayerCommand::Seek { seek_time }, "Seek")
+        self.send_command(PlayerCommand::Seek { seek_time }, "Seek")?;
+
+        // Seeking force-stops playback. A negative offset makes playback take longer, by the
+        // delay before the clip starts.
+        self.playback_tracker.start_offset = seek_time;
+        self.playback_tracker.play_start = None;
+        Ok(())
+    }
+
+    fn duration(&self) -> f32 {
+        self.clip_duration
     }
 
     fn set_amplitude_multiplication(&mut self, multiplication_factor: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new(
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
                 "Unable to set amplitude multiplication, no clip loaded.",
             ));
         }
@@ -361,19 +950,103 @@ impl crate::PreAuthoredClipPlayback for Player {
                 multiplication_factor,
             },
             "SetAmplitudeMultiplication",
-        )
+        )?;
+
+        // The whole clip is loaded again, discarding a previous seek
+        self.playback_tracker.start_offset = 0.0;
+        Ok(())
     }
 
-    fn set_frequency_shift(&mut self, _shift: f32) -> Result<(), Error> {
-        Err(Error::new("Frequency shift is not supported on Android."))
+    /// Not supported on Android yet, the clip always plays at its original speed.
+    fn set_playback_rate(&mut self, rate: f32) -> Result<(), Error> {
+        crate::check_playback_rate(rate)?;
+        Err(Error::new(
+            "Unable to set playback rate, not supported on Android",
+        ))
     }
 
-    fn set_looping(&mut self, enabled: bool) -> Result<(), Error> {
+    /// Changes the length of the emphasis, see convert_clip_to_waveform(). The frequency of the
+    /// amplitude envelope can't be changed on Android.
+    ///
+    /// Not supported with compositions, as click primitives have a fixed length.
+    fn set_frequency_shift(&mut self, shift: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to loop, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to set frequency shift, no clip loaded.",
+            ));
+        }
+        if self.uses_composition {
+            return Err(Error::new(
+                "Unable to set frequency shift, not supported with compositions",
+            ));
+        }
+        // Checked here as an invalid shift would result in an invalid emphasis length
+        if !(-1.0..=1.0).contains(&shift) {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to apply frequency shift {}, needs to be between -1 and 1",
+                    shift
+                ),
+            ));
         }
 
-        self.send_command(PlayerCommand::Loop { enabled }, "Loop")
+        self.send_command(
+            PlayerCommand::SetFrequencyShift { shift },
+            "SetFrequencyShift",
+        )?;
+
+        // The whole clip is loaded again, discarding a previous seek
+        self.playback_tracker.start_offset = 0.0;
+        Ok(())
+    }
+
+    /// Fails with `ErrorKind::InvalidArgument` for a count above MAX_LOOP_COUNT. Infinite
+    /// looping isn't supported with compositions, as they can't be repeated.
+    fn set_loop_count(&mut self, count: u32) -> Result<(), Error> {
+        if !self.clip_loaded {
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to loop, no clip loaded.",
+            ));
+        }
+        if count == 0 && self.uses_composition {
+            return Err(Error::new(
+                "Unable to loop infinitely, not supported with compositions",
+            ));
+        }
+        if count > MAX_LOOP_COUNT {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to set loop count {}, needs to be {} or less",
+                    count, MAX_LOOP_COUNT
+                ),
+            ));
+        }
+
+        self.send_command(PlayerCommand::Loop { count }, "Loop")?;
+
+        // The clip is loaded again from the position of a previous seek
+        self.playback_tracker.loop_count = count;
+        Ok(())
+    }
+
+    /// Returns the playback state based on the commands sent to the player and on the duration
+    /// of the clip, as the Vibrator API doesn't report when playback finishes.
+    fn playback_state(&self) -> PlaybackState {
+        self.playback_tracker.playback_state()
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        // The Android Vibrator API only supports amplitude changes. Emphasis is still played, as it
+        // is rendered into the amplitude envelope.
+        crate::Capabilities {
+            player_name: "Android player",
+            frequency_modulation: false,
+            emphasis: true,
+        }
     }
 }
 
@@ -407,7 +1080,7 @@ mod tests {
     }
 
     fn create_dummy_callbacks() -> Callbacks {
-        let load = |_timings: &[i64], _amplitudes: &[i32], _enabled: bool| Ok(());
+        let load = |_timings: &[i64], _amplitudes: &[i32], _repeat_index: Option<usize>| Ok(());
         let play = || Ok(());
         let stop = || Ok(());
         let unload = || Ok(());
@@ -419,7 +1092,8 @@ mod tests {
     #[test]
     fn convert_valid_v1() {
         let clip = load_test_file("src/test_data/valid_v1.haptic");
-        let actual_waveform = convert_clip_to_waveform(&clip);
+        let actual_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
         let expected_waveform = test_utils::create_waveform(&[
             (25, 51),
             (25, 57),
@@ -436,18 +1110,234 @@ mod tests {
         assert_eq!(actual_waveform, expected_waveform);
     }
 
+    /// Verifies that the waveform config set on the Player is used for the next loaded clip,
+    /// and that invalid configs are rejected
+    #[test]
+    fn waveform_config() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let config = AndroidWaveformConfig {
+            min_time_step: 0.01,
+            ..Default::default()
+        };
+        let default_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        let expected_waveform = convert_clip_to_waveform(&clip, 0.0, &config);
+        assert!(expected_waveform.timings.len() > default_waveform.timings.len());
+
+        let decimated_waveform = convert_clip_to_waveform(
+            &clip,
+            0.0,
+            &AndroidWaveformConfig {
+                max_entries: Some(5),
+                ..config
+            },
+        );
+        assert_eq!(decimated_waveform.entry_count(), 5);
+        assert_eq!(
+            decimated_waveform.total_duration_ms(),
+            expected_waveform.total_duration_ms()
+        );
+
+        let loaded_timings = Arc::new(Mutex::new(Vec::new()));
+        {
+            let loaded_timings = loaded_timings.clone();
+            let mut callbacks = create_dummy_callbacks();
+            callbacks.load_clip = Box::new(move |timings: &[i64], _: &[i32], _: Option<usize>| {
+                *loaded_timings.lock().unwrap() = timings.to_vec();
+                Ok(())
+            });
+            let mut player = Player::new(callbacks).unwrap();
+            assert!(player
+                .set_waveform_config(AndroidWaveformConfig {
+                    q_bits: 0,
+                    ..config
+                })
+                .is_err());
+            assert!(player
+                .set_waveform_config(AndroidWaveformConfig {
+                    min_time_step: 0.0,
+                    ..config
+                })
+                .is_err());
+            assert!(player
+                .set_waveform_config(AndroidWaveformConfig {
+                    max_entries: Some(0),
+                    ..config
+                })
+                .is_err());
+            player.set_waveform_config(config).unwrap();
+            player.load(clip).unwrap();
+        }
+
+        assert_eq!(*loaded_timings.lock().unwrap(), expected_waveform.timings);
+    }
+
+    /// Verifies that emphasis are converted to clicks between the parts of the continuous
+    /// signal, and that the composition is passed to the load_composition callback
+    #[test]
+    fn composition() {
+        let clip = datamodel::v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.5)
+            .amplitude_breakpoint(0.1, 0.5)
+            .emphasis(0.8, 1.0)
+            .amplitude_breakpoint(0.3, 0.5)
+            .emphasis(0.6, 1.0)
+            .build()
+            .unwrap();
+
+        let expected_composition = vec![
+            CompositionPrimitive::Waveform {
+                start_ms: 0,
+                waveform: test_utils::create_waveform(&[(100, 127)]),
+            },
+            CompositionPrimitive::Click {
+                start_ms: 100,
+                scale: 0.8,
+            },
+            CompositionPrimitive::Waveform {
+                start_ms: 100,
+                waveform: test_utils::create_waveform(&[(200, 127)]),
+            },
+            CompositionPrimitive::Click {
+                start_ms: 300,
+                scale: 0.6,
+            },
+        ];
+        assert_eq!(
+            convert_clip_to_composition(&clip, 1.0, &AndroidWaveformConfig::default()),
+            expected_composition
+        );
+
+        let loaded_composition = Arc::new(Mutex::new(Vec::new()));
+        let load_clip_called = Arc::new(AtomicBool::new(false));
+        {
+            let loaded_composition = loaded_composition.clone();
+            let load_clip_called = load_clip_called.clone();
+            let mut callbacks = create_dummy_callbacks();
+            callbacks.load_clip = Box::new(move |_: &[i64], _: &[i32], _: Option<usize>| {
+                load_clip_called.store(true, Ordering::SeqCst);
+                Ok(())
+            });
+            callbacks.load_composition = Some(Box::new(move |composition| {
+                *loaded_composition.lock().unwrap() = composition.to_vec();
+                Ok(())
+            }));
+            let mut player = Player::new(callbacks).unwrap();
+            player.load(clip).unwrap();
+        }
+
+        assert_eq!(*loaded_composition.lock().unwrap(), expected_composition);
+        assert!(!load_clip_called.load(Ordering::SeqCst));
+    }
+
+    /// Verifies that the amplitude multiplication, loop count and seeking are applied to
+    /// compositions, and that a frequency shift and infinite looping are rejected
+    #[test]
+    fn composition_runtime_parameters() {
+        let clip = datamodel::v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.5)
+            .amplitude_breakpoint(0.1, 0.5)
+            .emphasis(0.8, 1.0)
+            .amplitude_breakpoint(0.2, 0.5)
+            .build()
+            .unwrap();
+        let whole = convert_clip_to_composition(&clip, 1.0, &AndroidWaveformConfig::default());
+
+        let loaded_composition = Arc::new(Mutex::new(Vec::new()));
+        let load_clip_called = Arc::new(AtomicBool::new(false));
+        let mut callbacks = create_dummy_callbacks();
+        {
+            let loaded_composition = loaded_composition.clone();
+            let load_clip_called = load_clip_called.clone();
+            callbacks.load_clip = Box::new(move |_: &[i64], _: &[i32], _: Option<usize>| {
+                load_clip_called.store(true, Ordering::SeqCst);
+                Ok(())
+            });
+            callbacks.load_composition = Some(Box::new(move |composition| {
+                *loaded_composition.lock().unwrap() = composition.to_vec();
+                Ok(())
+            }));
+        }
+        let mut player = Player::new(callbacks).unwrap();
+        player.load(clip).unwrap();
+
+        // Test: The amplitude multiplication scales both the waveform parts and the clicks
+        player.set_amplitude_multiplication(0.5).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded_composition.lock().unwrap(),
+            vec![
+                CompositionPrimitive::Waveform {
+                    start_ms: 0,
+                    waveform: test_utils::create_waveform(&[(100, 63)]),
+                },
+                CompositionPrimitive::Click {
+                    start_ms: 100,
+                    scale: 0.4,
+                },
+                CompositionPrimitive::Waveform {
+                    start_ms: 100,
+                    waveform: test_utils::create_waveform(&[(100, 63)]),
+                },
+            ]
+        );
+        player.set_amplitude_multiplication(1.0).unwrap();
+
+        // Test: A finite loop count repeats the primitives after the end of the clip
+        player.set_loop_count(2).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded_composition.lock().unwrap(),
+            [whole.clone(), delay_composition(&whole, 200)].concat()
+        );
+
+        // Test: A negative seek delays the first repetition
+        player.seek(-0.1).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded_composition.lock().unwrap(),
+            [
+                delay_composition(&whole, 100),
+                delay_composition(&whole, 300)
+            ]
+            .concat()
+        );
+
+        // Test: Infinite looping and frequency shifts are rejected
+        assert!(player.set_looping(true).is_err());
+        assert!(player.set_frequency_shift(0.5).is_err());
+        drop(player);
+        assert!(!load_clip_called.load(Ordering::SeqCst));
+    }
+
+    /// Verifies that splitting a Waveform keeps the timings and amplitudes of all parts
+    #[test]
+    fn split() {
+        let waveform = test_utils::create_waveform(&[(100, 10), (50, 20), (200, 30)]);
+        let parts = split_waveform(&waveform, vec![0, 50, 150, 350].into_iter());
+        assert_eq!(
+            parts,
+            vec![
+                (0, test_utils::create_waveform(&[(50, 10)])),
+                (50, test_utils::create_waveform(&[(50, 10), (50, 20)])),
+                (150, test_utils::create_waveform(&[(200, 30)])),
+            ]
+        );
+    }
+
     /// Verifies that the correct timings and amplitudes are passed to the load callback
     #[test]
     fn load() {
         let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
 
-        let expected_waveform = convert_clip_to_waveform(&clip);
+        let expected_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
         let loaded_timings = Arc::new(Mutex::new(Vec::new()));
         let loaded_amplitudes = Arc::new(Mutex::new(Vec::new()));
         {
             let loaded_timings = loaded_timings.clone();
             let loaded_amplitudes = loaded_amplitudes.clone();
-            let load = move |timings: &[i64], amplitudes: &[i32], _: bool| {
+            let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
                 *loaded_timings.lock().unwrap() = timings.to_vec();
                 *loaded_amplitudes.lock().unwrap() = amplitudes.to_vec();
                 Ok(())
@@ -473,7 +1363,7 @@ mod tests {
         let sequence = Arc::new(Mutex::new(1));
         {
             let sequence_clone = sequence.clone();
-            let load = move |_: &[i64], _: &[i32], _: bool| {
+            let load = move |_: &[i64], _: &[i32], _: Option<usize>| {
                 assert_eq!(*sequence_clone.lock().unwrap(), 1);
                 *sequence_clone.lock().unwrap() = 2;
                 Ok(())
@@ -536,7 +1426,7 @@ mod tests {
     #[test]
     fn load_fail() {
         let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
-        let load = |_: &[i64], _: &[i32], _: bool| Err(utils::Error::new("Load failed!"));
+        let load = |_: &[i64], _: &[i32], _: Option<usize>| Err(utils::Error::new("Load failed!"));
         let mut callbacks = create_dummy_callbacks();
         callbacks.load_clip = Box::new(load);
         {
@@ -552,13 +1442,18 @@ mod tests {
 
         let seek_time_backward = 0.05;
         let mut clip_truncated_before = clip.clone();
-        let expected_loaded_waveform = convert_clip_to_waveform(&clip);
+        let expected_loaded_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
 
         clip_truncated_before
             .truncate_before(seek_time_backward)
             .unwrap();
 
-        let expected_sought_waveform = convert_clip_to_waveform(&clip_truncated_before);
+        let expected_sought_waveform = convert_clip_to_waveform(
+            &clip_truncated_before,
+            0.0,
+            &AndroidWaveformConfig::default(),
+        );
 
         let loaded_timings = Arc::new(Mutex::new(Vec::new()));
         let loaded_amplitudes = Arc::new(Mutex::new(Vec::new()));
@@ -568,7 +1463,7 @@ mod tests {
         {
             let loaded_timings = loaded_timings.clone();
             let loaded_amplitudes = loaded_amplitudes.clone();
-            let load = move |timings: &[i64], amplitudes: &[i32], _: bool| {
+            let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
                 *loaded_timings.lock().unwrap() = timings.to_vec();
                 *loaded_amplitudes.lock().unwrap() = amplitudes.to_vec();
                 Ok(())
@@ -620,7 +1515,8 @@ mod tests {
         let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
 
         let seek_time = 10.0;
-        let expected_loaded_waveform = convert_clip_to_waveform(&clip);
+        let expected_loaded_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
 
         // The waveform passed to the seek callback should be empty, as there is
         // nothing to play.
@@ -637,7 +1533,7 @@ mod tests {
         {
             let loaded_timings = loaded_timings.clone();
             let loaded_amplitudes = loaded_amplitudes.clone();
-            let load = move |timings: &[i64], amplitudes: &[i32], _: bool| {
+            let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
                 *loaded_timings.lock().unwrap() = timings.to_vec();
                 *loaded_amplitudes.lock().unwrap() = amplitudes.to_vec();
                 Ok(())
@@ -698,10 +1594,16 @@ mod tests {
             .truncate_before(seek_time_backward)
             .unwrap();
 
-        let expected_sought_waveform_forward =
-            convert_clip_to_waveform(&clip_truncated_before_forward);
-        let expected_sought_waveform_backward =
-            convert_clip_to_waveform(&clip_truncated_before_backward);
+        let expected_sought_waveform_forward = convert_clip_to_waveform(
+            &clip_truncated_before_forward,
+            0.0,
+            &AndroidWaveformConfig::default(),
+        );
+        let expected_sought_waveform_backward = convert_clip_to_waveform(
+            &clip_truncated_before_backward,
+            0.0,
+            &AndroidWaveformConfig::default(),
+        );
 
         let sought_timings = Arc::new(Mutex::new(Vec::new()));
         let sought_amplitudes = Arc::new(Mutex::new(Vec::new()));
@@ -752,19 +1654,23 @@ mod tests {
         let mut player = Player::new(create_dummy_callbacks()).unwrap();
         assert_eq!(
             player.seek(5.0).unwrap_err(),
-            Error::new("Unable to seek, no clip loaded.")
+            Error::with_kind(ErrorKind::NoClipLoaded, "Unable to seek, no clip loaded.")
         );
     }
 
     #[test]
     // Verifies that when seek is called for a negative value, the clip will be
-    // played from the start
+    // played from the start, after a silent segment with the length of the delay
     fn seek_negative() {
         let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
 
-        let seek_time_negative = -1.0;
-        let expected_loaded_waveform = convert_clip_to_waveform(&clip);
-        let expected_sought_waveform = convert_clip_to_waveform(&clip);
+        let seek_time_negative = -0.1;
+        let expected_loaded_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        let mut expected_sought_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        expected_sought_waveform.timings.insert(0, 100);
+        expected_sought_waveform.amplitudes.insert(0, 0);
 
         let loaded_timings = Arc::new(Mutex::new(Vec::new()));
         let loaded_amplitudes = Arc::new(Mutex::new(Vec::new()));
@@ -774,7 +1680,7 @@ mod tests {
         {
             let loaded_timings = loaded_timings.clone();
             let loaded_amplitudes = loaded_amplitudes.clone();
-            let load = move |timings: &[i64], amplitudes: &[i32], _: bool| {
+            let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
                 *loaded_timings.lock().unwrap() = timings.to_vec();
                 *loaded_amplitudes.lock().unwrap() = amplitudes.to_vec();
                 Ok(())
@@ -843,7 +1749,7 @@ mod tests {
         let loaded_amplitudes_clone = loaded_amplitudes.clone();
         let sought_timings_clone = sought_timings.clone();
         let sought_amplitudes_clone = sought_amplitudes.clone();
-        let load = move |timings: &[i64], amplitudes: &[i32], _: bool| {
+        let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
             *loaded_timings_clone.lock().unwrap() = timings.to_vec();
             *loaded_amplitudes_clone.lock().unwrap() = amplitudes.to_vec();
             Ok(())
@@ -892,9 +1798,77 @@ mod tests {
         assert_eq!(&*loaded_timings.lock().unwrap(), &original_timings);
         assert_eq!(&*loaded_amplitudes.lock().unwrap(), &zero_amplitudes);
 
+        // Test: Resetting the runtime parameters restores the original amplitudes
+        player.reset_runtime_params().unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(&*loaded_timings.lock().unwrap(), &original_timings);
+        assert_eq!(&*loaded_amplitudes.lock().unwrap(), &original_amplitudes);
+
         // Test: Setting the multiplication factor doesn't work after unloading the clip
         player.unload().unwrap();
         player.set_amplitude_multiplication(0.7).unwrap_err();
+        player.reset_runtime_params().unwrap_err();
+    }
+
+    /// Verifies that a frequency shift changes the length of the rendered emphasis
+    #[test]
+    fn frequency_shift() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let original_waveform =
+            convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        let shifted_waveform =
+            convert_clip_to_waveform(&clip, -1.0, &AndroidWaveformConfig::default());
+        assert_ne!(original_waveform, shifted_waveform);
+
+        let loaded_timings = Arc::new(Mutex::new(Vec::new()));
+        let loaded_amplitudes = Arc::new(Mutex::new(Vec::new()));
+        let loaded_timings_clone = loaded_timings.clone();
+        let loaded_amplitudes_clone = loaded_amplitudes.clone();
+        let load = move |timings: &[i64], amplitudes: &[i32], _: Option<usize>| {
+            *loaded_timings_clone.lock().unwrap() = timings.to_vec();
+            *loaded_amplitudes_clone.lock().unwrap() = amplitudes.to_vec();
+            Ok(())
+        };
+
+        let mut callbacks = create_dummy_callbacks();
+        callbacks.load_clip = Box::new(load);
+        let mut player = Player::new(callbacks).unwrap();
+
+        // Test: Setting the frequency shift doesn't work before a clip is loaded
+        player.set_frequency_shift(-1.0).unwrap_err();
+
+        // Test: A negative frequency shift makes the emphasis longer
+        player.load(clip).unwrap();
+        player.set_frequency_shift(-1.0).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(&*loaded_timings.lock().unwrap(), &shifted_waveform.timings);
+        assert_eq!(
+            &*loaded_amplitudes.lock().unwrap(),
+            &shifted_waveform.amplitudes
+        );
+
+        // Test: The amplitude multiplication is applied on top of the frequency shift
+        player.set_amplitude_multiplication(0.5).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            &*loaded_amplitudes.lock().unwrap(),
+            &shifted_waveform
+                .with_amplitude_multiplication(0.5)
+                .amplitudes
+        );
+
+        // Test: Shifts outside of the valid range are rejected
+        player.set_frequency_shift(1.5).unwrap_err();
+        player.set_frequency_shift(f32::NAN).unwrap_err();
+
+        // Test: Resetting the runtime parameters restores the original waveform
+        player.reset_runtime_params().unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(&*loaded_timings.lock().unwrap(), &original_waveform.timings);
+        assert_eq!(
+            &*loaded_amplitudes.lock().unwrap(),
+            &original_waveform.amplitudes
+        );
     }
 
     // Checks if the enabling looping sets the appropriate value when calling
@@ -907,8 +1881,8 @@ mod tests {
         let loop_enable_set = Arc::new(AtomicBool::new(false));
         {
             let loop_enable_set = loop_enable_set.clone();
-            let load = move |_: &[i64], _: &[i32], enabled: bool| {
-                loop_enable_set.store(enabled, Ordering::SeqCst);
+            let load = move |_: &[i64], _: &[i32], repeat_index: Option<usize>| {
+                loop_enable_set.store(repeat_index == Some(0), Ordering::SeqCst);
                 Ok(())
             };
 
@@ -924,4 +1898,243 @@ mod tests {
             loop_enable_set.load(Ordering::SeqCst)
         );
     }
+
+    /// Verifies that a finite loop count loads the waveform repeated, without enabling looping
+    #[test]
+    fn loop_count() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let waveform = convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+
+        let loaded_timings = Arc::new(Mutex::new(Vec::new()));
+        let loaded_amplitudes = Arc::new(Mutex::new(Vec::new()));
+        let loop_enable_set = Arc::new(AtomicBool::new(true));
+        let loaded_timings_clone = loaded_timings.clone();
+        let loaded_amplitudes_clone = loaded_amplitudes.clone();
+        let loop_enable_set_clone = loop_enable_set.clone();
+        let load = move |timings: &[i64], amplitudes: &[i32], repeat_index: Option<usize>| {
+            *loaded_timings_clone.lock().unwrap() = timings.to_vec();
+            *loaded_amplitudes_clone.lock().unwrap() = amplitudes.to_vec();
+            loop_enable_set_clone.store(repeat_index == Some(0), Ordering::SeqCst);
+            Ok(())
+        };
+
+        let mut callbacks = create_dummy_callbacks();
+        callbacks.load_clip = Box::new(load);
+        let mut player = Player::new(callbacks).unwrap();
+
+        // Test: Setting a loop count without a loaded clip fails
+        player.set_loop_count(3).unwrap_err();
+
+        player.load(clip).unwrap();
+        player.set_loop_count(3).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            &*loaded_timings.lock().unwrap(),
+            &waveform.timings.repeat(3)
+        );
+        assert_eq!(
+            &*loaded_amplitudes.lock().unwrap(),
+            &waveform.amplitudes.repeat(3)
+        );
+        assert!(!loop_enable_set.load(Ordering::SeqCst));
+
+        // Test: A loop count of 0 enables infinite looping of the unrepeated waveform
+        player.set_loop_count(0).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(&*loaded_timings.lock().unwrap(), &waveform.timings);
+        assert!(loop_enable_set.load(Ordering::SeqCst));
+
+        // Test: A loop count of 1 plays the clip once
+        player.set_loop_count(1).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(&*loaded_timings.lock().unwrap(), &waveform.timings);
+        assert!(!loop_enable_set.load(Ordering::SeqCst));
+
+        // Test: Loop counts that would repeat the waveform too often are rejected without
+        // loading anything
+        assert_eq!(
+            player.set_loop_count(MAX_LOOP_COUNT + 1).unwrap_err().kind,
+            ErrorKind::InvalidArgument
+        );
+        assert!(player.set_loop_count(u32::MAX).is_err());
+        player.set_loop_count(MAX_LOOP_COUNT).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            loaded_timings.lock().unwrap().len(),
+            waveform.timings.len() * MAX_LOOP_COUNT as usize
+        );
+    }
+
+    /// Creates callbacks that record the waveform and repeat index passed to the load callback
+    #[allow(clippy::type_complexity)]
+    fn create_load_recording_callbacks(
+    ) -> (Callbacks, Arc<Mutex<(Vec<i64>, Vec<i32>, Option<usize>)>>) {
+        let loaded = Arc::new(Mutex::new((Vec::new(), Vec::new(), None)));
+        let loaded_clone = loaded.clone();
+        let load = move |timings: &[i64], amplitudes: &[i32], repeat_index: Option<usize>| {
+            *loaded_clone.lock().unwrap() = (timings.to_vec(), amplitudes.to_vec(), repeat_index);
+            Ok(())
+        };
+        let mut callbacks = create_dummy_callbacks();
+        callbacks.load_clip = Box::new(load);
+        (callbacks, loaded)
+    }
+
+    /// Verifies that setting a loop count after seeking repeats the clip from the sought
+    /// position
+    #[test]
+    fn seek_then_loop() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let seek_time = 5.0;
+        let whole = convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        let mut clip_truncated = clip.clone();
+        clip_truncated.truncate_before(seek_time).unwrap();
+        let sought =
+            convert_clip_to_waveform(&clip_truncated, 0.0, &AndroidWaveformConfig::default());
+
+        let (callbacks, loaded) = create_load_recording_callbacks();
+        let mut player = Player::new(callbacks).unwrap();
+        player.load(clip).unwrap();
+        player.seek(seek_time).unwrap();
+
+        // Test: The first repetition plays from the sought position, the others the whole clip
+        player.set_loop_count(3).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (
+                [sought.timings.clone(), whole.timings.repeat(2)].concat(),
+                [sought.amplitudes.clone(), whole.amplitudes.repeat(2)].concat(),
+                None
+            )
+        );
+
+        // Test: Infinite looping repeats the part after the sought position
+        player.set_looping(true).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (sought.timings, sought.amplitudes, Some(0))
+        );
+    }
+
+    /// Verifies that seeking in a looping clip loads the clip again from the sought position,
+    /// with looping still enabled
+    #[test]
+    fn loop_then_seek() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let seek_time = 5.0;
+        let whole = convert_clip_to_waveform(&clip, 0.0, &AndroidWaveformConfig::default());
+        let mut clip_truncated = clip.clone();
+        clip_truncated.truncate_before(seek_time).unwrap();
+        let sought =
+            convert_clip_to_waveform(&clip_truncated, 0.0, &AndroidWaveformConfig::default());
+
+        let (callbacks, loaded) = create_load_recording_callbacks();
+        let mut player = Player::new(callbacks).unwrap();
+        player.load(clip).unwrap();
+        player.set_looping(true).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (whole.timings.clone(), whole.amplitudes.clone(), Some(0))
+        );
+
+        // Test: Seeking keeps looping enabled
+        player.seek(seek_time).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (sought.timings.clone(), sought.amplitudes.clone(), Some(0))
+        );
+
+        // Test: Seeking beyond the end of a looping clip loops the whole clip
+        player.seek(20.0).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (whole.timings.clone(), whole.amplitudes.clone(), Some(0))
+        );
+
+        // Test: With a finite loop count, the seek still applies to the first repetition
+        player.set_loop_count(2).unwrap();
+        player.seek(seek_time).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (
+                [sought.timings, whole.timings.clone()].concat(),
+                [sought.amplitudes, whole.amplitudes.clone()].concat(),
+                None
+            )
+        );
+
+        // Test: With infinite looping, the silent lead-in of a negative seek is played once,
+        // and only the clip is repeated
+        player.set_looping(true).unwrap();
+        player.seek(-0.1).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(
+            *loaded.lock().unwrap(),
+            (
+                [vec![100], whole.timings].concat(),
+                [vec![0], whole.amplitudes].concat(),
+                Some(1)
+            )
+        );
+    }
+
+    /// Verifies that the playback state is tracked from the commands and the clip duration
+    #[test]
+    fn playback_state() {
+        let clip = load_test_file("../core/datamodel/src/test_data/valid_v1.haptic");
+        let mut player = Player::new(create_dummy_callbacks()).unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+
+        player.load(clip).unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+
+        player.play().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Playing);
+
+        player.stop().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+
+        // Seeking stops playback, and playing afterwards finishes after the remaining duration
+        // of the clip
+        player.play().unwrap();
+        player.seek(9.9).unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+        player.play().unwrap();
+        std::thread::sleep(Duration::from_secs_f32(ASYNC_OPERATION_SLEEP_TIME_SECS));
+        assert_eq!(player.playback_state(), PlaybackState::Finished);
+
+        // Looping clips never finish
+        player.set_looping(true).unwrap();
+        player.play().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Playing);
+
+        player.unload().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+    }
+
+    /// Verifies that the duration includes a frequency envelope that is longer than the
+    /// amplitude envelope, like for the other players
+    #[test]
+    fn duration() {
+        let clip = latest::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.5)
+            .amplitude_breakpoint(1.0, 0.5)
+            .frequency_breakpoint(0.0, 0.5)
+            .frequency_breakpoint(1.5, 0.5)
+            .build()
+            .unwrap();
+
+        let mut player = Player::new(create_dummy_callbacks()).unwrap();
+        assert_eq!(player.duration(), 0.0);
+        player.load(clip).unwrap();
+        assert_eq!(player.duration(), 1.5);
+        player.unload().unwrap();
+        assert_eq!(player.duration(), 0.0);
+    }
 }
@@ -1,6 +1,8 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
 use datamodel::v1::{AmplitudeBreakpoint, Emphasis, FrequencyBreakpoint};
+use serde::{Serialize, Serializer};
+use utils::{Error, ErrorKind};
 
 /// The minimum distance, in seconds, that two breakpoints need to be spaced apart
 /// in order to be considered separate breakpoints. This is used in situations
@@ -49,7 +51,7 @@ enum EnvelopePosition {
 /// like a ramp.
 ///
 /// The emphasis shall be played at the beginning of the event.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize)]
 #[repr(C)]
 pub struct AmplitudeEvent {
     /// Start time of the event, as an offset in seconds from the start of the clip
@@ -63,9 +65,22 @@ pub struct AmplitudeEvent {
 
     // Since Option is not possible for repr(C), all fields of `emphasis` are
     // f32::NAN for amplitude events without emphasis.
+    #[serde(serialize_with = "serialize_emphasis")]
     pub emphasis: Emphasis,
 }
 
+/// Serializes an emphasis with NAN fields as null, as NAN is not valid in JSON
+fn serialize_emphasis<S: Serializer>(
+    emphasis: &Emphasis,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if emphasis.amplitude.is_nan() || emphasis.frequency.is_nan() {
+        serializer.serialize_none()
+    } else {
+        serializer.serialize_some(emphasis)
+    }
+}
+
 /// Returns true if both values are equal or if both a NAN
 fn eq_f32_no_nan(a: f32, b: f32) -> bool {
     a == b || (a.is_nan() && b.is_nan())
@@ -85,8 +100,72 @@ impl PartialEq for AmplitudeEvent {
     }
 }
 
+/// Describes how amplitudes that exceed 1.0 after amplitude multiplication are limited
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AmplitudeLimiter {
+    /// Amplitudes greater than 1.0 are clipped to 1.0. This flattens the dynamics of a boosted
+    /// clip, as all amplitudes above 1.0 end up being the same.
+    HardClip,
+
+    /// Amplitudes greater than `threshold` are compressed, approaching 1.0 without reaching it.
+    ///
+    /// Right above the threshold, the amplitude increases `ratio` times slower than without
+    /// the limiter, and then gradually flattens out towards 1.0. A ratio of 1.0 gives the
+    /// smoothest transition at the threshold.
+    ///
+    /// `threshold` needs to be between 0.0 (inclusive) and 1.0 (exclusive), and `ratio` needs
+    /// to be 1.0 or greater.
+    SoftKnee { threshold: f32, ratio: f32 },
+}
+
+impl Default for AmplitudeLimiter {
+    fn default() -> Self {
+        AmplitudeLimiter::HardClip
+    }
+}
+
+impl AmplitudeLimiter {
+    /// Returns an error if the parameters of the limiter are outside of their valid range
+    pub fn validate(&self) -> Result<(), Error> {
+        match *self {
+            AmplitudeLimiter::HardClip => Ok(()),
+            AmplitudeLimiter::SoftKnee { threshold, ratio } => {
+                if !(0.0..1.0).contains(&threshold) || !ratio.is_finite() || ratio < 1.0 {
+                    return Err(Error::with_kind(
+                        ErrorKind::InvalidArgument,
+                        &format!(
+                            "Invalid soft knee limiter with threshold {} and ratio {}, the \
+                             threshold needs to be between 0 and 1 and the ratio 1 or greater",
+                            threshold, ratio
+                        ),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Limits `amplitude` to the range of 0.0 to 1.0
+    pub fn limit(&self, amplitude: f32) -> f32 {
+        match *self {
+            AmplitudeLimiter::HardClip => amplitude.min(1.0),
+            AmplitudeLimiter::SoftKnee { threshold, ratio } => {
+                if amplitude <= threshold {
+                    return amplitude;
+                }
+                let headroom = 1.0 - threshold;
+                threshold + headroom * ((amplitude - threshold) / (headroom * ratio)).tanh()
+            }
+        }
+    }
+}
+
 impl AmplitudeEvent {
-    fn apply_amplitude_multiplication(&mut self, multiplication_factor: f32) {
+    fn apply_amplitude_multiplication(
+        &mut self,
+        multiplication_factor: f32,
+        limiter: AmplitudeLimiter,
+    ) {
         // What we actually want to send to Core Haptics is sqrt(amplitude) * multiplication_factor.
         // However, it is interfaces/ios/LofeltHaptics/LofeltHaptics/CoreHapticsPlayer.m that
         // communicates directly with Core Haptics, and it only receives self.amplitude (as calculated
@@ -102,11 +181,14 @@ impl AmplitudeEvent {
         //
         // We apply multiplication_factor once to let the sign take effect (if it has a negative sign
         // it will get clamped to zero) and then a second time to account for the above.
+        //
+        // Amplitudes that end up greater than 1.0 are brought back into range by `limiter`.
         self.amplitude = (self.amplitude * multiplication_factor).max(0.0);
-        self.amplitude = (self.amplitude * multiplication_factor).min(1.0);
+        self.amplitude = limiter.limit(self.amplitude * multiplication_factor);
         if !self.emphasis.amplitude.is_nan() {
             self.emphasis.amplitude = (self.emphasis.amplitude * multiplication_factor).max(0.0);
-            self.emphasis.amplitude = (self.emphasis.amplitude * multiplication_factor).min(1.0);
+            self.emphasis.amplitude =
+                limiter.limit(self.emphasis.amplitude * multiplication_factor);
         }
     }
 
@@ -118,7 +200,7 @@ impl AmplitudeEvent {
 }
 
 /// Same as AmplitudeEvent, but for frequency changes
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
 #[repr(C)]
 pub struct FrequencyEvent {
     pub time: f32,
@@ -134,7 +216,8 @@ impl FrequencyEvent {
 
 /// An event provided by the HapticEventProvider, which can either be an amplitude
 /// or a frequency event.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Event {
     Amplitude(AmplitudeEvent),
     Frequency(FrequencyEvent),
@@ -176,9 +259,13 @@ impl Event {
         }
     }
 
-    pub fn apply_amplitude_multiplication(&mut self, multiplication_factor: f32) {
+    pub fn apply_amplitude_multiplication(
+        &mut self,
+        multiplication_factor: f32,
+        limiter: AmplitudeLimiter,
+    ) {
         if let Event::Amplitude(amplitude_event) = self {
-            amplitude_event.apply_amplitude_multiplication(multiplication_factor)
+            amplitude_event.apply_amplitude_multiplication(multiplication_factor, limiter)
         }
     }
 
@@ -227,6 +314,9 @@ struct PeekedEvent {
 /// At the end of the clip, one haptic event is provided to ramp down the amplitude from
 /// the last breakpoint down to zero. The frequency is not ramped down at the end, and stays
 /// at the value of the last breakpoint.
+///
+/// For a clip without frequency envelope, one frequency event with the frequency of the
+/// emphasis is provided before each amplitude event with emphasis.
 pub struct HapticEventProvider {
     clip: datamodel::latest::DataModel,
 
@@ -239,6 +329,14 @@ pub struct HapticEventProvider {
     /// A multiplication factor that is applied to every amplitude event
     amplitude_multiplication: f32,
 
+    /// Limits the amplitudes that exceed 1.0 after applying `amplitude_multiplication`
+    amplitude_limiter: AmplitudeLimiter,
+
+    /// A time-varying multiplication factor that is applied to every amplitude event in
+    /// addition to `amplitude_multiplication`, see set_amplitude_envelope(). Empty for a flat
+    /// factor of 1.0.
+    amplitude_envelope: Vec<AmplitudeBreakpoint>,
+
     /// A frequency shift that is applied to every frequency event and to every
     /// emphasis of an amplitude event
     frequency_shift: f32,
@@ -252,6 +350,8 @@ impl HapticEventProvider {
             amplitude_position: EnvelopePosition::None,
             frequency_position: EnvelopePosition::None,
             amplitude_multiplication: 1.0,
+            amplitude_limiter: AmplitudeLimiter::HardClip,
+            amplitude_envelope: Vec::new(),
             frequency_shift: 0.0,
         };
         result.seek(0.0);
@@ -262,10 +362,82 @@ impl HapticEventProvider {
         self.amplitude_multiplication = multiplication_factor;
     }
 
+    /// Returns the multiplication factor set with set_amplitude_multiplication()
+    pub fn amplitude_multiplication(&self) -> f32 {
+        self.amplitude_multiplication
+    }
+
+    /// Sets how amplitudes that exceed 1.0 after amplitude multiplication are limited
+    pub fn set_amplitude_limiter(&mut self, limiter: AmplitudeLimiter) {
+        self.amplitude_limiter = limiter;
+    }
+
+    /// Returns the limiter set with set_amplitude_limiter()
+    pub fn amplitude_limiter(&self) -> AmplitudeLimiter {
+        self.amplitude_limiter
+    }
+
+    /// Sets a gain curve that multiplies the amplitude of the events, in addition to the
+    /// amplitude multiplication.
+    ///
+    /// The amplitude of each breakpoint in `envelope` is the multiplication factor at that
+    /// time. Between breakpoints the factor is interpolated linearly, and before the first and
+    /// after the last breakpoint the factor of that breakpoint is held. The factor is sampled at
+    /// the time at which an event reaches its amplitude, i.e. at the end of its ramp.
+    ///
+    /// The breakpoints need to be sorted by time. An empty envelope resets the factor to 1.0.
+    pub fn set_amplitude_envelope(&mut self, envelope: Vec<AmplitudeBreakpoint>) {
+        self.amplitude_envelope = envelope;
+    }
+
     pub fn set_frequency_shift(&mut self, shift: f32) {
         self.frequency_shift = shift;
     }
 
+    /// Returns the frequency shift set with set_frequency_shift()
+    pub fn frequency_shift(&self) -> f32 {
+        self.frequency_shift
+    }
+
+    /// Sets the playback position back to the beginning of the clip and removes all
+    /// modulations, so that the clip is provided like after `new()`.
+    ///
+    /// The amplitude multiplication is reset to 1.0, the frequency shift to 0.0, and the
+    /// amplitude limiter and amplitude envelope to their defaults. Use `seek()` instead to
+    /// restart the clip with the modulations kept, e.g. when looping.
+    pub fn reset(&mut self) {
+        self.amplitude_multiplication = 1.0;
+        self.amplitude_limiter = AmplitudeLimiter::HardClip;
+        self.amplitude_envelope.clear();
+        self.frequency_shift = 0.0;
+        self.seek(0.0);
+    }
+
+    /// Returns the multiplication factor of the amplitude envelope at the given time
+    fn amplitude_envelope_factor(&self, time: f32) -> f32 {
+        let envelope = &self.amplitude_envelope;
+        match envelope
+            .iter()
+            .position(|breakpoint| breakpoint.time > time)
+        {
+            None => envelope
+                .last()
+                .map_or(1.0, |breakpoint| breakpoint.amplitude),
+            Some(0) => envelope[0].amplitude,
+            Some(index) => {
+                let before = &envelope[index - 1];
+                let after = &envelope[index];
+                utils::interpolate(
+                    before.time,
+                    after.time,
+                    before.amplitude,
+                    after.amplitude,
+                    time,
+                )
+            }
+        }
+    }
+
     /// Sets the playback position to AfterLast.
     ///
     /// One last event to ramp down the amplitude will be provided. After that,
@@ -389,8 +561,8 @@ impl HapticEventProvider {
         }
 
         let envelope = match &self.clip.signals.continuous.envelopes.frequency {
-            Some(envelope) => envelope,
-            None => return EnvelopePosition::None,
+            Some(envelope) if !envelope.is_empty() => envelope,
+            _ => return self.emphasis_frequency_position_for_seek(seek_time),
         };
 
         let index_of_initial_breakpoint = match envelope
@@ -467,6 +639,51 @@ impl HapticEventProvider {
         }
     }
 
+    /// Returns the frequency envelope position after seeking for a clip without a frequency
+    /// envelope.
+    ///
+    /// Without a frequency envelope, the frequency of the emphasis still needs to be applied,
+    /// so that the emphasis plays at its authored frequency. For that, one frequency event is
+    /// provided at the time of each emphasis, right before the amplitude event with the emphasis.
+    fn emphasis_frequency_position_for_seek(&self, seek_time: f32) -> EnvelopePosition {
+        let events: Vec<Event> = self
+            .clip
+            .signals
+            .continuous
+            .envelopes
+            .amplitude
+            .iter()
+            .filter(|breakpoint| breakpoint.time >= seek_time)
+            .filter_map(|breakpoint| {
+                breakpoint.emphasis.map(|emphasis| {
+                    Event::Frequency(FrequencyEvent {
+                        time: breakpoint.time,
+                        duration: 0.0,
+                        frequency: emphasis.frequency,
+                    })
+                })
+            })
+            .collect();
+        if events.is_empty() {
+            return EnvelopePosition::None;
+        }
+
+        // Once all events are provided, the InClip position of the missing frequency envelope
+        // provides no more events
+        EnvelopePosition::BeforeInitial {
+            events,
+            index_of_initial_breakpoint: 0,
+        }
+    }
+
+    /// Returns true if the clip has a frequency envelope with at least one breakpoint
+    fn has_frequency_envelope(&self) -> bool {
+        matches!(
+            &self.clip.signals.continuous.envelopes.frequency,
+            Some(envelope) if !envelope.is_empty()
+        )
+    }
+
     /// Sets the playback position to the specified time after the beginning of the clip.
     pub fn seek(&mut self, seek_time: f32) {
         let seek_time = seek_time.max(0.0);
@@ -482,6 +699,27 @@ impl HapticEventProvider {
             .map(|event| event.time())
     }
 
+    /// Returns up to `n` of the next events, without advancing the playback position
+    ///
+    /// The events are the same that `n` calls to get_next_event() would return. Fewer than `n`
+    /// events are returned if the clip ends before, so `usize::MAX` peeks all remaining events.
+    pub fn peek_next_n(&self, n: usize) -> Vec<Event> {
+        let mut amplitude_position = self.amplitude_position.clone();
+        let mut frequency_position = self.frequency_position.clone();
+        // `n` can be far larger than the number of events, so don't reserve space for it
+        let mut events = Vec::new();
+        while events.len() < n {
+            let peeked_event = self.peek_event(&amplitude_position, &frequency_position);
+            match peeked_event.event {
+                Some(event) => events.push(event),
+                None => break,
+            }
+            amplitude_position = peeked_event.new_amplitude_position;
+            frequency_position = peeked_event.new_frequency_position;
+        }
+        events
+    }
+
     /// Returns the next event and advances the playback position
     pub fn get_next_event(&mut self) -> Option<Event> {
         let peeked_event = self.peek_event(&self.amplitude_position, &self.frequency_position);
@@ -618,6 +856,10 @@ impl HapticEventProvider {
                             )),
                             EnvelopePosition::InClip { index: index + 1 },
                         ),
+                        // The last frequency breakpoint was reached. No event is needed, as the
+                        // frequency stays at the value of the last breakpoint until playback ends.
+                        // This is also the case for an envelope with a single breakpoint, whose
+                        // value was already applied by the ramp in BeforeInitial.
                         None => (None, EnvelopePosition::None),
                     },
                     None => (None, EnvelopePosition::None),
@@ -659,7 +901,16 @@ impl HapticEventProvider {
 
         let amplitude_event_to_return = PeekedEvent {
             event: peeked_amplitude_event.map(|mut event| {
-                event.apply_amplitude_multiplication(self.amplitude_multiplication);
+                let multiplication_factor = match &event {
+                    Event::Amplitude(amplitude_event) => {
+                        self.amplitude_multiplication
+                            * self.amplitude_envelope_factor(
+                                amplitude_event.time + amplitude_event.duration,
+                            )
+                    }
+                    Event::Frequency(_) => self.amplitude_multiplication,
+                };
+                event.apply_amplitude_multiplication(multiplication_factor, self.amplitude_limiter);
                 event.apply_frequency_shift(self.frequency_shift);
                 event
             }),
@@ -684,7 +935,15 @@ impl HapticEventProvider {
             (Some(_), None) => amplitude_event_to_return,
             (None, Some(_)) => frequency_event_to_return,
             (Some(amplitude_event), Some(frequency_event)) => {
-                if amplitude_event.time() <= frequency_event.time() {
+                // Without a frequency envelope, frequency events only set the frequency of an
+                // emphasis, so they need to be provided before the amplitude event at the same
+                // time, which plays the emphasis
+                let amplitude_first = if self.has_frequency_envelope() {
+                    amplitude_event.time() <= frequency_event.time()
+                } else {
+                    amplitude_event.time() < frequency_event.time()
+                };
+                if amplitude_first {
                     amplitude_event_to_return
                 } else {
                     frequency_event_to_return
@@ -694,12 +953,164 @@ impl HapticEventProvider {
     }
 }
 
+/// Serializes all events that a HapticEventProvider provides for `clip` to a JSON array.
+///
+/// The amplitude multiplication and frequency shift are applied to the events, so the result is
+/// the fully resolved event timeline that a streaming player would play. Each entry has a `type`
+/// of either `amplitude` or `frequency`, and the same fields as `AmplitudeEvent` or
+/// `FrequencyEvent`. An amplitude event without emphasis has an `emphasis` of `null`.
+///
+/// Like in the events passed to the streaming callbacks, the amplitude multiplication is applied
+/// twice, to account for the square root that is applied later on iOS.
+pub fn to_event_list_json(
+    clip: &datamodel::latest::DataModel,
+    amplitude_multiplication: f32,
+    frequency_shift: f32,
+) -> Result<String, Error> {
+    let mut provider = HapticEventProvider::new(clip.clone());
+    provider.set_amplitude_multiplication(amplitude_multiplication);
+    provider.set_frequency_shift(frequency_shift);
+
+    let mut events = Vec::new();
+    while let Some(event) = provider.get_next_event() {
+        events.push(event);
+    }
+
+    serde_json::to_string(&events)
+        .map_err(|e| Error::new(&format!("Unable to serialize event list: {}", e)))
+}
+
+/// Iterates over the events of the clip, starting at the current playback position.
+///
+/// This allows inspecting the events of a clip without a player, for example with
+/// `HapticEventProvider::new(clip).collect::<Vec<Event>>()`.
+impl Iterator for HapticEventProvider {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.get_next_event()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils;
+    use crate::test_utils::{self, amp, emp, freq};
     use utils::assert_near;
 
+    // Tests that collecting the provider as an iterator yields all events of the clip
+    #[test]
+    fn iterator() {
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let events =
+            test_utils::rounded_events(&HapticEventProvider::new(clip).collect::<Vec<Event>>(), 5);
+        assert_eq!(
+            events,
+            vec![
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                amp(0.1, 0.1, 0.3),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.2),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+    }
+
+    // Tests that for a clip without frequency envelope, a frequency event with the emphasis
+    // frequency is provided right before each amplitude event with emphasis
+    #[test]
+    fn emphasis_frequency_without_frequency_envelope() {
+        let clip = test_utils::load_file_from_test_data("amplitude_only_with_emphasis.haptic");
+        let mut provider = HapticEventProvider::new(clip);
+        assert_eq!(
+            test_utils::gather_events_from_provider(&mut provider, None),
+            vec![
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.1, 0.0, 0.9),
+                emp(0.1, 0.1, 0.3, 0.8, 0.9),
+                amp(0.2, 0.1, 0.2),
+                freq(0.3, 0.0, 0.4),
+                emp(0.3, 0.0, 0.0, 0.5, 0.4),
+            ]
+        );
+
+        // After seeking past the first emphasis, only the frequency of the second one is provided
+        provider.seek(0.15);
+        let events = test_utils::gather_events_from_provider(&mut provider, None);
+        let frequency_events: Vec<&Event> = events
+            .iter()
+            .filter(|event| matches!(event, Event::Frequency(_)))
+            .collect();
+        assert_eq!(frequency_events, vec![&freq(0.3, 0.0, 0.4)]);
+        assert_eq!(events.last(), Some(&emp(0.3, 0.0, 0.0, 0.5, 0.4)));
+    }
+
+    // Tests that peek_next_n() returns the same events as calling get_next_event() repeatedly,
+    // without advancing the position
+    #[test]
+    fn peek_next_n() {
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut provider = HapticEventProvider::new(clip);
+        provider.seek(0.05);
+
+        let peeked_events = provider.peek_next_n(5);
+        assert_eq!(peeked_events.len(), 5);
+        assert_eq!(provider.peek_next_n(5), peeked_events);
+        let events = (0..5)
+            .filter_map(|_| provider.get_next_event())
+            .collect::<Vec<Event>>();
+        assert_eq!(peeked_events, events);
+
+        // Test: Fewer events are returned at the end of the clip
+        let remaining_events = provider.peek_next_n(100);
+        assert!(remaining_events.len() < 100);
+        assert_eq!(provider.peek_next_n(usize::MAX), remaining_events);
+        assert_eq!(
+            test_utils::rounded_events(&remaining_events, 5),
+            test_utils::gather_events_from_provider(&mut provider, None)
+        );
+        assert!(provider.peek_next_n(5).is_empty());
+    }
+
+    // Tests that after reset(), the events of the clip are provided from the beginning and
+    // without the modulations applied before
+    #[test]
+    fn reset() {
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let expected_events = test_utils::gather_events_from_provider(
+            &mut HapticEventProvider::new(clip.clone()),
+            None,
+        );
+
+        let mut provider = HapticEventProvider::new(clip);
+        provider.set_amplitude_multiplication(2.0);
+        provider.set_amplitude_limiter(AmplitudeLimiter::SoftKnee {
+            threshold: 0.5,
+            ratio: 2.0,
+        });
+        provider.set_amplitude_envelope(vec![AmplitudeBreakpoint {
+            time: 0.0,
+            amplitude: 0.5,
+            emphasis: None,
+        }]);
+        provider.set_frequency_shift(0.1);
+        provider.seek(0.15);
+        let modulated_events = test_utils::gather_events_from_provider(&mut provider, Some(3));
+        assert_ne!(modulated_events, expected_events[..3]);
+
+        provider.reset();
+        assert_eq!(
+            test_utils::gather_events_from_provider(&mut provider, None),
+            expected_events
+        );
+    }
+
     // Tests that the HapticEventProvider provides only one event after stopping.
     #[test]
     fn peek_and_get_after_stopping() {
@@ -727,7 +1138,7 @@ mod tests {
             },
         };
 
-        amplitude_event.apply_amplitude_multiplication(0.5);
+        amplitude_event.apply_amplitude_multiplication(0.5, AmplitudeLimiter::HardClip);
         assert_near!(amplitude_event.amplitude, 0.25, f32::EPSILON);
         assert_near!(amplitude_event.emphasis.amplitude, 0.125, f32::EPSILON);
     }
@@ -744,7 +1155,7 @@ mod tests {
             },
         };
 
-        amplitude_event.apply_amplitude_multiplication(2.0);
+        amplitude_event.apply_amplitude_multiplication(2.0, AmplitudeLimiter::HardClip);
         assert_near!(amplitude_event.amplitude, 1.0, f32::EPSILON);
         assert_near!(amplitude_event.emphasis.amplitude, 1.0, f32::EPSILON);
     }
@@ -761,11 +1172,83 @@ mod tests {
             },
         };
 
-        amplitude_event.apply_amplitude_multiplication(-1.0);
+        amplitude_event.apply_amplitude_multiplication(-1.0, AmplitudeLimiter::HardClip);
         assert_near!(amplitude_event.amplitude, 0.0, f32::EPSILON);
         assert_near!(amplitude_event.emphasis.amplitude, 0.0, f32::EPSILON);
     }
 
+    // Tests that the soft knee limiter keeps the dynamics of a boosted clip that hard clipping
+    // flattens
+    #[test]
+    fn amplitude_event_amplitude_multiplication_soft_knee() {
+        let soft_knee = AmplitudeLimiter::SoftKnee {
+            threshold: 0.5,
+            ratio: 1.0,
+        };
+        let multiply = |amplitude: f32, limiter: AmplitudeLimiter| {
+            let mut amplitude_event = AmplitudeEvent {
+                time: 0.0,
+                duration: 1.0,
+                amplitude,
+                emphasis: Emphasis {
+                    amplitude,
+                    frequency: 0.5,
+                },
+            };
+            amplitude_event.apply_amplitude_multiplication(2.0, limiter);
+            assert_near!(
+                amplitude_event.amplitude,
+                amplitude_event.emphasis.amplitude,
+                f32::EPSILON
+            );
+            amplitude_event.amplitude
+        };
+
+        // Below the threshold, both limiters leave the amplitude unchanged
+        assert_near!(multiply(0.1, AmplitudeLimiter::HardClip), 0.4, 1e-6);
+        assert_near!(multiply(0.1, soft_knee), 0.4, 1e-6);
+
+        // Above the threshold, the soft knee compresses the amplitude
+        assert_near!(multiply(0.2, AmplitudeLimiter::HardClip), 0.8, 1e-6);
+        assert_near!(multiply(0.2, soft_knee), 0.5 + 0.5 * 0.6_f32.tanh(), 1e-6);
+
+        // Hard clipping maps different amplitudes to 1.0, the soft knee keeps them apart and
+        // below 1.0
+        assert_near!(multiply(0.5, AmplitudeLimiter::HardClip), 1.0, f32::EPSILON);
+        assert_near!(multiply(1.0, AmplitudeLimiter::HardClip), 1.0, f32::EPSILON);
+        let soft_half = multiply(0.5, soft_knee);
+        let soft_full = multiply(1.0, soft_knee);
+        assert!(multiply(0.2, soft_knee) < soft_half);
+        assert!(soft_half < soft_full);
+        assert!(soft_full < 1.0);
+    }
+
+    #[test]
+    fn amplitude_limiter_validation() {
+        AmplitudeLimiter::HardClip.validate().unwrap();
+        AmplitudeLimiter::SoftKnee {
+            threshold: 0.0,
+            ratio: 1.0,
+        }
+        .validate()
+        .unwrap();
+        for (threshold, ratio) in &[
+            (1.0, 2.0),
+            (-0.1, 2.0),
+            (f32::NAN, 2.0),
+            (0.5, 0.5),
+            (0.5, f32::INFINITY),
+        ] {
+            let err = AmplitudeLimiter::SoftKnee {
+                threshold: *threshold,
+                ratio: *ratio,
+            }
+            .validate()
+            .unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidArgument);
+        }
+    }
+
     #[test]
     fn amplitude_event_frequency_shift() {
         let mut amplitude_event = AmplitudeEvent {
@@ -849,4 +1332,125 @@ mod tests {
         frequency_event.apply_frequency_shift(-1.0);
         assert_near!(frequency_event.frequency, 0.0, f32::EPSILON);
     }
+
+    // Tests that the amplitude envelope multiplies the amplitude of each event with the factor
+    // at the end of the event's ramp, holding the factor before the first and after the last
+    // breakpoint of the envelope
+    #[test]
+    fn amplitude_envelope() {
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut provider = HapticEventProvider::new(clip.clone());
+        provider.set_amplitude_envelope(vec![
+            AmplitudeBreakpoint {
+                time: 0.1,
+                amplitude: 1.0,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.2,
+                amplitude: 0.5,
+                emphasis: None,
+            },
+        ]);
+        assert_near!(provider.amplitude_envelope_factor(0.15), 0.75, f32::EPSILON);
+
+        // Like the amplitude multiplication, the factor is applied twice
+        let events = test_utils::gather_events_from_provider(&mut provider, None);
+        assert_eq!(
+            test_utils::rounded_events(&events, 5),
+            vec![
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                amp(0.1, 0.1, 0.075),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.05),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+
+        // An empty envelope resets the factor to 1.0
+        let mut provider = HapticEventProvider::new(clip.clone());
+        provider.set_amplitude_envelope(Vec::new());
+        assert_eq!(
+            test_utils::gather_events_from_provider(&mut provider, None),
+            test_utils::gather_events_from_provider(&mut HapticEventProvider::new(clip), None)
+        );
+    }
+
+    // Tests that a clip with a single frequency breakpoint applies that frequency from the start
+    // of playback, and holds it for the whole clip, also when starting playback after seeking.
+    // With a single breakpoint there is no next breakpoint to ramp to, so the only frequency
+    // event is the ramp up to the breakpoint.
+    #[test]
+    fn single_frequency_breakpoint_held() {
+        test_utils::init_logging();
+        let clip = test_utils::load_file_from_test_data("one_freq_bp.haptic");
+        for &seek_time in &[0.0, 0.15] {
+            let mut provider = HapticEventProvider::new(clip.clone());
+            provider.seek(seek_time);
+            let events = test_utils::gather_events_from_provider(&mut provider, None);
+
+            let frequency_events: Vec<&FrequencyEvent> = events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Frequency(event) => Some(event),
+                    Event::Amplitude(_) => None,
+                })
+                .collect();
+            assert_eq!(frequency_events.len(), 1);
+            let frequency_event = frequency_events[0];
+            assert_near!(frequency_event.frequency, 0.95, f32::EPSILON);
+
+            // The frequency is reached right at the start of playback...
+            assert!(frequency_event.time + frequency_event.duration <= seek_time);
+
+            // ...and is held until the final event that ramps down the amplitude at the end of
+            // the clip
+            let last_event = events.last().unwrap();
+            assert_eq!(*last_event, test_utils::amp(0.3, 0.0, 0.0));
+        }
+    }
+
+    // Rounds all numbers in a JSON value, so that JSON values containing f32 values converted
+    // to f64 can be compared
+    fn rounded_json(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Number(number) => {
+                serde_json::json!((number.as_f64().unwrap() * 1e5).round() / 1e5)
+            }
+            serde_json::Value::Array(array) => array.into_iter().map(rounded_json).collect(),
+            serde_json::Value::Object(object) => serde_json::Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, rounded_json(value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    // Tests that the event list JSON contains all events, with the amplitude multiplication and
+    // frequency shift applied
+    #[test]
+    fn event_list_json() {
+        let clip = test_utils::load_file_from_test_data("emphasis_at_start.haptic");
+        let json = to_event_list_json(&clip, 0.5, 0.1).unwrap();
+        let actual: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let expected = serde_json::json!([
+            { "type": "frequency", "time": 0.0, "duration": 0.0, "frequency": 0.4 },
+            { "type": "amplitude", "time": 0.0, "duration": 0.0, "amplitude": 0.025,
+              "emphasis": null },
+            { "type": "amplitude", "time": 0.0, "duration": 0.1, "amplitude": 0.05,
+              "emphasis": { "amplitude": 0.15, "frequency": 0.4 } },
+            { "type": "amplitude", "time": 0.1, "duration": 0.2, "amplitude": 0.0125,
+              "emphasis": null },
+            { "type": "amplitude", "time": 0.3, "duration": 0.0, "amplitude": 0.0,
+              "emphasis": null },
+        ]);
+        assert_eq!(rounded_json(actual), rounded_json(expected));
+    }
 }
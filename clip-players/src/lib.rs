@@ -2,22 +2,64 @@
 
 pub mod android;
 pub mod null;
+pub mod recorder;
 pub mod streaming;
 
 pub mod haptic_event_provider;
 
+pub use haptic_event_provider::{
+    AmplitudeEvent, AmplitudeLimiter, Event, FrequencyEvent, HapticEventProvider,
+};
+
 #[cfg(test)]
 mod test_utils;
 
-use datamodel::latest;
-use utils::Error;
+use datamodel::latest::{self, Feature};
+use utils::{Error, ErrorKind};
+
+/// Describes which features of a clip a player is able to play back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// Human-readable name of the player, used in diagnostic messages
+    pub player_name: &'static str,
+    /// Whether the player can change the frequency over time
+    pub frequency_modulation: bool,
+    /// Whether the player can play emphasis
+    pub emphasis: bool,
+}
+
+impl Capabilities {
+    /// Returns true if the player is able to play back the given feature
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::FrequencyModulation => self.frequency_modulation,
+            Feature::Emphasis => self.emphasis,
+        }
+    }
+}
+
+/// The playback state of a player, see `PreAuthoredClipPlayback::playback_state()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackState {
+    /// No clip is loaded, or the clip was loaded or stopped and not played since
+    Stopped,
+    /// The clip is playing, or will start playing after a delay caused by a negative seek time
+    Playing,
+    /// The clip played until its end, and is not looping
+    Finished,
+}
 
 /// Plays back a pre-authored haptic clip.
 pub trait PreAuthoredClipPlayback {
     /// Loads the clip and prepares it for playback.
+    ///
+    /// The clip starts without any modulation, i.e. with an amplitude multiplication of 1.0
+    /// and a frequency shift of 0.0, regardless of what was set for a previous clip.
     fn load(&mut self, data_model: latest::DataModel) -> Result<(), Error>;
 
     /// Unloads the clip, freeing any memory or resources taken in load().
+    ///
+    /// This also resets the amplitude multiplication to 1.0 and the frequency shift to 0.0.
     fn unload(&mut self) -> Result<(), Error>;
 
     /// Plays the clip.
@@ -28,14 +70,24 @@ pub trait PreAuthoredClipPlayback {
     /// play() has no effect if the clip is already playing.
     fn play(&mut self) -> Result<(), Error>;
 
+    /// Seeks to `offset`, in seconds, and plays the clip from there.
+    ///
+    /// This is the same as calling `seek()` followed by `play()`, but players may implement it as
+    /// a single operation, so that nothing else happens in between. If the clip is already
+    /// playing, playback jumps to `offset`.
+    fn play_at(&mut self, offset: f32) -> Result<(), Error> {
+        self.seek(offset)?;
+        self.play()
+    }
+
     /// Seeks to the given position, which is specified in seconds since the beginning
     /// of the clip.
     ///
     /// For iOS, the playback state (playing or stopped) will not be changed unless seeking
     /// beyond the end of the clip.
     /// However, in Android, due to limitations in the Vibrator API, the seek callback will
-    /// force-stop the playback. Nevertheless, the core is not aware of this at the moment since
-    /// there's no playback state mechanism.
+    /// force-stop the playback, and `playback_state()` reports `PlaybackState::Stopped`
+    /// afterwards.
     ///
     /// Seeking beyond the end of the clip will stop playback. However, on iOS, if looping is
     /// enabled, seeking past the end of the clip will make playback start from the beginning of
@@ -44,17 +96,50 @@ pub trait PreAuthoredClipPlayback {
     /// Clips are always defined to have a start time of 0, so negative seek times will result in a
     /// delay before playback starts.
     ///
-    /// On iOS, if looping is enabled, playback will start from the sought position until the end
-    /// of the clip, and then repeat from the beginning of the clip. However, in Android, seek will
-    /// have no effect and the playback after calling `set_looping()` will always start from the
-    /// beginning of the clip.
+    /// If looping is enabled, playback will start from the sought position until the end of the
+    /// clip, and then repeat from the beginning of the clip. However, on Android with infinite
+    /// looping, the Vibrator API can only repeat the part of the clip after the sought position.
     fn seek(&mut self, seek_offset: f32) -> Result<(), Error>;
 
+    /// Seeks to the given fraction of the clip duration, see `seek()`.
+    ///
+    /// The fraction is clamped to the range 0.0 to 1.0, so that 0.0 seeks to the beginning and
+    /// 1.0 to the end of the clip.
+    fn seek_by_fraction(&mut self, fraction: f32) -> Result<(), Error> {
+        if !fraction.is_finite() {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to seek to fraction {}, needs to be a finite number",
+                    fraction
+                ),
+            ));
+        }
+        self.seek(fraction.clamp(0.0, 1.0) * self.duration())
+    }
+
+    /// Returns the duration of the loaded clip in seconds, or 0.0 if no clip is loaded.
+    fn duration(&self) -> f32;
+
     /// Sets the playback to repeat from the beginning at the end of the clip.
     ///
-    /// On Android, the changes will only be applied when `play()` is called. If `seek()` is called,
-    /// it will have no effect when looping is enabled. It will always loop from start to end
-    fn set_looping(&mut self, enabled: bool) -> Result<(), Error>;
+    /// On Android, the changes will only be applied when `play()` is called. After `seek()`, only
+    /// the part of the clip after the sought position is looped, see `seek()`.
+    ///
+    /// Enabling looping is the same as a loop count of 0, disabling it the same as a loop count
+    /// of 1, see `set_loop_count()`.
+    fn set_looping(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set_loop_count(if enabled { 0 } else { 1 })
+    }
+
+    /// Sets how often the clip is played when calling `play()`.
+    ///
+    /// A count of 0 repeats the clip infinitely, a count of 1 plays it once without repeating.
+    /// The first repetition starts at the position of a previous `seek()`, and all further
+    /// repetitions play the whole clip. Loading a clip resets the count to 1.
+    ///
+    /// On Android, the count is limited to `android::MAX_LOOP_COUNT`.
+    fn set_loop_count(&mut self, count: u32) -> Result<(), Error>;
 
     /// Stops a clip that is playing
     ///
@@ -84,6 +169,54 @@ pub trait PreAuthoredClipPlayback {
     /// If the resulting frequency of a breakpoint is smaller than 0.0 or larger than 1.0, it is
     /// clipped to the valid range. The frequency is clipped hard, no limiter is used.
     fn set_frequency_shift(&mut self, shift: f32) -> Result<(), Error>;
+
+    /// Sets the speed at which the clip is played back, without changing the frequency.
+    ///
+    /// A rate of 2.0 plays the clip twice as fast, a rate of 0.5 at half the speed. The rate
+    /// needs to be between MIN_PLAYBACK_RATE and MAX_PLAYBACK_RATE. The default is 1.0.
+    ///
+    /// Changing the rate while a clip is playing takes effect immediately. Unlike the amplitude
+    /// multiplication, the rate is kept when loading or unloading a clip.
+    ///
+    /// Android doesn't support changing the playback rate yet and returns an error.
+    fn set_playback_rate(&mut self, rate: f32) -> Result<(), Error>;
+
+    /// Resets the amplitude multiplication to 1.0 and the frequency shift to 0.0, without
+    /// unloading the clip or changing the playback position.
+    ///
+    /// A clip needs to be loaded for this method to take effect.
+    fn reset_runtime_params(&mut self) -> Result<(), Error> {
+        self.set_amplitude_multiplication(1.0)?;
+        self.set_frequency_shift(0.0)
+    }
+
+    /// Returns whether the clip is stopped, playing or has finished playing.
+    fn playback_state(&self) -> PlaybackState;
+
+    /// Returns the features of a clip that this player is able to play back.
+    fn capabilities(&self) -> Capabilities;
+}
+
+/// The lowest rate accepted by `set_playback_rate()`. Slower rates would stretch the time
+/// between events beyond what `Duration` can represent.
+pub const MIN_PLAYBACK_RATE: f32 = 0.01;
+
+/// The highest rate accepted by `set_playback_rate()`
+pub const MAX_PLAYBACK_RATE: f32 = 100.0;
+
+/// Returns an error if `rate` is not a valid argument for `set_playback_rate()`
+pub(crate) fn check_playback_rate(rate: f32) -> Result<(), Error> {
+    if (MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+        Ok(())
+    } else {
+        Err(Error::with_kind(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "Unable to set playback rate {}, needs to be between {} and {}",
+                rate, MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE
+            ),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -1,54 +1,93 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
+use crate::{Event, HapticEventProvider, PlaybackState};
 use datamodel::latest;
-use utils::Error;
+use std::sync::{Arc, Mutex};
+use utils::{Error, ErrorKind};
 
 pub struct Player {
     haptic_clip: Option<latest::DataModel>,
+
+    /// The position of the last seek, in seconds
+    seek_time: f32,
+
+    /// The null player doesn't play anything, so a playing clip never finishes
+    playback_state: PlaybackState,
 }
 
 impl Player {
     pub fn new() -> Result<Player, Error> {
-        Ok(Player { haptic_clip: None })
+        Ok(Player {
+            haptic_clip: None,
+            seek_time: 0.0,
+            playback_state: PlaybackState::Stopped,
+        })
     }
 }
 
 impl crate::PreAuthoredClipPlayback for Player {
     fn load(&mut self, data_model: latest::DataModel) -> Result<(), Error> {
         self.haptic_clip = Some(data_model);
+        self.playback_state = PlaybackState::Stopped;
         Ok(())
     }
 
     fn play(&mut self) -> Result<(), Error> {
         match &self.haptic_clip {
-            Some(_) => Ok(()),
-            None => Err(Error::new("Player play: no clip loaded")),
+            Some(_) => {
+                self.playback_state = PlaybackState::Playing;
+                Ok(())
+            }
+            None => Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player play: no clip loaded",
+            )),
         }
     }
 
     fn stop(&mut self) -> Result<(), Error> {
         match &self.haptic_clip {
-            Some(_) => Ok(()),
-            None => Err(Error::new("Player stop: no clip loaded")),
+            Some(_) => {
+                self.playback_state = PlaybackState::Stopped;
+                Ok(())
+            }
+            None => Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player stop: no clip loaded",
+            )),
         }
     }
 
     fn unload(&mut self) -> Result<(), Error> {
         self.haptic_clip = None;
+        self.playback_state = PlaybackState::Stopped;
         Ok(())
     }
 
-    fn seek(&mut self, _seek_time: f32) -> Result<(), Error> {
+    fn seek(&mut self, seek_time: f32) -> Result<(), Error> {
         match &self.haptic_clip {
-            Some(_) => Ok(()),
-            None => Err(Error::new("Player seek: no clip loaded")),
+            Some(_) => {
+                self.seek_time = seek_time;
+                Ok(())
+            }
+            None => Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player seek: no clip loaded",
+            )),
         }
     }
 
+    fn duration(&self) -> f32 {
+        self.haptic_clip
+            .as_ref()
+            .map_or(0.0, |haptic_clip| haptic_clip.duration())
+    }
+
     fn set_amplitude_multiplication(&mut self, _multiplication_factor: f32) -> Result<(), Error> {
         match &self.haptic_clip {
             Some(_) => Ok(()),
-            None => Err(Error::new(
+            None => Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
                 "Player set_amplitude_multiplication: no clip loaded",
             )),
         }
@@ -57,17 +96,228 @@ impl crate::PreAuthoredClipPlayback for Player {
     fn set_frequency_shift(&mut self, _shift: f32) -> Result<(), Error> {
         match &self.haptic_clip {
             Some(_) => Ok(()),
-            None => Err(Error::new("Player set_frequency_shift: no clip loaded")),
+            None => Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player set_frequency_shift: no clip loaded",
+            )),
         }
     }
 
-    fn set_looping(&mut self, _enabled: bool) -> Result<(), Error> {
+    fn set_playback_rate(&mut self, rate: f32) -> Result<(), Error> {
+        crate::check_playback_rate(rate)
+    }
+
+    fn set_loop_count(&mut self, _count: u32) -> Result<(), Error> {
         match &self.haptic_clip {
             Some(_) => {}
-            None => return Err(Error::new("Player set_looping: no clip loaded")),
+            None => {
+                return Err(Error::with_kind(
+                    ErrorKind::NoClipLoaded,
+                    "Player set_loop_count: no clip loaded",
+                ))
+            }
         }
         Ok(())
     }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.playback_state
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        crate::Capabilities {
+            player_name: "Null player",
+            frequency_modulation: true,
+            emphasis: true,
+        }
+    }
+}
+
+/// A player that doesn't play anything, but records the events a `HapticEventProvider` produces
+/// for the loaded clip.
+///
+/// Unlike the streaming player, the events are produced synchronously in `play()`, without
+/// waiting for the time of each event, so that the playback content can be verified in tests
+/// without depending on timing. Playback therefore finishes right away, and `playback_state()`
+/// reports `PlaybackState::Finished` after `play()`.
+///
+/// Playback starts at the position of the last seek. With looping enabled, each repetition is
+/// recorded after the previous one, with event times relative to the start of the clip. As an
+/// infinite loop can't be recorded, a loop count of 0 records a single repetition.
+pub struct RecordingPlayer {
+    haptic_clip: Option<latest::DataModel>,
+
+    /// The position of the last seek, in seconds
+    seek_time: f32,
+
+    amplitude_multiplication: f32,
+    frequency_shift: f32,
+    loop_count: u32,
+    playback_state: PlaybackState,
+
+    /// All events recorded since the player was created
+    recorded_events: RecordedEvents,
+}
+
+/// The events recorded by a `RecordingPlayer`.
+///
+/// This is a handle that shares the recording with the player, so that the events can still be
+/// inspected after the player has been moved, for example into a `Box<dyn
+/// PreAuthoredClipPlayback>`.
+#[derive(Clone, Debug, Default)]
+pub struct RecordedEvents {
+    events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl RecordedEvents {
+    /// Returns a copy of all events recorded so far
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Removes all events recorded so far
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+impl RecordingPlayer {
+    pub fn new() -> Result<RecordingPlayer, Error> {
+        Ok(RecordingPlayer {
+            haptic_clip: None,
+            seek_time: 0.0,
+            amplitude_multiplication: 1.0,
+            frequency_shift: 0.0,
+            loop_count: 1,
+            playback_state: PlaybackState::Stopped,
+            recorded_events: RecordedEvents::default(),
+        })
+    }
+
+    /// Returns the events recorded by this player
+    pub fn recorded_events(&self) -> RecordedEvents {
+        self.recorded_events.clone()
+    }
+
+    fn no_clip_loaded_error(function_name: &str) -> Error {
+        Error::with_kind(
+            ErrorKind::NoClipLoaded,
+            &format!("RecordingPlayer {}: no clip loaded", function_name),
+        )
+    }
+}
+
+impl crate::PreAuthoredClipPlayback for RecordingPlayer {
+    fn load(&mut self, data_model: latest::DataModel) -> Result<(), Error> {
+        self.haptic_clip = Some(data_model);
+        self.seek_time = 0.0;
+        self.amplitude_multiplication = 1.0;
+        self.frequency_shift = 0.0;
+        self.loop_count = 1;
+        self.playback_state = PlaybackState::Stopped;
+        Ok(())
+    }
+
+    fn play(&mut self) -> Result<(), Error> {
+        let haptic_clip = self
+            .haptic_clip
+            .as_ref()
+            .ok_or_else(|| Self::no_clip_loaded_error("play"))?;
+
+        let mut event_provider = HapticEventProvider::new(haptic_clip.clone());
+        event_provider.set_amplitude_multiplication(self.amplitude_multiplication);
+        event_provider.set_frequency_shift(self.frequency_shift);
+        event_provider.seek(self.seek_time);
+
+        let mut events = self.recorded_events.events.lock().unwrap();
+        for _ in 0..self.loop_count.max(1) {
+            while let Some(event) = event_provider.get_next_event() {
+                events.push(event);
+            }
+            event_provider.seek(0.0);
+        }
+
+        self.playback_state = PlaybackState::Finished;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        match &self.haptic_clip {
+            Some(_) => {
+                self.playback_state = PlaybackState::Stopped;
+                Ok(())
+            }
+            None => Err(Self::no_clip_loaded_error("stop")),
+        }
+    }
+
+    fn unload(&mut self) -> Result<(), Error> {
+        self.haptic_clip = None;
+        self.playback_state = PlaybackState::Stopped;
+        Ok(())
+    }
+
+    fn seek(&mut self, seek_time: f32) -> Result<(), Error> {
+        match &self.haptic_clip {
+            Some(_) => {
+                self.seek_time = seek_time;
+                Ok(())
+            }
+            None => Err(Self::no_clip_loaded_error("seek")),
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        self.haptic_clip
+            .as_ref()
+            .map_or(0.0, |haptic_clip| haptic_clip.duration())
+    }
+
+    fn set_amplitude_multiplication(&mut self, multiplication_factor: f32) -> Result<(), Error> {
+        match &self.haptic_clip {
+            Some(_) => {
+                self.amplitude_multiplication = multiplication_factor;
+                Ok(())
+            }
+            None => Err(Self::no_clip_loaded_error("set_amplitude_multiplication")),
+        }
+    }
+
+    fn set_frequency_shift(&mut self, shift: f32) -> Result<(), Error> {
+        match &self.haptic_clip {
+            Some(_) => {
+                self.frequency_shift = shift;
+                Ok(())
+            }
+            None => Err(Self::no_clip_loaded_error("set_frequency_shift")),
+        }
+    }
+
+    fn set_playback_rate(&mut self, rate: f32) -> Result<(), Error> {
+        crate::check_playback_rate(rate)
+    }
+
+    fn set_loop_count(&mut self, count: u32) -> Result<(), Error> {
+        match &self.haptic_clip {
+            Some(_) => {
+                self.loop_count = count;
+                Ok(())
+            }
+            None => Err(Self::no_clip_loaded_error("set_loop_count")),
+        }
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.playback_state
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        crate::Capabilities {
+            player_name: "Recording player",
+            frequency_modulation: true,
+            emphasis: true,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -90,9 +340,76 @@ mod tests {
         let data = load_test_file_valid_v1();
         let data_model = datamodel::latest_from_json(&data).unwrap().1;
 
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
         player.load(data_model).unwrap();
         player.play().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Playing);
         player.stop().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Stopped);
+    }
+
+    #[test]
+    fn test_null_player_seek_by_fraction() {
+        let mut player = Player::new().unwrap();
+        assert_eq!(player.duration(), 0.0);
+        assert!(player.seek_by_fraction(0.5).is_err());
+
+        let data = load_test_file_valid_v1();
+        let data_model = datamodel::latest_from_json(&data).unwrap().1;
+        let duration = data_model.duration();
+        player.load(data_model).unwrap();
+        assert_eq!(player.duration(), duration);
+
+        player.seek_by_fraction(0.5).unwrap();
+        assert_eq!(player.seek_time, duration / 2.0);
+        player.seek_by_fraction(2.0).unwrap();
+        assert_eq!(player.seek_time, duration);
+        player.seek_by_fraction(-1.0).unwrap();
+        assert_eq!(player.seek_time, 0.0);
+        assert!(player.seek_by_fraction(f32::NAN).is_err());
+    }
+
+    #[test]
+    // The recording player records the same events as the HapticEventProvider, starting at the
+    // seek position and repeated for each loop
+    fn test_recording_player() {
+        let data = load_test_file_valid_v1();
+        let data_model = datamodel::latest_from_json(&data).unwrap().1;
+        let expected_events = crate::test_utils::gather_events_from_provider(
+            &mut HapticEventProvider::new(data_model.clone()),
+            None,
+        );
+
+        let mut player = RecordingPlayer::new().unwrap();
+        let recorded_events = player.recorded_events();
+        assert!(player.play().is_err());
+        player.load(data_model.clone()).unwrap();
+        player.play().unwrap();
+        assert_eq!(player.playback_state(), PlaybackState::Finished);
+        assert_eq!(
+            crate::test_utils::rounded_events(&recorded_events.events(), 5),
+            expected_events
+        );
+
+        // Test: Looping records the clip once per repetition
+        recorded_events.clear();
+        player.set_loop_count(2).unwrap();
+        player.play().unwrap();
+        assert_eq!(recorded_events.events().len(), expected_events.len() * 2);
+
+        // Test: Playback starts at the seek position
+        let mut event_provider = HapticEventProvider::new(data_model.clone());
+        event_provider.seek(1.0);
+        let expected_events =
+            crate::test_utils::gather_events_from_provider(&mut event_provider, None);
+        recorded_events.clear();
+        player.load(data_model).unwrap();
+        player.seek(1.0).unwrap();
+        player.play().unwrap();
+        assert_eq!(
+            crate::test_utils::rounded_events(&recorded_events.events(), 5),
+            expected_events
+        );
     }
 
     #[test]
@@ -0,0 +1,200 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! Records the events that a `streaming::Player` delivers, together with their delivery times.
+//!
+//! This is useful to check the timing behaviour of the streaming player, for example to measure
+//! the jitter between events or the delay of the first event after starting playback.
+
+use crate::{
+    haptic_event_provider::{AmplitudeEvent, Event, FrequencyEvent},
+    streaming::{Callbacks, Player},
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use utils::{test_utils::rounded_f32, Error};
+
+/// Number of decimal places the fields of recorded events are rounded to
+const RECORDED_EVENT_DECIMAL_PLACES: u32 = 5;
+
+struct RecordingData {
+    start_time: Instant,
+    events: Vec<Event>,
+    // For each event in `events`, the time since `start_time` at which it was delivered
+    delivery_times: Vec<Duration>,
+    errors: Vec<f32>, // in seconds
+}
+
+impl RecordingData {
+    // Records an event delivered now.
+    //
+    // If now is before the recording start time (which can happen when seeking to a negative
+    // time), the event is ignored, regardless of whether it is an amplitude or a frequency event.
+    fn record(&mut self, event: Event) {
+        let now = Instant::now();
+        if now < self.start_time {
+            return;
+        }
+
+        let time_since_start = now - self.start_time;
+        self.errors
+            .push(time_since_start.as_secs_f32() - event.time());
+        self.delivery_times.push(time_since_start);
+        self.events
+            .push(rounded_event(event, RECORDED_EVENT_DECIMAL_PLACES));
+    }
+}
+
+/// Rounds all fields of an event to the given number of decimal places, to avoid floating-point
+/// precision problems when comparing events.
+pub(crate) fn rounded_event(mut event: Event, decimal_places: u32) -> Event {
+    match &mut event {
+        Event::Amplitude(e) => {
+            e.amplitude = rounded_f32(e.amplitude, decimal_places);
+            e.time = rounded_f32(e.time, decimal_places);
+            e.duration = rounded_f32(e.duration, decimal_places);
+        }
+        Event::Frequency(e) => {
+            e.frequency = rounded_f32(e.frequency, decimal_places);
+            e.time = rounded_f32(e.time, decimal_places);
+            e.duration = rounded_f32(e.duration, decimal_places);
+        }
+    }
+    event
+}
+
+/// A `streaming::Player` that records the events it delivers, together with the timing errors.
+///
+/// All fields of the recorded events are rounded to 5 decimal places. Events delivered before
+/// the start of the recording are not recorded, see `clear_recording_data()`.
+pub struct PlayerEventRecorder {
+    recording_data: Arc<Mutex<RecordingData>>,
+    player: Player,
+}
+
+impl PlayerEventRecorder {
+    /// Creates a recorder with a new `streaming::Player`.
+    ///
+    /// The recording starts right away; call `clear_recording_data()` before starting playback
+    /// to reset the start time.
+    pub fn new() -> Result<PlayerEventRecorder, Error> {
+        let recording_data = Arc::new(Mutex::new(RecordingData {
+            start_time: Instant::now(),
+            events: Vec::new(),
+            delivery_times: Vec::new(),
+            errors: Vec::new(),
+        }));
+
+        let recording_data_for_thread = recording_data.clone();
+        let amplitude_event_callback = move |event: AmplitudeEvent| {
+            let mut recording_data = recording_data_for_thread.lock().unwrap();
+            recording_data.record(Event::Amplitude(event));
+        };
+        let recording_data_for_thread = recording_data.clone();
+        let frequency_event_callback = move |event: FrequencyEvent| {
+            let mut recording_data = recording_data_for_thread.lock().unwrap();
+            recording_data.record(Event::Frequency(event));
+        };
+
+        recording_data.lock().unwrap().start_time = Instant::now();
+        let callbacks = Callbacks {
+            amplitude_event: Box::new(amplitude_event_callback),
+            frequency_event: Box::new(frequency_event_callback),
+            init_thread: Box::new(|| {}),
+            clip_finished: None,
+            idle: None,
+            loop_iteration: None,
+        };
+        let player = Player::new(callbacks)?;
+        Ok(PlayerEventRecorder {
+            recording_data,
+            player,
+        })
+    }
+
+    /// Returns the recorded events, in the order in which they were delivered.
+    pub fn recorded_events(&self) -> Vec<Event> {
+        let recording_data = self.recording_data.lock().unwrap();
+        recording_data.events.clone()
+    }
+
+    /// Returns the recorded events, each together with the time at which it was delivered to the
+    /// callback.
+    ///
+    /// The time is relative to the start of the recording, which is the start of playback if
+    /// `clear_recording_data()` was called right before playing. Unlike `recorded_errors()`, this
+    /// allows computing statistics like the jitter between events.
+    pub fn recorded_events_with_timing(&self) -> Vec<(Event, Duration)> {
+        let recording_data = self.recording_data.lock().unwrap();
+        recording_data
+            .events
+            .iter()
+            .copied()
+            .zip(recording_data.delivery_times.iter().copied())
+            .collect()
+    }
+
+    /// Returns, for each recorded event, the difference in seconds between its delivery time
+    /// and its event time.
+    ///
+    /// Positive values mean that the event arrived later than designed, negative values mean
+    /// that it arrived earlier than designed.
+    pub fn recorded_errors(&self) -> Vec<f32> {
+        let recording_data = self.recording_data.lock().unwrap();
+        recording_data.errors.clone()
+    }
+
+    /// Discards everything recorded so far and restarts the recording.
+    ///
+    /// `start_time_offset` is the clip time in seconds that playback is at right now. A negative
+    /// offset means that playback will start in the future, for example after seeking to a
+    /// negative time.
+    pub fn clear_recording_data(&mut self, start_time_offset: f32) {
+        let mut recording_data = self.recording_data.lock().unwrap();
+        recording_data.events.clear();
+        recording_data.delivery_times.clear();
+        recording_data.errors.clear();
+        recording_data.start_time = if start_time_offset >= 0.0 {
+            Instant::now() - Duration::from_secs_f32(start_time_offset)
+        } else {
+            // A negative start time means that playback will start in the future
+            Instant::now() + Duration::from_secs_f32(-start_time_offset)
+        }
+    }
+
+    /// Returns the player whose events are recorded.
+    pub fn player(&mut self) -> &mut Player {
+        &mut self.player
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{amp, freq};
+
+    /// Tests that amplitude and frequency events delivered before the start of the recording are
+    /// both ignored.
+    #[test]
+    fn early_events_ignored() {
+        let mut recording_data = RecordingData {
+            start_time: Instant::now() + Duration::from_secs(60),
+            events: Vec::new(),
+            delivery_times: Vec::new(),
+            errors: Vec::new(),
+        };
+        recording_data.record(amp(0.0, 0.1, 0.5));
+        recording_data.record(freq(0.0, 0.1, 0.5));
+        assert!(recording_data.events.is_empty());
+        assert!(recording_data.delivery_times.is_empty());
+        assert!(recording_data.errors.is_empty());
+
+        recording_data.start_time = Instant::now();
+        recording_data.record(amp(0.0, 0.1, 0.5));
+        recording_data.record(freq(0.0, 0.1, 0.5));
+        assert_eq!(recording_data.events.len(), 2);
+        assert_eq!(recording_data.delivery_times.len(), 2);
+        assert_eq!(recording_data.errors.len(), 2);
+    }
+}
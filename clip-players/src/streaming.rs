@@ -1,24 +1,46 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
-use crate::haptic_event_provider::{Event, HapticEventProvider};
+use crate::{
+    haptic_event_provider::{
+        AmplitudeLimiter, Event, HapticEventProvider, MIN_BREAKPOINT_DISTANCE,
+    },
+    PlaybackState,
+};
 use crossbeam_channel::{self, Receiver, Sender};
+use datamodel::v1::AmplitudeBreakpoint;
 use std::{
     thread,
     thread::JoinHandle,
     time::{Duration, Instant},
 };
-use utils::Error;
+use utils::{Error, ErrorKind};
 
 pub use crate::haptic_event_provider::{AmplitudeEvent, FrequencyEvent};
 
 type AmplitudeEventCallback = dyn FnMut(AmplitudeEvent) + Send;
 type FrequencyEventCallback = dyn FnMut(FrequencyEvent) + Send;
 type InitThreadCallback = dyn FnMut() + Send;
+type ClipFinishedCallback = dyn FnMut() + Send;
+type IdleCallback = dyn FnMut() + Send;
+type LoopIterationCallback = dyn FnMut(u32) + Send;
 
 pub struct Callbacks {
     pub amplitude_event: Box<AmplitudeEventCallback>,
     pub frequency_event: Box<FrequencyEventCallback>,
     pub init_thread: Box<InitThreadCallback>,
+    /// Called from the streaming thread when playback reaches the end of the clip and the clip
+    /// isn't repeated. Not called when playback is stopped with `stop()` or `unload()`, and not
+    /// called at the end of each repetition of a looping clip.
+    pub clip_finished: Option<Box<ClipFinishedCallback>>,
+    /// Called from the streaming thread each time the idle timeout set with
+    /// `Player::set_idle_timeout()` elapses while no clip is playing. Can be used for periodic
+    /// housekeeping in the streaming thread.
+    pub idle: Option<Box<IdleCallback>>,
+    /// Called from the streaming thread each time a looping clip restarts from the beginning,
+    /// with the index of the repetition that starts. The index is 1 for the first repetition
+    /// after the initial playback, and counts up from there. Not called when playback starts
+    /// with `play()`.
+    pub loop_iteration: Option<Box<LoopIterationCallback>>,
 }
 
 /// A command sent from the player thread to the streaming thread
@@ -27,15 +49,110 @@ enum PlayerCommand {
     Load(datamodel::latest::DataModel),
     Unload,
     Play,
+    PlayAt {
+        offset: f32,
+    },
     Stop,
-    Seek { seek_time: f32 },
-    SetAmplitudeMultiplication { multiplication_factor: f32 },
-    SetFrequencyShift { shift: f32 },
-    Loop { enabled: bool },
+    Pause,
+    Resume,
+    Seek {
+        seek_time: f32,
+    },
+    SetAmplitudeMultiplication {
+        multiplication_factor: f32,
+        limiter: AmplitudeLimiter,
+    },
+    SetAmplitudeEnvelope {
+        envelope: Vec<AmplitudeBreakpoint>,
+    },
+    SetFrequencyShift {
+        shift: f32,
+    },
+    SetPlaybackRate {
+        rate: f32,
+    },
+    Loop {
+        count: u32,
+    },
+    SetPreroll {
+        lead: Duration,
+    },
+    SetIdleTimeout {
+        idle_timeout: f32,
+    },
+    QueryPosition {
+        response: Sender<Option<f32>>,
+    },
+    QueryState {
+        response: Sender<PlaybackState>,
+    },
     Quit,
 }
 
-/// Default sleep thread time value to avoid polling after starting the thread and after stop
+/// Describes a command for tracing, see the `trace-commands` feature.
+///
+/// Only the shape of clips and envelopes is described, e.g. the number of breakpoints, not the
+/// breakpoints themselves, to keep the output short and not leak clip contents into logs.
+impl std::fmt::Display for PlayerCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerCommand::Load(data) => {
+                let envelopes = &data.signals.continuous.envelopes;
+                write!(
+                    f,
+                    "Load(amplitude_breakpoints: {}, frequency_breakpoints: {}, emphasis: {})",
+                    envelopes.amplitude.len(),
+                    envelopes.frequency.as_ref().map_or(0, Vec::len),
+                    envelopes
+                        .amplitude
+                        .iter()
+                        .filter(|breakpoint| breakpoint.emphasis.is_some())
+                        .count()
+                )
+            }
+            PlayerCommand::Unload => write!(f, "Unload"),
+            PlayerCommand::Play => write!(f, "Play"),
+            PlayerCommand::PlayAt { offset } => write!(f, "PlayAt(offset: {})", offset),
+            PlayerCommand::Stop => write!(f, "Stop"),
+            PlayerCommand::Pause => write!(f, "Pause"),
+            PlayerCommand::Resume => write!(f, "Resume"),
+            PlayerCommand::Seek { seek_time } => write!(f, "Seek(seek_time: {})", seek_time),
+            PlayerCommand::SetAmplitudeMultiplication {
+                multiplication_factor,
+                limiter,
+            } => write!(
+                f,
+                "SetAmplitudeMultiplication(multiplication_factor: {}, limiter: {:?})",
+                multiplication_factor, limiter
+            ),
+            PlayerCommand::SetAmplitudeEnvelope { envelope } => {
+                write!(f, "SetAmplitudeEnvelope(breakpoints: {})", envelope.len())
+            }
+            PlayerCommand::SetFrequencyShift { shift } => {
+                write!(f, "SetFrequencyShift(shift: {})", shift)
+            }
+            PlayerCommand::SetPlaybackRate { rate } => {
+                write!(f, "SetPlaybackRate(rate: {})", rate)
+            }
+            PlayerCommand::Loop { count } => write!(f, "Loop(count: {})", count),
+            PlayerCommand::SetPreroll { lead } => {
+                write!(f, "SetPreroll(lead: {})", lead.as_secs_f32())
+            }
+            PlayerCommand::SetIdleTimeout { idle_timeout } => {
+                write!(f, "SetIdleTimeout(idle_timeout: {})", idle_timeout)
+            }
+            PlayerCommand::QueryPosition { .. } => write!(f, "QueryPosition"),
+            PlayerCommand::QueryState { .. } => write!(f, "QueryState"),
+            PlayerCommand::Quit => write!(f, "Quit"),
+        }
+    }
+}
+
+/// Default time in seconds that the streaming thread sleeps while no clip is playing, to avoid
+/// polling after starting the thread and after stop.
+///
+/// The thread is woken up by the next command anyway, so this can be long. See
+/// `Player::set_idle_timeout()` to wake up more often.
 const DEFAULT_TIME_TO_SLEEP: f32 = 10000.0;
 
 /// Plays pre-authored haptic clips by streaming haptic events to the callbacks provided
@@ -49,6 +166,12 @@ pub struct Player {
     sender: Sender<PlayerCommand>,
     clip_loaded: bool,
 
+    /// Duration of the loaded clip in seconds, 0.0 if no clip is loaded
+    clip_duration: f32,
+
+    /// Limiter sent along with every amplitude multiplication, see set_amplitude_limiter()
+    amplitude_limiter: AmplitudeLimiter,
+
     /// JoinHandle of the streaming thread, used to properly join it when dropping the Player
     join_handle: Option<JoinHandle<()>>,
 }
@@ -76,10 +199,41 @@ struct EventSender {
     /// Set to Some if a delay should be applied when a play command is received.
     play_delay: Option<Duration>,
 
-    /// If looping is enabled or not.
-    /// If `true`, when sending the last event, the provider is sought to 0.0
-    /// which will re-send events from the beginning of `clip`
-    looping_enabled: bool,
+    /// How much earlier than their scheduled time the events are sent when playback starts.
+    ///
+    /// See Player::set_preroll().
+    preroll: Duration,
+
+    /// How long to sleep while no clip is playing, in seconds. See Player::set_idle_timeout().
+    idle_timeout: f32,
+
+    /// The speed at which the clip is played, see Player::set_playback_rate().
+    ///
+    /// Positions in the clip are divided by the rate to get the wall-clock time at which they
+    /// play, and event durations are divided by it as well.
+    playback_rate: f32,
+
+    /// The playback position at which the clip was paused, in seconds.
+    ///
+    /// Set to Some while the clip is paused, see Player::pause().
+    paused_position: Option<f32>,
+
+    /// Set to true when the last event of the clip was sent and looping is disabled, and back
+    /// to false when playback is started, stopped or sought.
+    finished: bool,
+
+    /// How often the clip is played, 0 means infinitely.
+    /// When sending the last event and the clip needs to be repeated, the provider is sought
+    /// to 0.0 which will re-send events from the beginning of `clip`
+    loop_count: u32,
+
+    /// How many more times the clip is repeated after it finished playing. Only used if
+    /// `loop_count` is not 0.
+    remaining_repetitions: u32,
+
+    /// The index of the current repetition of a looping clip, 0 for the initial playback.
+    /// Passed to the loop_iteration callback.
+    loop_iteration: u32,
 }
 
 impl EventSender {
@@ -90,8 +244,11 @@ impl EventSender {
         self.start_time = None;
     }
 
-    pub fn set_looping_enabled(&mut self, enabled: bool) {
-        self.looping_enabled = enabled;
+    /// Sets how often the clip is played. If the clip is already playing, the current
+    /// repetition counts as the first one.
+    pub fn set_loop_count(&mut self, count: u32) {
+        self.loop_count = count;
+        self.remaining_repetitions = count.saturating_sub(1);
     }
 
     /// Sends a haptic event to ramp down the amplitude to zero to the amplitude callback,
@@ -112,9 +269,68 @@ impl EventSender {
                 self.rewind();
             }
         }
+
+        // Sending the ramp-down event above marks the clip as finished, but it was stopped
+        self.finished = false;
+    }
+
+    /// Resets the repetitions of a looping clip before playback starts with play(), if not
+    /// already playing.
+    fn start_repetitions(&mut self) {
+        if self.start_time.is_none() {
+            self.remaining_repetitions = self.loop_count.saturating_sub(1);
+            self.loop_iteration = 0;
+        }
+    }
+
+    /// Starts playback at the current position of the event provider, if not already playing.
+    fn play(&mut self) {
+        self.finished = false;
+        match self.event_provider.as_mut() {
+            // This case should not happen as it is caught by clip_loaded in the Player
+            None => {
+                log::error!("Attempting to play clip that is not loaded.");
+            }
+            Some(event_provider) => {
+                // Update start_time
+                if self.start_time.is_none() {
+                    self.start_time = match event_provider.peek_event_start_time() {
+                        Some(next_event_time) => {
+                            let now = Instant::now();
+                            let next_event =
+                                Duration::from_secs_f32(next_event_time / self.playback_rate);
+                            let play_delay = self
+                                .play_delay
+                                .take()
+                                .unwrap_or_else(|| Duration::from_secs(0));
+                            Some(now - next_event - self.preroll + play_delay)
+                        }
+                        None => Some(Instant::now()),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Stops playback like stop(), but remembers the playback position so that resume() can
+    /// continue from there.
+    fn pause(&mut self) {
+        if let Some(playhead_time) = self.playhead_time() {
+            self.stop();
+            self.paused_position = Some(playhead_time);
+        }
+    }
+
+    /// Continues playback at the position remembered by pause().
+    fn resume(&mut self) {
+        if let Some(paused_position) = self.paused_position.take() {
+            self.seek(paused_position);
+            self.play();
+        }
     }
 
     fn seek(&mut self, seek_time: f32) {
+        self.finished = false;
         if let Some(event_provider) = self.event_provider.as_mut() {
             event_provider.seek(seek_time);
 
@@ -123,7 +339,7 @@ impl EventSender {
                 if let Some(start_time) = self.start_time {
                     let now = Instant::now();
                     let new_start_time = if seek_time >= 0.0 {
-                        now - Duration::from_secs_f32(seek_time)
+                        now - Duration::from_secs_f32(seek_time / self.playback_rate)
                     } else {
                         if now > start_time {
                             // Seeking to a negative time,
@@ -134,7 +350,7 @@ impl EventSender {
 
                         // A negative seek time means that we're going to
                         // be starting playback *in the future*
-                        now + Duration::from_secs_f32(-seek_time)
+                        now + Duration::from_secs_f32(-seek_time / self.playback_rate)
                     };
 
                     self.start_time = Some(new_start_time);
@@ -142,7 +358,7 @@ impl EventSender {
                     // The clip is not yet playing
                     self.play_delay = if seek_time < 0.0 {
                         // Negative seek time, so the next Play command should apply a delay
-                        Some(Duration::from_secs_f32(-seek_time))
+                        Some(Duration::from_secs_f32(-seek_time / self.playback_rate))
                     } else {
                         None
                     };
@@ -150,7 +366,7 @@ impl EventSender {
             } else {
                 self.rewind();
                 self.play_delay = if seek_time < 0.0 {
-                    Some(Duration::from_secs_f32(-seek_time))
+                    Some(Duration::from_secs_f32(-seek_time / self.playback_rate))
                 } else {
                     None
                 };
@@ -164,34 +380,49 @@ impl EventSender {
     /// Gets the next event from the HapticEventProvider and passes it to the appropriate
     /// callback.
     ///
-    /// Returns the amount of seconds until the next event occurs, or DEFAULT_TIME_TO_SLEEP
+    /// Returns the amount of seconds until the next event occurs, or idle_timeout
     /// if there is no next event.
     ///
     /// If event_provider or the next event is None, then there is a timeout because the
     /// thread has been idle without playing for a long time. In that case do nothing
     /// and go back to sleep for a long time.
-    fn send_next_event(&mut self) {
+    ///
+    /// Returns true if the sent event was the last event of the clip, and playback finished
+    /// instead of repeating the clip.
+    fn send_next_event(&mut self) -> bool {
         if let Some(event_provider) = self.event_provider.as_mut() {
-            if let Some(event) = event_provider.get_next_event() {
+            if let Some(mut event) = event_provider.get_next_event() {
                 debug_assert!(self.start_time.is_some());
+                match &mut event {
+                    Event::Frequency(event) => event.duration /= self.playback_rate,
+                    Event::Amplitude(event) => event.duration /= self.playback_rate,
+                }
                 match &event {
                     Event::Frequency(event) => (self.callbacks.frequency_event)(*event),
                     Event::Amplitude(event) => (self.callbacks.amplitude_event)(*event),
                 }
 
                 if event_provider.peek_event_start_time().is_none() {
-                    // No more events to send, playback finished only if looping is not enabled.
-                    // Otherwise, it will continue sending events from the beginning of
-                    // the clip
-                    if self.looping_enabled {
+                    // No more events to send, playback finished only if the clip doesn't need
+                    // to be repeated. Otherwise, it will continue sending events from the
+                    // beginning of the clip
+                    if self.loop_count == 0 || self.remaining_repetitions > 0 {
+                        self.remaining_repetitions = self.remaining_repetitions.saturating_sub(1);
                         event_provider.seek(0.0);
                         self.start_time = Some(Instant::now());
+                        self.loop_iteration = self.loop_iteration.wrapping_add(1);
+                        if let Some(loop_iteration) = self.callbacks.loop_iteration.as_mut() {
+                            loop_iteration(self.loop_iteration);
+                        }
                     } else {
                         self.rewind();
+                        self.finished = true;
+                        return true;
                     }
                 }
             }
         }
+        false
     }
 
     fn send_event(&mut self, event: Event) {
@@ -208,14 +439,32 @@ impl EventSender {
     fn playhead_time(&self) -> Option<f32> {
         self.start_time.map(|start_time| {
             let now = Instant::now();
-            if now > start_time {
+            let elapsed = if now > start_time {
                 (now - start_time).as_secs_f32()
             } else {
                 -((start_time - now).as_secs_f32())
-            }
+            };
+            elapsed * self.playback_rate
         })
     }
 
+    /// Returns true if the clip is playing and the playhead is at `time`, within
+    /// MIN_BREAKPOINT_DISTANCE.
+    fn is_playhead_at(&self, time: f32) -> bool {
+        matches!(self.playhead_time(),
+            Some(playhead_time) if (playhead_time - time).abs() < MIN_BREAKPOINT_DISTANCE)
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        if self.start_time.is_some() {
+            PlaybackState::Playing
+        } else if self.finished {
+            PlaybackState::Finished
+        } else {
+            PlaybackState::Stopped
+        }
+    }
+
     fn time_to_next_event(&self) -> f32 {
         if let Some(playhead_time) = self.playhead_time() {
             if let Some(event_provider) = &self.event_provider {
@@ -225,11 +474,11 @@ impl EventSender {
                     // playhead_time can be negative if a negative seek time has been used,
                     // then then we automatically wait for the remaining time before 0.0,
                     // plus the first event's time.
-                    return (next_event_time - playhead_time).max(0.0);
+                    return ((next_event_time - playhead_time) / self.playback_rate).max(0.0);
                 }
             }
         }
-        DEFAULT_TIME_TO_SLEEP
+        self.idle_timeout
     }
 }
 
@@ -249,63 +498,88 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
         event_provider: None,
         start_time: None,
         play_delay: None,
-        looping_enabled: false,
+        preroll: Duration::from_secs(0),
+        idle_timeout: DEFAULT_TIME_TO_SLEEP,
+        playback_rate: 1.0,
+        paused_position: None,
+        finished: false,
+        loop_count: 1,
+        remaining_repetitions: 0,
+        loop_iteration: 0,
     };
 
     loop {
         match receiver.recv_timeout(Duration::from_secs_f32(event_sender.time_to_next_event())) {
             Ok(command) => {
+                // The command is only formatted if trace logging is enabled at runtime
+                #[cfg(feature = "trace-commands")]
+                log::trace!("Streaming thread received command {}", command);
+
                 match command {
                     PlayerCommand::Quit => {
                         // Break out of the loop so that the thread is exited
                         break;
                     }
                     PlayerCommand::Load(data) => {
+                        event_sender.paused_position = None;
                         event_sender.stop();
+                        // The new provider starts with an amplitude multiplication of 1.0 and
+                        // a frequency shift of 0.0, so no modulation of the previous clip leaks
+                        // into the new one. Like on Android, the loop count is reset as well.
                         event_sender.event_provider = Some(HapticEventProvider::new(data));
+                        event_sender.set_loop_count(1);
                     }
                     PlayerCommand::Unload => {
+                        event_sender.paused_position = None;
                         event_sender.stop();
                         event_sender.event_provider = None;
                     }
                     PlayerCommand::Play => {
-                        match event_sender.event_provider.as_mut() {
-                            // This case should not happen as it is caught by clip_loaded in the Player
-                            None => {
-                                log::error!("Attempting to play clip that is not loaded.");
-                            }
-                            Some(event_provider) => {
-                                // Update start_time
-                                if event_sender.start_time.is_none() {
-                                    event_sender.start_time =
-                                        match event_provider.peek_event_start_time() {
-                                            Some(next_event_time) => {
-                                                let now = Instant::now();
-                                                let next_event =
-                                                    Duration::from_secs_f32(next_event_time);
-                                                let play_delay = event_sender
-                                                    .play_delay
-                                                    .take()
-                                                    .unwrap_or_else(|| Duration::from_secs(0));
-                                                Some(now - next_event + play_delay)
-                                            }
-                                            None => Some(Instant::now()),
-                                        };
-                                }
-                            }
-                        }
+                        event_sender.paused_position = None;
+                        event_sender.start_repetitions();
+                        event_sender.play();
+                    }
+                    // Same as Seek followed by Play. Handling both in one command ensures that
+                    // no other command is processed in between.
+                    PlayerCommand::PlayAt { offset } => {
+                        event_sender.paused_position = None;
+                        event_sender.seek(offset);
+                        event_sender.start_repetitions();
+                        event_sender.play();
                     }
                     PlayerCommand::Stop => {
+                        event_sender.paused_position = None;
                         event_sender.stop();
                     }
+                    PlayerCommand::Pause => {
+                        event_sender.pause();
+                    }
+                    PlayerCommand::Resume => {
+                        event_sender.resume();
+                    }
                     PlayerCommand::Seek { seek_time } => {
-                        event_sender.seek(seek_time);
+                        if event_sender.paused_position.is_some() {
+                            event_sender.paused_position = Some(seek_time);
+                        } else if !event_sender.is_playhead_at(seek_time) {
+                            // Seeking to where the playhead already is would only send the
+                            // events ramping to the current position again, so it is skipped
+                            event_sender.seek(seek_time);
+                        }
                     }
                     PlayerCommand::SetAmplitudeMultiplication {
                         multiplication_factor,
+                        limiter,
                     } => match event_sender.event_provider.as_mut() {
+                        // Setting the values that are already set, which happens a lot when
+                        // they come from a UI slider, is a no-op. Otherwise each of these
+                        // commands would seek and send a burst of events.
+                        Some(event_provider)
+                            if event_provider.amplitude_multiplication()
+                                == multiplication_factor
+                                && event_provider.amplitude_limiter() == limiter => {}
                         Some(event_provider) => {
                             event_provider.set_amplitude_multiplication(multiplication_factor);
+                            event_provider.set_amplitude_limiter(limiter);
 
                             // If the clip is already playing, seek to the current position.
                             // While seeking to the current position sounds like a no-op at first,
@@ -325,10 +599,27 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
                             log::error!("Attempting to set amplitude multiplication failed, no clip loaded.");
                         }
                     },
+                    // Same as SetAmplitudeMultiplication, but for an amplitude envelope
+                    PlayerCommand::SetAmplitudeEnvelope { envelope } => {
+                        match event_sender.event_provider.as_mut() {
+                            Some(event_provider) => {
+                                event_provider.set_amplitude_envelope(envelope);
+                                if let Some(playhead_time) = event_sender.playhead_time() {
+                                    event_sender.seek(playhead_time);
+                                }
+                            }
+                            None => {
+                                log::error!(
+                                    "Attempting to set amplitude envelope failed, no clip loaded."
+                                );
+                            }
+                        }
+                    }
                     // Same as SetAmplitudeMultiplication, but for a frequency shift instead of an
                     // amplitude multiplication
                     PlayerCommand::SetFrequencyShift { shift } => {
                         match event_sender.event_provider.as_mut() {
+                            Some(event_provider) if event_provider.frequency_shift() == shift => {}
                             Some(event_provider) => {
                                 event_provider.set_frequency_shift(shift);
                                 if let Some(playhead_time) = event_sender.playhead_time() {
@@ -342,19 +633,52 @@ fn command_loop(mut callbacks: Callbacks, receiver: Receiver<PlayerCommand>) {
                             }
                         }
                     }
-                    PlayerCommand::Loop { enabled } => {
+                    // Like SetAmplitudeMultiplication, seek to the current position so that the
+                    // new rate applies right away instead of at the next event
+                    PlayerCommand::SetPlaybackRate { rate } => {
+                        let playhead_time = event_sender.playhead_time();
+                        event_sender.playback_rate = rate;
+                        if let Some(playhead_time) = playhead_time {
+                            event_sender.seek(playhead_time);
+                        }
+                    }
+                    PlayerCommand::SetPreroll { lead } => {
+                        event_sender.preroll = lead;
+                    }
+                    PlayerCommand::SetIdleTimeout { idle_timeout } => {
+                        event_sender.idle_timeout = idle_timeout;
+                    }
+                    PlayerCommand::QueryPosition { response } => {
+                        // The player might have given up waiting, nothing to do in that case
+                        let _ = response.send(event_sender.playhead_time());
+                    }
+                    PlayerCommand::QueryState { response } => {
+                        let _ = response.send(event_sender.playback_state());
+                    }
+                    PlayerCommand::Loop { count } => {
                         if event_sender.event_provider.is_none() {
                             // This case should not happen as it is caught by clip_loaded in the Player
                             log::error!("Attempting to loop a clip that is not loaded.");
                         } else {
-                            event_sender.set_looping_enabled(enabled)
+                            event_sender.set_loop_count(count)
                         }
                     }
                 }
             }
             // Since we set the timeout to be the duration until the next haptic event occurs, getting
             // a timeout error here means that it is time to stream the next haptic event.
-            Err(crossbeam_channel::RecvTimeoutError::Timeout) => event_sender.send_next_event(),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                if event_sender.start_time.is_none() {
+                    // Nothing is playing, so this is the idle timeout
+                    if let Some(idle) = event_sender.callbacks.idle.as_mut() {
+                        idle();
+                    }
+                } else if event_sender.send_next_event() {
+                    if let Some(clip_finished) = event_sender.callbacks.clip_finished.as_mut() {
+                        clip_finished();
+                    }
+                }
+            }
 
             // This case shouldn't really happen, the Player is supposed to disconnect properly by
             // sending the Quit command
@@ -401,46 +725,215 @@ impl Player {
         let join_handle = thread::Builder::new()
             .name("haptic_streaming".to_string())
             .spawn(move || command_loop(callbacks, receiver))
-            .map_err(|e| Error::new(&format!("Unable to start haptic streaming thread: {}", e)))?;
+            .map_err(|e| {
+                Error::with_kind(
+                    ErrorKind::PlaybackFailed,
+                    &format!("Unable to start haptic streaming thread: {}", e),
+                )
+            })?;
 
         let player = Player {
             sender,
             clip_loaded: false,
+            clip_duration: 0.0,
+            amplitude_limiter: AmplitudeLimiter::HardClip,
             join_handle: Some(join_handle),
         };
         Ok(player)
     }
 
+    /// Pauses playback, remembering the playback position.
+    ///
+    /// Like `stop()`, the amplitude is ramped down to zero and no more events are sent. Unlike
+    /// `stop()`, `resume()` continues playback from the position at which the clip was paused.
+    /// Seeking while paused changes the position at which `resume()` continues.
+    ///
+    /// Pausing a clip that is not playing does nothing.
+    pub fn pause(&mut self) -> Result<(), Error> {
+        if !self.clip_loaded {
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to pause, no clip loaded.",
+            ));
+        }
+        self.send_command(PlayerCommand::Pause, "Pause")
+    }
+
+    /// Continues playback from the position at which `pause()` was called.
+    ///
+    /// Calling `play()`, `stop()`, `load()` or `unload()` discards the paused position. Resuming
+    /// a clip that is not paused does nothing.
+    pub fn resume(&mut self) -> Result<(), Error> {
+        if !self.clip_loaded {
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to resume, no clip loaded.",
+            ));
+        }
+        self.send_command(PlayerCommand::Resume, "Resume")
+    }
+
+    /// Sets how much earlier than scheduled the events are sent when playback starts.
+    ///
+    /// On iOS, the first event is delayed by the creation of the Core Haptics objects. With a
+    /// preroll, `play()` moves the start of the clip `lead` into the past, so that the first event
+    /// is sent right away and the motor is already running at the intended time 0. All following
+    /// events are sent `lead` earlier as well, keeping the timing between events intact.
+    ///
+    /// The preroll applies to all following calls to `play()`, and does not affect a clip that
+    /// is already playing. The default is no preroll.
+    pub fn set_preroll(&mut self, lead: Duration) -> Result<(), Error> {
+        self.send_command(PlayerCommand::SetPreroll { lead }, "SetPreroll")
+    }
+
+    /// Sets a gain curve that multiplies the amplitude of the clip over time, in addition to
+    /// `set_amplitude_multiplication()`.
+    ///
+    /// The amplitude of each breakpoint in `envelope` is the multiplication factor at that time
+    /// of the clip. The factor is interpolated linearly between the breakpoints, and held before
+    /// the first and after the last breakpoint. See
+    /// `HapticEventProvider::set_amplitude_envelope()` for details.
+    ///
+    /// Like the amplitude multiplication, the envelope takes effect immediately, and is reset
+    /// when loading or unloading a clip. An empty envelope resets the factor to 1.0.
+    ///
+    /// The breakpoints need to be sorted by time, and the factors need to be 0 or greater.
+    pub fn set_amplitude_envelope(
+        &mut self,
+        envelope: Vec<AmplitudeBreakpoint>,
+    ) -> Result<(), Error> {
+        if !self.clip_loaded {
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to set amplitude envelope, no clip loaded.",
+            ));
+        }
+        let sorted = envelope.windows(2).all(|pair| pair[0].time <= pair[1].time);
+        let valid_factors = envelope
+            .iter()
+            .all(|breakpoint| breakpoint.amplitude.is_finite() && breakpoint.amplitude >= 0.0);
+        if !sorted || !valid_factors {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                "Unable to set amplitude envelope, breakpoints need to be sorted by time and have \
+                 an amplitude of 0 or greater",
+            ));
+        }
+        self.send_command(
+            PlayerCommand::SetAmplitudeEnvelope { envelope },
+            "SetAmplitudeEnvelope",
+        )
+    }
+
+    /// Sets how amplitudes that exceed 1.0 after amplitude multiplication are limited.
+    ///
+    /// By default, such amplitudes are clipped hard to 1.0. The limiter is sent along with the
+    /// multiplication factor, so it takes effect with the next call to
+    /// `set_amplitude_multiplication()`, and is kept when loading another clip.
+    pub fn set_amplitude_limiter(&mut self, limiter: AmplitudeLimiter) -> Result<(), Error> {
+        limiter.validate()?;
+        self.amplitude_limiter = limiter;
+        Ok(())
+    }
+
+    /// Sets how long the streaming thread sleeps while no clip is playing, in seconds, after
+    /// which the `idle` callback is called.
+    ///
+    /// Commands always wake up the streaming thread right away, so this is only needed for
+    /// periodic housekeeping. With None, the thread sleeps for a very long time, which is the
+    /// default. The timeout needs to be a finite number greater than 0.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<f32>) -> Result<(), Error> {
+        let idle_timeout = match idle_timeout {
+            None => DEFAULT_TIME_TO_SLEEP,
+            Some(idle_timeout) if idle_timeout.is_finite() && idle_timeout > 0.0 => idle_timeout,
+            Some(idle_timeout) => {
+                return Err(Error::with_kind(
+                    ErrorKind::InvalidArgument,
+                    &format!(
+                        "Unable to set idle timeout {}, needs to be a finite number greater than 0",
+                        idle_timeout
+                    ),
+                ))
+            }
+        };
+        self.send_command(
+            PlayerCommand::SetIdleTimeout { idle_timeout },
+            "SetIdleTimeout",
+        )
+    }
+
+    /// Returns the current playback position, as number of seconds from the beginning of the
+    /// clip.
+    ///
+    /// None is returned if no clip is playing. The position can be negative if playback
+    /// is pending after seeking to a negative time.
+    ///
+    /// The position is queried from the streaming thread, so this call blocks until the
+    /// streaming thread has processed all previously sent commands.
+    pub fn get_playback_position(&self) -> Option<f32> {
+        let (response_sender, response_receiver) = crossbeam_channel::bounded(1);
+        let query = PlayerCommand::QueryPosition {
+            response: response_sender,
+        };
+        if let Err(err) = self.send_command(query, "QueryPosition") {
+            log::error!("{}", err);
+            return None;
+        }
+        response_receiver.recv().unwrap_or_else(|err| {
+            log::error!("Unable to receive playback position: {}", err);
+            None
+        })
+    }
+
     fn send_command(&self, command: PlayerCommand, command_name: &str) -> Result<(), Error> {
         self.sender.send(command).map_err(|e| {
-            Error::new(&format!(
-                "Unable to send \"{}\" command to streaming thread: {}",
-                command_name, e
-            ))
+            Error::with_kind(
+                ErrorKind::PlaybackFailed,
+                &format!(
+                    "Unable to send \"{}\" command to streaming thread: {}",
+                    command_name, e
+                ),
+            )
         })
     }
 }
 
 impl crate::PreAuthoredClipPlayback for Player {
     fn load(&mut self, data_model: datamodel::v1::DataModel) -> Result<(), Error> {
+        let clip_duration = data_model.duration();
         self.send_command(PlayerCommand::Load(data_model), "Load")?;
         self.clip_loaded = true;
+        self.clip_duration = clip_duration;
         Ok(())
     }
 
     fn unload(&mut self) -> Result<(), Error> {
         self.send_command(PlayerCommand::Unload, "Unload")?;
         self.clip_loaded = false;
+        self.clip_duration = 0.0;
         Ok(())
     }
 
     fn play(&mut self) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to play, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to play, no clip loaded.",
+            ));
         }
         self.send_command(PlayerCommand::Play, "Play")
     }
 
+    fn play_at(&mut self, offset: f32) -> Result<(), Error> {
+        if !self.clip_loaded {
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to play, no clip loaded.",
+            ));
+        }
+        self.send_command(PlayerCommand::PlayAt { offset }, "PlayAt")
+    }
+
     fn stop(&mut self) -> Result<(), Error> {
         if self.clip_loaded {
             self.send_command(PlayerCommand::Stop, "Stop")
@@ -451,20 +944,29 @@ impl crate::PreAuthoredClipPlayback for Player {
 
     fn seek(&mut self, seek_time: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to seek, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to seek, no clip loaded.",
+            ));
         }
         self.send_command(PlayerCommand::Seek { seek_time }, "Seek")
     }
 
+    fn duration(&self) -> f32 {
+        self.clip_duration
+    }
+
     fn set_amplitude_multiplication(&mut self, multiplication_factor: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new(
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
                 "Unable to set amplitude multiplication, no clip loaded.",
             ));
         }
         self.send_command(
             PlayerCommand::SetAmplitudeMultiplication {
                 multiplication_factor,
+                limiter: self.amplitude_limiter,
             },
             "SetAmplitudeMultiplication",
         )
@@ -472,7 +974,10 @@ impl crate::PreAuthoredClipPlayback for Player {
 
     fn set_frequency_shift(&mut self, shift: f32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to set frequency shift, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to set frequency shift, no clip loaded.",
+            ));
         }
 
         self.send_command(
@@ -481,11 +986,44 @@ impl crate::PreAuthoredClipPlayback for Player {
         )
     }
 
-    fn set_looping(&mut self, enabled: bool) -> Result<(), Error> {
+    fn set_playback_rate(&mut self, rate: f32) -> Result<(), Error> {
+        crate::check_playback_rate(rate)?;
+        self.send_command(PlayerCommand::SetPlaybackRate { rate }, "SetPlaybackRate")
+    }
+
+    fn set_loop_count(&mut self, count: u32) -> Result<(), Error> {
         if !self.clip_loaded {
-            return Err(Error::new("Unable to loop, no clip loaded."));
+            return Err(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Unable to loop, no clip loaded.",
+            ));
+        }
+        self.send_command(PlayerCommand::Loop { count }, "Loop")
+    }
+
+    /// Queries the playback state from the streaming thread, blocking until the streaming thread
+    /// has processed all previously sent commands.
+    fn playback_state(&self) -> PlaybackState {
+        let (response_sender, response_receiver) = crossbeam_channel::bounded(1);
+        let query = PlayerCommand::QueryState {
+            response: response_sender,
+        };
+        if let Err(err) = self.send_command(query, "QueryState") {
+            log::error!("{}", err);
+            return PlaybackState::Stopped;
+        }
+        response_receiver.recv().unwrap_or_else(|err| {
+            log::error!("Unable to receive playback state: {}", err);
+            PlaybackState::Stopped
+        })
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        crate::Capabilities {
+            player_name: "Streaming player",
+            frequency_modulation: true,
+            emphasis: true,
         }
-        self.send_command(PlayerCommand::Loop { enabled }, "Loop")
     }
 }
 
@@ -660,6 +1198,36 @@ mod tests {
         );
     }
 
+    // Checks a clip that contains only one amplitude breakpoint, which is not at time 0.
+    // There is no previous breakpoint to ramp from, so the amplitude ramps up from 0 at the start
+    // of the clip, also when seeking into the ramp.
+    #[test]
+    fn test_single_amplitude_breakpoint_not_at_time_0() {
+        test_utils::init_logging();
+        #[rustfmt::skip]
+        test_utils::compare_events(
+            "single_amp_bp_not_time_0.haptic",
+            &[
+                amp(0.0, 0.2, 0.5),
+                amp(0.2, 0.0, 0.0),
+            ],
+        );
+
+        #[rustfmt::skip]
+        test_utils::compare_seek_events(
+            "single_amp_bp_not_time_0.haptic",
+            &[
+                amp(0.0, 0.2, 0.5),
+            ],
+            0.1,
+            &[
+                amp(0.1, 0.1, 0.5),
+                amp(0.2, 0.0, 0.0),
+            ],
+            false,
+        );
+    }
+
     // Checks a clip that contains only one amplitude breakpoint, but many
     // frequency breakpoints.
     #[test]
@@ -729,6 +1297,7 @@ mod tests {
                 amp(0.0, 0.0, 0.1),
                 amp(0.0, 0.1, 0.2),
                 amp(0.1, 0.2, 0.05),
+                freq(0.3, 0.0, 0.3),
                 emp(0.3, 0.0, 0.0, 0.6, 0.3),
             ],
         );
@@ -741,6 +1310,7 @@ mod tests {
         test_utils::compare_events(
             "emphasis_at_start.haptic",
             &[
+                freq(0.0, 0.0, 0.3),
                 amp(0.0, 0.0, 0.1),
                 emp(0.0, 0.1, 0.2, 0.6, 0.3),
                 amp(0.1, 0.2, 0.05),
@@ -1037,7 +1607,7 @@ mod tests {
 
         let clip = test_utils::load_file_from_test_data("normal.haptic");
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
         recorder.player().seek(0.25).unwrap();
 
@@ -1060,6 +1630,65 @@ mod tests {
         test_utils::print_timing_errors(&mut recorder, "normal.haptic");
     }
 
+    // Tests that each recorded event comes with the time it was delivered at, in the order of
+    // delivery
+    #[test]
+    fn recorded_events_with_timing() {
+        test_utils::init_logging();
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().load(clip).unwrap();
+        recorder.clear_recording_data(0.0);
+        recorder.player().play().unwrap();
+        std::thread::sleep(Duration::from_secs_f32(0.6));
+
+        let events_with_timing = recorder.recorded_events_with_timing();
+        assert_eq!(
+            events_with_timing
+                .iter()
+                .map(|(event, _)| *event)
+                .collect::<Vec<Event>>(),
+            recorder.recorded_events()
+        );
+        assert!(events_with_timing
+            .windows(2)
+            .all(|pair| pair[0].1 <= pair[1].1));
+
+        recorder.clear_recording_data(0.0);
+        assert!(recorder.recorded_events_with_timing().is_empty());
+    }
+
+    // Tests that play_at() starts playback at the given offset, like seek() followed by play()
+    #[test]
+    fn play_at() {
+        test_utils::init_logging();
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().load(clip).unwrap();
+        recorder.clear_recording_data(0.25);
+        recorder.player().play_at(0.25).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(0.6));
+
+        let expected_events = [
+            amp(0.25, 0.0, 0.25),
+            amp(0.25, 0.05, 0.2),
+            freq(0.25, 0.0, 0.7),
+            freq(0.25, 0.05, 0.6),
+            amp(0.3, 0.0, 0.0),
+        ];
+        assert_eq!(recorder.recorded_events(), expected_events);
+        test_utils::print_timing_errors(&mut recorder, "normal.haptic");
+
+        recorder.player().unload().unwrap();
+        assert_eq!(
+            recorder.player().play_at(0.25).unwrap_err().kind,
+            ErrorKind::NoClipLoaded
+        );
+    }
+
     // Tests that calling play() after playback has completely finished will restart the
     // playback from the beginning
     #[test]
@@ -1080,7 +1709,7 @@ mod tests {
             amp(0.3, 0.0, 0.0),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         recorder.player().play().unwrap();
@@ -1103,7 +1732,7 @@ mod tests {
 
         let clip = test_utils::load_file_from_test_data("normal.haptic");
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         recorder.player().play().unwrap();
@@ -1137,7 +1766,7 @@ mod tests {
             amp(0.3, 0.0, 0.0),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         recorder.player().play().unwrap();
@@ -1183,29 +1812,358 @@ mod tests {
         );
     }
 
-    // Verifies that stopping a clip works
+    // Tests that a preroll doesn't change the events, only sends them earlier
     #[test]
-    fn stop() {
+    fn preroll() {
         test_utils::init_logging();
 
-        // This test relies on timing and is too flaky on the CI
-        if !test_utils::ENABLE_TIMING_DEPENDENT_TESTS {
-            return;
+        let lead = Duration::from_millis(100);
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().set_preroll(lead).unwrap();
+        recorder.player().load(clip).unwrap();
+        recorder.clear_recording_data(0.0);
+        recorder.player().play().unwrap();
+        std::thread::sleep(Duration::from_secs_f32(0.6));
+
+        assert_eq!(
+            recorder.recorded_events(),
+            &[
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                amp(0.1, 0.1, 0.3),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.2),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+
+        if test_utils::ENABLE_TIMING_DEPENDENT_TESTS {
+            // Each event needs to be delivered `lead` before its scheduled time, or right away for
+            // the events scheduled before `lead`. The upper bound allows for half of `lead` of
+            // scheduling delay, which without a preroll would already fail for all events after
+            // `lead`.
+            let lead = lead.as_secs_f32();
+            for (event, delivery_time) in recorder.recorded_events_with_timing() {
+                let delivery_time = delivery_time.as_secs_f32();
+                let expected_delivery_time = (event.time() - lead).max(0.0);
+                assert!(
+                    delivery_time >= expected_delivery_time - 0.01,
+                    "{:?} delivered too early at {}s",
+                    event,
+                    delivery_time
+                );
+                assert!(
+                    delivery_time < expected_delivery_time + lead / 2.0,
+                    "{:?} delivered too late at {}s",
+                    event,
+                    delivery_time
+                );
+            }
         }
+    }
+
+    // Tests that the playback position is only available while the clip is playing
+    #[test]
+    fn playback_position() {
+        test_utils::init_logging();
 
         let clip = test_utils::load_file_from_test_data("normal.haptic");
-        let mut recorder = test_utils::PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        assert_eq!(recorder.player().get_playback_position(), None);
+
         recorder.player().load(clip.clone()).unwrap();
+        assert_eq!(recorder.player().get_playback_position(), None);
 
-        // Play for 150ms, which should play out 6 of the events
         recorder.player().play().unwrap();
-        std::thread::sleep(Duration::from_millis(150));
-        assert_eq!(recorder.recorded_events().len(), 6);
+        let position = recorder.player().get_playback_position().unwrap();
+        assert!(position >= 0.0);
 
-        // Stop the clip and wait for a bit. The only event that should be played
-        // is the event that ramps down the amplitude to zero
         recorder.player().stop().unwrap();
-        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(recorder.player().get_playback_position(), None);
+
+        // Seeking moves the playback position
+        recorder.player().seek(0.2).unwrap();
+        recorder.player().play().unwrap();
+        let position = recorder.player().get_playback_position().unwrap();
+        assert!(position >= 0.2);
+
+        // The playback position is None again once the clip finished playing
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(recorder.player().get_playback_position(), None);
+    }
+
+    // Verifies that pausing and resuming continues playback where it was paused
+    #[test]
+    fn pause_resume() {
+        test_utils::init_logging();
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().pause().unwrap_err();
+        recorder.player().resume().unwrap_err();
+
+        recorder.player().load(clip.clone()).unwrap();
+
+        // Pausing and resuming while not playing does nothing
+        recorder.player().pause().unwrap();
+        recorder.player().resume().unwrap();
+        assert_eq!(recorder.player().get_playback_position(), None);
+
+        // This part of the test relies on timing and is too flaky on the CI
+        if !test_utils::ENABLE_TIMING_DEPENDENT_TESTS {
+            return;
+        }
+
+        // Play for 150ms, which should play out 6 of the events
+        recorder.player().play().unwrap();
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(recorder.recorded_events().len(), 6);
+
+        // Pause the clip and wait for a bit. The only event that should be played
+        // is the event that ramps down the amplitude to zero
+        recorder.player().pause().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(recorder.recorded_events().len(), 7);
+        assert_eq!(recorder.player().get_playback_position(), None);
+
+        // Resuming continues at the paused position, and plays the remaining events
+        recorder.player().resume().unwrap();
+        let position = recorder.player().get_playback_position().unwrap();
+        assert!((0.15..0.2).contains(&position), "{}", position);
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(
+            *recorder.recorded_events().last().unwrap(),
+            amp(0.3, 0.0, 0.0)
+        );
+        assert_eq!(recorder.player().get_playback_position(), None);
+    }
+
+    // Tests that the playback state follows playing, stopping and finishing the clip
+    #[test]
+    fn playback_state() {
+        test_utils::init_logging();
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Stopped);
+
+        recorder.player().load(clip.clone()).unwrap();
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Stopped);
+
+        recorder.player().play().unwrap();
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Playing);
+
+        recorder.player().stop().unwrap();
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Stopped);
+
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Finished);
+
+        // Looping clips never finish
+        recorder.player().set_looping(true).unwrap();
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Playing);
+
+        recorder.player().unload().unwrap();
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Stopped);
+    }
+
+    // Tests that commands are described for tracing without the contents of the clip
+    #[test]
+    fn command_description() {
+        let clip = test_utils::load_file_from_test_data("normal_with_1_emphasis.haptic");
+        assert_eq!(
+            PlayerCommand::Load(clip).to_string(),
+            "Load(amplitude_breakpoints: 4, frequency_breakpoints: 5, emphasis: 1)"
+        );
+        assert_eq!(
+            PlayerCommand::Seek { seek_time: 0.5 }.to_string(),
+            "Seek(seek_time: 0.5)"
+        );
+        assert_eq!(
+            PlayerCommand::SetAmplitudeMultiplication {
+                multiplication_factor: 2.0,
+                limiter: AmplitudeLimiter::HardClip,
+            }
+            .to_string(),
+            "SetAmplitudeMultiplication(multiplication_factor: 2, limiter: HardClip)"
+        );
+        assert_eq!(
+            PlayerCommand::SetAmplitudeEnvelope {
+                envelope: vec![
+                    AmplitudeBreakpoint::new(0.0, 0.5),
+                    AmplitudeBreakpoint::new(0.1, 1.0),
+                ],
+            }
+            .to_string(),
+            "SetAmplitudeEnvelope(breakpoints: 2)"
+        );
+    }
+
+    // Tests that resetting the runtime parameters plays the clip as authored
+    #[test]
+    fn reset_runtime_params() {
+        test_utils::init_logging();
+        let clip_filename = "normal_with_1_emphasis.haptic";
+        let clip = test_utils::load_file_from_test_data(clip_filename);
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().reset_runtime_params().unwrap_err();
+
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_amplitude_multiplication(2.0).unwrap();
+        recorder.player().set_frequency_shift(0.1).unwrap();
+        recorder.player().reset_runtime_params().unwrap();
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        test_utils::print_timing_errors(&mut recorder, clip_filename);
+        assert_eq!(
+            recorder.recorded_events(),
+            &[
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                emp(0.1, 0.1, 0.3, 0.6, 0.3),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.2),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+    }
+
+    // Tests that setting an unchanged amplitude multiplication or frequency shift, and seeking to
+    // the current position, don't send any events in addition to the ones of the clip
+    #[test]
+    fn repeated_unchanged_commands() {
+        test_utils::init_logging();
+        let clip_filename = "normal_with_1_emphasis.haptic";
+        let clip = test_utils::load_file_from_test_data(clip_filename);
+
+        let mut provider = HapticEventProvider::new(clip.clone());
+        provider.set_amplitude_multiplication(2.0);
+        provider.set_frequency_shift(0.2);
+        let expected_events = test_utils::gather_events_from_provider(&mut provider, None);
+
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_amplitude_multiplication(2.0).unwrap();
+        recorder.player().set_frequency_shift(0.2).unwrap();
+        recorder.player().play().unwrap();
+        recorder.player().seek(0.0).unwrap();
+        for _ in 0..10 {
+            recorder.player().set_amplitude_multiplication(2.0).unwrap();
+            recorder.player().set_frequency_shift(0.2).unwrap();
+        }
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        test_utils::print_timing_errors(&mut recorder, clip_filename);
+        assert_eq!(recorder.recorded_events(), expected_events);
+    }
+
+    // Tests that the modulation set for a clip doesn't leak into the next clip loaded after
+    // unloading, and that the new clip is played as authored
+    #[test]
+    fn load_after_unload_resets_modulation() {
+        test_utils::init_logging();
+        let clip_filename = "normal_with_1_emphasis.haptic";
+        let clip = test_utils::load_file_from_test_data(clip_filename);
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+
+        recorder
+            .player()
+            .load(test_utils::load_file_from_test_data("valid_v1.haptic"))
+            .unwrap();
+        recorder.player().set_amplitude_multiplication(2.0).unwrap();
+        recorder.player().set_frequency_shift(0.1).unwrap();
+        recorder.player().unload().unwrap();
+        recorder
+            .player()
+            .set_amplitude_multiplication(2.0)
+            .unwrap_err();
+
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        test_utils::print_timing_errors(&mut recorder, clip_filename);
+        assert_eq!(
+            recorder.recorded_events(),
+            &[
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                emp(0.1, 0.1, 0.3, 0.6, 0.3),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.2),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+    }
+
+    // Tests that loading a clip resets the loop count of the previous clip, so that the new
+    // clip is played only once
+    #[test]
+    fn load_resets_loop_count() {
+        test_utils::init_logging();
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_loop_count(3).unwrap();
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 4);
+        assert_eq!(
+            recorder.recorded_events(),
+            &[
+                amp(0.0, 0.0, 0.1),
+                amp(0.0, 0.1, 0.2),
+                freq(0.0, 0.0, 0.95),
+                freq(0.0, 0.1, 0.9),
+                amp(0.1, 0.1, 0.3),
+                freq(0.1, 0.1, 0.8),
+                amp(0.2, 0.1, 0.2),
+                freq(0.2, 0.05, 0.7),
+                freq(0.25, 0.05, 0.6),
+                amp(0.3, 0.0, 0.0),
+            ]
+        );
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Finished);
+    }
+
+    // Verifies that stopping a clip works
+    #[test]
+    fn stop() {
+        test_utils::init_logging();
+
+        // This test relies on timing and is too flaky on the CI
+        if !test_utils::ENABLE_TIMING_DEPENDENT_TESTS {
+            return;
+        }
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let mut recorder = test_utils::PlayerEventRecorder::new().unwrap();
+        recorder.player().load(clip.clone()).unwrap();
+
+        // Play for 150ms, which should play out 6 of the events
+        recorder.player().play().unwrap();
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(recorder.recorded_events().len(), 6);
+
+        // Stop the clip and wait for a bit. The only event that should be played
+        // is the event that ramps down the amplitude to zero
+        recorder.player().stop().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
         assert_eq!(recorder.recorded_events().len(), 7);
         let ramp_down_event = *recorder.recorded_events().last().unwrap();
         let expected_event = amp(0.3, 0.0, 0.0);
@@ -1225,6 +2183,9 @@ mod tests {
             amplitude_event: Box::new(|_| {}),
             frequency_event: Box::new(|_| {}),
             init_thread: Box::new(|| {}),
+            clip_finished: None,
+            idle: None,
+            loop_iteration: None,
         };
         let mut player = Player::new(callbacks).unwrap();
         player.stop().unwrap();
@@ -1239,12 +2200,152 @@ mod tests {
             amplitude_event: Box::new(|_| {}),
             frequency_event: Box::new(|_| {}),
             init_thread: Box::new(|| {}),
+            clip_finished: None,
+            idle: None,
+            loop_iteration: None,
         };
         let mut player = Player::new(callbacks).unwrap();
         player.load(clip).unwrap();
         player.stop().unwrap();
     }
 
+    // Verifies that the clip_finished callback is called once when playback reaches the end,
+    // but not when stopping or at the end of each repetition of a looping clip
+    #[test]
+    fn clip_finished_callback() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        test_utils::init_logging();
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let finished_count = Arc::new(AtomicUsize::new(0));
+        let finished_count_clone = finished_count.clone();
+        let callbacks = Callbacks {
+            amplitude_event: Box::new(|_| {}),
+            frequency_event: Box::new(|_| {}),
+            init_thread: Box::new(|| {}),
+            clip_finished: Some(Box::new(move || {
+                finished_count_clone.fetch_add(1, Ordering::SeqCst);
+            })),
+            idle: None,
+            loop_iteration: None,
+        };
+        let mut player = Player::new(callbacks).unwrap();
+        player.load(clip.clone()).unwrap();
+
+        player.play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(finished_count.load(Ordering::SeqCst), 1);
+
+        // Test: Stopping doesn't count as finishing
+        player.play().unwrap();
+        player.stop().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(finished_count.load(Ordering::SeqCst), 1);
+
+        // Test: A clip played 3 times finishes only once
+        player.set_loop_count(3).unwrap();
+        player.play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 5);
+        assert_eq!(finished_count.load(Ordering::SeqCst), 2);
+
+        // Test: Unloading doesn't count as finishing
+        player.play().unwrap();
+        player.unload().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert_eq!(finished_count.load(Ordering::SeqCst), 2);
+    }
+
+    // Verifies that the loop_iteration callback is called with an incrementing index each time a
+    // looping clip restarts, but not when playback starts
+    #[test]
+    fn loop_iteration_callback() {
+        use std::sync::{Arc, Mutex};
+
+        if !test_utils::ENABLE_TIMING_DEPENDENT_TESTS {
+            return;
+        }
+
+        test_utils::init_logging();
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let iterations = Arc::new(Mutex::new(Vec::new()));
+        let iterations_clone = iterations.clone();
+        let callbacks = Callbacks {
+            amplitude_event: Box::new(|_| {}),
+            frequency_event: Box::new(|_| {}),
+            init_thread: Box::new(|| {}),
+            clip_finished: None,
+            idle: None,
+            loop_iteration: Some(Box::new(move |iteration| {
+                iterations_clone.lock().unwrap().push(iteration);
+            })),
+        };
+        let mut player = Player::new(callbacks).unwrap();
+        player.load(clip.clone()).unwrap();
+
+        // Test: Without looping, the callback isn't called
+        player.play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        assert!(iterations.lock().unwrap().is_empty());
+
+        // Test: A clip played 3 times restarts twice
+        player.set_loop_count(3).unwrap();
+        player.play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 5);
+        assert_eq!(*iterations.lock().unwrap(), vec![1, 2]);
+
+        // Test: The index starts again when playing again
+        iterations.lock().unwrap().clear();
+        player.play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 5);
+        assert_eq!(*iterations.lock().unwrap(), vec![1, 2]);
+    }
+
+    // Verifies that a short idle timeout wakes up the streaming thread periodically while no
+    // clip is loaded, and that resetting it stops the wakeups
+    #[test]
+    fn idle_timeout() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        test_utils::init_logging();
+        let idle_count = Arc::new(AtomicUsize::new(0));
+        let idle_count_clone = idle_count.clone();
+        let callbacks = Callbacks {
+            amplitude_event: Box::new(|_| {}),
+            frequency_event: Box::new(|_| {}),
+            init_thread: Box::new(|| {}),
+            clip_finished: None,
+            idle: Some(Box::new(move || {
+                idle_count_clone.fetch_add(1, Ordering::SeqCst);
+            })),
+            loop_iteration: None,
+        };
+        let mut player = Player::new(callbacks).unwrap();
+        assert_eq!(
+            player.set_idle_timeout(Some(0.0)).unwrap_err().kind,
+            ErrorKind::InvalidArgument
+        );
+
+        // Test: Without an idle timeout, the thread doesn't wake up
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(idle_count.load(Ordering::SeqCst), 0);
+
+        player.set_idle_timeout(Some(0.01)).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(idle_count.load(Ordering::SeqCst) >= 3);
+
+        // Test: Going back to the default stops the wakeups
+        player.set_idle_timeout(None).unwrap();
+        let count = idle_count.load(Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(idle_count.load(Ordering::SeqCst) <= count + 1);
+    }
+
     // Verifies that unloading a clip stops playback.
     // Works the same way as the stop() test.
     #[test]
@@ -1257,7 +2358,7 @@ mod tests {
         }
 
         let clip = test_utils::load_file_from_test_data("normal.haptic");
-        let mut recorder = test_utils::PlayerEventRecorder::new();
+        let mut recorder = test_utils::PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip.clone()).unwrap();
 
         recorder.player().play().unwrap();
@@ -1289,7 +2390,7 @@ mod tests {
         }
 
         let first_clip = test_utils::load_file_from_test_data("normal.haptic");
-        let mut recorder = test_utils::PlayerEventRecorder::new();
+        let mut recorder = test_utils::PlayerEventRecorder::new().unwrap();
         recorder.player().load(first_clip.clone()).unwrap();
 
         recorder.player().play().unwrap();
@@ -1346,7 +2447,55 @@ mod tests {
         //
         // Test Player
         //
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_amplitude_multiplication(2.0).unwrap();
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 2);
+        test_utils::print_timing_errors(&mut recorder, clip_filename);
+        assert_eq!(recorder.recorded_events(), expected_events);
+    }
+
+    // Same as test_amplitude_multiplication(), but with a soft knee limiter that compresses
+    // the boosted amplitudes instead of clipping them.
+    #[test]
+    fn test_amplitude_limiter() {
+        test_utils::init_logging();
+        let clip_filename = "normal_with_1_emphasis.haptic";
+        let clip = test_utils::load_file_from_test_data(clip_filename);
+        let limiter = AmplitudeLimiter::SoftKnee {
+            threshold: 0.5,
+            ratio: 1.0,
+        };
+
+        let mut provider = HapticEventProvider::new(clip.clone());
+        provider.set_amplitude_multiplication(2.0);
+        provider.set_amplitude_limiter(limiter);
+        let expected_events = test_utils::gather_events_from_provider(&mut provider, None);
+        let amplitude_events: Vec<_> = expected_events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Amplitude(event) => Some(*event),
+                Event::Frequency(_) => None,
+            })
+            .collect();
+        utils::assert_near!(amplitude_events[0].amplitude, 0.4, 1e-5);
+        utils::assert_near!(
+            amplitude_events[1].amplitude,
+            0.5 + 0.5 * 0.6_f32.tanh(),
+            1e-5
+        );
+        assert!(amplitude_events[2].emphasis.amplitude < 1.0);
+
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder
+            .player()
+            .set_amplitude_limiter(AmplitudeLimiter::SoftKnee {
+                threshold: 1.0,
+                ratio: 1.0,
+            })
+            .unwrap_err();
+        recorder.player().set_amplitude_limiter(limiter).unwrap();
         recorder.player().load(clip.clone()).unwrap();
         recorder.player().set_amplitude_multiplication(2.0).unwrap();
         recorder.player().play().unwrap();
@@ -1386,7 +2535,7 @@ mod tests {
         //
         // Test Player
         //
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip.clone()).unwrap();
         recorder.player().set_frequency_shift(0.2).unwrap();
         recorder.player().play().unwrap();
@@ -1437,7 +2586,7 @@ mod tests {
             amp(0.3, 0.0, 0.0),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         //
@@ -1505,7 +2654,7 @@ mod tests {
             amp(0.3, 0.0, 0.0),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         //
@@ -1543,6 +2692,43 @@ mod tests {
         );
     }
 
+    // Tests that a playback rate of 2.0 sends the same events with half the duration, and that
+    // the whole clip is played in half the time.
+    #[test]
+    fn test_playback_rate() {
+        test_utils::init_logging();
+        let clip_filename = "normal.haptic";
+        let clip = test_utils::load_file_from_test_data(clip_filename);
+        let expected_events = &[
+            amp(0.0, 0.0, 0.1),
+            amp(0.0, 0.05, 0.2),
+            freq(0.0, 0.0, 0.95),
+            freq(0.0, 0.05, 0.9),
+            amp(0.1, 0.05, 0.3),
+            freq(0.1, 0.05, 0.8),
+            amp(0.2, 0.05, 0.2),
+            freq(0.2, 0.025, 0.7),
+            freq(0.25, 0.025, 0.6),
+            amp(0.3, 0.0, 0.0),
+        ];
+
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        for rate in &[0.0, -1.0, 1e-20, 1000.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                recorder.player().set_playback_rate(*rate).unwrap_err().kind,
+                ErrorKind::InvalidArgument
+            );
+        }
+
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_playback_rate(2.0).unwrap();
+        recorder.player().play().unwrap();
+
+        // At the original rate, the last event would only be sent after the full clip length
+        std::thread::sleep(test_utils::clip_length(&clip) * 3 / 4);
+        assert_eq!(recorder.recorded_events(), expected_events);
+    }
+
     // Verifies that enabling looping makes the playback repeat from the beginning when
     // the player reaches end
     // Should repeat at least 2 times
@@ -1590,7 +2776,7 @@ mod tests {
             freq(0.25, 0.05, 0.6),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
 
         recorder.player().set_looping(true).unwrap();
@@ -1607,6 +2793,42 @@ mod tests {
         assert_eq!(recorder.recorded_events(), expected_events);
     }
 
+    // Verifies that a finite loop count repeats the clip that many times before finishing
+    #[test]
+    fn loop_count() {
+        test_utils::init_logging();
+
+        let clip = test_utils::load_file_from_test_data("normal.haptic");
+        let clip_events = [
+            amp(0.0, 0.0, 0.1),
+            amp(0.0, 0.1, 0.2),
+            freq(0.0, 0.0, 0.95),
+            freq(0.0, 0.1, 0.9),
+            amp(0.1, 0.1, 0.3),
+            freq(0.1, 0.1, 0.8),
+            amp(0.2, 0.1, 0.2),
+            freq(0.2, 0.05, 0.7),
+            freq(0.25, 0.05, 0.6),
+            amp(0.3, 0.0, 0.0),
+        ];
+
+        let mut recorder = PlayerEventRecorder::new().unwrap();
+        recorder.player().set_loop_count(2).unwrap_err();
+        recorder.player().load(clip.clone()).unwrap();
+        recorder.player().set_loop_count(2).unwrap();
+
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 4);
+        assert_eq!(recorder.recorded_events(), clip_events.repeat(2));
+        assert_eq!(recorder.player().playback_state(), PlaybackState::Finished);
+
+        // Playing again starts counting from the beginning
+        recorder.clear_recording_data(0.0);
+        recorder.player().play().unwrap();
+        std::thread::sleep(test_utils::clip_length(&clip) * 4);
+        assert_eq!(recorder.recorded_events(), clip_events.repeat(2));
+    }
+
     // Verifies that enabling looping while the clip is playing makes the playback repeat from
     // the beginning when the player reaches end.
     // Should repeat at least 2 times.
@@ -1654,7 +2876,7 @@ mod tests {
             freq(0.25, 0.05, 0.6),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
         recorder.player().play().unwrap();
 
@@ -1700,7 +2922,7 @@ mod tests {
             amp(0.3, 0.0, 0.0),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
         recorder.player().set_looping(true).unwrap();
         recorder.player().play().unwrap();
@@ -1757,7 +2979,7 @@ mod tests {
             freq(0.25, 0.05, 0.6),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
         recorder.player().set_looping(true).unwrap();
         recorder.player().play().unwrap();
@@ -1830,7 +3052,7 @@ mod tests {
             freq(0.25, 0.05, 0.6),
         ];
 
-        let mut recorder = PlayerEventRecorder::new();
+        let mut recorder = PlayerEventRecorder::new().unwrap();
         recorder.player().load(clip).unwrap();
         recorder.player().set_looping(true).unwrap();
         recorder.player().play().unwrap();
@@ -2,20 +2,15 @@
 
 #![cfg(test)]
 
+pub use crate::recorder::PlayerEventRecorder;
 use crate::{
     haptic_event_provider::{AmplitudeEvent, Event, FrequencyEvent, HapticEventProvider},
-    streaming::Callbacks,
-    streaming::Player,
+    recorder::rounded_event,
     PreAuthoredClipPlayback,
 };
 use datamodel::v1::{DataModel, Emphasis};
 use env_logger::{Builder, Env};
-use std::{
-    path::Path,
-    sync::{Arc, Mutex},
-    time::Duration,
-    time::Instant,
-};
+use std::{path::Path, time::Duration};
 use utils::assert_near;
 use utils::test_utils::rounded_f32;
 
@@ -59,23 +54,6 @@ pub fn load_file_from_test_data(path: &str) -> DataModel {
     datamodel::latest_from_json(&clip).unwrap().1
 }
 
-// Similar to rounded_f32, only that it rounds all fields in an Event
-fn rounded_event(mut event: Event, decimal_places: u32) -> Event {
-    match &mut event {
-        Event::Amplitude(e) => {
-            e.amplitude = rounded_f32(e.amplitude, decimal_places);
-            e.time = rounded_f32(e.time, decimal_places);
-            e.duration = rounded_f32(e.duration, decimal_places);
-        }
-        Event::Frequency(e) => {
-            e.frequency = rounded_f32(e.frequency, decimal_places);
-            e.time = rounded_f32(e.time, decimal_places);
-            e.duration = rounded_f32(e.duration, decimal_places);
-        }
-    }
-    event
-}
-
 pub fn rounded_events(events: &[Event], decimal_places: u32) -> Vec<Event> {
     events
         .iter()
@@ -165,97 +143,6 @@ pub fn record_events_from_provider(clip_filename: &str) -> Vec<Event> {
     gather_events_from_provider(&mut provider, None)
 }
 
-struct RecordingData {
-    start_time: Instant,
-    events: Vec<Event>,
-    errors: Vec<f32>, // in seconds
-}
-
-// Records events that a Player provides, together with the timing errors.
-//
-// When the player invokes the callbacks, the event passed to the callbacks is recorded
-// into recording_data.
-pub struct PlayerEventRecorder {
-    recording_data: Arc<Mutex<RecordingData>>,
-    player: Player,
-}
-
-impl PlayerEventRecorder {
-    pub fn new() -> PlayerEventRecorder {
-        let recording_data = Arc::new(Mutex::new(RecordingData {
-            start_time: Instant::now(),
-            events: Vec::new(),
-            errors: Vec::new(),
-        }));
-
-        let recording_data_for_thread = recording_data.clone();
-        let amplitude_event_callback = move |event: AmplitudeEvent| {
-            let mut recording_data = recording_data_for_thread.lock().unwrap();
-            let now = Instant::now();
-
-            // If now is less than the recording start time (which can happen when seeking to a
-            // negative time), then we can ignore the event.
-            if now >= recording_data.start_time {
-                let actual_time_since_start = (now - recording_data.start_time).as_secs_f32();
-                let error = actual_time_since_start - event.time;
-                recording_data.errors.push(error);
-                recording_data
-                    .events
-                    .push(rounded_event(Event::Amplitude(event), 5));
-            }
-        };
-        let recording_data_for_thread = recording_data.clone();
-        let frequency_event_callback = move |event: FrequencyEvent| {
-            let mut recording_data = recording_data_for_thread.lock().unwrap();
-            let actual_time_since_start =
-                (Instant::now() - recording_data.start_time).as_secs_f32();
-            let error = actual_time_since_start - event.time;
-            recording_data.errors.push(error);
-            recording_data
-                .events
-                .push(rounded_event(Event::Frequency(event), 5));
-        };
-
-        (*recording_data.lock().unwrap()).start_time = Instant::now();
-        let callbacks = Callbacks {
-            amplitude_event: Box::new(amplitude_event_callback),
-            frequency_event: Box::new(frequency_event_callback),
-            init_thread: Box::new(|| {}),
-        };
-        let player = Player::new(callbacks).unwrap();
-        PlayerEventRecorder {
-            recording_data,
-            player,
-        }
-    }
-
-    pub fn recorded_events(&self) -> Vec<Event> {
-        let recording_data = self.recording_data.lock().unwrap();
-        recording_data.events.clone()
-    }
-
-    pub fn recorded_errors(&self) -> Vec<f32> {
-        let recording_data = self.recording_data.lock().unwrap();
-        recording_data.errors.clone()
-    }
-
-    pub fn clear_recording_data(&mut self, start_time_offset: f32) {
-        let mut recording_data = self.recording_data.lock().unwrap();
-        recording_data.events.clear();
-        recording_data.errors.clear();
-        recording_data.start_time = if start_time_offset >= 0.0 {
-            Instant::now() - Duration::from_secs_f32(start_time_offset)
-        } else {
-            // A negative start time means that playback will start in the future
-            Instant::now() + Duration::from_secs_f32(-start_time_offset)
-        }
-    }
-
-    pub fn player(&mut self) -> &mut Player {
-        &mut self.player
-    }
-}
-
 pub fn print_timing_errors(recorder: &mut PlayerEventRecorder, clip_filename: &str) {
     if ENABLE_TIMING_DEPENDENT_TESTS {
         let mut errors = recorder.recorded_errors();
@@ -289,7 +176,7 @@ pub fn print_timing_errors(recorder: &mut PlayerEventRecorder, clip_filename: &s
 // Also verifies that the events arrive roughly at the correct time.
 pub fn record_events_from_player(clip_filename: &str) -> Vec<Event> {
     let clip = load_file_from_test_data(clip_filename);
-    let mut recorder = PlayerEventRecorder::new();
+    let mut recorder = PlayerEventRecorder::new().unwrap();
     recorder.player().load(clip.clone()).unwrap();
     recorder.player().play().unwrap();
 
@@ -357,7 +244,7 @@ pub fn compare_seek_events_from_player(
     }
 
     let clip = load_file_from_test_data(clip_filename);
-    let mut recorder = PlayerEventRecorder::new();
+    let mut recorder = PlayerEventRecorder::new().unwrap();
     recorder.player().load(clip).unwrap();
     recorder.player().play().unwrap();
     if !expected_pre_seek_events.is_empty() {
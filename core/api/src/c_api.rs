@@ -16,7 +16,8 @@
 
 use {
     crate::c_errors::{
-        get_error_message, get_error_message_length, set_error, PARTIAL_VERSION_SUPPORT, SUCCESS,
+        get_error_code, get_error_message, get_error_message_length, set_error,
+        set_error_with_kind, PARTIAL_VERSION_SUPPORT, SUCCESS,
     },
     env_logger::Builder,
     lib::{
@@ -126,11 +127,14 @@ pub extern "C" fn lofelt_sdk_controller_create(
         amplitude_event: Box::new(play_streaming_amplitude_event),
         frequency_event: Box::new(play_streaming_frequency_event),
         init_thread: Box::new(init_thread),
+        clip_finished: None,
+        idle: None,
+        loop_iteration: None,
     });
     let player = match player {
         Ok(player) => player,
         Err(err) => {
-            set_error(format!("Unable to create clip player: {}", err));
+            set_error_with_kind(format!("Unable to create clip player: {}", err), err.kind);
             return std::ptr::null_mut();
         }
     };
@@ -171,16 +175,14 @@ pub unsafe extern "C" fn lofelt_sdk_controller_load(
     data_size_bytes: usize,
 ) -> c_int {
     let data = slice::from_raw_parts(data as *const u8, data_size_bytes);
-    let data = std::str::from_utf8(data);
-    let data = match data {
-        Ok(data) => data,
-        Err(err) => return set_error(format!("Haptic data is not valid UTF-8: {}", err)),
-    };
 
-    match controller.0.load(data) {
+    match controller.0.load_slice(data) {
         Ok(VersionSupport::Full) => SUCCESS,
         Ok(VersionSupport::Partial) => PARTIAL_VERSION_SUPPORT,
-        Err(error) => set_error(format!("Error loading haptic data: \n{}", error)),
+        Err(error) => set_error_with_kind(
+            format!("Error loading haptic data: \n{}", error),
+            error.kind,
+        ),
     }
 }
 
@@ -189,7 +191,10 @@ pub unsafe extern "C" fn lofelt_sdk_controller_load(
 pub unsafe extern "C" fn lofelt_sdk_controller_play(controller: &mut LofeltSdkController) -> c_int {
     match controller.0.play() {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!("Error playing haptic clip: \n{}", error)),
+        Err(error) => set_error_with_kind(
+            format!("Error playing haptic clip: \n{}", error),
+            error.kind,
+        ),
     }
 }
 
@@ -198,12 +203,18 @@ pub unsafe extern "C" fn lofelt_sdk_controller_play(controller: &mut LofeltSdkCo
 pub unsafe extern "C" fn lofelt_sdk_controller_stop(controller: &mut LofeltSdkController) -> c_int {
     match controller.0.stop() {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!("Error stopping haptic clip: \n{}", error)),
+        Err(error) => set_error_with_kind(
+            format!("Error stopping haptic clip: \n{}", error),
+            error.kind,
+        ),
     }
 }
 
 /// Jumps to a position in the haptic clip.
 ///
+/// A negative time delays the start of playback. A time that is not a finite number results in
+/// an error with `ERROR_CODE_INVALID_ARGUMENT`.
+///
 /// # Arguments
 /// * `time` - the new position within the clip, as seconds from the beginning of the clip
 #[no_mangle]
@@ -213,10 +224,13 @@ pub unsafe extern "C" fn lofelt_sdk_controller_seek(
 ) -> c_int {
     match controller.0.seek(time) {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!(
-            "Error seeking to position {:.3}s in haptic clip: \n{}",
-            time, error
-        )),
+        Err(error) => set_error_with_kind(
+            format!(
+                "Error seeking to position {:.3}s in haptic clip: \n{}",
+                time, error
+            ),
+            error.kind,
+        ),
     }
 }
 
@@ -234,10 +248,13 @@ pub unsafe extern "C" fn lofelt_sdk_controller_set_amplitude_multiplication(
         .set_amplitude_multiplication(amplitude_multiplication)
     {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!(
-            "Error setting amplitude multiplication to {:.2}: \n{}",
-            amplitude_multiplication, error
-        )),
+        Err(error) => set_error_with_kind(
+            format!(
+                "Error setting amplitude multiplication to {:.2}: \n{}",
+                amplitude_multiplication, error
+            ),
+            error.kind,
+        ),
     }
 }
 
@@ -252,10 +269,10 @@ pub unsafe extern "C" fn lofelt_sdk_controller_set_frequency_shift(
 ) -> c_int {
     match controller.0.set_frequency_shift(shift) {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!(
-            "Error setting frequency shift to {:.2}: \n{}",
-            shift, error
-        )),
+        Err(error) => set_error_with_kind(
+            format!("Error setting frequency shift to {:.2}: \n{}", shift, error),
+            error.kind,
+        ),
     }
 }
 
@@ -271,7 +288,10 @@ pub unsafe extern "C" fn lofelt_sdk_controller_loop(
 ) -> c_int {
     match controller.0.set_looping(enabled) {
         Ok(_) => SUCCESS,
-        Err(error) => set_error(format!("Error enabling loop for haptic clip: \n{}", error)),
+        Err(error) => set_error_with_kind(
+            format!("Error enabling loop for haptic clip: \n{}", error),
+            error.kind,
+        ),
     }
 }
 
@@ -297,6 +317,22 @@ pub extern "C" fn lofelt_sdk_get_error_message_length() -> c_int {
     get_error_message_length()
 }
 
+/// Returns the code of the kind of the last error, or 0 if there was no error yet.
+///
+/// The codes are:
+/// - 1: Other error
+/// - 2: The haptic data is invalid
+/// - 3: The version of the haptic data is not supported
+/// - 4: No clip is loaded
+/// - 5: Playback failed
+/// - 6: An argument is out of range
+///
+/// Like the error message, the code is only set for errors that happened on the calling thread.
+#[no_mangle]
+pub extern "C" fn lofelt_sdk_get_error_code() -> c_int {
+    get_error_code()
+}
+
 /// Writes the error message to the buffer that the client passes in.
 ///
 /// An error will cause ERROR to be returned.
@@ -344,6 +380,10 @@ mod tests {
             } else if lofelt_sdk_get_error_message_length() <= 0 {
                 panic!("Error message length should be > 0");
             } // TODO: Test getting error string with lofelt_sdk_get_error_message
+            assert_eq!(
+                lofelt_sdk_get_error_code(),
+                crate::c_errors::ERROR_CODE_NO_CLIP_LOADED
+            );
         }
     }
 
@@ -364,4 +404,81 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn check_errors_seek_and_loop() {
+        let callbacks = Callbacks {
+            play_streaming_amplitude_event: play_streaming_amplitude_event_dummy,
+            play_streaming_frequency_event: play_streaming_frequency_event_dummy,
+            init_thread: init_thread_dummy,
+        };
+        let controller = lofelt_sdk_controller_create(std::ptr::null_mut(), callbacks);
+        let data = include_bytes!("../../datamodel/src/test_data/valid_required_v1.haptic");
+        unsafe {
+            assert_eq!(
+                lofelt_sdk_controller_seek(&mut *controller, 0.1),
+                crate::c_errors::ERROR
+            );
+            assert_eq!(
+                lofelt_sdk_get_error_code(),
+                crate::c_errors::ERROR_CODE_NO_CLIP_LOADED
+            );
+            assert_eq!(
+                lofelt_sdk_controller_loop(&mut *controller, true),
+                crate::c_errors::ERROR
+            );
+            assert_eq!(
+                lofelt_sdk_get_error_code(),
+                crate::c_errors::ERROR_CODE_NO_CLIP_LOADED
+            );
+
+            assert_eq!(
+                lofelt_sdk_controller_load(
+                    &mut *controller,
+                    data.as_ptr() as *const c_char,
+                    data.len()
+                ),
+                SUCCESS
+            );
+            assert_eq!(lofelt_sdk_controller_seek(&mut *controller, -0.5), SUCCESS);
+            assert_eq!(lofelt_sdk_controller_loop(&mut *controller, true), SUCCESS);
+            assert_eq!(
+                lofelt_sdk_controller_seek(&mut *controller, f32::NAN),
+                crate::c_errors::ERROR
+            );
+            assert_eq!(
+                lofelt_sdk_get_error_code(),
+                crate::c_errors::ERROR_CODE_INVALID_ARGUMENT
+            );
+        }
+    }
+
+    #[test]
+    fn get_clip_duration() {
+        let callbacks = Callbacks {
+            play_streaming_amplitude_event: play_streaming_amplitude_event_dummy,
+            play_streaming_frequency_event: play_streaming_frequency_event_dummy,
+            init_thread: init_thread_dummy,
+        };
+        let controller = lofelt_sdk_controller_create(std::ptr::null_mut(), callbacks);
+        let data = include_bytes!("../../datamodel/src/test_data/valid_required_v1.haptic");
+        unsafe {
+            assert_eq!(
+                lofelt_sdk_controller_get_clip_duration(&mut *controller),
+                0.0
+            );
+            assert_eq!(
+                lofelt_sdk_controller_load(
+                    &mut *controller,
+                    data.as_ptr() as *const c_char,
+                    data.len()
+                ),
+                SUCCESS
+            );
+            assert_eq!(
+                lofelt_sdk_controller_get_clip_duration(&mut *controller),
+                0.3
+            );
+        }
+    }
 }
@@ -5,8 +5,9 @@
 //! Module for error handling of the Lofelt SDK Core library.
 //! A C-string conversion of the last error's message can be accessed via get_error_message().
 
+use lib::ErrorKind;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     os::raw::{c_char, c_int},
     ptr, slice,
 };
@@ -18,6 +19,16 @@ pub const ERROR: c_int = -1;
 /// features may not work.
 pub const PARTIAL_VERSION_SUPPORT: c_int = 1;
 
+// Codes for the kind of the last error, returned by get_error_code(). These values are part of
+// the C API, so existing values must not change.
+pub const ERROR_CODE_NONE: c_int = 0;
+pub const ERROR_CODE_OTHER: c_int = 1;
+pub const ERROR_CODE_INVALID_DATA: c_int = 2;
+pub const ERROR_CODE_UNSUPPORTED_VERSION: c_int = 3;
+pub const ERROR_CODE_NO_CLIP_LOADED: c_int = 4;
+pub const ERROR_CODE_PLAYBACK_FAILED: c_int = 5;
+pub const ERROR_CODE_INVALID_ARGUMENT: c_int = 6;
+
 thread_local! {
     // The last error that was passed into set__error().
     //
@@ -29,16 +40,42 @@ thread_local! {
     // A. Static values in Rust are immutable, so RefCell is used to provide 'interior mutability'.
     //    See: https://doc.rust-lang.org/book/ch15-05-interior-mutability.html
     static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+
+    // The code of the kind of the last error, see LAST_ERROR.
+    static LAST_ERROR_CODE: Cell<c_int> = Cell::new(ERROR_CODE_NONE);
+}
+
+// Returns the stable code of an error kind
+pub fn error_code(kind: ErrorKind) -> c_int {
+    match kind {
+        ErrorKind::Other => ERROR_CODE_OTHER,
+        ErrorKind::InvalidData => ERROR_CODE_INVALID_DATA,
+        ErrorKind::UnsupportedVersion => ERROR_CODE_UNSUPPORTED_VERSION,
+        ErrorKind::NoClipLoaded => ERROR_CODE_NO_CLIP_LOADED,
+        ErrorKind::PlaybackFailed => ERROR_CODE_PLAYBACK_FAILED,
+        ErrorKind::InvalidArgument => ERROR_CODE_INVALID_ARGUMENT,
+    }
 }
 
 // Caches the last error message encountered by the C API so that it can be inspected further.
 pub fn set_error(error: String) -> c_int {
+    set_error_with_kind(error, ErrorKind::Other)
+}
+
+// Like set_error(), but also caches the code of the error kind, see get_error_code().
+pub fn set_error_with_kind(error: String, kind: ErrorKind) -> c_int {
     LAST_ERROR.with(|last_error| {
         *last_error.borrow_mut() = Some(error);
     });
+    LAST_ERROR_CODE.with(|last_error_code| last_error_code.set(error_code(kind)));
     ERROR
 }
 
+// Returns the code of the kind of the last error, or ERROR_CODE_NONE if there was no error yet.
+pub fn get_error_code() -> c_int {
+    LAST_ERROR_CODE.with(|last_error_code| last_error_code.get())
+}
+
 // Returns the size of the buffer required by get_error_message().
 pub fn get_error_message_length() -> c_int {
     LAST_ERROR.with(|last_error| match last_error.borrow().as_ref() {
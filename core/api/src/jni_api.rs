@@ -11,7 +11,7 @@ use jni::{
     JNIEnv,
 };
 use lib::{
-    clip_players::android::{Callbacks, Player},
+    clip_players::android::{Callbacks, CompositionPrimitive, Player},
     HapticsController,
 };
 use log::{Level, LevelFilter};
@@ -36,6 +36,11 @@ pub struct LofeltSdkController(HapticsController);
 /// keeps hold of that handle to `HapticsController` and passes it to every function as a
 /// parameter. The functions then cast the `jlong` back to a `HapticsController` and uses it.
 ///
+/// A handle of 0, as returned by a failed `Java_com_lofelt_haptics_LofeltHaptics_create()`, is
+/// rejected by every function with an `ErrorKind::InvalidArgument` error. Non-null handles can't
+/// be validated, so the Java layer must not use a handle anymore after passing it to
+/// `Java_com_lofelt_haptics_LofeltHaptics_destroy()`, and resets it to 0 instead.
+///
 /// # Error handling
 ///
 /// ## Errors when Java called into Rust
@@ -129,8 +134,13 @@ fn handle_exception_from_call(
 }
 
 fn get_controller<'a>(controller_handle: jlong) -> Result<&'a mut HapticsController, lib::Error> {
+    // Only 0 is invalid, as create() returns it on failure. Other handles can be negative, as
+    // heap pointers on Android 11+ arm64 are tagged in their top byte.
     if controller_handle == 0 {
-        return Err(lib::Error::new("Controller is null"));
+        return Err(lib::Error::with_kind(
+            lib::ErrorKind::InvalidArgument,
+            "Invalid controller handle",
+        ));
     }
     let controller = unsafe { &mut *(controller_handle as *mut HapticsController) };
     Ok(controller)
@@ -151,7 +161,10 @@ fn create_inner(env: &JNIEnv, callback_object: JObject) -> Result<jlong, lib::Er
         // Create a closure that matches the signature needed by `Callbacks::new()`.
         // `jvm` and `callback_object_global_ref` are captured and therefore not part
         // of the signature.
-        move |timings: &[i64], amplitudes: &[i32], enabled: bool| -> Result<(), lib::Error> {
+        move |timings: &[i64],
+              amplitudes: &[i32],
+              repeat_index: Option<usize>|
+              -> Result<(), lib::Error> {
             // Get back the JNIEnv (or rather, a wrapper around it) from the JavaVM
             let env = jvm.attach_current_thread()?;
 
@@ -162,13 +175,20 @@ fn create_inner(env: &JNIEnv, callback_object: JObject) -> Result<jlong, lib::Er
             env.set_long_array_region(timings_java, 0, timings)?;
             env.set_int_array_region(amplitudes_java, 0, amplitudes)?;
 
+            // Like VibrationEffect.createWaveform(), the Java method uses -1 for not repeating
+            let repeat_index = repeat_index.map_or(-1, |index| index as i32);
+
             // Call the Java method.
             // See also http://journals.ecs.soton.ac.uk/java/tutorial/native1.1/implementing/method.html.
             let result = env.call_method(
                 &callback_object_global_ref,
                 "loadCallback",
-                "([J[IZ)V",
-                &[timings_java.into(), amplitudes_java.into(), enabled.into()],
+                "([J[II)V",
+                &[
+                    timings_java.into(),
+                    amplitudes_java.into(),
+                    repeat_index.into(),
+                ],
             );
             handle_exception_from_call(env, result)
         }
@@ -231,13 +251,82 @@ fn create_inner(env: &JNIEnv, callback_object: JObject) -> Result<jlong, lib::Er
         }
     };
 
-    let player = Player::new(Callbacks::new(
+    let load_composition_callback = {
+        let jvm = env.get_java_vm()?;
+        let callback_object_global_ref = env.new_global_ref(callback_object)?;
+
+        // JNI can't pass the primitives to Java directly, so they are flattened into arrays:
+        // For each primitive, its start time, its click scale and its number of waveform
+        // entries, followed by the entries of all waveform parts. Clicks have 0 waveform entries.
+        move |composition: &[CompositionPrimitive]| -> Result<(), lib::Error> {
+            let env = jvm.attach_current_thread()?;
+
+            let mut start_times = Vec::with_capacity(composition.len());
+            let mut click_scales = Vec::with_capacity(composition.len());
+            let mut entry_counts = Vec::with_capacity(composition.len());
+            let mut timings = Vec::new();
+            let mut amplitudes = Vec::new();
+            for primitive in composition {
+                match primitive {
+                    CompositionPrimitive::Click { start_ms, scale } => {
+                        start_times.push(*start_ms);
+                        click_scales.push(*scale);
+                        entry_counts.push(0);
+                    }
+                    CompositionPrimitive::Waveform { start_ms, waveform } => {
+                        start_times.push(*start_ms);
+                        click_scales.push(0.0);
+                        entry_counts.push(waveform.timings.len() as i32);
+                        timings.extend_from_slice(&waveform.timings);
+                        amplitudes.extend_from_slice(&waveform.amplitudes);
+                    }
+                }
+            }
+
+            let start_times_java = env.new_long_array(start_times.len() as i32)?;
+            let click_scales_java = env.new_float_array(click_scales.len() as i32)?;
+            let entry_counts_java = env.new_int_array(entry_counts.len() as i32)?;
+            let timings_java = env.new_long_array(timings.len() as i32)?;
+            let amplitudes_java = env.new_int_array(amplitudes.len() as i32)?;
+            env.set_long_array_region(start_times_java, 0, &start_times)?;
+            env.set_float_array_region(click_scales_java, 0, &click_scales)?;
+            env.set_int_array_region(entry_counts_java, 0, &entry_counts)?;
+            env.set_long_array_region(timings_java, 0, &timings)?;
+            env.set_int_array_region(amplitudes_java, 0, &amplitudes)?;
+
+            let result = env.call_method(
+                &callback_object_global_ref,
+                "loadCompositionCallback",
+                "([J[F[I[J[I)V",
+                &[
+                    start_times_java.into(),
+                    click_scales_java.into(),
+                    entry_counts_java.into(),
+                    timings_java.into(),
+                    amplitudes_java.into(),
+                ],
+            );
+            handle_exception_from_call(env, result)
+        }
+    };
+
+    let mut callbacks = Callbacks::new(
         load_callback,
         play_callback,
         stop_callback,
         unload_callback,
         seek_callback,
-    ))?;
+    );
+
+    // Only use compositions on devices that support the click primitive
+    let supports_composition = env
+        .call_method(callback_object, "supportsComposition", "()Z", &[])?
+        .z()?;
+    if supports_composition {
+        callbacks.load_composition = Some(Box::new(load_composition_callback));
+    }
+
+    let player = Player::new(callbacks)?;
     let controller = HapticsController::new(Box::new(player));
     let raw_controller_handle = Box::into_raw(Box::new(controller));
     Ok(raw_controller_handle as jlong)
@@ -279,7 +368,7 @@ pub extern "system" fn Java_com_lofelt_haptics_LofeltHaptics_create(
         Ok(controller_handle) => controller_handle,
         Err(err) => {
             throw_exception(&env, err);
-            -1
+            0
         }
     }
 }
@@ -314,12 +403,12 @@ pub fn load_direct_inner(
     clip_size_bytes: usize,
 ) -> Result<(), lib::Error> {
     let controller = &mut unsafe { controller.as_mut() }
-        .ok_or_else(|| lib::Error::new("Invalid controller handle"))?
+        .ok_or_else(|| {
+            lib::Error::with_kind(lib::ErrorKind::InvalidArgument, "Invalid controller handle")
+        })?
         .0;
     let clip = unsafe { slice::from_raw_parts(clip as *const u8, clip_size_bytes) };
-    let clip = std::str::from_utf8(clip)
-        .map_err(|err| lib::Error::new(&format!("Clip is not valid UTF-8: {}", err)))?;
-    controller.load(clip)?;
+    controller.load_slice(clip)?;
     Ok(())
 }
 
@@ -363,9 +452,7 @@ pub extern "system" fn lofeltHapticsLoadDirect(
 fn load_inner(env: &JNIEnv, controller_handle: jlong, clip: jbyteArray) -> Result<(), lib::Error> {
     let controller = get_controller(controller_handle)?;
     let clip = env.convert_byte_array(clip)?;
-    let clip = std::str::from_utf8(&clip)
-        .map_err(|err| lib::Error::new(&format!("Reading clip data as UTF-8 failed: {}", err)))?;
-    controller.load(clip)?;
+    controller.load_slice(&clip)?;
     Ok(())
 }
 
@@ -465,6 +552,28 @@ pub extern "system" fn Java_com_lofelt_haptics_LofeltHaptics_setAmplitudeMultipl
     }
 }
 
+fn set_frequency_shift_inner(controller_handle: jlong, shift: jfloat) -> Result<(), lib::Error> {
+    let controller = get_controller(controller_handle)?;
+    controller.set_frequency_shift(shift)
+}
+
+/// Shifts the frequency of the loaded clip
+///
+/// On Android, the frequency of the motor can't be changed, so the shift changes the length of
+/// the rendered emphasis instead.
+#[no_mangle]
+pub extern "system" fn Java_com_lofelt_haptics_LofeltHaptics_setFrequencyShift(
+    env: JNIEnv,
+    _caller: JObject,
+    controller_handle: jlong,
+    shift: jfloat,
+) {
+    let result = set_frequency_shift_inner(controller_handle, shift);
+    if let Err(err) = result {
+        throw_exception(&env, err);
+    }
+}
+
 fn loop_inner(controller_handle: jlong, enabled: jboolean) -> Result<(), lib::Error> {
     let controller = get_controller(controller_handle)?;
     controller.set_looping(enabled != 0)
@@ -505,3 +614,35 @@ pub extern "system" fn Java_com_lofelt_haptics_LofeltHaptics_getClipDuration(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid_handle(result: Result<(), lib::Error>) {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind, lib::ErrorKind::InvalidArgument);
+        assert_eq!(err.message, "Invalid controller handle");
+    }
+
+    // Tests that a null handle, which create() returns on failure, returns an error instead of
+    // being dereferenced
+    #[test]
+    fn null_handle() {
+        assert_invalid_handle(play_inner(0));
+        assert_invalid_handle(stop_inner(0));
+        assert_invalid_handle(seek_inner(0, 0.0));
+        assert_invalid_handle(set_amplitude_multiplication_inner(0, 1.0));
+        assert_invalid_handle(set_frequency_shift_inner(0, 0.0));
+        assert_invalid_handle(loop_inner(0, 1));
+        assert_invalid_handle(get_clip_duration(0).map(|_| ()));
+        assert_invalid_handle(destroy_inner(0));
+
+        let clip = [0_u8; 1];
+        assert_invalid_handle(load_direct_inner(
+            std::ptr::null_mut(),
+            clip.as_ptr() as *const c_char,
+            clip.len(),
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! Breakpoint types of the data model.
+//!
+//! Unlike the rest of the data model, these types and the transformations in `transform` don't
+//! need `std`, only `alloc`.
+
+use serde::{Deserialize, Serialize};
+
+/// Amplitude breakpoints of a `SignalContinuous` Amplitude envelope. Allows to apply emphasis to a point.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AmplitudeBreakpoint {
+    /// Time in seconds since the start of the clip
+    pub time: f32,
+    /// Amplitude, from 0.0 to 1.0
+    pub amplitude: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emphasis: Option<Emphasis>,
+}
+
+impl AmplitudeBreakpoint {
+    /// Creates a breakpoint without emphasis
+    ///
+    /// ```
+    /// use datamodel::v1::AmplitudeBreakpoint;
+    ///
+    /// let breakpoint = AmplitudeBreakpoint::new(0.1, 0.5);
+    /// assert_eq!(breakpoint.time, 0.1);
+    /// assert_eq!(breakpoint.amplitude, 0.5);
+    /// assert!(breakpoint.emphasis.is_none());
+    /// ```
+    pub fn new(time: f32, amplitude: f32) -> Self {
+        AmplitudeBreakpoint {
+            time,
+            amplitude,
+            emphasis: None,
+        }
+    }
+
+    /// Creates a breakpoint with emphasis
+    ///
+    /// ```
+    /// use datamodel::v1::{AmplitudeBreakpoint, Emphasis};
+    ///
+    /// let breakpoint = AmplitudeBreakpoint::with_emphasis(0.1, 0.5, 0.8, 0.6);
+    /// assert_eq!(
+    ///     breakpoint.emphasis,
+    ///     Some(Emphasis {
+    ///         amplitude: 0.8,
+    ///         frequency: 0.6
+    ///     })
+    /// );
+    /// ```
+    pub fn with_emphasis(
+        time: f32,
+        amplitude: f32,
+        emphasis_amplitude: f32,
+        emphasis_frequency: f32,
+    ) -> Self {
+        AmplitudeBreakpoint {
+            time,
+            amplitude,
+            emphasis: Some(Emphasis {
+                amplitude: emphasis_amplitude,
+                frequency: emphasis_frequency,
+            }),
+        }
+    }
+
+    pub fn from_interpolated_breakpoints(
+        breakpoint_a: &AmplitudeBreakpoint,
+        breakpoint_b: &AmplitudeBreakpoint,
+        time: f32,
+    ) -> Self {
+        AmplitudeBreakpoint {
+            time,
+            amplitude: utils::interpolate(
+                breakpoint_a.time,
+                breakpoint_b.time,
+                breakpoint_a.amplitude,
+                breakpoint_b.amplitude,
+                time,
+            ),
+            emphasis: None,
+        }
+    }
+}
+
+/// Emphasis structure associated with a Amplitude envelope breakpoint. Allows for a "haptic highlight" of the breakpoint.
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Emphasis {
+    /// Amplitude, from 0.0 to 1.0
+    pub amplitude: f32,
+    /// Frequency, from 0.0 to 1.0
+    pub frequency: f32,
+}
+
+/// Data associated with a Frequency envelope breakpoint.
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FrequencyBreakpoint {
+    /// Time in seconds since the start of the clip
+    pub time: f32,
+    /// Frequency, from 0.0 to 1.0
+    pub frequency: f32,
+}
+
+impl FrequencyBreakpoint {
+    pub fn from_interpolated_breakpoints(
+        breakpoint_a: &FrequencyBreakpoint,
+        breakpoint_b: &FrequencyBreakpoint,
+        time: f32,
+    ) -> Self {
+        FrequencyBreakpoint {
+            time,
+            frequency: utils::interpolate(
+                breakpoint_a.time,
+                breakpoint_b.time,
+                breakpoint_a.frequency,
+                breakpoint_b.frequency,
+                time,
+            ),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! Import of raw breakpoints from CSV, for example exported from a spreadsheet.
+//!
+//! Only available with the `csv` feature.
+use crate::v1::{self, DataModelBuilder};
+use std::io::Read;
+
+/// Reads breakpoints from CSV data and builds a validated clip from them.
+///
+/// The first row is a header naming the columns, which need to be `time,amplitude` or
+/// `time,amplitude,frequency`. Each following row adds a breakpoint to the amplitude envelope,
+/// and, if the frequency cell isn't empty, a breakpoint to the frequency envelope. Times are in
+/// seconds, amplitudes and frequencies between 0.0 and 1.0.
+///
+/// The rows need to be sorted by time. A row with an earlier time than the row before it, as well
+/// as a row that can't be parsed, results in an error containing the line number of the row.
+pub fn from_csv<R: Read>(reader: R) -> Result<v1::DataModel, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Error reading CSV header: {}", e))?;
+    let has_frequency = match headers.iter().collect::<Vec<&str>>().as_slice() {
+        ["time", "amplitude"] => false,
+        ["time", "amplitude", "frequency"] => true,
+        _ => {
+            return Err(format!(
+                "Invalid CSV header \"{}\", expected \"time,amplitude\" or \
+                 \"time,amplitude,frequency\"",
+                headers.iter().collect::<Vec<&str>>().join(",")
+            ))
+        }
+    };
+    let column_count = if has_frequency { 3 } else { 2 };
+
+    let mut builder = DataModelBuilder::new();
+    let mut last_time = f32::NEG_INFINITY;
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Error reading CSV: {}", e))?;
+        let line = record.position().map_or(0, |position| position.line());
+        if record.len() != column_count {
+            return Err(format!(
+                "Line {}: Expected {} columns, found {}",
+                line,
+                column_count,
+                record.len()
+            ));
+        }
+
+        let parse = |index: usize| {
+            record[index].parse::<f32>().map_err(|e| {
+                format!(
+                    "Line {}: Invalid {} \"{}\": {}",
+                    line, COLUMN_NAMES[index], &record[index], e
+                )
+            })
+        };
+        let time = parse(0)?;
+        if time < last_time {
+            return Err(format!(
+                "Line {}: Time {} is before the time {} of the previous row",
+                line, time, last_time
+            ));
+        }
+        last_time = time;
+
+        builder = builder.amplitude_breakpoint(time, parse(1)?);
+        if has_frequency && !record[2].is_empty() {
+            builder = builder.frequency_breakpoint(time, parse(2)?);
+        }
+    }
+
+    builder.build()
+}
+
+/// Names of the CSV columns, used in error messages
+const COLUMN_NAMES: [&str; 3] = ["time", "amplitude", "frequency"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{AmplitudeBreakpoint, FrequencyBreakpoint};
+
+    #[test]
+    fn import() {
+        let csv = "time,amplitude,frequency\n\
+                   0.0,0.2,0.5\n\
+                   0.1, 0.8 ,\n\
+                   0.25,0.4,1.0\n";
+        let clip = from_csv(csv.as_bytes()).unwrap();
+        let envelopes = &clip.signals.continuous.envelopes;
+        assert_eq!(
+            envelopes.amplitude,
+            vec![
+                AmplitudeBreakpoint {
+                    time: 0.0,
+                    amplitude: 0.2,
+                    emphasis: None
+                },
+                AmplitudeBreakpoint {
+                    time: 0.1,
+                    amplitude: 0.8,
+                    emphasis: None
+                },
+                AmplitudeBreakpoint {
+                    time: 0.25,
+                    amplitude: 0.4,
+                    emphasis: None
+                },
+            ]
+        );
+        assert_eq!(
+            envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 0.0,
+                    frequency: 0.5
+                },
+                FrequencyBreakpoint {
+                    time: 0.25,
+                    frequency: 1.0
+                },
+            ])
+        );
+
+        // The frequency column is optional
+        let clip = from_csv("time,amplitude\n0.0,0.2\n0.5,0.3\n".as_bytes()).unwrap();
+        assert_eq!(clip.signals.continuous.envelopes.amplitude.len(), 2);
+        assert_eq!(clip.signals.continuous.envelopes.frequency, None);
+        assert_eq!(clip.duration(), 0.5);
+    }
+
+    #[test]
+    fn import_errors() {
+        assert_eq!(
+            from_csv("time,amplitude\n0.0,0.2\n0.5,0.3\n0.4,0.1\n".as_bytes()).unwrap_err(),
+            "Line 4: Time 0.4 is before the time 0.5 of the previous row"
+        );
+        assert_eq!(
+            from_csv("time,amplitude\n0.0,0.2\n0.5,loud\n".as_bytes()).unwrap_err(),
+            "Line 3: Invalid amplitude \"loud\": invalid float literal"
+        );
+        assert_eq!(
+            from_csv("time,amplitude\n0.0,0.2,0.3\n".as_bytes()).unwrap_err(),
+            "Line 2: Expected 2 columns, found 3"
+        );
+        assert!(from_csv("seconds,amplitude\n0.0,0.2\n".as_bytes())
+            .unwrap_err()
+            .starts_with("Invalid CSV header"));
+
+        // Values out of range are caught by the validation
+        assert!(from_csv("time,amplitude\n0.0,1.5\n".as_bytes())
+            .unwrap_err()
+            .starts_with("V1 Validation Error"));
+    }
+}
@@ -1,20 +1,45 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
-use crate::v1::{AmplitudeBreakpoint, Emphasis};
-use std::time::Duration;
+use crate::breakpoints::{AmplitudeBreakpoint, Emphasis};
+use alloc::{vec, vec::Vec};
+use core::time::Duration;
 
+/// Parameters for `emphasize()`.
+///
+/// Unlike the breakpoint times of the data model, which are in seconds as `f32`, the lengths
+/// here are `Duration`s.
 pub struct EmphasisParameters {
     pub ducking_before_length: Duration,
     pub ducking_after_length: Duration,
     pub emphasis_length: Duration,
+    /// Amplitude, from 0.0 to 1.0, used in the ducking areas around an emphasis
     pub ducking_amplitude: f32,
+    /// How the amplitude of the emphasis area is chosen.
+    ///
+    /// With `None`, the authored amplitude of the emphasis is ignored, and the highest possible
+    /// amplitude, 1.0, is always used. This is done so that the emulated emphasis is more
+    /// distinct, especially when the surrounding continuous amplitude envelope is already at a
+    /// high amplitude.
+    ///
+    /// With `Some(factor)`, the authored amplitude of the emphasis is multiplied by `factor`
+    /// and clipped to 1.0. `Some(1.0)` plays the emphasis with its authored amplitude, which
+    /// keeps quieter emphasis distinct on devices where a full-scale pulse is too strong.
+    pub emphasis_amplitude: Option<f32>,
+    /// Whether the ducking and emphasis lengths adapt to the spacing of the emphasis
+    /// breakpoints.
+    ///
+    /// With `false`, the lengths are always used as they are, and an emphasis breakpoint that
+    /// falls into the emphasis or ducking after area of the previous one is skipped.
+    ///
+    /// With `true`, the ducking before, emphasis and ducking after lengths of an emphasis
+    /// breakpoint are shrunk proportionally if a neighboring emphasis breakpoint is closer than
+    /// the sum of these lengths. This way, closely spaced emphasis breakpoints are still
+    /// rendered distinctly, with shorter pulses.
+    pub adaptive: bool,
 }
 
-// We ignore the amplitude of the emphasis here, and always use the highest
-// possible amplitude, 1.0. This is done so that the emulated emphasis is
-// more distinct, especially when the surrounding continuous amplitude envelope
-// is already at a high amplitude.
-const EMPHASIS_AMPLITUDE: f32 = 1.0;
+/// The amplitude of the emphasis area if `EmphasisParameters::emphasis_amplitude` is `None`
+const DEFAULT_EMPHASIS_AMPLITUDE: f32 = 1.0;
 
 impl Default for EmphasisParameters {
     fn default() -> Self {
@@ -23,6 +48,8 @@ impl Default for EmphasisParameters {
             emphasis_length: Duration::from_millis(30),
             ducking_after_length: Duration::from_millis(30),
             ducking_amplitude: 0.0,
+            emphasis_amplitude: None,
+            adaptive: false,
         }
     }
 }
@@ -54,7 +81,8 @@ pub fn emphasize(
 ///    The length of the ducking before can be controlled with
 ///    EmphasisParameters::ducking_before_length.
 /// 2. The amplitude of the continuous signal is set to 1.0 for a short time after
-///    the breakpoint with emphasis.
+///    the breakpoint with emphasis. See EmphasisParameters::emphasis_amplitude for using
+///    a different amplitude.
 ///    Without this, the emphasis would have a duration of zero, which is not supported
 ///    by Waveform.
 ///    The length of the emphasis can be controlled with EmphasisParameters::emphasis_length.
@@ -65,11 +93,18 @@ pub fn emphasize(
 ///    The length of the ducking after can be controlled with
 ///    EmphasisParameters::ducking_after_length.
 ///
+/// If EmphasisParameters::adaptive is enabled, the lengths of all three areas are scaled down
+/// for each breakpoint with emphasis so that they fit between the neighboring breakpoints with
+/// emphasis. See length_scales().
+///
 /// The above algorithm is implemented in process().
 ///
 struct Emphasizer<'bps> {
     parameters: EmphasisParameters,
     amplitude_breakpoints: &'bps [AmplitudeBreakpoint],
+    /// The factor by which the ducking and emphasis lengths are scaled, for each breakpoint.
+    /// Always 1.0 unless EmphasisParameters::adaptive is enabled.
+    length_scales: Vec<f32>,
     result: Vec<AmplitudeBreakpoint>,
 }
 
@@ -78,13 +113,67 @@ impl<'bps> Emphasizer<'bps> {
         parameters: EmphasisParameters,
         amplitude_breakpoints: &'bps [AmplitudeBreakpoint],
     ) -> Self {
+        let length_scales = Self::length_scales(&parameters, amplitude_breakpoints);
         Self {
             parameters,
             amplitude_breakpoints,
+            length_scales,
             result: Vec::new(),
         }
     }
 
+    // Calculates the factor by which the ducking and emphasis lengths of each breakpoint
+    // are scaled.
+    //
+    // In adaptive mode, the ducking before, emphasis and ducking after areas of a breakpoint
+    // with emphasis need to fit into the gap to the previous and to the next breakpoint with
+    // emphasis. If a gap is smaller than the sum of the three lengths, the lengths are scaled
+    // down proportionally, using the smaller of the two gaps.
+    fn length_scales(
+        parameters: &EmphasisParameters,
+        amplitude_breakpoints: &[AmplitudeBreakpoint],
+    ) -> Vec<f32> {
+        let mut scales = vec![1.0; amplitude_breakpoints.len()];
+        if !parameters.adaptive {
+            return scales;
+        }
+
+        let total_length = (parameters.ducking_before_length
+            + parameters.emphasis_length
+            + parameters.ducking_after_length)
+            .as_secs_f32();
+        if total_length <= 0.0 {
+            return scales;
+        }
+
+        let emphasis_indices: Vec<usize> = amplitude_breakpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, breakpoint)| breakpoint.emphasis.is_some())
+            .map(|(index, _)| index)
+            .collect();
+        for pair in emphasis_indices.windows(2) {
+            let gap = amplitude_breakpoints[pair[1]].time - amplitude_breakpoints[pair[0]].time;
+            let scale = (gap / total_length).clamp(0.0, 1.0);
+            for &index in pair {
+                scales[index] = scales[index].min(scale);
+            }
+        }
+        scales
+    }
+
+    fn ducking_before_length(&self, emphasis_index: usize) -> f32 {
+        self.parameters.ducking_before_length.as_secs_f32() * self.length_scales[emphasis_index]
+    }
+
+    fn emphasis_length(&self, emphasis_index: usize) -> f32 {
+        self.parameters.emphasis_length.as_secs_f32() * self.length_scales[emphasis_index]
+    }
+
+    fn ducking_after_length(&self, emphasis_index: usize) -> f32 {
+        self.parameters.ducking_after_length.as_secs_f32() * self.length_scales[emphasis_index]
+    }
+
     pub fn result(self) -> Vec<AmplitudeBreakpoint> {
         self.result
     }
@@ -95,8 +184,8 @@ impl<'bps> Emphasizer<'bps> {
         let mut next_emphasis = self
             .amplitude_breakpoints
             .iter()
-            .find(|breakpoint| breakpoint.emphasis.is_some());
-        let mut prev_emphasis: Option<&AmplitudeBreakpoint> = None;
+            .position(|breakpoint| breakpoint.emphasis.is_some());
+        let mut prev_emphasis: Option<usize> = None;
         for (index, breakpoint) in self.amplitude_breakpoints.iter().enumerate() {
             match breakpoint.emphasis {
                 None => {
@@ -107,7 +196,8 @@ impl<'bps> Emphasizer<'bps> {
                     prev_emphasis = next_emphasis;
                     next_emphasis = self.amplitude_breakpoints[index + 1..]
                         .iter()
-                        .find(|breakpoint| breakpoint.emphasis.is_some());
+                        .position(|breakpoint| breakpoint.emphasis.is_some())
+                        .map(|position| position + index + 1);
                 }
             }
         }
@@ -117,29 +207,32 @@ impl<'bps> Emphasizer<'bps> {
     // It is skipped if the breakpoint is within a ducking area or an emphasis
     // area, i.e. if it is either closely before or closely after a breakpoint
     // with emphasis.
+    //
+    // prev_emphasis and next_emphasis are the indices of the surrounding breakpoints
+    // with emphasis.
     fn process_normal_breakpoint(
         &mut self,
         breakpoint: &AmplitudeBreakpoint,
-        prev_emphasis: Option<&AmplitudeBreakpoint>,
-        next_emphasis: Option<&AmplitudeBreakpoint>,
+        prev_emphasis: Option<usize>,
+        next_emphasis: Option<usize>,
     ) {
         let skip_due_to_ducking_before = match next_emphasis {
             None => false,
-            Some(next_emphasis) => {
-                let ducking_before_start = (next_emphasis.time
-                    - self.parameters.ducking_before_length.as_secs_f32())
-                .max(0.0);
+            Some(next_emphasis_index) => {
+                let next_emphasis = &self.amplitude_breakpoints[next_emphasis_index];
+                let ducking_before_start =
+                    (next_emphasis.time - self.ducking_before_length(next_emphasis_index)).max(0.0);
                 let ducking_before_range = ducking_before_start..=next_emphasis.time;
                 ducking_before_range.contains(&breakpoint.time)
             }
         };
         let skip_due_to_emphasis_and_ducking_after = match prev_emphasis {
             None => false,
-            Some(prev_emphasis) => {
-                let emphasis_end =
-                    prev_emphasis.time + self.parameters.emphasis_length.as_secs_f32();
+            Some(prev_emphasis_index) => {
+                let prev_emphasis = &self.amplitude_breakpoints[prev_emphasis_index];
+                let emphasis_end = prev_emphasis.time + self.emphasis_length(prev_emphasis_index);
                 let ducking_after_end =
-                    emphasis_end + self.parameters.ducking_after_length.as_secs_f32();
+                    emphasis_end + self.ducking_after_length(prev_emphasis_index);
                 let range = prev_emphasis.time..=ducking_after_end;
                 range.contains(&breakpoint.time)
             }
@@ -182,7 +275,7 @@ impl<'bps> Emphasizer<'bps> {
             return;
         }
         let ducking_before_start = (emphasis_breakpoint.time
-            - self.parameters.ducking_before_length.as_secs_f32())
+            - self.ducking_before_length(emphasis_index))
         .max(0.0)
         .max(last_time);
         let index_before_ducking_before = self.amplitude_breakpoints[..emphasis_index]
@@ -239,7 +332,7 @@ impl<'bps> Emphasizer<'bps> {
         &mut self,
         emphasis_breakpoint: &AmplitudeBreakpoint,
         emphasis_index: usize,
-        _emphasis: Emphasis,
+        emphasis: Emphasis,
     ) {
         let last_time = match self.result.last() {
             Some(last) => last.time,
@@ -247,9 +340,8 @@ impl<'bps> Emphasizer<'bps> {
         };
 
         let emphasis_start = emphasis_breakpoint.time.max(last_time);
-        let emphasis_end = (emphasis_breakpoint.time
-            + self.parameters.emphasis_length.as_secs_f32())
-        .max(last_time);
+        let emphasis_end =
+            (emphasis_breakpoint.time + self.emphasis_length(emphasis_index)).max(last_time);
 
         // If the emphasis has a duration of 0ms, return right away without adding
         // any emphasis or ducking after.
@@ -259,17 +351,22 @@ impl<'bps> Emphasizer<'bps> {
             return;
         }
 
+        let emphasis_amplitude = match self.parameters.emphasis_amplitude {
+            None => DEFAULT_EMPHASIS_AMPLITUDE,
+            Some(factor) => (emphasis.amplitude * factor).clamp(0.0, 1.0),
+        };
+
         // Breakpoint 1: Start of emphasis, amplitude 1.0
         self.result.push(AmplitudeBreakpoint {
             time: emphasis_start,
-            amplitude: EMPHASIS_AMPLITUDE,
+            amplitude: emphasis_amplitude,
             emphasis: None,
         });
 
         // Breakpoint 2: End of emphasis, amplitude 1.0
         self.result.push(AmplitudeBreakpoint {
             time: emphasis_end,
-            amplitude: EMPHASIS_AMPLITUDE,
+            amplitude: emphasis_amplitude,
             emphasis: None,
         });
 
@@ -289,8 +386,7 @@ impl<'bps> Emphasizer<'bps> {
         });
 
         // Breakpoint 4: End of ducking after, amplitude 0
-        let ducking_after_end =
-            ducking_after_start + self.parameters.ducking_after_length.as_secs_f32();
+        let ducking_after_end = ducking_after_start + self.ducking_after_length(emphasis_index);
         self.result.push(AmplitudeBreakpoint {
             time: ducking_after_end,
             amplitude: self.parameters.ducking_amplitude,
@@ -329,11 +425,11 @@ impl<'bps> Emphasizer<'bps> {
 #[cfg(test)]
 mod tests {
     use crate::{
-        emphasis::{emphasize, EmphasisParameters},
+        emphasis::{emphasize, EmphasisParameters, DEFAULT_EMPHASIS_AMPLITUDE},
         latest_from_json,
         test_utils::emp,
         test_utils::{amp, rounded_amplitude_breakpoints},
-        v1::DataModel,
+        v1::{AmplitudeBreakpoint, DataModel},
         Validation,
     };
     use std::{fs, path::Path, time::Duration};
@@ -364,6 +460,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(15),
                 ducking_after_length: Duration::from_millis(5),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
 
@@ -407,6 +505,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(15),
                 ducking_after_length: Duration::from_millis(5),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
 
@@ -447,6 +547,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(30),
                 ducking_after_length: Duration::from_millis(30),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -485,6 +587,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(30),
                 ducking_after_length: Duration::from_millis(5),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -534,6 +638,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(30),
                 ducking_after_length: Duration::from_millis(20),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -584,6 +690,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(10),
                 ducking_after_length: Duration::from_millis(10),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -621,6 +729,58 @@ mod tests {
         assert_eq!(actual_emphasized_clip, expected_emphasized_clip);
     }
 
+    // Checks that with adaptive lengths, all of the nine close emphasis breakpoints from
+    // nine_emphasis_breakpoints_close() are rendered, while only every other one is
+    // rendered without.
+    #[test]
+    fn nine_emphasis_breakpoints_close_adaptive() {
+        let clip: Vec<AmplitudeBreakpoint> = std::iter::once(amp(0.0, 0.0))
+            .chain((11..20).map(|i| emp(i as f32 / 100.0, 0.4, 0.9, 0.7)))
+            .chain(std::iter::once(amp(0.3, 0.0)))
+            .collect();
+        let parameters = |adaptive| EmphasisParameters {
+            ducking_before_length: Duration::from_millis(10),
+            emphasis_length: Duration::from_millis(10),
+            ducking_after_length: Duration::from_millis(10),
+            ducking_amplitude: 1.1 / 255.0,
+            emphasis_amplitude: None,
+            adaptive,
+        };
+
+        // Each rendered emphasis consists of two breakpoints with amplitude 1.0
+        let rendered_emphasis_count = |emphasized_clip: &[AmplitudeBreakpoint]| {
+            emphasized_clip
+                .iter()
+                .filter(|breakpoint| breakpoint.amplitude == DEFAULT_EMPHASIS_AMPLITUDE)
+                .count()
+                / 2
+        };
+
+        let fixed = emphasize(&clip, parameters(false));
+        assert_eq!(rendered_emphasis_count(&fixed), 5);
+
+        let adaptive = emphasize(&clip, parameters(true));
+        assert_eq!(rendered_emphasis_count(&adaptive), 9);
+        assert!(adaptive
+            .windows(2)
+            .all(|pair| pair[0].time <= pair[1].time + f32::EPSILON));
+
+        // The windows are scaled to a third, so that ducking before, emphasis and
+        // ducking after fill the 10ms between two emphasis breakpoints
+        let rounded = rounded_amplitude_breakpoints(&adaptive);
+        assert_eq!(
+            &rounded[..6],
+            &[
+                amp(0.0, 0.0),
+                amp(0.10667, 0.38788),
+                amp(0.10667, 0.00431),
+                amp(0.11, 0.00431),
+                amp(0.11, 1.0),
+                amp(0.11333, 1.0),
+            ]
+        );
+    }
+
     #[test]
     fn emphasis_on_first_breakpoint() {
         let clip = vec![emp(0.0, 0.3, 0.9, 0.7), amp(0.1, 0.2), amp(0.2, 0.0)];
@@ -631,6 +791,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(5),
                 ducking_after_length: Duration::from_millis(30),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -655,6 +817,8 @@ mod tests {
                 emphasis_length: Duration::from_millis(5),
                 ducking_after_length: Duration::from_millis(30),
                 ducking_amplitude: 1.1 / 255.0,
+                emphasis_amplitude: None,
+                adaptive: false,
             },
         ));
         let expected_emphasized_clip = vec![
@@ -669,6 +833,57 @@ mod tests {
         assert_eq!(actual_emphasized_clip, expected_emphasized_clip);
     }
 
+    // Checks that the authored emphasis amplitude is used, scaled by the factor, if
+    // emphasis_amplitude is set
+    #[test]
+    fn emphasis_amplitude() {
+        let clip = vec![amp(0.0, 0.2), emp(0.1, 0.2, 0.6, 0.7), amp(0.2, 0.2)];
+        let parameters = |emphasis_amplitude| EmphasisParameters {
+            ducking_before_length: Duration::from_millis(10),
+            emphasis_length: Duration::from_millis(10),
+            ducking_after_length: Duration::from_millis(10),
+            ducking_amplitude: 0.0,
+            emphasis_amplitude,
+            adaptive: false,
+        };
+        let expected_emphasized_clip = |amplitude| {
+            vec![
+                amp(0.0, 0.2),
+                amp(0.09, 0.2),
+                amp(0.09, 0.0),
+                amp(0.1, 0.0),
+                amp(0.1, amplitude),
+                amp(0.11, amplitude),
+                amp(0.11, 0.0),
+                amp(0.12, 0.0),
+                amp(0.12, 0.2),
+                amp(0.2, 0.2),
+            ]
+        };
+
+        // Test: Without an emphasis amplitude, the emphasis is always played at 1.0
+        assert_eq!(
+            rounded_amplitude_breakpoints(&emphasize(&clip, parameters(None))),
+            expected_emphasized_clip(1.0)
+        );
+
+        // Test: The authored emphasis amplitude is scaled by the factor
+        assert_eq!(
+            rounded_amplitude_breakpoints(&emphasize(&clip, parameters(Some(1.0)))),
+            expected_emphasized_clip(0.6)
+        );
+        assert_eq!(
+            rounded_amplitude_breakpoints(&emphasize(&clip, parameters(Some(0.5)))),
+            expected_emphasized_clip(0.3)
+        );
+
+        // Test: The scaled emphasis amplitude is clipped to 1.0
+        assert_eq!(
+            rounded_amplitude_breakpoints(&emphasize(&clip, parameters(Some(2.0)))),
+            expected_emphasized_clip(1.0)
+        );
+    }
+
     // Loads and emphasized all .haptic files in test_data/, to check that the
     // processing doesn't panic and that the result is still a valid haptic clip.
     #[test]
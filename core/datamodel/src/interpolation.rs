@@ -3,6 +3,46 @@
 use crate::v1::AmplitudeBreakpoint;
 use core::f32;
 
+/// The shape of the curve with which `Interpolator` fills the space between two amplitude
+/// breakpoints.
+///
+/// Only the amplitude is affected, as `Interpolator` doesn't process frequency breakpoints.
+/// Frequency interpolation remains linear.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterpolationCurve {
+    /// A straight line between the breakpoints
+    Linear,
+    /// The smoothstep function `3t² - 2t³`, which starts and ends the ramp gently
+    Smoothstep,
+    /// A cubic ease-in-out, which starts and ends the ramp more gently than `Smoothstep` and
+    /// is steeper in the middle
+    Cubic,
+}
+
+impl Default for InterpolationCurve {
+    fn default() -> Self {
+        InterpolationCurve::Linear
+    }
+}
+
+impl InterpolationCurve {
+    /// Maps `factor`, the linear position between two breakpoints from 0.0 to 1.0, to the
+    /// position on the curve, also from 0.0 to 1.0
+    pub fn apply(self, factor: f32) -> f32 {
+        match self {
+            InterpolationCurve::Linear => factor,
+            InterpolationCurve::Smoothstep => factor * factor * (3.0 - 2.0 * factor),
+            InterpolationCurve::Cubic => {
+                if factor < 0.5 {
+                    4.0 * factor * factor * factor
+                } else {
+                    1.0 - (2.0 - 2.0 * factor).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
 /// Contains parameters used by `Interpolator`
 
 #[derive(Debug, PartialEq)]
@@ -16,6 +56,8 @@ pub struct InterpolationParameters {
     /// Sampling frequency of the interpolation linear space, based on
     /// min_time_step
     sampling_freq: f32,
+    /// Curve used for the amplitude between two breakpoints
+    curve: InterpolationCurve,
 }
 
 impl InterpolationParameters {
@@ -33,8 +75,15 @@ impl InterpolationParameters {
             q_depth,
             min_time_step,
             sampling_freq,
+            curve: InterpolationCurve::default(),
         }
     }
+
+    /// Returns the parameters with the amplitude interpolated along `curve`, instead of the
+    /// default `InterpolationCurve::Linear`
+    pub fn with_curve(self, curve: InterpolationCurve) -> Self {
+        Self { curve, ..self }
+    }
 }
 
 // Can't use f32::clamp(), which was introduced in Rust 1.50.0. We are stuck
@@ -92,8 +141,9 @@ impl Interpolator {
         amplitude_breakpoints_interpolated
     }
 
-    /// Creates an array of linear interpolated values between time_a and time_b
+    /// Creates an array of interpolated values between time_a and time_b
     /// that area linearly spaced with 1/parameters.freq_sampling
+    /// The values follow parameters.curve.
     /// Always includes original amplitude values for time_a and time_b.
     fn linear_space_interpolation(
         &self,
@@ -123,7 +173,9 @@ impl Interpolator {
                 // range [time_a, time_b]. This is needed because sometimes
                 // itertools_num::linspace() returns values outside of that range,
                 // probably due to floating point precision issues.
-                utils::interpolate(time_a, time_b, amp_a, amp_b, clamp(*x, time_a, time_b))
+                let factor =
+                    utils::interpolate(time_a, time_b, 0.0, 1.0, clamp(*x, time_a, time_b));
+                amp_a + (amp_b - amp_a) * self.parameters.curve.apply(factor)
             })) {
                 time_result.push(*time);
                 amplitude_result.push(amplitude);
@@ -406,7 +458,40 @@ mod tests {
             min_time_step: 0.0,
             sampling_freq: 0.0,
             q_depth: 256,
+            curve: InterpolationCurve::Linear,
         };
         assert_eq!(result_parameters, expected_parameters);
     }
+
+    #[test]
+    /// Check that a ramp from 0 to 1 is interpolated along the selected curve
+    fn check_interpolation_curves() {
+        let input_data = vec![amp(0.0, 0.0), amp(0.1, 1.0)];
+        let expected_amplitudes = [
+            (InterpolationCurve::Linear, [0.0, 0.25, 0.5, 0.75, 1.0]),
+            (
+                InterpolationCurve::Smoothstep,
+                [0.0, 0.15625, 0.5, 0.84375, 1.0],
+            ),
+            (InterpolationCurve::Cubic, [0.0, 0.0625, 0.5, 0.9375, 1.0]),
+        ];
+
+        for (curve, expected_amplitudes) in expected_amplitudes.iter() {
+            let interpolator = Interpolator::new(
+                InterpolationParameters::new(Q_BITS, MIN_TIME_STEP).with_curve(*curve),
+            );
+            let result_interpolated_data =
+                rounded_amplitude_breakpoints(&interpolator.process(&input_data));
+            let expected_interpolated_data = [0.0, 0.025, 0.05, 0.075, 0.1]
+                .iter()
+                .zip(expected_amplitudes.iter())
+                .map(|(time, amplitude)| amp(*time, *amplitude))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                expected_interpolated_data, result_interpolated_data,
+                "{:?}",
+                curve
+            );
+        }
+    }
 }
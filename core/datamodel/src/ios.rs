@@ -4,9 +4,120 @@
 pub mod v1;
 use crate::*;
 
+use emphasis::{emphasize, EmphasisParameters};
 use v1::*;
 
+/// How the emphasis of a clip is exported when converting Lofelt Data to AHAP, see
+/// `convert_to_ahaps()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AhapExportMode {
+    /// Creates one AHAP with continuous events and one AHAP with transient events for the
+    /// emphasis, which need to be played at the same time. Applying the parameter curves of the
+    /// continuous events to transients would distort them, which is why they are split.
+    Split,
+    /// Creates one AHAP with continuous events only. The emphasis is dropped.
+    ContinuousOnly,
+    /// Creates one AHAP with continuous events only. The emphasis is folded into the continuous
+    /// amplitude with `emphasis::emphasize()`, so that a single AHAP can be played, at the cost
+    /// of less crisp transients.
+    EmphasisAsContinuous,
+}
+
+impl Default for AhapExportMode {
+    fn default() -> Self {
+        AhapExportMode::Split
+    }
+}
+
 ///Converts latest version of Lofelt Data to two AHAPs with continuous and transients events
 pub fn convert_to_transient_and_continuous_ahaps(data: latest::DataModel) -> (Ahap, Option<Ahap>) {
     Ahap::from(data).into_continuous_and_transients_ahaps()
 }
+
+/// Converts latest version of Lofelt Data to AHAP, handling the emphasis according to `mode`
+///
+/// Returns the AHAP with continuous events, and the AHAP with transient events if `mode` is
+/// `AhapExportMode::Split` and the clip has emphasis.
+pub fn convert_to_ahaps(mut data: latest::DataModel, mode: AhapExportMode) -> (Ahap, Option<Ahap>) {
+    let amplitudes = &mut data.signals.continuous.envelopes.amplitude;
+    match mode {
+        AhapExportMode::Split => return convert_to_transient_and_continuous_ahaps(data),
+        AhapExportMode::ContinuousOnly => {
+            for breakpoint in amplitudes.iter_mut() {
+                breakpoint.emphasis = None;
+            }
+        }
+        AhapExportMode::EmphasisAsContinuous => {
+            *amplitudes = emphasize(amplitudes, EmphasisParameters::default());
+        }
+    }
+
+    // Without emphasis, there are no transients to split off
+    (Ahap::from(data), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn load_test_file_valid_v1() -> latest::DataModel {
+        let data = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_data/valid_v1.haptic"),
+        )
+        .unwrap();
+        latest_from_json(&data).unwrap().1
+    }
+
+    fn has_transients(ahap: &Ahap) -> bool {
+        ahap.pattern
+            .iter()
+            .any(|pattern| matches!(pattern, Pattern::Event(v1::Event::HapticTransient { .. })))
+    }
+
+    ///Tests that the split mode creates the same AHAPs as before export modes were added
+    #[test]
+    fn test_export_mode_split() {
+        let data = load_test_file_valid_v1();
+        let (continuous, transients) = convert_to_ahaps(data.clone(), AhapExportMode::default());
+        assert_eq!(
+            (continuous, transients),
+            convert_to_transient_and_continuous_ahaps(data)
+        );
+    }
+
+    ///Tests that the continuous only mode drops the emphasis
+    #[test]
+    fn test_export_mode_continuous_only() {
+        let mut data = load_test_file_valid_v1();
+        let (continuous, transients) =
+            convert_to_ahaps(data.clone(), AhapExportMode::ContinuousOnly);
+        assert!(transients.is_none());
+        assert!(!has_transients(&continuous));
+
+        for breakpoint in data.signals.continuous.envelopes.amplitude.iter_mut() {
+            breakpoint.emphasis = None;
+        }
+        assert_eq!(continuous, Ahap::from(data));
+    }
+
+    ///Tests that the emphasis as continuous mode folds the emphasis into the continuous events
+    #[test]
+    fn test_export_mode_emphasis_as_continuous() {
+        let data = load_test_file_valid_v1();
+        let (continuous, transients) =
+            convert_to_ahaps(data.clone(), AhapExportMode::EmphasisAsContinuous);
+        assert!(transients.is_none());
+        assert!(!has_transients(&continuous));
+
+        let (continuous_only, _) = convert_to_ahaps(data.clone(), AhapExportMode::ContinuousOnly);
+        assert_ne!(continuous, continuous_only);
+
+        let mut emphasized = data;
+        emphasized.signals.continuous.envelopes.amplitude = emphasize(
+            &emphasized.signals.continuous.envelopes.amplitude,
+            EmphasisParameters::default(),
+        );
+        assert_eq!(continuous, Ahap::from(emphasized));
+    }
+}
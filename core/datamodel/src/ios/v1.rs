@@ -3,6 +3,7 @@
 //! Defines the iOS data model version 1.0.0
 use crate::*;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::f32;
 use v1::AmplitudeBreakpoint;
 
@@ -11,6 +12,31 @@ const AMPLITUDE_DUCKING: f32 = 0.2;
 
 const MAX_CONTINUOUS_EVENT_DURATION: f32 = 30.0;
 
+/// Parameters used when converting Lofelt Data to AHAP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AhapConversionParameters {
+    /// Factor by which the continuous intensity is reduced at breakpoints with emphasis, so
+    /// that the transient stands out. 0.0 disables ducking, 1.0 silences the continuous signal.
+    pub amplitude_ducking: f32,
+    /// Maximum duration of a single continuous event, in seconds. Longer clips are split into
+    /// multiple events. CoreHaptics limits continuous events to 30 seconds.
+    pub max_continuous_event_duration: f32,
+    /// Sharpness, between 0.0 and 1.0, of a constant sharpness parameter curve that is added
+    /// for clips without frequency envelope. With None, no sharpness parameter curve is added
+    /// for such clips, and the sharpness depends on what Core Haptics uses by default.
+    pub default_sharpness: Option<f32>,
+}
+
+impl Default for AhapConversionParameters {
+    fn default() -> Self {
+        Self {
+            amplitude_ducking: AMPLITUDE_DUCKING,
+            max_continuous_event_duration: MAX_CONTINUOUS_EVENT_DURATION,
+            default_sharpness: None,
+        }
+    }
+}
+
 ///Core Haptics AHAP data model structure
 #[derive(Default, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -41,8 +67,14 @@ impl Ahap {
     pub fn into_continuous_and_transients_ahaps(self) -> (Ahap, Option<Ahap>) {
         let ahap_version = 1.0;
 
-        let mut ahap_transients = Ahap { version: ahap_version, ..Default::default() };
-        let mut ahap_continuous = Ahap { version: ahap_version, ..Default::default() };
+        let mut ahap_transients = Ahap {
+            version: ahap_version,
+            ..Default::default()
+        };
+        let mut ahap_continuous = Ahap {
+            version: ahap_version,
+            ..Default::default()
+        };
 
         for pattern in self.pattern {
             match pattern {
@@ -67,6 +99,7 @@ impl Ahap {
                             time,
                             event_parameters,
                         })),
+                    audio_event => ahap_continuous.pattern.push(Pattern::Event(audio_event)),
                 },
                 Pattern::ParameterCurve(parameter_curve) => {
                     ahap_continuous
@@ -83,6 +116,20 @@ impl Ahap {
             (ahap_continuous, Some(ahap_transients))
         }
     }
+
+    /// Merges two AHAPs with continuous and transient events respectively into one AHAP.
+    ///
+    /// This is the reverse of `into_continuous_and_transients_ahaps()`. The metadata of the
+    /// continuous AHAP is kept.
+    pub fn from_continuous_and_transients_ahaps(
+        mut continuous: Ahap,
+        transients: Option<Ahap>,
+    ) -> Ahap {
+        if let Some(mut transients) = transients {
+            continuous.pattern.append(&mut transients.pattern);
+        }
+        continuous
+    }
 }
 
 fn ahap_transient_events_from_breakpoints(breakpoints: &[AmplitudeBreakpoint]) -> Vec<Pattern> {
@@ -114,18 +161,21 @@ fn ahap_transient_events_from_breakpoints(breakpoints: &[AmplitudeBreakpoint]) -
 /// that modulate these constant values are added to the AHAP in another place.
 ///
 /// The only reason to use multiple events here is because CoreHaptics limits events
-/// of type HapticContinuous to 30 seconds.
-fn ahap_continuous_events_from_v1(clip: &v1::DataModel) -> Vec<Pattern> {
+/// of type HapticContinuous to 30 seconds, or to `max_event_duration` if that is shorter.
+///
+/// The events end with the amplitude envelope, like the intensity parameter curve, even if the
+/// frequency envelope is longer, see `DataModel::duration()`.
+fn ahap_continuous_events_from_v1(clip: &v1::DataModel, max_event_duration: f32) -> Vec<Pattern> {
     let mut total_remaining_duration = match clip.signals.continuous.envelopes.amplitude.last() {
         None => 0.0,
         Some(last) => last.time,
     };
-    let event_count = (total_remaining_duration / MAX_CONTINUOUS_EVENT_DURATION).ceil() as u32;
+    let event_count = (total_remaining_duration / max_event_duration).ceil() as u32;
     let mut result = Vec::new();
     for i in 0..event_count {
-        let time = i as f32 * MAX_CONTINUOUS_EVENT_DURATION;
-        let event_duration = if total_remaining_duration > MAX_CONTINUOUS_EVENT_DURATION {
-            MAX_CONTINUOUS_EVENT_DURATION
+        let time = i as f32 * max_event_duration;
+        let event_duration = if total_remaining_duration > max_event_duration {
+            max_event_duration
         } else {
             total_remaining_duration
         };
@@ -151,10 +201,30 @@ fn ahap_continuous_events_from_v1(clip: &v1::DataModel) -> Vec<Pattern> {
     result
 }
 
-///Creates an AHAP data structure with data from Lofelt Data V1.0.0
+///Creates an AHAP data structure with data from Lofelt Data V1.0.0, using the default
+///`AhapConversionParameters`
 impl From<v1::DataModel> for Ahap {
     fn from(v1: v1::DataModel) -> Self {
+        Ahap::from_data_model_with_params(v1, AhapConversionParameters::default())
+    }
+}
+
+impl Ahap {
+    ///Creates an AHAP data structure with data from Lofelt Data V1.0.0, using the given
+    ///conversion parameters
+    pub fn from_data_model_with_params(
+        v1: v1::DataModel,
+        params: AhapConversionParameters,
+    ) -> Self {
         let ahap_version = 1.0;
+        let ducking = params.amplitude_ducking.clamp(0.0, 1.0);
+        let max_event_duration = if params.max_continuous_event_duration > 0.0 {
+            params
+                .max_continuous_event_duration
+                .min(MAX_CONTINUOUS_EVENT_DURATION)
+        } else {
+            MAX_CONTINUOUS_EVENT_DURATION
+        };
 
         let v1_signals = &v1.signals;
 
@@ -180,7 +250,7 @@ impl From<v1::DataModel> for Ahap {
             //first point in the CHParameterCurve comes from control_point
             let mut parameter_curve_control_points = vec![ParameterCurveControlPoint {
                 time: control_point.time,
-                parameter_value: get_intensity_from_amplitude_bp(control_point),
+                parameter_value: get_intensity_from_amplitude_bp(control_point, ducking),
             }];
 
             //Add remaining 15 control points
@@ -189,7 +259,7 @@ impl From<v1::DataModel> for Ahap {
                     .iter()
                     .map(|point| ParameterCurveControlPoint {
                         time: point.time,
-                        parameter_value: get_intensity_from_amplitude_bp(point),
+                        parameter_value: get_intensity_from_amplitude_bp(point, ducking),
                     })
                     .collect::<Vec<ParameterCurveControlPoint>>(),
             );
@@ -222,8 +292,7 @@ impl From<v1::DataModel> for Ahap {
         // ----------------------------------------------------------------
 
         match &v1_signals.continuous.envelopes.frequency {
-            None => {}
-            Some(frequency_breakpoint_vec) => {
+            Some(frequency_breakpoint_vec) if !frequency_breakpoint_vec.is_empty() => {
                 // get first point
                 let default_control_point = v1::FrequencyBreakpoint::default();
                 let mut control_point = match frequency_breakpoint_vec.first() {
@@ -269,11 +338,34 @@ impl From<v1::DataModel> for Ahap {
                     ahap_data.pattern.push(parameter_curve_sharpness);
                 }
             }
+            // Without frequency envelope, optionally add a constant sharpness so that the
+            // result doesn't depend on the default sharpness of Core Haptics
+            _ => {
+                if let Some(sharpness) = params.default_sharpness {
+                    let sharpness = sharpness.clamp(0.0, 1.0);
+                    ahap_data
+                        .pattern
+                        .push(Pattern::ParameterCurve(ParameterCurve {
+                            parameter_id: DynamicParameterId::HapticSharpnessControl,
+                            time: 0.0,
+                            parameter_curve_control_points: vec![
+                                ParameterCurveControlPoint {
+                                    time: 0.0,
+                                    parameter_value: sharpness,
+                                },
+                                ParameterCurveControlPoint {
+                                    time: v1.duration(),
+                                    parameter_value: sharpness,
+                                },
+                            ],
+                        }));
+                }
+            }
         };
 
         ahap_data
             .pattern
-            .append(&mut ahap_continuous_events_from_v1(&v1));
+            .append(&mut ahap_continuous_events_from_v1(&v1, max_event_duration));
 
         //Appending transients at the end of AHAP to make AHAPs more organized
         ahap_data.pattern.append(&mut transient_events_data);
@@ -289,9 +381,268 @@ impl From<v1::DataModel> for Ahap {
     }
 }
 
-fn get_intensity_from_amplitude_bp(breakpoint: &AmplitudeBreakpoint) -> f32 {
+/// Collects the control points of all parameter curves with the given `parameter_id`.
+///
+/// When converting from Lofelt Data, the last control point of a curve is repeated as the first
+/// control point of the next curve. These duplicates are skipped here.
+fn control_points_from_parameter_curves(
+    ahap: &Ahap,
+    parameter_id: DynamicParameterId,
+) -> Vec<ParameterCurveControlPoint> {
+    let mut curves = ahap
+        .pattern
+        .iter()
+        .filter_map(|pattern| match pattern {
+            Pattern::ParameterCurve(curve) if curve.parameter_id == parameter_id => Some(curve),
+            _ => None,
+        })
+        .collect::<Vec<&ParameterCurve>>();
+    curves.sort_by(|a, b| {
+        a.time
+            .partial_cmp(&b.time)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut control_points: Vec<ParameterCurveControlPoint> = Vec::new();
+    for point in curves
+        .iter()
+        .flat_map(|curve| curve.parameter_curve_control_points.iter())
+    {
+        if control_points.last() != Some(point) {
+            control_points.push(*point);
+        }
+    }
+    control_points
+}
+
+/// Returns the time ranges covered by the parameter curves with the given `parameter_id`, from the
+/// first to the last control point of each curve.
+fn parameter_curve_time_ranges(ahap: &Ahap, parameter_id: DynamicParameterId) -> Vec<(f32, f32)> {
+    ahap.pattern
+        .iter()
+        .filter_map(|pattern| match pattern {
+            Pattern::ParameterCurve(curve) if curve.parameter_id == parameter_id => {
+                match (
+                    curve.parameter_curve_control_points.first(),
+                    curve.parameter_curve_control_points.last(),
+                ) {
+                    (Some(first), Some(last)) => Some((first.time, last.time)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Creates Lofelt Data V1.0.0 from an AHAP data structure
+///
+/// This is the reverse of `From<v1::DataModel> for Ahap`:
+/// - The amplitude envelope is created from the `HapticIntensityControl` parameter curves
+/// - The frequency envelope is created from the `HapticSharpnessControl` parameter curves
+/// - Emphasis is created from `HapticTransient` events at the time of an amplitude breakpoint.
+///   For transients between two amplitude breakpoints, a new breakpoint is inserted.
+///
+/// AHAPs authored by hand often don't use parameter curves, and set the intensity and sharpness
+/// with the static `HapticIntensity` and `HapticSharpness` parameters of a continuous event
+/// instead. If no parameter curve overlaps a continuous event, its static parameters are
+/// imported as a constant segment of the amplitude or frequency envelope. A missing intensity
+/// defaults to 1.0 and a missing sharpness to 0.0.
+///
+/// AHAP features that can't be represented in Lofelt Data, like audio events, transients outside
+/// of the amplitude envelope, or continuous events overlapped by a parameter curve with an
+/// intensity or sharpness other than the ones used when converting from Lofelt Data, result in
+/// an error.
+impl TryFrom<Ahap> for v1::DataModel {
+    type Error = String;
+
+    fn try_from(ahap: Ahap) -> Result<Self, Self::Error> {
+        let intensity_curve_ranges =
+            parameter_curve_time_ranges(&ahap, DynamicParameterId::HapticIntensityControl);
+        let sharpness_curve_ranges =
+            parameter_curve_time_ranges(&ahap, DynamicParameterId::HapticSharpnessControl);
+
+        let mut transients = Vec::new();
+        let mut constant_amplitude = Vec::new();
+        let mut constant_frequency = Vec::new();
+        for pattern in &ahap.pattern {
+            match pattern {
+                Pattern::Event(Event::HapticContinuous {
+                    time,
+                    event_duration,
+                    event_parameters,
+                }) => {
+                    let start = *time;
+                    let end = time + event_duration;
+                    let overlaps_curve = |ranges: &[(f32, f32)]| {
+                        ranges.iter().any(|(curve_start, curve_end)| {
+                            *curve_start < end && *curve_end > start
+                        })
+                    };
+                    let parameter_value = |parameter_id, default_value| {
+                        event_parameters
+                            .iter()
+                            .find(|parameter| parameter.parameter_id == parameter_id)
+                            .map_or(default_value, |parameter| parameter.parameter_value)
+                    };
+
+                    for (parameter_id, parameter_curve_ranges, curve_value) in [
+                        (ParameterId::HapticIntensity, &intensity_curve_ranges, 1.0),
+                        (ParameterId::HapticSharpness, &sharpness_curve_ranges, 0.0),
+                    ]
+                    .iter()
+                    {
+                        let value = parameter_value(*parameter_id, *curve_value);
+                        if !overlaps_curve(parameter_curve_ranges) {
+                            match parameter_id {
+                                // The event starts and stops the vibration abruptly
+                                ParameterId::HapticIntensity => {
+                                    let amplitude = value.powi(2);
+                                    constant_amplitude.extend_from_slice(&[
+                                        (start, 0.0),
+                                        (start, amplitude),
+                                        (end, amplitude),
+                                        (end, 0.0),
+                                    ]);
+                                }
+                                ParameterId::HapticSharpness => {
+                                    let frequency = value.powi(2);
+                                    constant_frequency
+                                        .extend_from_slice(&[(start, frequency), (end, frequency)]);
+                                }
+                            }
+                        } else if (value - curve_value).abs() > DELTA_ERR {
+                            return Err(format!(
+                                "AHAP import: continuous event at {}s has {:?} {}, only {} is supported",
+                                time, parameter_id, value, curve_value
+                            ));
+                        }
+                    }
+                }
+                Pattern::Event(Event::HapticTransient {
+                    time,
+                    event_parameters,
+                }) => transients.push((*time, event_parameters)),
+                Pattern::Event(Event::AudioContinuous { time, .. })
+                | Pattern::Event(Event::AudioCustom { time, .. }) => {
+                    return Err(format!(
+                        "AHAP import: audio event at {}s can't be represented",
+                        time
+                    ));
+                }
+                Pattern::ParameterCurve(_) => {}
+            }
+        }
+
+        let mut amplitude =
+            control_points_from_parameter_curves(&ahap, DynamicParameterId::HapticIntensityControl)
+                .iter()
+                .map(|point| (point.time, point.parameter_value.powi(2)))
+                .chain(constant_amplitude)
+                .map(|(time, amplitude)| AmplitudeBreakpoint {
+                    time,
+                    amplitude,
+                    emphasis: None,
+                })
+                .collect::<Vec<AmplitudeBreakpoint>>();
+        // Segments from continuous events don't overlap with the parameter curves, sort them
+        // into place. The sort is stable, keeping the order of steps.
+        amplitude.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        amplitude.dedup();
+
+        for (time, event_parameters) in transients {
+            let mut emphasis = v1::Emphasis::default();
+            for parameter in event_parameters {
+                match parameter.parameter_id {
+                    ParameterId::HapticIntensity => {
+                        emphasis.amplitude = parameter.parameter_value.powi(2)
+                    }
+                    ParameterId::HapticSharpness => emphasis.frequency = parameter.parameter_value,
+                }
+            }
+
+            let index_of_next_breakpoint = amplitude
+                .iter()
+                .position(|breakpoint| breakpoint.time >= time - DELTA_ERR)
+                .ok_or_else(|| {
+                    format!(
+                        "AHAP import: transient at {}s is after the end of the amplitude envelope",
+                        time
+                    )
+                })?;
+            let next_breakpoint = &mut amplitude[index_of_next_breakpoint];
+
+            if (next_breakpoint.time - time).abs() <= DELTA_ERR {
+                // Breakpoints with emphasis are ducked when converting to AHAP, undo that here
+                next_breakpoint.amplitude = (next_breakpoint.amplitude.sqrt()
+                    / (1.0 - AMPLITUDE_DUCKING))
+                    .powi(2)
+                    .min(MAX_ENVELOPE_AMPLITUDE);
+                emphasis.amplitude = emphasis.amplitude.max(next_breakpoint.amplitude);
+                next_breakpoint.emphasis = Some(emphasis);
+            } else if index_of_next_breakpoint > 0 {
+                // AHAPs not created from Lofelt Data can have transients anywhere, insert a
+                // new breakpoint for them
+                let mut breakpoint = AmplitudeBreakpoint::from_interpolated_breakpoints(
+                    &amplitude[index_of_next_breakpoint - 1],
+                    &amplitude[index_of_next_breakpoint],
+                    time,
+                );
+                emphasis.amplitude = emphasis.amplitude.max(breakpoint.amplitude);
+                breakpoint.emphasis = Some(emphasis);
+                amplitude.insert(index_of_next_breakpoint, breakpoint);
+            } else {
+                return Err(format!(
+                    "AHAP import: transient at {}s is before the start of the amplitude envelope",
+                    time
+                ));
+            }
+        }
+
+        // Continuous events exported from a clip without a frequency envelope have the default
+        // sharpness, don't turn that into a flat frequency envelope.
+        if sharpness_curve_ranges.is_empty()
+            && constant_frequency
+                .iter()
+                .all(|(_, frequency)| *frequency == 0.0)
+        {
+            constant_frequency.clear();
+        }
+
+        let mut frequency =
+            control_points_from_parameter_curves(&ahap, DynamicParameterId::HapticSharpnessControl)
+                .iter()
+                .map(|point| (point.time, point.parameter_value.powi(2)))
+                .chain(constant_frequency)
+                .map(|(time, frequency)| v1::FrequencyBreakpoint { time, frequency })
+                .collect::<Vec<v1::FrequencyBreakpoint>>();
+        frequency.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        frequency.dedup();
+
+        let mut data = v1::DataModel::default();
+        data.metadata.project = ahap.metadata.project;
+        data.metadata.author = ahap.metadata.created;
+        data.metadata.description = ahap.metadata.description;
+        data.signals.continuous.envelopes.amplitude = amplitude;
+        if !frequency.is_empty() {
+            data.signals.continuous.envelopes.frequency = Some(frequency);
+        }
+
+        data.validate()
+    }
+}
+
+fn get_intensity_from_amplitude_bp(breakpoint: &AmplitudeBreakpoint, ducking: f32) -> f32 {
     if breakpoint.emphasis.is_some() {
-        breakpoint.amplitude.sqrt() * (1.0 - AMPLITUDE_DUCKING)
+        breakpoint.amplitude.sqrt() * (1.0 - ducking)
     } else {
         breakpoint.amplitude.sqrt()
     }
@@ -333,6 +684,15 @@ pub enum Event {
         time: f32,
         event_parameters: Vec<EventParameter>,
     },
+    /// Audio events are never created when converting from Lofelt Data, but can be part of AHAPs
+    /// authored elsewhere. Only the fields needed to identify them are kept.
+    #[serde(rename_all = "PascalCase")]
+    AudioContinuous { time: f32, event_duration: f32 },
+    #[serde(rename_all = "PascalCase")]
+    AudioCustom {
+        time: f32,
+        event_waveform_path: String,
+    },
 }
 
 ///Core Haptics AHAP EventParameter data structure
@@ -546,6 +906,109 @@ mod tests {
         compare_v1_with_ahap("valid_v1.haptic", "ios/ahap_from_valid_v1.ahap");
     }
 
+    ///Testing that the amplitude ducking parameter changes the intensity at emphasis breakpoints
+    #[test]
+    fn test_ahap_from_v1_with_params() {
+        let v1_data = v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.25)
+            .emphasis(1.0, 0.5)
+            .amplitude_breakpoint(1.0, 0.25)
+            .build()
+            .unwrap();
+        let intensities = |ahap: &Ahap| {
+            control_points_from_parameter_curves(ahap, DynamicParameterId::HapticIntensityControl)
+                .iter()
+                .map(|point| point.parameter_value)
+                .collect::<Vec<f32>>()
+        };
+
+        // The default parameters are the same as the From implementation
+        let ahap =
+            Ahap::from_data_model_with_params(v1_data.clone(), AhapConversionParameters::default());
+        assert_eq!(ahap, Ahap::from(v1_data.clone()));
+        assert_eq!(intensities(&ahap), vec![0.4, 0.5]);
+
+        for (amplitude_ducking, expected) in &[(0.0, 0.5), (0.5, 0.25), (1.0, 0.0)] {
+            let params = AhapConversionParameters {
+                amplitude_ducking: *amplitude_ducking,
+                ..Default::default()
+            };
+            let ahap = Ahap::from_data_model_with_params(v1_data.clone(), params);
+            assert_eq!(intensities(&ahap), vec![*expected, 0.5]);
+        }
+
+        // Shorter continuous events split the clip into more events
+        let params = AhapConversionParameters {
+            max_continuous_event_duration: 0.4,
+            ..Default::default()
+        };
+        let ahap = Ahap::from_data_model_with_params(v1_data, params);
+        let continuous_event_count = ahap
+            .pattern
+            .iter()
+            .filter(|pattern| matches!(pattern, Pattern::Event(Event::HapticContinuous { .. })))
+            .count();
+        assert_eq!(continuous_event_count, 3);
+    }
+
+    ///Testing that a constant sharpness curve is added for clips without frequency envelope if
+    ///a default sharpness is set
+    #[test]
+    fn test_ahap_from_v1_default_sharpness() {
+        let v1_data = v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.25)
+            .amplitude_breakpoint(1.5, 0.25)
+            .build()
+            .unwrap();
+        let sharpness_curves = |ahap: &Ahap| {
+            control_points_from_parameter_curves(ahap, DynamicParameterId::HapticSharpnessControl)
+                .iter()
+                .map(|point| (point.time, point.parameter_value))
+                .collect::<Vec<(f32, f32)>>()
+        };
+
+        let ahap =
+            Ahap::from_data_model_with_params(v1_data.clone(), AhapConversionParameters::default());
+        assert!(sharpness_curves(&ahap).is_empty());
+
+        let params = AhapConversionParameters {
+            default_sharpness: Some(0.5),
+            ..Default::default()
+        };
+        let ahap = Ahap::from_data_model_with_params(v1_data, params);
+        assert_eq!(sharpness_curves(&ahap), vec![(0.0, 0.5), (1.5, 0.5)]);
+
+        // Clips with a frequency envelope keep their sharpness curve
+        let v1_data = v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.25)
+            .frequency_breakpoint(0.0, 0.25)
+            .amplitude_breakpoint(1.5, 0.25)
+            .frequency_breakpoint(1.5, 1.0)
+            .build()
+            .unwrap();
+        let ahap = Ahap::from_data_model_with_params(v1_data, params);
+        assert_eq!(sharpness_curves(&ahap), vec![(0.0, 0.5), (1.5, 1.0)]);
+    }
+
+    ///Testing that splitting an AHAP and merging it again results in the same data
+    #[test]
+    fn test_ahap_split_and_merge() {
+        let v1_data: v1::DataModel =
+            serde_json::from_str::<v1::DataModel>(&load_file_from_test_data("valid_v1.haptic"))
+                .unwrap();
+        let (continuous, transients) =
+            Ahap::from(v1_data.clone()).into_continuous_and_transients_ahaps();
+        assert!(transients.is_some());
+        let merged = Ahap::from_continuous_and_transients_ahaps(continuous, transients);
+        // Splitting doesn't keep the metadata, only compare the signals
+        assert_eq!(
+            v1::DataModel::try_from(merged).unwrap().signals,
+            v1::DataModel::try_from(Ahap::from(v1_data))
+                .unwrap()
+                .signals
+        );
+    }
+
     ///Testing conversion from v1 to AHAP with required fields only (no emphasis)
     #[test]
     fn test_ahap_from_v1_required() {
@@ -582,4 +1045,180 @@ mod tests {
     fn test_30_second_limit() {
         compare_v1_with_ahap("ios/long_clip.haptic", "ios/long_clip.ahap");
     }
+
+    fn assert_approx_eq(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.0001, "{} != {}", a, b);
+    }
+
+    ///Testing that converting v1 to AHAP and back results in the original clip
+    #[test]
+    fn test_v1_from_ahap_round_trip() {
+        for v1_path in &[
+            "valid_v1.haptic",
+            "valid_required_v1.haptic",
+            "ios/17_points.haptic",
+            "ios/long_clip.haptic",
+        ] {
+            let v1_data: v1::DataModel =
+                serde_json::from_str::<v1::DataModel>(&load_file_from_test_data(v1_path)).unwrap();
+            let v1_from_ahap = v1::DataModel::try_from(Ahap::from(v1_data.clone())).unwrap();
+
+            let envelopes = &v1_data.signals.continuous.envelopes;
+            let envelopes_from_ahap = &v1_from_ahap.signals.continuous.envelopes;
+            assert_eq!(
+                envelopes.amplitude.len(),
+                envelopes_from_ahap.amplitude.len()
+            );
+            for (a, b) in envelopes
+                .amplitude
+                .iter()
+                .zip(&envelopes_from_ahap.amplitude)
+            {
+                assert_approx_eq(a.time, b.time);
+                assert_approx_eq(a.amplitude, b.amplitude);
+                assert_eq!(a.emphasis.is_some(), b.emphasis.is_some());
+                if let (Some(a), Some(b)) = (a.emphasis, b.emphasis) {
+                    assert_approx_eq(a.amplitude, b.amplitude);
+                    assert_approx_eq(a.frequency, b.frequency);
+                }
+            }
+
+            let frequency = envelopes.frequency.clone().unwrap_or_default();
+            let frequency_from_ahap = envelopes_from_ahap.frequency.clone().unwrap_or_default();
+            assert_eq!(frequency.len(), frequency_from_ahap.len());
+            for (a, b) in frequency.iter().zip(&frequency_from_ahap) {
+                assert_approx_eq(a.time, b.time);
+                assert_approx_eq(a.frequency, b.frequency);
+            }
+        }
+    }
+
+    ///Testing conversion from an AHAP file exported from Studio Desktop to v1
+    #[test]
+    fn test_v1_from_studio_export_ahap() {
+        let studio_ahap = load_file_from_test_data("ios/studio_export.ahap");
+        let ahap = serde_json::from_str::<Ahap>(&studio_ahap).unwrap();
+        let v1_data = v1::DataModel::try_from(ahap).unwrap();
+        assert!(!v1_data.signals.continuous.envelopes.amplitude.is_empty());
+    }
+
+    ///Testing that AHAP features that can't be represented in v1 result in an error
+    #[test]
+    fn test_v1_from_ahap_unsupported() {
+        let audio_ahap = r#"{
+            "Version": 1,
+            "Metadata": {},
+            "Pattern": [
+                {
+                    "Event": {
+                        "Time": 0.5,
+                        "EventType": "AudioCustom",
+                        "EventWaveformPath": "sound.wav",
+                        "EventParameters": [{ "ParameterID": "AudioVolume", "ParameterValue": 1 }]
+                    }
+                }
+            ]
+        }"#;
+        let ahap = serde_json::from_str::<Ahap>(audio_ahap).unwrap();
+        let err = v1::DataModel::try_from(ahap).unwrap_err();
+        assert!(err.contains("audio event"), "{}", err);
+
+        let v1_data: v1::DataModel = serde_json::from_str::<v1::DataModel>(
+            &load_file_from_test_data("valid_required_v1.haptic"),
+        )
+        .unwrap();
+        let mut ahap = Ahap::from(v1_data);
+        ahap.pattern.push(Pattern::Event(Event::HapticTransient {
+            time: 100.0,
+            event_parameters: vec![],
+        }));
+        let err = v1::DataModel::try_from(ahap).unwrap_err();
+        assert!(
+            err.contains("after the end of the amplitude envelope"),
+            "{}",
+            err
+        );
+    }
+
+    ///Testing that static parameters of continuous events without parameter curves are imported
+    #[test]
+    fn test_v1_from_ahap_static_parameters() {
+        use crate::test_utils::{amp, rounded_amplitude_breakpoints};
+
+        let static_ahap = r#"{
+            "Version": 1,
+            "Metadata": {},
+            "Pattern": [
+                {
+                    "Event": {
+                        "Time": 0.0,
+                        "EventType": "HapticContinuous",
+                        "EventDuration": 0.5,
+                        "EventParameters": [
+                            { "ParameterID": "HapticIntensity", "ParameterValue": 0.5 },
+                            { "ParameterID": "HapticSharpness", "ParameterValue": 0.8 }
+                        ]
+                    }
+                },
+                {
+                    "Event": {
+                        "Time": 1.0,
+                        "EventType": "HapticContinuous",
+                        "EventDuration": 1.0,
+                        "EventParameters": []
+                    }
+                }
+            ]
+        }"#;
+        let ahap = serde_json::from_str::<Ahap>(static_ahap).unwrap();
+        let v1_data = v1::DataModel::try_from(ahap).unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&v1_data.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                amp(0.0, 0.25),
+                amp(0.5, 0.25),
+                amp(0.5, 0.0),
+                amp(1.0, 0.0),
+                amp(1.0, 1.0),
+                amp(2.0, 1.0),
+                amp(2.0, 0.0),
+            ]
+        );
+        let frequency = v1_data.signals.continuous.envelopes.frequency.unwrap();
+        let frequency = frequency
+            .iter()
+            .map(|breakpoint| {
+                (
+                    breakpoint.time,
+                    (breakpoint.frequency * 100.0).round() / 100.0,
+                )
+            })
+            .collect::<Vec<(f32, f32)>>();
+        assert_eq!(
+            frequency,
+            vec![(0.0, 0.64), (0.5, 0.64), (1.0, 0.0), (2.0, 0.0)]
+        );
+
+        // A static intensity is still unsupported if a parameter curve overlaps the event
+        let v1_data: v1::DataModel = serde_json::from_str::<v1::DataModel>(
+            &load_file_from_test_data("valid_required_v1.haptic"),
+        )
+        .unwrap();
+        let mut ahap = Ahap::from(v1_data);
+        for pattern in ahap.pattern.iter_mut() {
+            if let Pattern::Event(Event::HapticContinuous {
+                event_parameters, ..
+            }) = pattern
+            {
+                for parameter in event_parameters.iter_mut() {
+                    if parameter.parameter_id == ParameterId::HapticIntensity {
+                        parameter.parameter_value = 0.5;
+                    }
+                }
+            }
+        }
+        let err = v1::DataModel::try_from(ahap).unwrap_err();
+        assert!(err.contains("only 1 is supported"), "{}", err);
+    }
 }
@@ -1,71 +1,330 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
 //! Crate containing Lofelt Data model related functions, schema and versioning.
+//!
+//! With the default `std` feature disabled, only the breakpoint types in `breakpoints` and the
+//! transformations in `transform` and `emphasis` are available, which only need `alloc`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod breakpoints;
+#[cfg(all(feature = "std", feature = "csv"))]
+pub mod csv_import;
 pub mod emphasis;
+#[cfg(feature = "std")]
 pub mod interpolation;
+#[cfg(feature = "std")]
 pub mod ios;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
 pub mod test_utils;
+pub mod transform;
+#[cfg(feature = "std")]
 pub mod v0;
+#[cfg(feature = "std")]
 pub mod v1;
+#[cfg(feature = "std")]
 pub mod version;
+#[cfg(feature = "std")]
 pub mod waveform;
 
+#[cfg(feature = "msgpack")]
+use serde::Deserialize;
+#[cfg(feature = "std")]
 pub use v1 as latest;
+#[cfg(feature = "std")]
 use version::*;
 
 /// Constants for DataModel validation.
+#[cfg(feature = "std")]
 const MAX_ENVELOPE_AMPLITUDE: f32 = 1.0;
+#[cfg(feature = "std")]
 const MIN_ENVELOPE_AMPLITUDE: f32 = 0.0;
 
+#[cfg(feature = "std")]
 pub enum DataModel {
     V0(v0::DataModel),
     V1(v1::DataModel),
 }
 
+#[cfg(feature = "std")]
+impl DataModel {
+    /// Serializes the Lofelt Data to a compact JSON string, in the format of its version.
+    pub fn to_json(&self) -> Result<String, String> {
+        match self {
+            DataModel::V0(data) => serde_json::to_string(data),
+            DataModel::V1(data) => serde_json::to_string(data),
+        }
+        .map_err(|e| format!("Error serializing V{}: {}", self.version().major, e))
+    }
+
+    /// Serializes the Lofelt Data to an indented JSON string, in the format of its version.
+    pub fn to_json_pretty(&self) -> Result<String, String> {
+        match self {
+            DataModel::V0(data) => serde_json::to_string_pretty(data),
+            DataModel::V1(data) => serde_json::to_string_pretty(data),
+        }
+        .map_err(|e| format!("Error serializing V{}: {}", self.version().major, e))
+    }
+
+    /// Serializes the Lofelt Data to MessagePack, a compact binary format.
+    ///
+    /// The version is written first, followed by the data in the format of its version, so that
+    /// from_msgpack() can pick the right data model before decoding the data.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::new();
+        rmp_serde::encode::write_named(&mut buffer, &self.version())
+            .and_then(|_| match self {
+                DataModel::V0(data) => rmp_serde::encode::write_named(&mut buffer, data),
+                DataModel::V1(data) => rmp_serde::encode::write_named(&mut buffer, data),
+            })
+            .map_err(|e| format!("Error serializing V{}: {}", self.version().major, e))?;
+        Ok(buffer)
+    }
+
+    fn version(&self) -> Version {
+        match self {
+            DataModel::V0(data) => data.version,
+            DataModel::V1(data) => data.version,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 #[derive(PartialEq, Debug)]
 pub enum VersionSupport {
     Full,
     Partial,
 }
 
+#[cfg(feature = "std")]
+impl VersionSupport {
+    /// Returns how well data of the `loaded` version is supported by a data model of the
+    /// `current` version.
+    ///
+    /// Support is full if `loaded` is compatible with `current`, see
+    /// `Version::is_compatible_with()`. Otherwise the data may use features that the data model
+    /// doesn't know about, and support is partial.
+    pub fn for_version(loaded: &Version, current: &Version) -> VersionSupport {
+        if loaded.is_compatible_with(current) {
+            VersionSupport::Full
+        } else {
+            VersionSupport::Partial
+        }
+    }
+}
+
+/// A note about loaded Lofelt Data that didn't prevent loading it, but that may explain why a
+/// clip doesn't play back as authored, see `latest_from_json_with_diagnostics()`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    /// The data has a newer minor version than the data model, for example because it was
+    /// created with a newer version of Studio Desktop than the SDK. Fields unknown to the data
+    /// model were ignored.
+    NewerVersion { loaded: Version, supported: Version },
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::NewerVersion { loaded, supported } => write!(
+                f,
+                "Loaded version {} is newer than the supported version {}, fields unknown to \
+                 version {} were ignored",
+                loaded, supported, supported
+            ),
+        }
+    }
+}
+
+/// Error returned when loading Lofelt Data fails.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum DataModelError {
+    /// The version of the data isn't supported by this version of the data model.
+    UnsupportedVersion(Version),
+    /// The data couldn't be deserialized into the data model of the given version.
+    Deserialization {
+        version: Version,
+        source: serde_json::Error,
+    },
+    /// The MessagePack data couldn't be decoded into the data model of the given version.
+    #[cfg(feature = "msgpack")]
+    MessagePack {
+        version: Version,
+        source: rmp_serde::decode::Error,
+    },
+    /// The data was deserialized, but didn't pass validation.
+    Validation { version: Version, message: String },
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for DataModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataModelError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported version {}", version)
+            }
+            DataModelError::Deserialization { version, source } => {
+                write!(f, "Error deserializing V{}: {}", version.major, source)
+            }
+            #[cfg(feature = "msgpack")]
+            DataModelError::MessagePack { version, source } => {
+                write!(f, "Error decoding V{}: {}", version.major, source)
+            }
+            DataModelError::Validation { version, message } => {
+                write!(f, "Error validating V{}: {}", version.major, message)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DataModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DataModelError::Deserialization { source, .. } => Some(source),
+            #[cfg(feature = "msgpack")]
+            DataModelError::MessagePack { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 /// Receives a JSON string data with Lofelt Data and returns deserialized data with the correspondent
 /// version of the Lofelt Data model.
-pub fn from_json(data: &str) -> Result<DataModel, String> {
-    match Version::from_json(data) {
+#[cfg(feature = "std")]
+pub fn from_json(data: &str) -> Result<DataModel, DataModelError> {
+    from_slice(data.as_bytes())
+}
+
+/// Like from_json(), but takes the JSON as UTF-8 encoded bytes.
+///
+/// This avoids validating and copying the data into a string first when it comes in as a byte
+/// buffer, for example from a file or from the platform.
+#[cfg(feature = "std")]
+pub fn from_slice(data: &[u8]) -> Result<DataModel, DataModelError> {
+    let version = Version::from_slice(data);
+    match version {
         Version {
             major: 1,
             minor: _,
             patch: _,
-        } => match serde_json::from_str::<v1::DataModel>(data) {
-            Ok(deserialized_data) => match deserialized_data.validate() {
-                // successfully deserialized
-                Ok(validated_data) => Ok(DataModel::V1(validated_data)), // successfully validated datamodel
-                Err(e) => Err(format!("Error validating V1: {}", e)),    // validation error
-            },
-            Err(e) => Err(format!("Error deserializing V1: {}", e)),
-        },
+        } => {
+            let deserialized_data = serde_json::from_slice::<v1::DataModel>(data)
+                .map_err(|source| DataModelError::Deserialization { version, source })?;
+            let validated_data = deserialized_data
+                .validate()
+                .map_err(|message| DataModelError::Validation { version, message })?;
+            Ok(DataModel::V1(validated_data))
+        }
         Version {
             major: 0,
             minor: 2,
             patch: 0,
-        } => match serde_json::from_str::<v0::DataModel>(data) {
-            Ok(deserialized_data) => match deserialized_data.validate() {
-                // successfully deserialized
-                Ok(validated_data) => Ok(DataModel::V0(validated_data)), // successfully validated datamodel
-                Err(e) => Err(format!("Error validating V0: {}", e)),    // validation error
-            },
-            Err(e) => Err(format!("Error deserializing V0: {}", e)), // deserialization error
-        },
-        _ => Err(String::from("Unsupported version")),
+        } => {
+            let deserialized_data = serde_json::from_slice::<v0::DataModel>(data)
+                .map_err(|source| DataModelError::Deserialization { version, source })?;
+            let validated_data = deserialized_data
+                .validate()
+                .map_err(|message| DataModelError::Validation { version, message })?;
+            Ok(DataModel::V0(validated_data))
+        }
+        _ => Err(DataModelError::UnsupportedVersion(version)),
+    }
+}
+
+/// Like from_slice(), but takes MessagePack data written by DataModel::to_msgpack().
+///
+/// The version at the start of the data selects the data model that decodes the rest of it.
+/// Like in from_slice(), data without a readable version is decoded with the default version.
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack(data: &[u8]) -> Result<DataModel, DataModelError> {
+    let mut deserializer = rmp_serde::Deserializer::from_read_ref(data);
+    let version = Version::deserialize(&mut deserializer).unwrap_or_default();
+    match version {
+        Version {
+            major: 1,
+            minor: _,
+            patch: _,
+        } => {
+            let deserialized_data = v1::DataModel::deserialize(&mut deserializer)
+                .map_err(|source| DataModelError::MessagePack { version, source })?;
+            let validated_data = deserialized_data
+                .validate()
+                .map_err(|message| DataModelError::Validation { version, message })?;
+            Ok(DataModel::V1(validated_data))
+        }
+        Version {
+            major: 0,
+            minor: 2,
+            patch: 0,
+        } => {
+            let deserialized_data = v0::DataModel::deserialize(&mut deserializer)
+                .map_err(|source| DataModelError::MessagePack { version, source })?;
+            let validated_data = deserialized_data
+                .validate()
+                .map_err(|message| DataModelError::Validation { version, message })?;
+            Ok(DataModel::V0(validated_data))
+        }
+        _ => Err(DataModelError::UnsupportedVersion(version)),
     }
 }
 
 /// Like from_json(), but also upgrades the datamodel to the latest version.
-pub fn latest_from_json(data: &str) -> Result<(VersionSupport, latest::DataModel), String> {
+#[cfg(feature = "std")]
+pub fn latest_from_json(data: &str) -> Result<(VersionSupport, latest::DataModel), DataModelError> {
     upgrade_to_latest(&from_json(data)?)
 }
 
+/// Like latest_from_json(), but also returns diagnostics about the loaded data, like data of a
+/// newer version being loaded with partial support.
+#[cfg(feature = "std")]
+pub fn latest_from_json_with_diagnostics(
+    data: &str,
+) -> Result<(VersionSupport, latest::DataModel, Vec<Diagnostic>), DataModelError> {
+    let (version_support, data_model) = latest_from_json(data)?;
+    let mut diagnostics = Vec::new();
+    if version_support == VersionSupport::Partial {
+        // upgrade_to_latest() keeps the version of data newer than CURRENT
+        diagnostics.push(Diagnostic::NewerVersion {
+            loaded: data_model.version,
+            supported: latest::DataModel::CURRENT,
+        });
+    }
+    Ok((version_support, data_model, diagnostics))
+}
+
+/// Like from_slice(), but also upgrades the datamodel to the latest version.
+#[cfg(feature = "std")]
+pub fn latest_from_slice(
+    data: &[u8],
+) -> Result<(VersionSupport, latest::DataModel), DataModelError> {
+    upgrade_to_latest(&from_slice(data)?)
+}
+
+/// Parses, upgrades and validates a clip, returning it as the latest data model version.
+///
+/// This is the single entry point for code that only needs a valid clip, like asset importers
+/// or command line tools, and doesn't need to know which version the clip was stored in.
+/// Unlike latest_from_json(), the clip is validated again after upgrading, so that clips upgraded
+/// from an older version are guaranteed to be valid as well.
+#[cfg(feature = "std")]
+pub fn load_validated(data: &str) -> Result<latest::DataModel, DataModelError> {
+    let (_, data_model) = latest_from_json(data)?;
+    let version = data_model.version;
+    data_model
+        .validate()
+        .map_err(|message| DataModelError::Validation { version, message })
+}
+
 /// Datamodel Validation trait
+#[cfg(feature = "std")]
 pub trait Validation {
     fn validate(self) -> Result<Self, String>
     where
@@ -73,10 +332,30 @@ pub trait Validation {
 }
 
 /// Upgrades Lofelt Data to the latest version available
-pub fn upgrade_to_latest(data: &DataModel) -> Result<(VersionSupport, latest::DataModel), String> {
+#[cfg(feature = "std")]
+pub fn upgrade_to_latest(
+    data: &DataModel,
+) -> Result<(VersionSupport, latest::DataModel), DataModelError> {
     match data {
         DataModel::V0(v0_data) => Ok((VersionSupport::Full, v1::DataModel::from(v0_data.clone()))),
         DataModel::V1(v1) => {
+            // If the version of "data" is incompatible because it is higher than CURRENT, the
+            // data is used as is, and only a warning is logged. Fields unknown to CURRENT have
+            // already been ignored when deserializing.
+            // This can happen when trying to load a .haptic file that was created
+            // with a version of Studio Desktop that is more recent than the SDK.
+            // Example: CURRENT is 1.3, and the version of "data" is 1.4.
+            let version_support =
+                VersionSupport::for_version(&v1.version, &latest::DataModel::CURRENT);
+            if version_support == VersionSupport::Partial {
+                log::warn!(
+                    "{}",
+                    Diagnostic::NewerVersion {
+                        loaded: v1.version,
+                        supported: latest::DataModel::CURRENT,
+                    }
+                );
+            }
             if v1.version < latest::DataModel::CURRENT {
                 // If the version of "data" is lower than CURRENT, we run upgrade code.
                 // Example: CURRENT is 1.2, and the version of "data" is 1.1.
@@ -85,16 +364,9 @@ pub fn upgrade_to_latest(data: &DataModel) -> Result<(VersionSupport, latest::Da
                 // TODO: Add upgrade code here, once we have a 1.x version
                 v1_latest.version = latest::DataModel::CURRENT;
 
-                Ok((VersionSupport::Full, v1_latest))
-            } else if v1.version == latest::DataModel::CURRENT {
-                Ok((VersionSupport::Full, v1.clone()))
+                Ok((version_support, v1_latest))
             } else {
-                // If the version of "data" is higher than CURRENT, we do nothing here.
-                // Elsewhere a warning is printed.
-                // This can happen when trying to load a .haptic file that was created
-                // with a version of Studio Desktop that is more recent than the SDK.
-                // Example: CURRENT is 1.3, and the version of "data" is 1.4.
-                Ok((VersionSupport::Partial, v1.clone()))
+                Ok((version_support, v1.clone()))
             }
         }
     }
@@ -129,14 +401,92 @@ mod tests {
         };
     }
 
+    /// Tests that parsing from bytes gives the same result as parsing from a string
     #[test]
-    fn test_invalid_version_v1_from_json() {
-        let data_json = load_file_from_test_data("invalid_version_v1.haptic");
+    fn test_valid_v1_from_slice() {
+        let data_json = load_test_file_valid_v1();
 
+        let (version_support, data_v1) = latest_from_slice(data_json.as_bytes()).unwrap();
+        assert_eq!(version_support, VersionSupport::Full);
+        assert_eq!(data_v1, latest_from_json(&data_json).unwrap().1);
         assert_eq!(
-            from_json(&data_json).err(),
-            Some("Unsupported version".to_string())
+            Version::from_slice(data_json.as_bytes()),
+            Version::from_json(&data_json)
         );
+
+        let err = from_slice(&[0xff, 0xfe]).map(|_| ()).unwrap_err();
+        assert!(matches!(err, DataModelError::Deserialization { .. }));
+    }
+
+    /// Tests that serializing and parsing again results in the same data
+    #[test]
+    fn test_to_json_round_trip() {
+        let data = from_json(&load_test_file_valid_v1()).unwrap();
+        let original_signals = match &data {
+            DataModel::V1(data_v1) => data_v1.signals.clone(),
+            DataModel::V0(_) => panic!("Version should be V1"),
+        };
+
+        for json in [data.to_json().unwrap(), data.to_json_pretty().unwrap()].iter() {
+            match from_json(json).unwrap() {
+                DataModel::V1(data_v1) => assert_eq!(data_v1.signals, original_signals),
+                DataModel::V0(_) => panic!("Version should be V1"),
+            }
+        }
+
+        let data_v0 = from_json(&load_file_from_test_data("valid_v0.vij")).unwrap();
+        assert!(matches!(
+            from_json(&data_v0.to_json().unwrap()).unwrap(),
+            DataModel::V0(_)
+        ));
+    }
+
+    /// Tests that encoding to MessagePack and decoding again results in the same data
+    #[test]
+    #[cfg(feature = "msgpack")]
+    fn test_msgpack_round_trip() {
+        let data_json = load_test_file_valid_v1();
+        let data = from_json(&data_json).unwrap();
+        let msgpack = data.to_msgpack().unwrap();
+        assert!(msgpack.len() < data_json.len());
+        match (from_msgpack(&msgpack).unwrap(), data) {
+            (DataModel::V1(decoded), DataModel::V1(original)) => assert_eq!(decoded, original),
+            _ => panic!("Version should be V1"),
+        }
+
+        let data_v0 = from_json(&load_file_from_test_data("valid_v0.vij")).unwrap();
+        assert!(matches!(
+            from_msgpack(&data_v0.to_msgpack().unwrap()).unwrap(),
+            DataModel::V0(_)
+        ));
+
+        // The version written first decides which data model is used
+        let mut unsupported = Vec::new();
+        rmp_serde::encode::write_named(
+            &mut unsupported,
+            &Version {
+                major: 2,
+                minor: 0,
+                patch: 0,
+            },
+        )
+        .unwrap();
+        let err = from_msgpack(&unsupported).map(|_| ()).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported version 2.0.0");
+
+        let err = from_msgpack(&msgpack[..msgpack.len() / 2])
+            .map(|_| ())
+            .unwrap_err();
+        assert!(matches!(err, DataModelError::MessagePack { .. }));
+    }
+
+    #[test]
+    fn test_invalid_version_v1_from_json() {
+        let data_json = load_file_from_test_data("invalid_version_v1.haptic");
+
+        let err = from_json(&data_json).map(|_| ()).unwrap_err();
+        assert!(matches!(err, DataModelError::UnsupportedVersion(_)));
+        assert_eq!(err.to_string(), "Unsupported version 2.0.0");
     }
 
     #[test]
@@ -173,7 +523,7 @@ mod tests {
         let data_json = load_file_from_test_data("no_voices_v0.vij");
         let err = from_json(&data_json).map(|_| ()).unwrap_err();
         assert!(
-            err.contains("Error deserializing V0"),
+            err.to_string().contains("Error deserializing V0"),
             "Version should be Unreadable"
         );
     }
@@ -184,7 +534,7 @@ mod tests {
         let data_json = load_file_from_test_data("invalid_v0_conversions_transients.vij");
         let err = from_json(&data_json).map(|_| ()).unwrap_err();
         assert!(
-            err.contains("Error validating V0"),
+            err.to_string().contains("Error validating V0"),
             "Validation should fail"
         );
     }
@@ -239,7 +589,7 @@ mod tests {
         let data_json = load_file_from_test_data("validation_v1_amplitude.haptic");
         let err = from_json(&data_json).map(|_| ()).unwrap_err();
         assert!(
-            err.contains("Error validating V1"),
+            err.to_string().contains("Error validating V1"),
             "Validation should fail"
         );
     }
@@ -256,6 +606,26 @@ mod tests {
         assert_eq!(version_support, VersionSupport::Full);
     }
 
+    // Unit test for loading a validated clip in the latest version, from both v0 and v1 files
+    #[test]
+    fn test_load_validated() {
+        let target_data: v1::DataModel =
+            serde_json::from_str(&load_file_from_test_data("valid_v1_from_v0.haptic")).unwrap();
+        let data = load_validated(&load_file_from_test_data("valid_v0_conversion.vij")).unwrap();
+        assert_eq!(data.signals, target_data.signals);
+        assert_eq!(data.version, v1::DataModel::CURRENT);
+
+        load_validated(&load_test_file_valid_v1()).unwrap();
+
+        let err = load_validated(&load_file_from_test_data("validation_v1_amplitude.haptic"))
+            .map(|_| ())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Error validating V1"),
+            "Validation should fail"
+        );
+    }
+
     // Unit test for loading .haptic file with a higher minor version than what we support
     #[test]
     #[cfg(not(target_os = "ios"))]
@@ -265,6 +635,55 @@ mod tests {
         assert_eq!(version_support, VersionSupport::Partial);
     }
 
+    // Tests that loading a newer minor version produces a diagnostic, and that loading a
+    // supported version doesn't
+    #[test]
+    #[cfg(not(target_os = "ios"))]
+    fn test_newer_minor_version_diagnostic() {
+        let data = load_file_from_test_data("v1_additional_fields.haptic");
+        let (version_support, data_model, diagnostics) =
+            latest_from_json_with_diagnostics(&data).unwrap();
+        assert_eq!(version_support, VersionSupport::Partial);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::NewerVersion {
+                loaded: data_model.version,
+                supported: latest::DataModel::CURRENT,
+            }]
+        );
+        assert!(diagnostics[0].to_string().starts_with(&format!(
+            "Loaded version {} is newer than the supported version {}",
+            data_model.version,
+            latest::DataModel::CURRENT
+        )));
+
+        let (_, _, diagnostics) =
+            latest_from_json_with_diagnostics(&load_test_file_valid_v1()).unwrap();
+        assert!(diagnostics.is_empty());
+    }
+
+    // Tests the version support for older, newer and incompatible versions
+    #[test]
+    fn test_version_support_for_version() {
+        let version = |major, minor| Version {
+            major,
+            minor,
+            patch: 0,
+        };
+        assert_eq!(
+            VersionSupport::for_version(&version(1, 0), &version(1, 1)),
+            VersionSupport::Full
+        );
+        assert_eq!(
+            VersionSupport::for_version(&version(1, 2), &version(1, 0)),
+            VersionSupport::Partial
+        );
+        assert_eq!(
+            VersionSupport::for_version(&version(2, 0), &version(1, 0)),
+            VersionSupport::Partial
+        );
+    }
+
     // Unit test for default version when creating datamodel by hand
     #[test]
     fn test_default_version() {
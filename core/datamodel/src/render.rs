@@ -0,0 +1,82 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! Offline rendering of a clip into a dense array of amplitude samples.
+//!
+//! The result is platform-independent and deterministic, which makes it suitable for
+//! waveform thumbnails and for comparing against golden files in tests.
+
+use crate::{
+    emphasis::{emphasize, EmphasisParameters},
+    v1::{amplitude_after, DataModel},
+};
+
+/// Renders the amplitude envelope of `clip` into samples taken at `sample_rate_hz`.
+///
+/// Emphasis is rendered into the continuous amplitude signal first, using the default
+/// `EmphasisParameters`, the same way it is done for players without support for
+/// transients. The amplitude is then interpolated linearly between breakpoints.
+///
+/// Sample `n` is taken at `n / sample_rate_hz` seconds, from the start of the clip up to
+/// and including its end. At a step with multiple breakpoints at the same time, the
+/// amplitude after the step is used. Frequency is ignored.
+///
+/// Returns an empty array if `sample_rate_hz` is 0 or if the clip has no amplitude
+/// breakpoints.
+pub fn render_samples(clip: &DataModel, sample_rate_hz: u32) -> Vec<f32> {
+    let amplitudes = emphasize(
+        &clip.signals.continuous.envelopes.amplitude,
+        EmphasisParameters::default(),
+    );
+    let end_time = match amplitudes.last() {
+        Some(last) if sample_rate_hz > 0 => last.time,
+        _ => return Vec::new(),
+    };
+
+    let sample_rate = sample_rate_hz as f32;
+    let sample_count = (end_time * sample_rate).floor() as usize + 1;
+    (0..sample_count)
+        .map(|index| amplitude_after(&amplitudes, index as f32 / sample_rate))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_samples;
+    use crate::{
+        test_utils::{amp, emp},
+        v1::{AmplitudeBreakpoint, DataModel},
+    };
+
+    fn clip_with_amplitudes(amplitudes: Vec<AmplitudeBreakpoint>) -> DataModel {
+        let mut clip = DataModel::default();
+        clip.signals.continuous.envelopes.amplitude = amplitudes;
+        clip
+    }
+
+    // Checks that the amplitude envelope is sampled with linear interpolation
+    #[test]
+    fn render_interpolated() {
+        let clip = clip_with_amplitudes(vec![amp(0.0, 0.0), amp(0.1, 1.0), amp(0.2, 0.5)]);
+        assert_eq!(render_samples(&clip, 20), vec![0.0, 0.5, 1.0, 0.75, 0.5]);
+    }
+
+    // Checks that emphasis is rendered into the samples, with the default ducking and
+    // emphasis lengths of 30ms
+    #[test]
+    fn render_emphasis() {
+        let clip =
+            clip_with_amplitudes(vec![amp(0.0, 0.5), emp(0.1, 0.5, 0.8, 0.5), amp(0.2, 0.5)]);
+        assert_eq!(
+            render_samples(&clip, 40),
+            vec![0.5, 0.5, 0.5, 0.0, 1.0, 1.0, 0.0, 0.5, 0.5]
+        );
+    }
+
+    // Checks that nothing is rendered for an empty clip or a sample rate of 0
+    #[test]
+    fn render_empty() {
+        assert!(render_samples(&clip_with_amplitudes(Vec::new()), 100).is_empty());
+        let clip = clip_with_amplitudes(vec![amp(0.0, 0.5), amp(0.1, 0.5)]);
+        assert!(render_samples(&clip, 0).is_empty());
+    }
+}
@@ -1,9 +1,6 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
-use crate::{
-    v1::{AmplitudeBreakpoint, Emphasis},
-    waveform::Waveform,
-};
+use crate::{v1::AmplitudeBreakpoint, waveform::Waveform};
 use utils::test_utils::{self, rounded_f32};
 
 /// Prints the given list of breakpoints as CSV format, so that the output can be copy & pasted
@@ -58,14 +55,7 @@ pub fn emp(
     emphasis_amplitude: f32,
     emphasis_frequency: f32,
 ) -> AmplitudeBreakpoint {
-    AmplitudeBreakpoint {
-        time,
-        amplitude,
-        emphasis: Some(Emphasis {
-            amplitude: emphasis_amplitude,
-            frequency: emphasis_frequency,
-        }),
-    }
+    AmplitudeBreakpoint::with_emphasis(time, amplitude, emphasis_amplitude, emphasis_frequency)
 }
 
 // Helper function to round amplitude breakpoints ´time´ and ´amplitude´ values using
@@ -0,0 +1,205 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+
+//! Transformations of breakpoint envelopes that only need `alloc`, not `std`.
+//!
+//! The methods of `v1::DataModel` with the same names delegate to these functions, which makes
+//! them usable with the `std` feature disabled, e.g. on embedded systems.
+
+use crate::breakpoints::{AmplitudeBreakpoint, FrequencyBreakpoint};
+use alloc::vec::Vec;
+
+/// Removes all breakpoints before `time` (in seconds) from the envelopes, see
+/// `v1::DataModel::truncate_before()`.
+///
+/// A frequency envelope without breakpoints at or after `time` is removed.
+pub fn truncate_before(
+    amplitudes: &mut Vec<AmplitudeBreakpoint>,
+    frequency_envelope: &mut Option<Vec<FrequencyBreakpoint>>,
+    time: f32,
+) -> Result<(), &'static str> {
+    //
+    // Truncate amplitude
+    //
+    let index_of_first_breakpoint_in_range = amplitudes
+        .iter()
+        .position(|breakpoint| breakpoint.time >= time);
+
+    if index_of_first_breakpoint_in_range.is_none() {
+        return Err("No amplitude breakpoint before the specified starting time");
+    }
+
+    let index_of_first_breakpoint_in_range = index_of_first_breakpoint_in_range.unwrap();
+    if index_of_first_breakpoint_in_range > 0 {
+        let breakpoint_before = &amplitudes[index_of_first_breakpoint_in_range - 1];
+        let breakpoint_after = &amplitudes[index_of_first_breakpoint_in_range];
+        let new_first_breakpoint = if breakpoint_after.time - breakpoint_before.time > f32::EPSILON
+        {
+            Some(AmplitudeBreakpoint {
+                time: 0.0,
+                amplitude: utils::interpolate(
+                    breakpoint_before.time,
+                    breakpoint_after.time,
+                    breakpoint_before.amplitude,
+                    breakpoint_after.amplitude,
+                    time,
+                ),
+                emphasis: None,
+            })
+        } else {
+            None
+        };
+
+        // Remove breakpoints before `time`
+        amplitudes.retain(|breakpoint| breakpoint.time >= time);
+
+        // Shift the time of all breakpoints by `time`
+        for breakpoint in amplitudes.iter_mut() {
+            breakpoint.time -= time;
+        }
+
+        // Insert a new first breakpoint
+        if let Some(new_first_breakpoint) = new_first_breakpoint {
+            amplitudes.insert(0, new_first_breakpoint);
+        }
+    }
+
+    //
+    // Truncate frequency
+    // Same algorithm as for the amplitude, except that the frequency envelope is optional.
+    //
+    if let Some(frequencies) = frequency_envelope {
+        let index_of_first_breakpoint_in_range = frequencies
+            .iter()
+            .position(|breakpoint| breakpoint.time >= time);
+
+        if let Some(index_of_first_breakpoint_in_range) = index_of_first_breakpoint_in_range {
+            if index_of_first_breakpoint_in_range > 0 {
+                let breakpoint_before = &frequencies[index_of_first_breakpoint_in_range - 1];
+                let breakpoint_after = &frequencies[index_of_first_breakpoint_in_range];
+                let new_first_breakpoint =
+                    if breakpoint_after.time - breakpoint_before.time > f32::EPSILON {
+                        Some(FrequencyBreakpoint {
+                            time: 0.0,
+                            frequency: utils::interpolate(
+                                breakpoint_before.time,
+                                breakpoint_after.time,
+                                breakpoint_before.frequency,
+                                breakpoint_after.frequency,
+                                time,
+                            ),
+                        })
+                    } else {
+                        None
+                    };
+                frequencies.retain(|breakpoint| breakpoint.time >= time);
+                for breakpoint in frequencies.iter_mut() {
+                    breakpoint.time -= time;
+                }
+                if let Some(new_first_breakpoint) = new_first_breakpoint {
+                    frequencies.insert(0, new_first_breakpoint);
+                }
+            }
+        } else {
+            *frequency_envelope = None;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes all breakpoints after `time` (in seconds) from the envelopes, see
+/// `v1::DataModel::truncate_after()`.
+///
+/// A frequency envelope without breakpoints at or before `time` is removed.
+pub fn truncate_after(
+    amplitudes: &mut Vec<AmplitudeBreakpoint>,
+    frequency_envelope: &mut Option<Vec<FrequencyBreakpoint>>,
+    time: f32,
+) -> Result<(), &'static str> {
+    //
+    // Truncate amplitude
+    //
+    let index_of_first_breakpoint_out_of_range = amplitudes
+        .iter()
+        .position(|breakpoint| breakpoint.time > time);
+
+    if let Some(index_of_first_breakpoint_out_of_range) = index_of_first_breakpoint_out_of_range {
+        // Truncating at the first breakpoint would leave an envelope with a duration of 0
+        if index_of_first_breakpoint_out_of_range == 0 || time <= amplitudes[0].time {
+            return Err("No amplitude breakpoint before the specified end time");
+        }
+
+        let breakpoint_before = &amplitudes[index_of_first_breakpoint_out_of_range - 1];
+        let breakpoint_after = &amplitudes[index_of_first_breakpoint_out_of_range];
+        let new_last_breakpoint = if time - breakpoint_before.time > f32::EPSILON {
+            Some(AmplitudeBreakpoint::from_interpolated_breakpoints(
+                breakpoint_before,
+                breakpoint_after,
+                time,
+            ))
+        } else {
+            None
+        };
+
+        // Remove breakpoints after `time`
+        amplitudes.truncate(index_of_first_breakpoint_out_of_range);
+
+        // Insert a new last breakpoint
+        if let Some(new_last_breakpoint) = new_last_breakpoint {
+            amplitudes.push(new_last_breakpoint);
+        }
+    }
+
+    //
+    // Truncate frequency
+    // Same algorithm as for the amplitude, except that the frequency envelope is optional.
+    //
+    if let Some(frequencies) = frequency_envelope {
+        let index_of_first_breakpoint_out_of_range = frequencies
+            .iter()
+            .position(|breakpoint| breakpoint.time > time);
+
+        match index_of_first_breakpoint_out_of_range {
+            Some(0) => *frequency_envelope = None,
+            Some(index_of_first_breakpoint_out_of_range) => {
+                let breakpoint_before = &frequencies[index_of_first_breakpoint_out_of_range - 1];
+                let breakpoint_after = &frequencies[index_of_first_breakpoint_out_of_range];
+                let new_last_breakpoint = if time - breakpoint_before.time > f32::EPSILON {
+                    Some(FrequencyBreakpoint::from_interpolated_breakpoints(
+                        breakpoint_before,
+                        breakpoint_after,
+                        time,
+                    ))
+                } else {
+                    None
+                };
+                frequencies.truncate(index_of_first_breakpoint_out_of_range);
+                if let Some(new_last_breakpoint) = new_last_breakpoint {
+                    frequencies.push(new_last_breakpoint);
+                }
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Multiplies the time of all breakpoints by `factor`, see `v1::DataModel::scale_time()`.
+///
+/// `factor` needs to be finite and greater than 0.
+pub fn scale_time(
+    amplitudes: &mut [AmplitudeBreakpoint],
+    frequencies: Option<&mut [FrequencyBreakpoint]>,
+    factor: f32,
+) {
+    debug_assert!(factor.is_finite() && factor > 0.0);
+    for breakpoint in amplitudes.iter_mut() {
+        breakpoint.time *= factor;
+    }
+    if let Some(frequencies) = frequencies {
+        for breakpoint in frequencies.iter_mut() {
+            breakpoint.time *= factor;
+        }
+    }
+}
@@ -53,6 +53,50 @@ pub struct MetaData {
 pub struct Voices {
     pub envelopes: Vec<Envelope>,
     pub transients: Vec<Envelope>,
+
+    /// (optional) The type of each envelope in `envelopes`, in the same order.
+    ///
+    /// Older files don't have this field. For those, the first envelope is the amplitude
+    /// envelope and the second envelope, if present, is the frequency envelope.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub envelope_types: Option<Vec<EnvelopeType>>,
+}
+
+/// The type of an envelope in `Voices::envelopes`
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvelopeType {
+    Amplitude,
+    Frequency,
+}
+
+impl Voices {
+    /// Returns the index of the envelope with the given type in `envelopes`, if any
+    fn envelope_index(&self, envelope_type: EnvelopeType) -> Option<usize> {
+        match &self.envelope_types {
+            Some(envelope_types) => envelope_types
+                .iter()
+                .position(|t| *t == envelope_type)
+                .filter(|index| *index < self.envelopes.len()),
+            None => match envelope_type {
+                EnvelopeType::Amplitude if !self.envelopes.is_empty() => Some(0),
+                EnvelopeType::Frequency if self.envelopes.len() == 2 => Some(1),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the amplitude envelope, if any
+    pub fn amplitude_envelope(&self) -> Option<&Envelope> {
+        self.envelope_index(EnvelopeType::Amplitude)
+            .map(|index| &self.envelopes[index])
+    }
+
+    /// Returns the frequency envelope, if any
+    pub fn frequency_envelope(&self) -> Option<&Envelope> {
+        self.envelope_index(EnvelopeType::Frequency)
+            .map(|index| &self.envelopes[index])
+    }
 }
 
 pub type Envelope = Vec<Breakpoint>;
@@ -66,18 +110,48 @@ pub struct Breakpoint {
 
 /// Validation trait implementation
 /// An invalid Data Model would be one that:
-/// - Has no breakpoints at all.
-/// - Has no amplitude breakpoints.
+/// - Has no breakpoints and no transients at all.
+/// - Has no amplitude breakpoints and no transients.
+/// - Has envelope types that don't match the envelopes.
 /// - Breakpoint and transient amplitude values are < 0.0 or > 1.0.
 /// - The breakpoint or transient time values are not consecutive.
 /// - The transient amplitude and frequency values have no matching pairs or have different lengths.
 impl Validation for DataModel {
     fn validate(self) -> Result<Self, String> {
-        if self.voices.envelopes.is_empty() {
+        // A file with transients only is valid, an amplitude envelope is synthesized for it
+        // when upgrading to V1
+        let has_transients = !self.voices.transients.is_empty();
+
+        if self.voices.envelopes.is_empty() && !has_transients {
             return Err(String::from("V0 Validation Error: Envelopes are empty"));
         }
 
-        if self.voices.envelopes[0].is_empty() {
+        if let Some(envelope_types) = &self.voices.envelope_types {
+            if envelope_types.len() != self.voices.envelopes.len() {
+                return Err(format!(
+                    "V0 Validation Error: {} envelope types for {} envelopes",
+                    envelope_types.len(),
+                    self.voices.envelopes.len()
+                ));
+            }
+            for envelope_type in &[EnvelopeType::Amplitude, EnvelopeType::Frequency] {
+                if envelope_types
+                    .iter()
+                    .filter(|t| *t == envelope_type)
+                    .count()
+                    > 1
+                {
+                    return Err(format!(
+                        "V0 Validation Error: More than one envelope of type {:?}",
+                        envelope_type
+                    ));
+                }
+            }
+        }
+
+        let has_amplitude_breakpoints =
+            matches!(self.voices.amplitude_envelope(), Some(envelope) if !envelope.is_empty());
+        if !has_transients && !has_amplitude_breakpoints {
             return Err(String::from(
                 "V0 Validation Error: Amplitude envelope is empty",
             ));
@@ -285,6 +359,7 @@ mod tests {
         let test_voices = Voices {
             envelopes: test_breakpoints,
             transients: test_transients,
+            envelope_types: None,
         };
 
         assert_eq!(vij.version, test_version);
@@ -315,6 +390,7 @@ mod tests {
         let test_voices = Voices {
             envelopes: test_breakpoints,
             transients: test_transients,
+            envelope_types: None,
         };
 
         assert_eq!(vij.version, test_version);
@@ -354,6 +430,7 @@ mod tests {
         let test_voices = Voices {
             envelopes: test_breakpoints,
             transients: test_transients,
+            envelope_types: None,
         };
 
         let test_datamodel = DataModel {
@@ -381,6 +458,7 @@ mod tests {
         let test_voices = Voices {
             envelopes: test_breakpoints,
             transients: test_transients,
+            envelope_types: None,
         };
 
         assert_eq!(deserialized_data.version, test_version);
@@ -422,11 +500,53 @@ mod tests {
     /// Unit test datamodel validation.
     #[test]
     fn check_validation_fail_envelopes() {
+        let vij = DataModel::default();
+        let err = vij.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Envelopes are empty"),
+            "Failed validation at wrong point: {}",
+            err
+        );
+    }
+
+    /// Unit test datamodel validation of a file with only transients, which is valid.
+    #[test]
+    fn check_validation_pass_transients_only() {
         let data = load_file_from_test_data("no_envelopes_v0.vij");
         let vij: DataModel = serde_json::from_str(&data).unwrap();
+        vij.validate().unwrap();
+    }
+
+    /// Unit test for finding the amplitude and frequency envelopes with and without explicit
+    /// envelope types.
+    #[test]
+    fn check_envelope_types() {
+        let mut voices = Voices {
+            envelopes: generate_test_envelopes(),
+            transients: vec![],
+            envelope_types: None,
+        };
+        assert_eq!(voices.amplitude_envelope(), Some(&voices.envelopes[0]));
+        assert_eq!(voices.frequency_envelope(), Some(&voices.envelopes[1]));
+
+        voices.envelope_types = Some(vec![EnvelopeType::Frequency, EnvelopeType::Amplitude]);
+        assert_eq!(voices.amplitude_envelope(), Some(&voices.envelopes[1]));
+        assert_eq!(voices.frequency_envelope(), Some(&voices.envelopes[0]));
+
+        voices.envelope_types = Some(vec![EnvelopeType::Frequency]);
+        assert_eq!(voices.amplitude_envelope(), None);
+
+        let vij = DataModel {
+            metadata: MetaData {
+                editor: "".to_owned(),
+                duration: 1.0,
+            },
+            voices,
+            ..Default::default()
+        };
         let err = vij.validate().map(|_| ()).unwrap_err();
         assert!(
-            err.contains("Envelopes are empty"),
+            err.contains("1 envelope types for 2 envelopes"),
             "Failed validation at wrong point: {}",
             err
         );
@@ -2,12 +2,17 @@
 
 //! Lofelt Data Model Version 1.0.0
 
+use crate::emphasis::{emphasize, EmphasisParameters};
+use crate::transform;
 use crate::version::{DataModelVersion, Version};
+use crate::waveform::is_motor_off;
 use crate::Validation;
 use crate::MAX_ENVELOPE_AMPLITUDE;
 use crate::MIN_ENVELOPE_AMPLITUDE;
 use serde::{Deserialize, Serialize};
 
+pub use crate::breakpoints::{AmplitudeBreakpoint, Emphasis, FrequencyBreakpoint};
+
 impl DataModelVersion for DataModel {
     const CURRENT: Version = Version {
         major: 1,
@@ -54,6 +59,12 @@ pub struct MetaData {
     pub tags: Vec<String>,
     #[serde(default)]
     pub description: String,
+    /// Amplitude factor, 0 or greater, applied to the whole clip when it is loaded.
+    ///
+    /// This corrects clips that were authored louder or quieter than intended. It is only
+    /// serialized when present, so that files without a gain stay unchanged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f32>,
 }
 
 /// Signal structure that describes haptic data.
@@ -80,70 +91,336 @@ pub struct Envelopes {
     pub frequency: Option<Vec<FrequencyBreakpoint>>,
 }
 
-/// Amplitude breakpoints of a `SignalContinuous` Amplitude envelope. Allows to apply emphasis to a point.
-#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub struct AmplitudeBreakpoint {
-    pub time: f32,
-    pub amplitude: f32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub emphasis: Option<Emphasis>,
-}
-
-impl AmplitudeBreakpoint {
-    pub fn from_interpolated_breakpoints(
-        breakpoint_a: &AmplitudeBreakpoint,
-        breakpoint_b: &AmplitudeBreakpoint,
-        time: f32,
-    ) -> Self {
-        AmplitudeBreakpoint {
-            time,
-            amplitude: utils::interpolate(
-                breakpoint_a.time,
-                breakpoint_b.time,
-                breakpoint_a.amplitude,
-                breakpoint_b.amplitude,
-                time,
-            ),
-            emphasis: None,
+impl From<Vec<AmplitudeBreakpoint>> for Envelopes {
+    /// Creates envelopes with the given amplitude breakpoints and without a frequency envelope.
+    fn from(amplitude: Vec<AmplitudeBreakpoint>) -> Self {
+        Self {
+            amplitude,
+            frequency: None,
+        }
+    }
+}
+
+impl Signals {
+    /// Creates signals with a continuous signal that only has an amplitude envelope, for
+    /// example one that was computed instead of authored.
+    pub fn from_amplitude(amplitude: Vec<AmplitudeBreakpoint>) -> Self {
+        Self {
+            continuous: SignalContinuous {
+                envelopes: Envelopes::from(amplitude),
+            },
+        }
+    }
+}
+
+impl From<Signals> for DataModel {
+    /// Creates a clip of version `CURRENT` with the given signals and empty metadata.
+    fn from(signals: Signals) -> Self {
+        Self {
+            signals,
+            ..Default::default()
         }
     }
 }
 
-/// Emphasis structure associated with a Amplitude envelope breakpoint. Allows for a "haptic highlight" of the breakpoint.
-#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
-#[repr(C)]
-pub struct Emphasis {
-    pub amplitude: f32,
-    pub frequency: f32,
+/// A feature of the data model that a clip can make use of, and that a player may or may not be
+/// able to play back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Feature {
+    /// The clip has a frequency envelope that changes the frequency over time
+    FrequencyModulation,
+    /// The clip has at least one amplitude breakpoint with an emphasis
+    Emphasis,
 }
 
-/// Data associated with a Frequency envelope breakpoint.
-#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
-pub struct FrequencyBreakpoint {
-    pub time: f32,
-    pub frequency: f32,
+/// Statistics about the amplitude of a clip, see `DataModel::stats()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ClipStats {
+    /// The highest amplitude of all amplitude breakpoints, not including emphasis
+    pub peak_amplitude: f32,
+    /// The root mean square of the amplitude envelope, from the first to the last amplitude
+    /// breakpoint
+    pub rms_amplitude: f32,
+    /// The duration of the clip in seconds, see `DataModel::duration()`
+    pub duration: f32,
+    /// The number of amplitude and frequency breakpoints
+    pub breakpoint_count: usize,
+    /// The number of amplitude breakpoints with an emphasis
+    pub emphasis_count: usize,
 }
 
-impl FrequencyBreakpoint {
-    pub fn from_interpolated_breakpoints(
-        breakpoint_a: &FrequencyBreakpoint,
-        breakpoint_b: &FrequencyBreakpoint,
-        time: f32,
-    ) -> Self {
-        FrequencyBreakpoint {
-            time,
-            frequency: utils::interpolate(
-                breakpoint_a.time,
-                breakpoint_b.time,
-                breakpoint_a.frequency,
-                breakpoint_b.frequency,
-                time,
-            ),
+impl std::fmt::Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Feature::FrequencyModulation => write!(f, "frequency modulation"),
+            Feature::Emphasis => write!(f, "emphasis"),
+        }
+    }
+}
+
+/// Describes which haptic features the actuator of a device is able to render
+///
+/// The capabilities are detected by the platform layer and passed to the core, so that the
+/// decision on how to render a clip is made in one place for all platforms. See
+/// `DataModel::downgrade_for()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    /// Whether the strength of the vibration can be changed
+    pub amplitude_control: bool,
+    /// Whether the frequency of the vibration can be changed
+    pub frequency_control: bool,
+    /// Whether the device can play short transients natively
+    pub emphasis: bool,
+}
+
+/// How the emphasis of a clip is rendered on a device, see
+/// `DeviceCapabilities::emphasis_rendering()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmphasisRendering {
+    /// Emphasis is played as native transients, like on iOS
+    Transients,
+    /// Emphasis is emulated by changing the amplitude of the vibration, like on Android
+    AmplitudeEmulation,
+    /// Emphasis can't be rendered and is dropped
+    Unsupported,
+}
+
+impl DeviceCapabilities {
+    /// Returns how the emphasis of a clip is rendered with these capabilities
+    ///
+    /// Native transients are preferred. Without them, emphasis is emulated if the amplitude can
+    /// be controlled.
+    pub fn emphasis_rendering(&self) -> EmphasisRendering {
+        if self.emphasis {
+            EmphasisRendering::Transients
+        } else if self.amplitude_control {
+            EmphasisRendering::AmplitudeEmulation
+        } else {
+            EmphasisRendering::Unsupported
+        }
+    }
+
+    /// Returns true if the given feature can be rendered, natively or emulated
+    pub fn can_render(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::FrequencyModulation => self.frequency_control,
+            Feature::Emphasis => self.emphasis_rendering() != EmphasisRendering::Unsupported,
         }
     }
 }
 
 impl DataModel {
+    /// Returns the duration of the clip in milliseconds.
+    ///
+    /// The duration is the time of the last amplitude breakpoint, which is in seconds in the
+    /// data model.
+    pub fn total_duration_ms(&self) -> i64 {
+        self.signals
+            .continuous
+            .envelopes
+            .amplitude
+            .last()
+            .map_or(0, |breakpoint| utils::seconds_to_ms(breakpoint.time))
+    }
+
+    /// Returns the duration of the clip in seconds.
+    ///
+    /// The duration is the time of the last amplitude or frequency breakpoint, whichever is
+    /// later.
+    pub fn duration(&self) -> f32 {
+        let envelopes = &self.signals.continuous.envelopes;
+        let last_amplitude_time = envelopes
+            .amplitude
+            .last()
+            .map_or(0.0, |breakpoint| breakpoint.time);
+        let last_frequency_time = envelopes
+            .frequency
+            .as_ref()
+            .and_then(|frequencies| frequencies.last())
+            .map_or(0.0, |breakpoint| breakpoint.time);
+        last_amplitude_time.max(last_frequency_time)
+    }
+
+    /// Returns the amplitude of the amplitude envelope at `time`, in seconds.
+    ///
+    /// The amplitude is interpolated linearly between breakpoints. Before the first and after
+    /// the last breakpoint, the amplitude of that breakpoint is returned. Emphasis is ignored.
+    pub fn amplitude_at(&self, time: f32) -> f32 {
+        let amplitudes = &self.signals.continuous.envelopes.amplitude;
+        match amplitudes
+            .iter()
+            .position(|breakpoint| breakpoint.time > time)
+        {
+            None => amplitudes
+                .last()
+                .map_or(0.0, |breakpoint| breakpoint.amplitude),
+            Some(0) => amplitudes[0].amplitude,
+            Some(index) => {
+                AmplitudeBreakpoint::from_interpolated_breakpoints(
+                    &amplitudes[index - 1],
+                    &amplitudes[index],
+                    time,
+                )
+                .amplitude
+            }
+        }
+    }
+
+    /// Returns the frequency of the frequency envelope at `time`, in seconds, or None if the
+    /// clip has no frequency envelope.
+    ///
+    /// Like in `amplitude_at()`, the frequency is interpolated linearly and held before the first
+    /// and after the last breakpoint.
+    pub fn frequency_at(&self, time: f32) -> Option<f32> {
+        let frequencies = self.signals.continuous.envelopes.frequency.as_ref()?;
+        match frequencies
+            .iter()
+            .position(|breakpoint| breakpoint.time > time)
+        {
+            None => frequencies.last().map(|breakpoint| breakpoint.frequency),
+            Some(0) => Some(frequencies[0].frequency),
+            Some(index) => Some(
+                FrequencyBreakpoint::from_interpolated_breakpoints(
+                    &frequencies[index - 1],
+                    &frequencies[index],
+                    time,
+                )
+                .frequency,
+            ),
+        }
+    }
+
+    /// Returns true if the motor is off at every breakpoint, see `waveform::is_motor_off()`.
+    ///
+    /// A breakpoint with an emphasis amplitude that turns the motor on is not silent, as the
+    /// emphasis is played even if the continuous amplitude is 0.0.
+    pub fn is_silent(&self) -> bool {
+        self.signals
+            .continuous
+            .envelopes
+            .amplitude
+            .iter()
+            .all(|breakpoint| {
+                is_motor_off(breakpoint.amplitude)
+                    && !matches!(breakpoint.emphasis, Some(emphasis) if !is_motor_off(emphasis.amplitude))
+            })
+    }
+
+    /// Returns statistics about the amplitude of the clip.
+    ///
+    /// The RMS amplitude integrates the square of the piecewise-linear amplitude envelope over
+    /// time. It is 0.0 if the amplitude envelope has a length of zero.
+    pub fn stats(&self) -> ClipStats {
+        let envelopes = &self.signals.continuous.envelopes;
+        let amplitudes = &envelopes.amplitude;
+
+        // The integral of the squared linear ramp from a to b over the duration d is
+        // d * (a² + ab + b²) / 3
+        let energy: f32 = amplitudes
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (pair[0].amplitude, pair[1].amplitude);
+                (pair[1].time - pair[0].time) * (a * a + a * b + b * b) / 3.0
+            })
+            .sum();
+        let envelope_length = match (amplitudes.first(), amplitudes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        };
+        let rms_amplitude = if envelope_length > 0.0 {
+            (energy / envelope_length).sqrt()
+        } else {
+            0.0
+        };
+
+        ClipStats {
+            peak_amplitude: amplitudes
+                .iter()
+                .map(|breakpoint| breakpoint.amplitude)
+                .fold(0.0, f32::max),
+            rms_amplitude,
+            duration: self.duration(),
+            breakpoint_count: amplitudes.len() + envelopes.frequency.as_ref().map_or(0, Vec::len),
+            emphasis_count: amplitudes
+                .iter()
+                .filter(|breakpoint| breakpoint.emphasis.is_some())
+                .count(),
+        }
+    }
+
+    /// Returns the features this clip makes use of.
+    ///
+    /// A player that doesn't support one of the returned features will play the clip differently
+    /// than authored.
+    pub fn required_features(&self) -> Vec<Feature> {
+        let envelopes = &self.signals.continuous.envelopes;
+        let mut features = Vec::new();
+        if matches!(&envelopes.frequency, Some(frequencies) if !frequencies.is_empty()) {
+            features.push(Feature::FrequencyModulation);
+        }
+        if envelopes
+            .amplitude
+            .iter()
+            .any(|breakpoint| breakpoint.emphasis.is_some())
+        {
+            features.push(Feature::Emphasis);
+        }
+        features
+    }
+
+    /// Removes emphasis from breakpoints so that there are at most `max_per_second` emphasis
+    /// per second.
+    ///
+    /// The rate is enforced as a minimum distance of `1.0 / max_per_second` seconds between two
+    /// emphasis. The emphasis with the highest amplitude are kept; of two emphasis with the same
+    /// amplitude, the earlier one is kept. The breakpoints themselves are not removed, only
+    /// their emphasis.
+    ///
+    /// Dense emphasis can overwhelm the motor, and emphasis that are too close together are
+    /// skipped when rendering emphasis into the amplitude envelope anyway. Thinning them out
+    /// beforehand makes sure the strongest ones are the ones that are played.
+    pub fn thin_emphasis(&mut self, max_per_second: f32) -> Result<(), String> {
+        if max_per_second.is_nan() || max_per_second.is_infinite() || max_per_second <= 0.0 {
+            return Err(format!(
+                "Unable to thin emphasis to {} per second, needs to be greater than 0",
+                max_per_second
+            ));
+        }
+        let min_distance = 1.0 / max_per_second;
+
+        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
+        let mut emphasis_indices: Vec<usize> = amplitudes
+            .iter()
+            .enumerate()
+            .filter(|(_, breakpoint)| breakpoint.emphasis.is_some())
+            .map(|(index, _)| index)
+            .collect();
+
+        // Strongest emphasis first. The sort is stable, so earlier emphasis come first on ties.
+        emphasis_indices.sort_by(|a, b| {
+            let amplitude_a = amplitudes[*a].emphasis.unwrap().amplitude;
+            let amplitude_b = amplitudes[*b].emphasis.unwrap().amplitude;
+            amplitude_b
+                .partial_cmp(&amplitude_a)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut kept_times: Vec<f32> = Vec::new();
+        for index in emphasis_indices {
+            let time = amplitudes[index].time;
+            if kept_times
+                .iter()
+                .all(|kept_time| (time - kept_time).abs() >= min_distance)
+            {
+                kept_times.push(time);
+            } else {
+                amplitudes[index].emphasis = None;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Removes all breakpoints before the specified `time` (in seconds) from the DataModel.
     ///
     /// The time of all remaining breakpoints is shifted so that the new first breakpoint starts
@@ -156,455 +433,1422 @@ impl DataModel {
     /// frequency of this new first breakpoint is an interpolation of the amplitude/frequency of its
     /// neighboring breakpoints.
     pub fn truncate_before(&mut self, time: f32) -> Result<(), String> {
-        //
-        // Truncate amplitude
-        //
-        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
-        let index_of_first_breakpoint_in_range = amplitudes
-            .iter()
-            .position(|breakpoint| breakpoint.time >= time);
-
-        if index_of_first_breakpoint_in_range.is_none() {
-            return Err("No amplitude breakpoint before the specified starting time".to_string());
-        }
-
-        let index_of_first_breakpoint_in_range = index_of_first_breakpoint_in_range.unwrap();
-        if index_of_first_breakpoint_in_range > 0 {
-            let breakpoint_before = &amplitudes[index_of_first_breakpoint_in_range - 1];
-            let breakpoint_after = &amplitudes[index_of_first_breakpoint_in_range];
-            let new_first_breakpoint =
-                if breakpoint_after.time - breakpoint_before.time > f32::EPSILON {
-                    Some(AmplitudeBreakpoint {
-                        time: 0.0,
-                        amplitude: utils::interpolate(
-                            breakpoint_before.time,
-                            breakpoint_after.time,
-                            breakpoint_before.amplitude,
-                            breakpoint_after.amplitude,
-                            time,
-                        ),
-                        emphasis: None,
-                    })
-                } else {
-                    None
-                };
+        let envelopes = &mut self.signals.continuous.envelopes;
+        transform::truncate_before(&mut envelopes.amplitude, &mut envelopes.frequency, time)
+            .map_err(String::from)
+    }
 
-            // Remove breakpoints before `time`
-            amplitudes.retain(|breakpoint| breakpoint.time >= time);
+    /// Removes all breakpoints after the specified `time` (in seconds) from the DataModel.
+    ///
+    /// Unlike with `truncate_before()`, the time of the remaining breakpoints stays the same, so
+    /// the total duration becomes `time`.
+    ///
+    /// If truncation happens to occur at exactly the same time as an existing breakpoint, no new
+    /// last breakpoint needs to be inserted.
+    /// Otherwise a new last breakpoint is inserted at `time`, with the amplitude and frequency
+    /// interpolated from its neighboring breakpoints.
+    ///
+    /// Truncating after the last breakpoint leaves the DataModel unchanged.
+    pub fn truncate_after(&mut self, time: f32) -> Result<(), String> {
+        let envelopes = &mut self.signals.continuous.envelopes;
+        transform::truncate_after(&mut envelopes.amplitude, &mut envelopes.frequency, time)
+            .map_err(String::from)
+    }
 
-            // Shift the time of all breakpoints by `time`
-            for breakpoint in amplitudes.iter_mut() {
-                breakpoint.time -= time;
-            }
+    /// Splits the DataModel at `time` (in seconds) into two independent clips.
+    ///
+    /// The first clip contains the part before `time` and ends at `time`, like with
+    /// `truncate_after()`. The second clip contains the part after `time`, shifted to start at
+    /// 0.0, like with `truncate_before()`. Both clips get a breakpoint at the split point, with
+    /// the same interpolated amplitude and frequency, so concatenating them with `concat()`
+    /// reproduces the original envelopes.
+    ///
+    /// `time` needs to be after the first and before the last amplitude breakpoint, so that
+    /// neither clip is empty.
+    pub fn split_at(&self, time: f32) -> Result<(DataModel, DataModel), String> {
+        let amplitudes = &self.signals.continuous.envelopes.amplitude;
+        let within_envelope = match (amplitudes.first(), amplitudes.last()) {
+            (Some(first), Some(last)) => time > first.time && time < last.time,
+            _ => false,
+        };
+        if !time.is_finite() || !within_envelope {
+            return Err(format!(
+                "Split time needs to be within the amplitude envelope, but is {}",
+                time
+            ));
+        }
 
-            // Insert a new first breakpoint
-            if let Some(new_first_breakpoint) = new_first_breakpoint {
-                amplitudes.insert(0, new_first_breakpoint);
+        let mut before = self.clone();
+        before.truncate_after(time)?;
+        let mut after = self.clone();
+        after.truncate_before(time)?;
+        Ok((before, after))
+    }
+
+    /// Appends the breakpoints of `other` to this DataModel, so that `other` plays after this
+    /// clip has finished.
+    ///
+    /// The breakpoints of `other` are shifted by the duration of this clip plus `gap` (in
+    /// seconds), see `duration()`. The clip is silent from the end of its amplitude envelope
+    /// until the first amplitude breakpoint of `other`: Zero-amplitude breakpoints are added at
+    /// both ends of that silence, unless the motor is already off there (see
+    /// `waveform::is_motor_off()`). If the clips touch, and the last breakpoint of this clip
+    /// has the same value as the first breakpoint of `other`, only one of them is kept.
+    ///
+    /// If only one of the clips has a frequency envelope, the resulting clip has a frequency
+    /// envelope as well, which holds the last frequency of this clip while `other` plays, or
+    /// starts when `other` starts.
+    pub fn concat(&mut self, other: &DataModel, gap: f32) -> Result<(), String> {
+        if !gap.is_finite() || gap < 0.0 {
+            return Err(format!(
+                "Gap needs to be finite and 0 or positive, but is {}",
+                gap
+            ));
+        }
+
+        let offset = self.duration() + gap;
+        let other_envelopes = &other.signals.continuous.envelopes;
+        let envelopes = &mut self.signals.continuous.envelopes;
+
+        let mut other_amplitudes = other_envelopes
+            .amplitude
+            .iter()
+            .map(|breakpoint| AmplitudeBreakpoint {
+                time: breakpoint.time + offset,
+                ..breakpoint.clone()
+            })
+            .peekable();
+        if let Some(first) = other_amplitudes.peek() {
+            let last = envelopes
+                .amplitude
+                .last()
+                .cloned()
+                .unwrap_or_else(|| silent_amplitude_breakpoint(0.0));
+            if last.time < first.time {
+                if envelopes.amplitude.is_empty() {
+                    envelopes.amplitude.push(last.clone());
+                } else if !is_motor_off(last.amplitude) {
+                    envelopes
+                        .amplitude
+                        .push(silent_amplitude_breakpoint(last.time));
+                }
+                if !is_motor_off(first.amplitude) {
+                    envelopes
+                        .amplitude
+                        .push(silent_amplitude_breakpoint(first.time));
+                }
+            } else if last.amplitude == first.amplitude && first.emphasis.is_none() {
+                other_amplitudes.next();
             }
         }
+        envelopes.amplitude.extend(other_amplitudes);
 
-        //
-        // Truncate frequency
-        // Same algorithm as for the amplitude, except that the frequency envelope is optional.
-        //
-        let frequencies = &mut self.signals.continuous.envelopes.frequency;
-        if let Some(frequencies) = frequencies {
-            let index_of_first_breakpoint_in_range = frequencies
+        if let Some(other_frequencies) = &other_envelopes.frequency {
+            let frequencies = envelopes.frequency.get_or_insert_with(Vec::new);
+            let mut other_frequencies = other_frequencies
                 .iter()
-                .position(|breakpoint| breakpoint.time >= time);
-
-            if let Some(index_of_first_breakpoint_in_range) = index_of_first_breakpoint_in_range {
-                if index_of_first_breakpoint_in_range > 0 {
-                    let breakpoint_before = &frequencies[index_of_first_breakpoint_in_range - 1];
-                    let breakpoint_after = &frequencies[index_of_first_breakpoint_in_range];
-                    let new_first_breakpoint =
-                        if breakpoint_after.time - breakpoint_before.time > f32::EPSILON {
-                            Some(FrequencyBreakpoint {
-                                time: 0.0,
-                                frequency: utils::interpolate(
-                                    breakpoint_before.time,
-                                    breakpoint_after.time,
-                                    breakpoint_before.frequency,
-                                    breakpoint_after.frequency,
-                                    time,
-                                ),
-                            })
-                        } else {
-                            None
-                        };
-                    frequencies.retain(|breakpoint| breakpoint.time >= time);
-                    for breakpoint in frequencies.iter_mut() {
-                        breakpoint.time -= time;
-                    }
-                    if let Some(new_first_breakpoint) = new_first_breakpoint {
-                        frequencies.insert(0, new_first_breakpoint);
-                    }
+                .map(|breakpoint| FrequencyBreakpoint {
+                    time: breakpoint.time + offset,
+                    frequency: breakpoint.frequency,
+                })
+                .peekable();
+            if let (Some(last), Some(first)) = (frequencies.last(), other_frequencies.peek()) {
+                if last.time == first.time && last.frequency == first.frequency {
+                    other_frequencies.next();
                 }
-            } else {
-                self.signals.continuous.envelopes.frequency = None;
             }
+            frequencies.extend(other_frequencies);
         }
 
         Ok(())
     }
-}
-
-/// Validation trait implementation
-/// An invalid Data Model would be one that:
-/// - Breakpoints and emphasis values are < 0.0 or > 1.0.
-/// - The breakpoint time values are not consecutive.
-/// - Emphasis amplitude is smaller than breakpoint amplitude value
-impl Validation for DataModel {
-    fn validate(self) -> Result<Self, String> {
-        let mut last_time: f32 = 0.0; // variable to keep track of the previous breakpoint time
 
-        if self.signals.continuous.envelopes.amplitude.is_empty() {
-            return Err(String::from(
-                "V1 Validation Error: Amplitude envelope is empty",
+    /// Multiplies the time of all amplitude and frequency breakpoints by `factor`.
+    ///
+    /// A factor greater than 1.0 slows the clip down, a factor smaller than 1.0 speeds it up.
+    /// Emphasis is not time-based and stays unchanged.
+    pub fn scale_time(&mut self, factor: f32) -> Result<(), String> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(format!(
+                "Time scale factor needs to be finite and greater than 0, but is {}",
+                factor
             ));
         }
 
-        for amplitude_envelope in self.signals.continuous.envelopes.amplitude.iter() {
-            if amplitude_envelope.amplitude < MIN_ENVELOPE_AMPLITUDE
-                || amplitude_envelope.amplitude > MAX_ENVELOPE_AMPLITUDE
-            {
-                return Err(format!(
-                    "V1 Validation Error: Breakpoint amplitude out of range: {}",
-                    amplitude_envelope.time,
-                ));
-            }
+        let envelopes = &mut self.signals.continuous.envelopes;
+        transform::scale_time(
+            &mut envelopes.amplitude,
+            envelopes.frequency.as_deref_mut(),
+            factor,
+        );
+        Ok(())
+    }
 
-            if last_time > amplitude_envelope.time {
-                return Err(format!(
-                    "V1 Validation Error: Breakpoint times not consecutive: {} after {}",
-                    amplitude_envelope.time, last_time,
-                ));
+    /// Adds `seconds` of silence before the clip.
+    ///
+    /// All amplitude and frequency breakpoints are shifted by `seconds`, and zero-amplitude
+    /// breakpoints are inserted at 0.0 and, if the motor isn't off at the start of the clip
+    /// (see `waveform::is_motor_off()`), at the start of the shifted clip, so that the amplitude
+    /// stays at zero until the clip starts. Padding with 0 seconds, or padding a clip without
+    /// amplitude breakpoints, leaves the clip unchanged.
+    pub fn prepend_silence(&mut self, seconds: f32) -> Result<(), String> {
+        check_silence_duration(seconds)?;
+
+        let envelopes = &mut self.signals.continuous.envelopes;
+        let first_amplitude = match envelopes.amplitude.first() {
+            Some(first) if seconds > 0.0 => first.amplitude,
+            _ => return Ok(()),
+        };
+
+        for breakpoint in envelopes.amplitude.iter_mut() {
+            breakpoint.time += seconds;
+        }
+        if let Some(frequencies) = &mut envelopes.frequency {
+            for breakpoint in frequencies.iter_mut() {
+                breakpoint.time += seconds;
             }
+        }
 
-            last_time = amplitude_envelope.time;
+        let mut silence = vec![silent_amplitude_breakpoint(0.0)];
+        if !is_motor_off(first_amplitude) {
+            silence.push(silent_amplitude_breakpoint(seconds));
+        }
+        envelopes.amplitude.splice(0..0, silence);
 
-            if let Some(emphasis) = &amplitude_envelope.emphasis {
-                if emphasis.amplitude > MAX_ENVELOPE_AMPLITUDE
-                    || emphasis.amplitude < MIN_ENVELOPE_AMPLITUDE
-                {
-                    return Err(format!(
-                        "V1 Validation Error: Emphasis amplitude out of range: {}",
-                        emphasis.amplitude,
-                    ));
-                }
+        Ok(())
+    }
 
-                if emphasis.frequency > MAX_ENVELOPE_AMPLITUDE
-                    || emphasis.frequency < MIN_ENVELOPE_AMPLITUDE
-                {
-                    return Err(format!(
-                        "V1 Validation Error: Emphasis frequency out of range: {}",
-                        emphasis.frequency,
-                    ));
-                }
+    /// Adds `seconds` of silence after the clip.
+    ///
+    /// A zero-amplitude breakpoint is added `seconds` after the end of the clip, see
+    /// `duration()`. If the motor isn't off at the end of the clip, a zero-amplitude breakpoint
+    /// is added at the time of the last amplitude breakpoint as well, so that the amplitude drops
+    /// to zero where the clip ends. Padding with 0 seconds, or padding a clip without amplitude
+    /// breakpoints, leaves the clip unchanged.
+    pub fn append_silence(&mut self, seconds: f32) -> Result<(), String> {
+        check_silence_duration(seconds)?;
+
+        let end = self.duration();
+        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
+        let (last_time, last_amplitude) = match amplitudes.last() {
+            Some(last) if seconds > 0.0 => (last.time, last.amplitude),
+            _ => return Ok(()),
+        };
 
-                if emphasis.amplitude < amplitude_envelope.amplitude {
-                    return Err(format!(
-                        "V1 Validation: Emphasis amplitude can't be lower than Envelope amplitude:
-                        {} smaller than {} at {}",
-                        emphasis.amplitude, amplitude_envelope.amplitude, amplitude_envelope.time
-                    ));
-                }
+        if !is_motor_off(last_amplitude) {
+            amplitudes.push(silent_amplitude_breakpoint(last_time));
+        }
+        amplitudes.push(silent_amplitude_breakpoint(end + seconds));
+
+        Ok(())
+    }
+
+    /// Scales the amplitude of all breakpoints so that the loudest breakpoint has an amplitude
+    /// of 1.0.
+    ///
+    /// Emphasis amplitudes are scaled by the same factor, and clipped to 1.0. A silent clip is
+    /// left unchanged.
+    pub fn normalize_amplitude(&mut self) {
+        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
+        let peak = amplitudes
+            .iter()
+            .map(|breakpoint| breakpoint.amplitude)
+            .fold(0.0, f32::max);
+        if peak <= 0.0 {
+            return;
+        }
+
+        let factor = MAX_ENVELOPE_AMPLITUDE / peak;
+        for breakpoint in amplitudes.iter_mut() {
+            breakpoint.amplitude = (breakpoint.amplitude * factor).min(MAX_ENVELOPE_AMPLITUDE);
+            if let Some(emphasis) = &mut breakpoint.emphasis {
+                // Clipping the emphasis amplitude to 1.0 keeps it at or above the breakpoint
+                // amplitude, as both were scaled by the same factor
+                emphasis.amplitude = (emphasis.amplitude * factor).min(MAX_ENVELOPE_AMPLITUDE);
             }
         }
+    }
 
-        if let Some(frequency_envelopes) = &self.signals.continuous.envelopes.frequency {
-            last_time = 0.0;
-            for frequency_envelope in frequency_envelopes.iter() {
-                if frequency_envelope.frequency < MIN_ENVELOPE_AMPLITUDE
-                    || frequency_envelope.frequency > MAX_ENVELOPE_AMPLITUDE
-                {
-                    return Err(format!(
-                        "V1 Validation Error: Breakpoint frequency out of range: {}",
-                        frequency_envelope.time,
-                    ));
-                }
+    /// Scales the amplitude of all breakpoints down so that the RMS amplitude of the clip, see
+    /// `stats()`, is at most `max_rms`.
+    ///
+    /// Unlike `normalize_amplitude()`, this limits the average energy of the clip instead of its
+    /// peak, for example to save battery. Emphasis amplitudes are scaled by the same factor, so
+    /// that they stay at or above their breakpoint amplitude. A clip with an RMS amplitude of
+    /// `max_rms` or less is left unchanged.
+    pub fn limit_energy(&mut self, max_rms: f32) -> Result<(), String> {
+        if !max_rms.is_finite() || max_rms < 0.0 {
+            return Err(format!(
+                "Maximum RMS amplitude needs to be finite and 0 or greater, but is {}",
+                max_rms
+            ));
+        }
 
-                if last_time > frequency_envelope.time {
-                    return Err(format!(
-                        "V1 Validation Error: Breakpoint frequency times not consecutive: {} after {}",
-                        frequency_envelope.time, last_time,
-                    ));
-                }
+        let rms_amplitude = self.stats().rms_amplitude;
+        if rms_amplitude <= max_rms {
+            return Ok(());
+        }
 
-                last_time = frequency_envelope.time;
+        let factor = max_rms / rms_amplitude;
+        for breakpoint in self.signals.continuous.envelopes.amplitude.iter_mut() {
+            breakpoint.amplitude *= factor;
+            if let Some(emphasis) = &mut breakpoint.emphasis {
+                emphasis.amplitude *= factor;
             }
         }
 
-        Ok(self)
+        Ok(())
     }
-}
 
-fn add_v0_transients_to_v1_breakpoints(
-    mut v0_transients: Vec<crate::v0::Envelope>,
-    v1_amplitude_breakpoints: &mut [AmplitudeBreakpoint],
-) {
-    if v0_transients.len() != 2 || v0_transients[0].len() != v0_transients[1].len() {
-        return;
+    /// Raises the amplitude of all breakpoints to the power of `gamma`, to correct for the
+    /// perceived intensity of an actuator not being linear to the amplitude.
+    ///
+    /// A gamma greater than 1.0 reduces mid-level amplitudes, a gamma smaller than 1.0 raises
+    /// them. Amplitudes of 0.0 and 1.0 stay unchanged. Emphasis amplitudes are curved the same
+    /// way, and as the curve is monotonic, they stay at or above their breakpoint amplitude.
+    pub fn apply_amplitude_curve(&mut self, gamma: f32) -> Result<(), String> {
+        if !gamma.is_finite() || gamma <= 0.0 {
+            return Err(format!(
+                "Gamma needs to be finite and greater than 0, but is {}",
+                gamma
+            ));
+        }
+
+        let curve = |amplitude: f32| {
+            amplitude
+                .clamp(MIN_ENVELOPE_AMPLITUDE, MAX_ENVELOPE_AMPLITUDE)
+                .powf(gamma)
+        };
+        for breakpoint in self.signals.continuous.envelopes.amplitude.iter_mut() {
+            breakpoint.amplitude = curve(breakpoint.amplitude);
+            if let Some(emphasis) = &mut breakpoint.emphasis {
+                emphasis.amplitude = curve(emphasis.amplitude);
+            }
+        }
+
+        Ok(())
     }
 
-    // Iterate over all amplitude breakpoints and check if there is a transient at the same
-    // timestamp. If that's the case, convert the transient to emphasis and add it to the
-    // amplitude breakpoint.
-    // Transients that don't have a matching amplitude breakpoint at the same
-    // timestamp are silently ignored. It would be possible to insert a new amplitude breakpoint
-    // with such a timestamp, but since v0 is an old format and such transients can probably not
-    // be found in the wild, it's not worth the effort.
-    v1_amplitude_breakpoints
-        .iter_mut()
-        .for_each(|v1_amplitude_breakpoint| {
-            if let Ok(v0_transient_index) = v0_transients[0].binary_search_by(|v0_transient| {
-                v0_transient
-                    .time
-                    .partial_cmp(&v1_amplitude_breakpoint.time)
-                    .unwrap()
-            }) {
-                let v0_transient_amplitude = v0_transients[0][v0_transient_index].amplitude;
-                let v0_transient_frequency = v0_transients[1][v0_transient_index].amplitude;
-                v1_amplitude_breakpoint.emphasis = Some(Emphasis {
-                    amplitude: v0_transient_amplitude,
-                    frequency: v0_transient_frequency,
-                });
+    /// Returns a new clip that plays this clip and `other` at the same time.
+    ///
+    /// The amplitude at any time is the sum of the amplitudes of both clips at that time. If the
+    /// sum is greater than 1.0, it is clipped to 1.0. The amplitude is clipped hard, no limiter
+    /// is used.
+    ///
+    /// The emphasis of both clips are kept. If both clips have an emphasis at the same time, the
+    /// one with the higher amplitude is kept.
+    ///
+    /// The frequency envelope can't be mixed in a meaningful way, so the frequency envelope of
+    /// this clip is used, or the one of `other` if this clip has none.
+    pub fn mix(&self, other: &DataModel) -> DataModel {
+        let amplitudes = &self.signals.continuous.envelopes.amplitude;
+        let other_amplitudes = &other.signals.continuous.envelopes.amplitude;
 
-                v0_transients[0].remove(v0_transient_index);
-                v0_transients[1].remove(v0_transient_index);
+        let mut times = amplitudes
+            .iter()
+            .chain(other_amplitudes.iter())
+            .map(|breakpoint| breakpoint.time)
+            .collect::<Vec<f32>>();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        times.dedup();
+
+        let mut mixed_amplitudes: Vec<AmplitudeBreakpoint> = Vec::with_capacity(times.len());
+        for time in times {
+            let amplitude_before = (amplitude_before(amplitudes, time)
+                + amplitude_before(other_amplitudes, time))
+            .min(MAX_ENVELOPE_AMPLITUDE);
+            let amplitude_after = (amplitude_after(amplitudes, time)
+                + amplitude_after(other_amplitudes, time))
+            .min(MAX_ENVELOPE_AMPLITUDE);
+
+            // If one of the clips has a step at this time, keep the step in the mixed clip
+            if amplitude_before != amplitude_after {
+                mixed_amplitudes.push(AmplitudeBreakpoint {
+                    time,
+                    amplitude: amplitude_before,
+                    emphasis: None,
+                });
             }
-        });
-}
 
-/// Implementation of upgrade functionality from version V0.
-impl From<crate::v0::DataModel> for crate::v1::DataModel {
-    fn from(v0: crate::v0::DataModel) -> Self {
-        let version: Version = DataModel::CURRENT;
-        let mut signals = Signals::default();
+            let emphasis = amplitudes
+                .iter()
+                .chain(other_amplitudes.iter())
+                .filter(|breakpoint| breakpoint.time == time)
+                .filter_map(|breakpoint| breakpoint.emphasis)
+                .fold(
+                    None,
+                    |strongest: Option<Emphasis>, emphasis| match strongest {
+                        Some(strongest) if strongest.amplitude >= emphasis.amplitude => {
+                            Some(strongest)
+                        }
+                        _ => Some(emphasis),
+                    },
+                )
+                .map(|emphasis| Emphasis {
+                    amplitude: emphasis.amplitude.max(amplitude_after),
+                    frequency: emphasis.frequency,
+                });
 
-        // The first array of breakpoints is mapped to amplitude.
-        let mut amplitude_envelopes: Vec<AmplitudeBreakpoint> = v0.voices.envelopes[0]
-            .iter()
-            .map(|breakpoint| AmplitudeBreakpoint {
-                time: breakpoint.time,
-                amplitude: breakpoint.amplitude,
-                emphasis: None,
-            })
-            .collect();
+            mixed_amplitudes.push(AmplitudeBreakpoint {
+                time,
+                amplitude: amplitude_after,
+                emphasis,
+            });
+        }
 
-        // add a last point to the continuous amplitude envelope, corresponding to the
-        // duration of the signal
-        let event_amplitude_to_add = match amplitude_envelopes.last() {
-            Some(last_event) => {
-                if v0.metadata.duration > last_event.time {
-                    Some(last_event.amplitude)
-                } else {
-                    None
-                }
-            }
-            None => Some(0.0),
-        };
+        let mut mixed = self.clone();
+        mixed.signals.continuous.envelopes.amplitude = mixed_amplitudes;
+        if mixed.signals.continuous.envelopes.frequency.is_none() {
+            mixed.signals.continuous.envelopes.frequency =
+                other.signals.continuous.envelopes.frequency.clone();
+        }
+        mixed
+    }
 
-        if let Some(amplitude) = event_amplitude_to_add {
-            amplitude_envelopes.push(AmplitudeBreakpoint {
-                time: v0.metadata.duration,
-                amplitude,
-                emphasis: None,
-            });
+    /// Removes breakpoints that barely change the shape of the amplitude and frequency
+    /// envelopes.
+    ///
+    /// This uses the Ramer–Douglas–Peucker algorithm: A breakpoint is removed if its value
+    /// deviates from the line between the remaining breakpoints around it by `tolerance` or
+    /// less. The deviation is measured in amplitude or frequency, not as a geometric distance.
+    ///
+    /// The first and last breakpoints, breakpoints with emphasis and breakpoints that are part
+    /// of a step, i.e. that share their time with a neighbor, are never removed.
+    pub fn simplify(&mut self, tolerance: f32) -> Result<(), String> {
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(format!(
+                "Tolerance needs to be finite and 0 or positive, but is {}",
+                tolerance
+            ));
         }
 
-        // The second array of breakpoints is mapped to frequency.
-        let frequency_envelopes: Vec<FrequencyBreakpoint> = if v0.voices.envelopes.len() == 2 {
-            v0.voices.envelopes[1]
+        let envelopes = &mut self.signals.continuous.envelopes;
+        let points = envelopes
+            .amplitude
+            .iter()
+            .map(|breakpoint| (breakpoint.time, breakpoint.amplitude))
+            .collect::<Vec<_>>();
+        let mut keep = envelopes
+            .amplitude
+            .iter()
+            .map(|breakpoint| breakpoint.emphasis.is_some())
+            .collect::<Vec<_>>();
+        simplify_points(&points, &mut keep, tolerance);
+        let mut keep = keep.into_iter();
+        envelopes.amplitude.retain(|_| keep.next().unwrap_or(true));
+
+        if let Some(frequencies) = &mut envelopes.frequency {
+            let points = frequencies
                 .iter()
-                .map(|breakpoint| FrequencyBreakpoint {
-                    time: breakpoint.time,
-                    frequency: breakpoint.amplitude,
-                })
-                .collect()
-        } else {
-            vec![]
-        };
+                .map(|breakpoint| (breakpoint.time, breakpoint.frequency))
+                .collect::<Vec<_>>();
+            let mut keep = vec![false; points.len()];
+            simplify_points(&points, &mut keep, tolerance);
+            let mut keep = keep.into_iter();
+            frequencies.retain(|_| keep.next().unwrap_or(true));
+        }
 
-        add_v0_transients_to_v1_breakpoints(v0.voices.transients, &mut amplitude_envelopes);
+        Ok(())
+    }
 
-        // The only thing common in Metadata is the editor field.
-        let metadata = MetaData {
-            editor: v0.metadata.editor,
-            ..Default::default()
+    /// Reverses the clip, so that it plays backwards.
+    ///
+    /// Each breakpoint time is mirrored around the clip duration, the time of the last amplitude
+    /// breakpoint, with `new_time = duration - old_time`, so that the last amplitude breakpoint
+    /// becomes the first one at 0.0. Emphasis stays attached to its breakpoint.
+    ///
+    /// Frequency breakpoints after the end of the amplitude envelope are cut off, as they would
+    /// end up at negative times. If the clip doesn't start at 0.0, the silence before the first
+    /// amplitude breakpoint is dropped, and the reversed clip ends that much earlier.
+    pub fn reverse(&mut self) {
+        let duration = match self.signals.continuous.envelopes.amplitude.last() {
+            Some(last) => last.time,
+            None => return,
         };
 
-        // Assign the amplitude envelopes to our signals struct.
-        signals.continuous.envelopes.amplitude = amplitude_envelopes;
+        // Can't fail, as no amplitude breakpoint is after `duration`
+        let truncated = self.truncate_after(duration);
+        debug_assert!(truncated.is_ok());
 
-        // Add frequency envelopes if present.
-        if !frequency_envelopes.is_empty() {
-            signals.continuous.envelopes.frequency = Some(frequency_envelopes);
-        } else {
-            signals.continuous.envelopes.frequency = None;
+        let envelopes = &mut self.signals.continuous.envelopes;
+        envelopes.amplitude.reverse();
+        for breakpoint in envelopes.amplitude.iter_mut() {
+            breakpoint.time = duration - breakpoint.time;
         }
-
-        // Return the updated data model structure.
-        DataModel {
-            version,
-            metadata,
-            signals,
+        if let Some(frequencies) = &mut envelopes.frequency {
+            frequencies.reverse();
+            for breakpoint in frequencies.iter_mut() {
+                breakpoint.time = duration - breakpoint.time;
+            }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// Applies a linear fade-in over the first `fade_in` seconds and a linear fade-out over the
+    /// last `fade_out` seconds of the amplitude envelope.
+    ///
+    /// Breakpoint and emphasis amplitudes are multiplied by the fade gain at their time. If a
+    /// fade doesn't end or start at an existing breakpoint, a breakpoint is inserted there so
+    /// that the shape of the envelope outside of the fade is kept.
+    ///
+    /// A fade longer than the clip is clamped to the duration of the clip. A fade of 0 seconds
+    /// leaves the clip unchanged.
+    pub fn apply_fade(&mut self, fade_in: f32, fade_out: f32) -> Result<(), String> {
+        for (name, duration) in [("Fade-in", fade_in), ("Fade-out", fade_out)].iter() {
+            if !duration.is_finite() || *duration < 0.0 {
+                return Err(format!(
+                    "{} duration needs to be finite and 0 or positive, but is {}",
+                    name, duration
+                ));
+            }
+        }
 
-    use crate::latest_from_json;
+        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
+        let (start, end) = match (amplitudes.first(), amplitudes.last()) {
+            (Some(first), Some(last)) if last.time > first.time => (first.time, last.time),
+            _ => return Ok(()),
+        };
+        let fade_in = fade_in.min(end - start);
+        let fade_out = fade_out.min(end - start);
 
-    use super::*;
-    use std::path::Path;
+        if fade_in > 0.0 {
+            insert_interpolated_amplitude_breakpoint(amplitudes, start + fade_in);
+        }
+        if fade_out > 0.0 {
+            insert_interpolated_amplitude_breakpoint(amplitudes, end - fade_out);
+        }
 
-    fn load_file_from_test_data(path: &str) -> String {
-        std::fs::read_to_string(
-            Path::new(env!("CARGO_MANIFEST_DIR"))
-                .join("src/test_data")
-                .join(path),
-        )
-        .unwrap()
-    }
+        for breakpoint in amplitudes.iter_mut() {
+            let mut gain: f32 = 1.0;
+            if fade_in > 0.0 {
+                gain = gain.min((breakpoint.time - start) / fade_in);
+            }
+            if fade_out > 0.0 {
+                gain = gain.min((end - breakpoint.time) / fade_out);
+            }
+            let gain = gain.clamp(0.0, 1.0);
 
-    pub fn latest_from_test_data(path: &str) -> DataModel {
-        let clip_json = load_file_from_test_data(path);
-        latest_from_json(&clip_json).unwrap().1
+            breakpoint.amplitude *= gain;
+            if let Some(emphasis) = &mut breakpoint.emphasis {
+                emphasis.amplitude *= gain;
+            }
+        }
+
+        Ok(())
     }
 
-    fn load_test_file_valid_required_v1() -> String {
-        load_file_from_test_data("valid_required_v1.haptic")
+    /// Returns a copy of the clip with the features removed that a device with the given
+    /// capabilities can't render.
+    ///
+    /// Without frequency control, the frequency envelope is dropped. Without native transients,
+    /// the emphasis is folded into the amplitude envelope with `emphasis::emphasize()`, using
+    /// the default `EmphasisParameters`.
+    pub fn downgrade_for(&self, capabilities: &DeviceCapabilities) -> DataModel {
+        let mut downgraded = self.clone();
+        let envelopes = &mut downgraded.signals.continuous.envelopes;
+        if !capabilities.frequency_control {
+            envelopes.frequency = None;
+        }
+        if !capabilities.emphasis {
+            envelopes.amplitude = emphasize(&envelopes.amplitude, EmphasisParameters::default());
+        }
+        downgraded
     }
 
-    #[test]
-    fn check_test_json_deserialized_required_fields_only() {
-        let data: DataModel = serde_json::from_str(&load_test_file_valid_required_v1()).unwrap();
+    /// Validates the clip like `Validation::validate()`, and additionally rejects clips that
+    /// are accepted for playback but are unusual for authored content.
+    ///
+    /// On top of the lenient validation, the first amplitude breakpoint needs to be at 0.0, and
+    /// the breakpoint times of both envelopes need to be strictly increasing, i.e. steps with
+    /// several breakpoints at the same time are rejected as well.
+    ///
+    /// This is meant for authoring tools. Playback uses the lenient validation.
+    pub fn validate_strict(self) -> Result<Self, String> {
+        let data = self.validate()?;
+        let envelopes = &data.signals.continuous.envelopes;
+
+        // validate() ensures that the amplitude envelope isn't empty
+        let first_time = envelopes.amplitude[0].time;
+        if first_time != 0.0 {
+            return Err(format!(
+                "V1 Strict Validation Error: First amplitude breakpoint is at {} instead of 0",
+                first_time
+            ));
+        }
 
-        let metadata = MetaData::default();
-        let version = Version {
-            major: 1,
-            minor: 0,
-            patch: 0,
+        let duplicate_time = |times: Vec<f32>| {
+            times
+                .windows(2)
+                .find(|pair| pair[0] == pair[1])
+                .map(|pair| pair[0])
         };
+        if let Some(time) = duplicate_time(
+            envelopes
+                .amplitude
+                .iter()
+                .map(|breakpoint| breakpoint.time)
+                .collect(),
+        ) {
+            return Err(format!(
+                "V1 Strict Validation Error: Multiple amplitude breakpoints at {}",
+                time
+            ));
+        }
+        if let Some(frequencies) = &envelopes.frequency {
+            if let Some(time) = duplicate_time(
+                frequencies
+                    .iter()
+                    .map(|breakpoint| breakpoint.time)
+                    .collect(),
+            ) {
+                return Err(format!(
+                    "V1 Strict Validation Error: Multiple frequency breakpoints at {}",
+                    time
+                ));
+            }
+        }
 
-        //check if value of data not included in the file is the default
-        assert_eq!(metadata, data.metadata);
-        assert_eq!(version, data.version);
-        assert_eq!(data.signals.continuous.envelopes.frequency, None);
+        Ok(data)
     }
 
-    #[test]
-    fn check_serialized_required_only() {
-        let reference_data: DataModel =
-            serde_json::from_str(&load_test_file_valid_required_v1()).unwrap();
+    /// Smooths the amplitude envelope with a moving average, to reduce buzzy noise from
+    /// audio-to-haptic conversion.
+    ///
+    /// The amplitude of each breakpoint is replaced with the average amplitude of the original
+    /// envelope over a span of `window` seconds centered on the breakpoint, clipped to the
+    /// start and end of the envelope. The average is weighted by time, so the mean amplitude of
+    /// the clip is preserved. Breakpoint times are not changed.
+    ///
+    /// Breakpoints with emphasis keep their amplitude, so that transients stay sharp. A window of
+    /// 0 seconds leaves the clip unchanged.
+    pub fn smooth_amplitude(&mut self, window: f32) -> Result<(), String> {
+        if !window.is_finite() || window < 0.0 {
+            return Err(format!(
+                "Smoothing window needs to be finite and 0 or positive, but is {}",
+                window
+            ));
+        }
 
-        let metadata = MetaData::default();
-        let version = Version {
-            major: 1,
-            minor: 0,
-            patch: 0,
+        let amplitudes = &mut self.signals.continuous.envelopes.amplitude;
+        let (start, end) = match (amplitudes.first(), amplitudes.last()) {
+            (Some(first), Some(last)) if window > 0.0 => (first.time, last.time),
+            _ => return Ok(()),
         };
 
-        let amplitude_envelope = vec![
-            AmplitudeBreakpoint {
-                time: 0.0,
-                amplitude: 0.2,
-                emphasis: None,
-            },
-            AmplitudeBreakpoint {
-                time: 0.1,
-                amplitude: 0.3,
-                emphasis: None,
-            },
-            AmplitudeBreakpoint {
-                time: 0.2,
-                amplitude: 0.2,
-                emphasis: None,
-            },
-            AmplitudeBreakpoint {
-                time: 0.3,
-                amplitude: 0.5,
-                emphasis: None,
-            },
-        ];
-
-        let signal_continuous = SignalContinuous {
-            envelopes: Envelopes {
-                amplitude: amplitude_envelope,
-                frequency: None,
-            },
-        };
+        let original = amplitudes.clone();
+        for breakpoint in amplitudes.iter_mut() {
+            if breakpoint.emphasis.is_some() {
+                continue;
+            }
+            let from = (breakpoint.time - window / 2.0).max(start);
+            let to = (breakpoint.time + window / 2.0).min(end);
+            if to > from {
+                breakpoint.amplitude = average_amplitude(&original, from, to);
+            }
+        }
 
-        let data = DataModel {
-            version,
-            metadata,
-            signals: Signals {
-                continuous: signal_continuous,
-            },
-        };
+        Ok(())
+    }
+}
 
-        assert_eq!(reference_data, data);
+/// Returns an error if `seconds` isn't a valid duration of silence, see
+/// `DataModel::prepend_silence()`
+fn check_silence_duration(seconds: f32) -> Result<(), String> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!(
+            "Silence duration needs to be finite and 0 or positive, but is {}",
+            seconds
+        ));
     }
+    Ok(())
+}
 
-    #[test]
-    fn check_test_json_deserialize() {
-        let data: DataModel =
-            serde_json::from_str(&load_file_from_test_data("valid_v1.haptic")).unwrap();
+/// Returns an amplitude breakpoint with an amplitude of zero at `time`
+fn silent_amplitude_breakpoint(time: f32) -> AmplitudeBreakpoint {
+    AmplitudeBreakpoint::new(time, 0.0)
+}
 
-        let version = Version {
-            major: 1,
-            minor: 0,
-            patch: 0,
-        };
+/// Marks the `(time, value)` points that need to be kept in `keep`, using the
+/// Ramer–Douglas–Peucker algorithm, see `DataModel::simplify()`.
+///
+/// Points already marked in `keep` are always kept. The first and last points, and points
+/// sharing their time with a neighbor, are marked as well.
+fn simplify_points(points: &[(f32, f32)], keep: &mut [bool], tolerance: f32) {
+    debug_assert_eq!(points.len(), keep.len());
+    if points.is_empty() {
+        return;
+    }
 
-        //check if value of data not included in the file is the default
-        assert_eq!(version, data.version);
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    for index in 1..points.len() {
+        if points[index].0 == points[index - 1].0 {
+            keep[index - 1] = true;
+            keep[index] = true;
+        }
     }
 
-    #[test]
-    fn check_test_json_deserialize_invalid_fields() {
-        let data = serde_json::from_str::<DataModel>(&load_file_from_test_data(
-            "invalid_fields_v1.haptic",
-        ));
-        let err = data.map(|_| ()).unwrap_err();
-        assert!(err.to_string().contains("missing field `signals`"));
+    // The segments between points that are always kept are simplified independently
+    let anchors = (0..points.len())
+        .filter(|index| keep[*index])
+        .collect::<Vec<_>>();
+    for segment in anchors.windows(2) {
+        simplify_segment(points, keep, tolerance, segment[0], segment[1]);
     }
+}
 
-    pub fn create_test_data_model() -> DataModel {
-        //building data
-        let version: Version = Version {
-            major: 1,
-            minor: 0,
-            patch: 0,
-        };
+/// Marks the point between `first` and `last` that deviates most from the line between them,
+/// if it deviates by more than `tolerance`, and continues recursively on both sides of it.
+fn simplify_segment(
+    points: &[(f32, f32)],
+    keep: &mut [bool],
+    tolerance: f32,
+    first: usize,
+    last: usize,
+) {
+    if last <= first + 1 {
+        return;
+    }
 
-        let metadata = MetaData {
-            editor: "VSCode".to_owned(),
-            author: "SDK Team".to_owned(),
-            tags: vec!["Test".to_owned()],
-            description: "Testing".to_owned(),
-            ..Default::default()
-        };
+    let (time_a, value_a) = points[first];
+    let (time_b, value_b) = points[last];
+    let (index_of_max_deviation, max_deviation) = points[first + 1..last]
+        .iter()
+        .enumerate()
+        .map(|(index, (time, value))| {
+            let line_value = utils::interpolate(time_a, time_b, value_a, value_b, *time);
+            (first + 1 + index, (value - line_value).abs())
+        })
+        .fold(
+            (first, 0.0),
+            |max, current| {
+                if current.1 > max.1 {
+                    current
+                } else {
+                    max
+                }
+            },
+        );
+
+    if max_deviation > tolerance {
+        keep[index_of_max_deviation] = true;
+        simplify_segment(points, keep, tolerance, first, index_of_max_deviation);
+        simplify_segment(points, keep, tolerance, index_of_max_deviation, last);
+    }
+}
+
+/// Inserts a breakpoint at `time` into `breakpoints`, with the amplitude interpolated from its
+/// neighboring breakpoints.
+///
+/// Nothing is inserted if a breakpoint already exists at `time`, or if `time` is outside of the
+/// envelope.
+fn insert_interpolated_amplitude_breakpoint(breakpoints: &mut Vec<AmplitudeBreakpoint>, time: f32) {
+    if let Some(index) = breakpoints
+        .iter()
+        .position(|breakpoint| breakpoint.time >= time)
+    {
+        if index > 0 && breakpoints[index].time - time > f32::EPSILON {
+            let breakpoint = AmplitudeBreakpoint::from_interpolated_breakpoints(
+                &breakpoints[index - 1],
+                &breakpoints[index],
+                time,
+            );
+            breakpoints.insert(index, breakpoint);
+        }
+    }
+}
+
+/// Returns the amplitude of the envelope at `time`, approaching `time` from before.
+///
+/// If there is a step with multiple breakpoints at `time`, the amplitude of the first one is
+/// returned. Outside of the envelope, the amplitude is 0.0.
+fn amplitude_before(breakpoints: &[AmplitudeBreakpoint], time: f32) -> f32 {
+    match breakpoints
+        .iter()
+        .position(|breakpoint| breakpoint.time >= time)
+    {
+        None => 0.0,
+        Some(index) if breakpoints[index].time == time => breakpoints[index].amplitude,
+        Some(0) => 0.0,
+        Some(index) => {
+            AmplitudeBreakpoint::from_interpolated_breakpoints(
+                &breakpoints[index - 1],
+                &breakpoints[index],
+                time,
+            )
+            .amplitude
+        }
+    }
+}
+
+/// Returns the amplitude of the envelope at `time`, approaching `time` from after.
+///
+/// If there is a step with multiple breakpoints at `time`, the amplitude of the last one is
+/// returned. Outside of the envelope, the amplitude is 0.0.
+pub(crate) fn amplitude_after(breakpoints: &[AmplitudeBreakpoint], time: f32) -> f32 {
+    match breakpoints
+        .iter()
+        .rposition(|breakpoint| breakpoint.time <= time)
+    {
+        None => 0.0,
+        Some(index) if breakpoints[index].time == time => breakpoints[index].amplitude,
+        Some(index) if index + 1 == breakpoints.len() => 0.0,
+        Some(index) => {
+            AmplitudeBreakpoint::from_interpolated_breakpoints(
+                &breakpoints[index],
+                &breakpoints[index + 1],
+                time,
+            )
+            .amplitude
+        }
+    }
+}
+
+/// Returns the average amplitude of the envelope between `from` and `to`, weighted by time.
+///
+/// `from` needs to be smaller than `to`, and both need to be within the envelope.
+fn average_amplitude(breakpoints: &[AmplitudeBreakpoint], from: f32, to: f32) -> f32 {
+    debug_assert!(from < to);
+    let mut area = 0.0;
+    for segment in breakpoints.windows(2) {
+        let (a, b) = (&segment[0], &segment[1]);
+        let overlap_start = a.time.max(from);
+        let overlap_end = b.time.min(to);
+        if overlap_end <= overlap_start {
+            continue;
+        }
+
+        let amplitude_start =
+            AmplitudeBreakpoint::from_interpolated_breakpoints(a, b, overlap_start).amplitude;
+        let amplitude_end =
+            AmplitudeBreakpoint::from_interpolated_breakpoints(a, b, overlap_end).amplitude;
+        area += (amplitude_start + amplitude_end) / 2.0 * (overlap_end - overlap_start);
+    }
+    area / (to - from)
+}
+
+/// Builds a `DataModel` breakpoint by breakpoint.
+///
+/// ```
+/// use datamodel::v1::DataModelBuilder;
+///
+/// let clip = DataModelBuilder::new()
+///     .amplitude_breakpoint(0.0, 0.2)
+///     .amplitude_breakpoint(0.1, 0.5)
+///     .emphasis(0.8, 0.6)
+///     .amplitude_breakpoint(0.2, 0.0)
+///     .frequency_breakpoint(0.0, 0.5)
+///     .frequency_breakpoint(0.2, 0.9)
+///     .build()
+///     .unwrap();
+/// assert_eq!(clip.duration(), 0.2);
+/// ```
+#[derive(Debug, Default)]
+pub struct DataModelBuilder {
+    data: DataModel,
+    /// An error that occurred while adding data, returned by `build()`
+    error: Option<String>,
+}
+
+impl DataModelBuilder {
+    /// Creates a builder for an empty clip with the current version
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a breakpoint to the amplitude envelope
+    pub fn amplitude_breakpoint(mut self, time: f32, amplitude: f32) -> Self {
+        self.data
+            .signals
+            .continuous
+            .envelopes
+            .amplitude
+            .push(AmplitudeBreakpoint::new(time, amplitude));
+        self
+    }
+
+    /// Adds emphasis to the amplitude breakpoint appended last
+    ///
+    /// If no amplitude breakpoint was added yet, `build()` returns an error.
+    pub fn emphasis(mut self, amplitude: f32, frequency: f32) -> Self {
+        match self.data.signals.continuous.envelopes.amplitude.last_mut() {
+            Some(breakpoint) => {
+                breakpoint.emphasis = Some(Emphasis {
+                    amplitude,
+                    frequency,
+                })
+            }
+            None => {
+                self.error.get_or_insert_with(|| {
+                    "Emphasis can only be added after an amplitude breakpoint".to_string()
+                });
+            }
+        }
+        self
+    }
+
+    /// Appends a breakpoint to the frequency envelope, creating the envelope if needed
+    pub fn frequency_breakpoint(mut self, time: f32, frequency: f32) -> Self {
+        self.data
+            .signals
+            .continuous
+            .envelopes
+            .frequency
+            .get_or_insert_with(Vec::new)
+            .push(FrequencyBreakpoint { time, frequency });
+        self
+    }
+
+    /// Sets the metadata of the clip
+    pub fn metadata(mut self, metadata: MetaData) -> Self {
+        self.data.metadata = metadata;
+        self
+    }
+
+    /// Returns the built clip after validating it
+    ///
+    /// Invalid data, like breakpoints that are out of order or out of range, results in the
+    /// validation error being returned.
+    pub fn build(self) -> Result<DataModel, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => self.data.validate(),
+        }
+    }
+}
+
+/// Validation trait implementation
+/// An invalid Data Model would be one that:
+/// - Breakpoints and emphasis values are < 0.0 or > 1.0.
+/// - The breakpoint time values are not consecutive.
+/// - Emphasis amplitude is smaller than breakpoint amplitude value
+impl Validation for DataModel {
+    fn validate(self) -> Result<Self, String> {
+        let mut last_time: f32 = 0.0; // variable to keep track of the previous breakpoint time
+
+        if self.signals.continuous.envelopes.amplitude.is_empty() {
+            return Err(String::from(
+                "V1 Validation Error: Amplitude envelope is empty",
+            ));
+        }
+
+        if let Some(gain) = self.metadata.gain {
+            if !gain.is_finite() || gain < 0.0 {
+                return Err(format!(
+                    "V1 Validation Error: Gain needs to be finite and 0 or greater: {}",
+                    gain
+                ));
+            }
+        }
+
+        for amplitude_envelope in self.signals.continuous.envelopes.amplitude.iter() {
+            // NaN fails every comparison, so the range checks below would not catch it
+            if !amplitude_envelope.time.is_finite() {
+                return Err(format!(
+                    "V1 Validation Error: Breakpoint time is not finite: {}",
+                    amplitude_envelope.time,
+                ));
+            }
+
+            if !amplitude_envelope.amplitude.is_finite() {
+                return Err(format!(
+                    "V1 Validation Error: Breakpoint amplitude is not finite: {} at {}",
+                    amplitude_envelope.amplitude, amplitude_envelope.time,
+                ));
+            }
+
+            if amplitude_envelope.amplitude < MIN_ENVELOPE_AMPLITUDE
+                || amplitude_envelope.amplitude > MAX_ENVELOPE_AMPLITUDE
+            {
+                return Err(format!(
+                    "V1 Validation Error: Breakpoint amplitude out of range: {}",
+                    amplitude_envelope.time,
+                ));
+            }
+
+            if last_time > amplitude_envelope.time {
+                return Err(format!(
+                    "V1 Validation Error: Breakpoint times not consecutive: {} after {}",
+                    amplitude_envelope.time, last_time,
+                ));
+            }
+
+            last_time = amplitude_envelope.time;
+
+            if let Some(emphasis) = &amplitude_envelope.emphasis {
+                if !emphasis.amplitude.is_finite() || !emphasis.frequency.is_finite() {
+                    return Err(format!(
+                        "V1 Validation Error: Emphasis amplitude or frequency is not finite at {}",
+                        amplitude_envelope.time,
+                    ));
+                }
+
+                if emphasis.amplitude > MAX_ENVELOPE_AMPLITUDE
+                    || emphasis.amplitude < MIN_ENVELOPE_AMPLITUDE
+                {
+                    return Err(format!(
+                        "V1 Validation Error: Emphasis amplitude out of range: {}",
+                        emphasis.amplitude,
+                    ));
+                }
+
+                if emphasis.frequency > MAX_ENVELOPE_AMPLITUDE
+                    || emphasis.frequency < MIN_ENVELOPE_AMPLITUDE
+                {
+                    return Err(format!(
+                        "V1 Validation Error: Emphasis frequency out of range: {}",
+                        emphasis.frequency,
+                    ));
+                }
+
+                if emphasis.amplitude < amplitude_envelope.amplitude {
+                    return Err(format!(
+                        "V1 Validation: Emphasis amplitude can't be lower than Envelope amplitude:
+                        {} smaller than {} at {}",
+                        emphasis.amplitude, amplitude_envelope.amplitude, amplitude_envelope.time
+                    ));
+                }
+            }
+        }
+
+        if let Some(frequency_envelopes) = &self.signals.continuous.envelopes.frequency {
+            last_time = 0.0;
+            for frequency_envelope in frequency_envelopes.iter() {
+                if !frequency_envelope.time.is_finite() {
+                    return Err(format!(
+                        "V1 Validation Error: Breakpoint frequency time is not finite: {}",
+                        frequency_envelope.time,
+                    ));
+                }
+
+                if !frequency_envelope.frequency.is_finite() {
+                    return Err(format!(
+                        "V1 Validation Error: Breakpoint frequency is not finite: {} at {}",
+                        frequency_envelope.frequency, frequency_envelope.time,
+                    ));
+                }
+
+                if frequency_envelope.frequency < MIN_ENVELOPE_AMPLITUDE
+                    || frequency_envelope.frequency > MAX_ENVELOPE_AMPLITUDE
+                {
+                    return Err(format!(
+                        "V1 Validation Error: Breakpoint frequency out of range: {}",
+                        frequency_envelope.time,
+                    ));
+                }
+
+                if last_time > frequency_envelope.time {
+                    return Err(format!(
+                        "V1 Validation Error: Breakpoint frequency times not consecutive: {} after {}",
+                        frequency_envelope.time, last_time,
+                    ));
+                }
+
+                last_time = frequency_envelope.time;
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+/// Creates silent amplitude breakpoints at the times of the given v0 transients, starting at 0.0
+fn amplitude_breakpoints_for_v0_transients(
+    v0_transients: &[crate::v0::Envelope],
+) -> Vec<AmplitudeBreakpoint> {
+    let mut breakpoints = vec![AmplitudeBreakpoint::default()];
+    if let Some(transients) = v0_transients.first() {
+        for transient in transients {
+            if transient.time > breakpoints.last().unwrap().time {
+                breakpoints.push(AmplitudeBreakpoint {
+                    time: transient.time,
+                    amplitude: 0.0,
+                    emphasis: None,
+                });
+            }
+        }
+    }
+    breakpoints
+}
+
+fn add_v0_transients_to_v1_breakpoints(
+    mut v0_transients: Vec<crate::v0::Envelope>,
+    v1_amplitude_breakpoints: &mut [AmplitudeBreakpoint],
+) {
+    if v0_transients.len() != 2 || v0_transients[0].len() != v0_transients[1].len() {
+        return;
+    }
+
+    // Iterate over all amplitude breakpoints and check if there is a transient at the same
+    // timestamp. If that's the case, convert the transient to emphasis and add it to the
+    // amplitude breakpoint.
+    // Transients that don't have a matching amplitude breakpoint at the same
+    // timestamp are silently ignored. It would be possible to insert a new amplitude breakpoint
+    // with such a timestamp, but since v0 is an old format and such transients can probably not
+    // be found in the wild, it's not worth the effort.
+    v1_amplitude_breakpoints
+        .iter_mut()
+        .for_each(|v1_amplitude_breakpoint| {
+            if let Ok(v0_transient_index) = v0_transients[0].binary_search_by(|v0_transient| {
+                v0_transient
+                    .time
+                    .partial_cmp(&v1_amplitude_breakpoint.time)
+                    .unwrap()
+            }) {
+                let v0_transient_amplitude = v0_transients[0][v0_transient_index].amplitude;
+                let v0_transient_frequency = v0_transients[1][v0_transient_index].amplitude;
+                v1_amplitude_breakpoint.emphasis = Some(Emphasis {
+                    amplitude: v0_transient_amplitude,
+                    frequency: v0_transient_frequency,
+                });
+
+                v0_transients[0].remove(v0_transient_index);
+                v0_transients[1].remove(v0_transient_index);
+            }
+        });
+}
+
+/// Implementation of upgrade functionality from version V0.
+impl From<crate::v0::DataModel> for crate::v1::DataModel {
+    fn from(v0: crate::v0::DataModel) -> Self {
+        let version: Version = DataModel::CURRENT;
+        let mut signals = Signals::default();
+
+        // The amplitude envelope, usually the first array of breakpoints, is mapped to amplitude.
+        let mut amplitude_envelopes: Vec<AmplitudeBreakpoint> = match v0
+            .voices
+            .amplitude_envelope()
+            .filter(|envelope| !envelope.is_empty())
+        {
+            Some(envelope) => envelope
+                .iter()
+                .map(|breakpoint| AmplitudeBreakpoint {
+                    time: breakpoint.time,
+                    amplitude: breakpoint.amplitude,
+                    emphasis: None,
+                })
+                .collect(),
+            // Files with only transients have no amplitude envelope. Synthesize a silent one with
+            // a breakpoint at each transient, so that the transients can be added as emphasis.
+            None => amplitude_breakpoints_for_v0_transients(&v0.voices.transients),
+        };
+
+        // add a last point to the continuous amplitude envelope, corresponding to the
+        // duration of the signal
+        let event_amplitude_to_add = match amplitude_envelopes.last() {
+            Some(last_event) => {
+                if v0.metadata.duration > last_event.time {
+                    Some(last_event.amplitude)
+                } else {
+                    None
+                }
+            }
+            None => Some(0.0),
+        };
+
+        if let Some(amplitude) = event_amplitude_to_add {
+            amplitude_envelopes.push(AmplitudeBreakpoint {
+                time: v0.metadata.duration,
+                amplitude,
+                emphasis: None,
+            });
+        }
+
+        // The frequency envelope, usually the second array of breakpoints, is mapped to
+        // frequency.
+        let frequency_envelopes: Vec<FrequencyBreakpoint> = match v0.voices.frequency_envelope() {
+            Some(envelope) => envelope
+                .iter()
+                .map(|breakpoint| FrequencyBreakpoint {
+                    time: breakpoint.time,
+                    frequency: breakpoint.amplitude,
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        add_v0_transients_to_v1_breakpoints(v0.voices.transients, &mut amplitude_envelopes);
+
+        // The only thing common in Metadata is the editor field.
+        let metadata = MetaData {
+            editor: v0.metadata.editor,
+            ..Default::default()
+        };
+
+        // Assign the amplitude envelopes to our signals struct.
+        signals.continuous.envelopes.amplitude = amplitude_envelopes;
+
+        // Add frequency envelopes if present.
+        if !frequency_envelopes.is_empty() {
+            signals.continuous.envelopes.frequency = Some(frequency_envelopes);
+        } else {
+            signals.continuous.envelopes.frequency = None;
+        }
+
+        // Return the updated data model structure.
+        DataModel {
+            version,
+            metadata,
+            signals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::latest_from_json;
+
+    use super::*;
+    use std::path::Path;
+
+    fn load_file_from_test_data(path: &str) -> String {
+        std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("src/test_data")
+                .join(path),
+        )
+        .unwrap()
+    }
+
+    pub fn latest_from_test_data(path: &str) -> DataModel {
+        let clip_json = load_file_from_test_data(path);
+        latest_from_json(&clip_json).unwrap().1
+    }
+
+    fn load_test_file_valid_required_v1() -> String {
+        load_file_from_test_data("valid_required_v1.haptic")
+    }
+
+    #[test]
+    fn check_test_json_deserialized_required_fields_only() {
+        let data: DataModel = serde_json::from_str(&load_test_file_valid_required_v1()).unwrap();
+
+        let metadata = MetaData::default();
+        let version = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+
+        //check if value of data not included in the file is the default
+        assert_eq!(metadata, data.metadata);
+        assert_eq!(version, data.version);
+        assert_eq!(data.signals.continuous.envelopes.frequency, None);
+    }
+
+    #[test]
+    fn check_serialized_required_only() {
+        let reference_data: DataModel =
+            serde_json::from_str(&load_test_file_valid_required_v1()).unwrap();
+
+        let metadata = MetaData::default();
+        let version = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+
+        let amplitude_envelope = vec![
+            AmplitudeBreakpoint {
+                time: 0.0,
+                amplitude: 0.2,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.1,
+                amplitude: 0.3,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.2,
+                amplitude: 0.2,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.3,
+                amplitude: 0.5,
+                emphasis: None,
+            },
+        ];
+
+        let signal_continuous = SignalContinuous {
+            envelopes: Envelopes {
+                amplitude: amplitude_envelope,
+                frequency: None,
+            },
+        };
+
+        let data = DataModel {
+            version,
+            metadata,
+            signals: Signals {
+                continuous: signal_continuous,
+            },
+        };
+
+        assert_eq!(reference_data, data);
+    }
+
+    #[test]
+    fn check_test_json_deserialize() {
+        let data: DataModel =
+            serde_json::from_str(&load_file_from_test_data("valid_v1.haptic")).unwrap();
+
+        let version = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+
+        //check if value of data not included in the file is the default
+        assert_eq!(version, data.version);
+    }
+
+    #[test]
+    fn check_test_json_deserialize_invalid_fields() {
+        let data = serde_json::from_str::<DataModel>(&load_file_from_test_data(
+            "invalid_fields_v1.haptic",
+        ));
+        let err = data.map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("missing field `signals`"));
+    }
+
+    pub fn create_test_data_model() -> DataModel {
+        //building data
+        let version: Version = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+
+        let metadata = MetaData {
+            editor: "VSCode".to_owned(),
+            author: "SDK Team".to_owned(),
+            tags: vec!["Test".to_owned()],
+            description: "Testing".to_owned(),
+            ..Default::default()
+        };
 
         let envelope_amplitude = vec![
             AmplitudeBreakpoint {
                 time: 0.0,
                 amplitude: 0.2,
-                emphasis: None,
-            },
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.1,
+                amplitude: 0.3,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.2,
+                amplitude: 0.2,
+                emphasis: None,
+            },
+            AmplitudeBreakpoint {
+                time: 0.3,
+                amplitude: 0.5,
+                emphasis: Some(Emphasis {
+                    amplitude: 0.69,
+                    frequency: 0.7,
+                }),
+            },
+        ];
+
+        let envelope_frequency = vec![
+            FrequencyBreakpoint {
+                time: 0.1,
+                frequency: 0.99,
+            },
+            FrequencyBreakpoint {
+                time: 0.2,
+                frequency: 0.54,
+            },
+            FrequencyBreakpoint {
+                time: 0.25,
+                frequency: 0.8,
+            },
+            FrequencyBreakpoint {
+                time: 0.3,
+                frequency: 0.9,
+            },
+        ];
+
+        let signal_continuous = SignalContinuous {
+            envelopes: Envelopes {
+                amplitude: envelope_amplitude,
+                frequency: Some(envelope_frequency),
+            },
+        };
+
+        DataModel {
+            version,
+            metadata,
+            signals: Signals {
+                continuous: signal_continuous,
+            },
+        }
+    }
+
+    fn serialize_test_data_json() -> String {
+        let data = create_test_data_model();
+        serde_json::to_string_pretty(&data).unwrap()
+    }
+
+    fn deserialize_test_data_json() -> DataModel {
+        let serialized_json = serialize_test_data_json();
+        let deserialized_json: DataModel = serde_json::from_str(&serialized_json).unwrap();
+
+        deserialized_json
+    }
+
+    #[test]
+    fn check_test_json_serialize_deserialize() {
+        //verify if deserialized data matches the created data to be serialized
+        let deserialized_json = deserialize_test_data_json();
+
+        //version
+        assert_eq!(deserialized_json.version.major, 1);
+        assert_eq!(deserialized_json.version.minor, 0);
+        assert_eq!(deserialized_json.version.patch, 0);
+
+        //metadata
+        assert_eq!(deserialized_json.metadata.author, "SDK Team");
+        assert_eq!(deserialized_json.metadata.description, "Testing");
+        assert_eq!(deserialized_json.metadata.editor, "VSCode");
+        assert_eq!(deserialized_json.metadata.tags[0], "Test");
+
+        //signals
+        let serialized_signals = deserialized_json.signals;
+
+        // check continuous
+
+        assert_eq!(
+            serialized_signals.continuous.envelopes.amplitude[0],
+            AmplitudeBreakpoint {
+                time: 0.0,
+                amplitude: 0.2,
+                emphasis: None
+            }
+        );
+        assert_eq!(
+            serialized_signals.continuous.envelopes.amplitude[1],
             AmplitudeBreakpoint {
                 time: 0.1,
                 amplitude: 0.3,
-                emphasis: None,
-            },
+                emphasis: None
+            }
+        );
+        assert_eq!(
+            serialized_signals.continuous.envelopes.amplitude[2],
             AmplitudeBreakpoint {
                 time: 0.2,
                 amplitude: 0.2,
-                emphasis: None,
-            },
+                emphasis: None
+            }
+        );
+        assert_eq!(
+            serialized_signals.continuous.envelopes.amplitude[3],
             AmplitudeBreakpoint {
                 time: 0.3,
                 amplitude: 0.5,
@@ -612,356 +1856,1478 @@ mod tests {
                     amplitude: 0.69,
                     frequency: 0.7,
                 }),
-            },
+            }
+        );
+
+        let freq_vec = serialized_signals.continuous.envelopes.frequency.unwrap();
+        assert_eq!(
+            freq_vec[0],
+            FrequencyBreakpoint {
+                time: 0.1,
+                frequency: 0.99
+            }
+        );
+        assert_eq!(
+            freq_vec[1],
+            FrequencyBreakpoint {
+                time: 0.2,
+                frequency: 0.54
+            }
+        );
+        assert_eq!(
+            freq_vec[2],
+            FrequencyBreakpoint {
+                time: 0.25,
+                frequency: 0.8
+            }
+        );
+        assert_eq!(
+            freq_vec[3],
+            FrequencyBreakpoint {
+                time: 0.3,
+                frequency: 0.9
+            }
+        );
+    }
+
+    /// Utility function to check v0 to v1 version upgrading
+    fn check_v0_to_v1_upgrade(v0_file_name: &str, v1_file_name: &str, validate_v0: bool) {
+        let v0: crate::v0::DataModel =
+            serde_json::from_str(&load_file_from_test_data(v0_file_name)).unwrap();
+
+        let v0 = if validate_v0 {
+            v0.validate().unwrap()
+        } else {
+            v0
+        };
+
+        let v1 = crate::v1::DataModel::from(v0);
+
+        let v1_validation: crate::v1::DataModel =
+            serde_json::from_str(&load_file_from_test_data(v1_file_name)).unwrap();
+        assert_eq!(v1.version, v1_validation.version);
+        assert_eq!(v1.signals, v1_validation.signals);
+    }
+
+    /// unit test to check version upgrading.
+    #[test]
+    fn check_version_upgrade() {
+        check_v0_to_v1_upgrade("valid_v0_conversion.vij", "valid_v1_from_v0.haptic", true);
+    }
+
+    // Unit to to check v0 to v1 upgrade on a real-world file produced by the DSP code.
+    // All transients in that file are valid.
+    #[test]
+    fn check_version_upgrade_v0_from_dsp() {
+        check_v0_to_v1_upgrade(
+            "valid_v0_from_dsp.vij",
+            "valid_v1_from_v0_from_dsp.haptic",
+            true,
+        );
+    }
+
+    // Unit test to check v0 to v1 upgrade. The v0 file has one valid transient
+    // and one transient without a matching amplitude breakpoint at the same timestamp.
+    // While that's a valid v0 file, we ignore that transient in the upgrade.
+    #[test]
+    fn check_version_upgrade_transient_amplitude_breakpoint_mismatch() {
+        check_v0_to_v1_upgrade(
+            "valid_v0_transient_time_mismatch.vij",
+            "valid_v1_from_v0_transient_time_mismatch.haptic",
+            true,
+        );
+    }
+
+    // unit test to check version upgrading ignoring incorrect transients.
+    #[test]
+    fn check_version_upgrade_transients() {
+        check_v0_to_v1_upgrade(
+            "invalid_v0_conversions_transients.vij",
+            "valid_v1_from_invalid_v0_conversions_transients.haptic",
+            false,
+        );
+    }
+
+    /// unit test to check version upgrading ignoring incorrect transients and frequency_envelopes.
+    #[test]
+    fn check_version_upgrade_invalid() {
+        check_v0_to_v1_upgrade(
+            "invalid_v0_conversion.vij",
+            "valid_v1_from_invalid_v0_conversion.haptic",
+            false,
+        );
+    }
+
+    // Unit test to check v0 to v1 upgrade of a file in which the envelope types are given
+    // explicitly, with the frequency envelope being first
+    #[test]
+    fn check_version_upgrade_swapped_envelopes() {
+        check_v0_to_v1_upgrade(
+            "valid_v0_swapped_envelopes.vij",
+            "valid_v1_from_v0.haptic",
+            true,
+        );
+    }
+
+    // Unit test to check v0 to v1 upgrade of a file with only transients and no envelopes
+    #[test]
+    fn check_version_upgrade_transients_only() {
+        check_v0_to_v1_upgrade(
+            "no_envelopes_v0.vij",
+            "valid_v1_from_v0_transients_only.haptic",
+            true,
+        );
+        let v0: crate::v0::DataModel =
+            serde_json::from_str(&load_file_from_test_data("no_envelopes_v0.vij")).unwrap();
+        crate::v1::DataModel::from(v0).validate().unwrap();
+    }
+
+    /// Unit test datamodel validation.
+    #[test]
+    fn check_validation_pass() {
+        let data = load_file_from_test_data("valid_v1.haptic");
+        let data: DataModel = serde_json::from_str(&data).unwrap();
+        data.validate().unwrap();
+    }
+
+    /// Unit test datamodel validation optionals.
+    #[test]
+    fn check_validation_optional() {
+        let data = load_file_from_test_data("validation_v1_optionals.haptic");
+        let data: DataModel = serde_json::from_str(&data).unwrap();
+        data.validate().unwrap();
+    }
+
+    /// Unit test datamodel validation amplitude range.
+    #[test]
+    fn check_validation_fail_range() {
+        let data = load_file_from_test_data("validation_v1_amplitude.haptic");
+        let data: DataModel = serde_json::from_str(&data).unwrap();
+        let err = data.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Breakpoint amplitude out of range"),
+            "Failed validation at wrong point: {}",
+            err
+        );
+    }
+
+    /// Unit test datamodel validation of NaN and infinite values.
+    #[test]
+    fn check_validation_fail_not_finite() {
+        use crate::test_utils::{amp, emp};
+
+        let data = create_test_data_model();
+        data.clone().validate().unwrap();
+
+        // Each case makes one value non-finite, and the expected validation error
+        type Case = (fn(&mut DataModel), &'static str);
+        let cases: Vec<Case> = vec![
+            (
+                |data| data.signals.continuous.envelopes.amplitude[1].time = f32::NAN,
+                "Breakpoint time is not finite",
+            ),
+            (
+                |data| data.signals.continuous.envelopes.amplitude[1].amplitude = f32::NAN,
+                "Breakpoint amplitude is not finite",
+            ),
+            (
+                |data| data.signals.continuous.envelopes.amplitude[1].amplitude = f32::INFINITY,
+                "Breakpoint amplitude is not finite",
+            ),
+            (
+                |data| {
+                    data.signals.continuous.envelopes.amplitude =
+                        vec![amp(0.0, 0.0), emp(0.1, 0.2, f32::NAN, 0.5), amp(0.2, 0.0)]
+                },
+                "Emphasis amplitude or frequency is not finite",
+            ),
+            (
+                |data| {
+                    data.signals.continuous.envelopes.amplitude = vec![
+                        amp(0.0, 0.0),
+                        emp(0.1, 0.2, 0.5, f32::NEG_INFINITY),
+                        amp(0.2, 0.0),
+                    ]
+                },
+                "Emphasis amplitude or frequency is not finite",
+            ),
+            (
+                |data| {
+                    data.signals
+                        .continuous
+                        .envelopes
+                        .frequency
+                        .as_mut()
+                        .unwrap()[0]
+                        .time = f32::INFINITY
+                },
+                "Breakpoint frequency time is not finite",
+            ),
+            (
+                |data| {
+                    data.signals
+                        .continuous
+                        .envelopes
+                        .frequency
+                        .as_mut()
+                        .unwrap()[0]
+                        .frequency = f32::NAN
+                },
+                "Breakpoint frequency is not finite",
+            ),
         ];
 
-        let envelope_frequency = vec![
-            FrequencyBreakpoint {
-                time: 0.1,
-                frequency: 0.99,
-            },
-            FrequencyBreakpoint {
-                time: 0.2,
-                frequency: 0.54,
-            },
-            FrequencyBreakpoint {
-                time: 0.25,
-                frequency: 0.8,
-            },
-            FrequencyBreakpoint {
-                time: 0.3,
-                frequency: 0.9,
-            },
-        ];
+        for (modify, expected_error) in cases {
+            let mut invalid_data = data.clone();
+            modify(&mut invalid_data);
+            let err = invalid_data.validate().map(|_| ()).unwrap_err();
+            assert!(
+                err.contains(expected_error),
+                "Failed validation at wrong point: {}",
+                err
+            );
+        }
+    }
+
+    /// Unit test datamodel validation consecutive breakpoints.
+    #[test]
+    fn check_validation_fail_sequence() {
+        let data = load_file_from_test_data("validation_v1_sequence.haptic");
+        let data: DataModel = serde_json::from_str(&data).unwrap();
+        let err = data.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Breakpoint times not consecutive"),
+            "Failed validation at wrong point: {}",
+            err
+        );
+    }
+
+    /// Unit test datamodel validation of the gain metadata.
+    #[test]
+    fn check_validation_gain() {
+        let mut data = create_test_data_model();
+        data.metadata.gain = Some(0.5);
+        let data = data.validate().unwrap();
+
+        // Test: The gain is only serialized when present
+        let json = serde_json::to_string(&data).unwrap();
+        assert!(json.contains("\"gain\":0.5"));
+        let mut data: DataModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(data.metadata.gain, Some(0.5));
+        data.metadata.gain = None;
+        assert!(!serde_json::to_string(&data).unwrap().contains("gain"));
+
+        for gain in &[-0.5, f32::NAN, f32::INFINITY] {
+            let mut invalid_data = data.clone();
+            invalid_data.metadata.gain = Some(*gain);
+            let err = invalid_data.validate().map(|_| ()).unwrap_err();
+            assert!(
+                err.contains("Gain needs to be finite and 0 or greater"),
+                "Failed validation with wrong message: {}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn check_validation_fail_emphasis_amplitude_vs_signal_amplitude() {
+        let data = load_file_from_test_data("validation_v1_emphasis_amplitude.haptic");
+        let data: DataModel = serde_json::from_str(&data).unwrap();
+        let err = data.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Emphasis amplitude can't be lower than Envelope amplitude"),
+            "Failed validation with wrong message: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn check_validation_fail_emphasis_amplitude_range() {
+        let data = load_file_from_test_data("validation_v1_emphasis_amplitude_range.haptic");
+        let haptic: DataModel = serde_json::from_str(&data).unwrap();
+        let err = haptic.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Emphasis amplitude out of range"),
+            "Failed validation with wrong message: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn check_validation_fail_emphasis_frequency_range() {
+        let data = load_file_from_test_data("validation_v1_emphasis_frequency_range.haptic");
+        let haptic: DataModel = serde_json::from_str(&data).unwrap();
+        let err = haptic.validate().map(|_| ()).unwrap_err();
+        assert!(
+            err.contains("Emphasis frequency out of range"),
+            "Failed validation with wrong message: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn check_valid_beta_impulses() {
+        let data: String = load_file_from_test_data("valid_beta_impulses.haptic");
+        let haptic: DataModel = serde_json::from_str(&data).unwrap();
+        haptic.validate().unwrap();
+    }
+
+    #[test]
+    // Test that the duration is converted to milliseconds
+    fn total_duration_ms() {
+        let mut data = create_test_data_model();
+        assert_eq!(data.total_duration_ms(), 300);
 
-        let signal_continuous = SignalContinuous {
-            envelopes: Envelopes {
-                amplitude: envelope_amplitude,
-                frequency: Some(envelope_frequency),
-            },
-        };
+        data.signals.continuous.envelopes.amplitude.clear();
+        assert_eq!(data.total_duration_ms(), 0);
+    }
 
-        DataModel {
-            version,
-            metadata,
-            signals: Signals {
-                continuous: signal_continuous,
-            },
+    #[test]
+    // Test that the features used by a clip are detected
+    fn required_features() {
+        let mut data = create_test_data_model();
+        assert_eq!(
+            data.required_features(),
+            vec![Feature::FrequencyModulation, Feature::Emphasis]
+        );
+
+        data.signals.continuous.envelopes.frequency = None;
+        for breakpoint in &mut data.signals.continuous.envelopes.amplitude {
+            breakpoint.emphasis = None;
         }
+        assert!(data.required_features().is_empty());
     }
 
-    fn serialize_test_data_json() -> String {
-        let data = create_test_data_model();
-        serde_json::to_string_pretty(&data).unwrap()
+    #[test]
+    // Test that only the strongest emphasis are kept when thinning out emphasis
+    fn thin_emphasis() {
+        use crate::test_utils::{amp, emp};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.0),
+            emp(0.1, 0.4, 0.6, 0.7),
+            emp(0.2, 0.4, 0.9, 0.7),
+            emp(0.3, 0.4, 0.9, 0.7),
+            emp(0.5, 0.4, 0.5, 0.7),
+            emp(0.8, 0.4, 0.7, 0.7),
+            amp(1.0, 0.0),
+        ];
+
+        assert!(data.thin_emphasis(0.0).is_err());
+        assert!(data.thin_emphasis(f32::NAN).is_err());
+
+        data.thin_emphasis(4.0).unwrap();
+        let data = data.validate().unwrap();
+        assert_eq!(
+            data.signals.continuous.envelopes.amplitude,
+            vec![
+                amp(0.0, 0.0),
+                amp(0.1, 0.4),
+                emp(0.2, 0.4, 0.9, 0.7),
+                amp(0.3, 0.4),
+                emp(0.5, 0.4, 0.5, 0.7),
+                emp(0.8, 0.4, 0.7, 0.7),
+                amp(1.0, 0.0),
+            ]
+        );
     }
 
-    fn deserialize_test_data_json() -> DataModel {
-        let serialized_json = serialize_test_data_json();
-        let deserialized_json: DataModel = serde_json::from_str(&serialized_json).unwrap();
+    #[test]
+    // Test that truncating before a value works as expected
+    fn truncate() {
+        let mut before_truncate = latest_from_test_data("truncate_before.haptic");
+        let after_truncate = latest_from_test_data("truncate_after.haptic");
+        before_truncate.truncate_before(2.5).unwrap();
+        assert_eq!(before_truncate.signals, after_truncate.signals);
+    }
 
-        deserialized_json
+    #[test]
+    // Test that truncating before a value after the end of the clip returns an error
+    fn truncate_after_end() {
+        let mut before_truncate = latest_from_test_data("truncate_before.haptic");
+        assert_eq!(
+            before_truncate.truncate_before(100.0),
+            Err("No amplitude breakpoint before the specified starting time".to_string())
+        );
     }
 
     #[test]
-    fn check_test_json_serialize_deserialize() {
-        //verify if deserialized data matches the created data to be serialized
-        let deserialized_json = deserialize_test_data_json();
+    // Truncating with just 2 breakpoints
+    fn truncate_2_breakpoints() {
+        let mut before_truncate = latest_from_test_data("truncate_before_2_bp.haptic");
+        let after_truncate = latest_from_test_data("truncate_after_2_bp.haptic");
+        before_truncate.truncate_before(0.5).unwrap();
+        assert_eq!(before_truncate.signals, after_truncate.signals);
+    }
 
-        //version
-        assert_eq!(deserialized_json.version.major, 1);
-        assert_eq!(deserialized_json.version.minor, 0);
-        assert_eq!(deserialized_json.version.patch, 0);
+    #[test]
+    // Truncating with 1 breakpoint fails
+    fn truncate_1_breakpoint() {
+        let mut before_truncate = latest_from_test_data("truncate_before_1_bp.haptic");
+        assert_eq!(
+            before_truncate.truncate_before(1.0),
+            Err("No amplitude breakpoint before the specified starting time".to_string())
+        );
+    }
 
-        //metadata
-        assert_eq!(deserialized_json.metadata.author, "SDK Team");
-        assert_eq!(deserialized_json.metadata.description, "Testing");
-        assert_eq!(deserialized_json.metadata.editor, "VSCode");
-        assert_eq!(deserialized_json.metadata.tags[0], "Test");
+    #[test]
+    // Truncating with empty frequency
+    fn truncate_empty_frequency_envelope_before() {
+        // empty frequency envelope before truncating
+        let mut before_truncate =
+            latest_from_test_data("truncate_with_empty_frequency_before.haptic");
+        let after_truncate =
+            latest_from_test_data("truncate_after_with_empty_frequency_before.haptic");
+        before_truncate.truncate_before(2.5).unwrap();
+        assert_eq!(before_truncate.signals, after_truncate.signals);
+    }
+    #[test]
+    // Truncating results in a empty frequency envelope
+    fn truncate_empty_frequency_envelope_after() {
+        // empty frequency envelope before truncating
+        let mut before_truncate =
+            latest_from_test_data("truncate_with_empty_frequency_after.haptic");
+        let after_truncate =
+            latest_from_test_data("truncate_after_with_empty_frequency_after.haptic");
 
-        //signals
-        let serialized_signals = deserialized_json.signals;
+        before_truncate.truncate_before(2.5).unwrap();
+        assert_eq!(before_truncate.signals, after_truncate.signals);
+    }
 
-        // check continuous
+    #[test]
+    // Test that truncating after a value cuts off the end of the clip
+    fn truncate_after() {
+        use crate::test_utils::{amp, emp};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.0),
+            emp(0.5, 0.8, 1.0, 0.6),
+            amp(1.0, 0.5),
+            amp(2.0, 0.0),
+        ];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.2,
+            },
+            FrequencyBreakpoint {
+                time: 2.0,
+                frequency: 0.6,
+            },
+        ]);
 
+        // Truncating after the end of the clip doesn't change anything
+        let mut unchanged = data.clone();
+        unchanged.truncate_after(2.0).unwrap();
+        unchanged.truncate_after(100.0).unwrap();
+        assert_eq!(unchanged.signals, data.signals);
+
+        // Truncating at the first breakpoint or before fails
+        assert!(data.clone().truncate_after(0.0).is_err());
+        assert!(data.clone().truncate_after(-1.0).is_err());
+
+        // Truncating at an existing breakpoint keeps it, including its emphasis
+        let mut truncated = data.clone();
+        truncated.truncate_after(0.5).unwrap();
         assert_eq!(
-            serialized_signals.continuous.envelopes.amplitude[0],
-            AmplitudeBreakpoint {
-                time: 0.0,
-                amplitude: 0.2,
-                emphasis: None
-            }
+            truncated.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.0), emp(0.5, 0.8, 1.0, 0.6)]
         );
         assert_eq!(
-            serialized_signals.continuous.envelopes.amplitude[1],
-            AmplitudeBreakpoint {
-                time: 0.1,
-                amplitude: 0.3,
-                emphasis: None
-            }
+            truncated.signals.continuous.envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 0.0,
+                    frequency: 0.2,
+                },
+                FrequencyBreakpoint {
+                    time: 0.5,
+                    frequency: 0.3,
+                },
+            ])
         );
+
+        // Truncating between breakpoints inserts an interpolated breakpoint
+        let mut truncated = data;
+        truncated.truncate_after(1.5).unwrap();
         assert_eq!(
-            serialized_signals.continuous.envelopes.amplitude[2],
-            AmplitudeBreakpoint {
-                time: 0.2,
-                amplitude: 0.2,
-                emphasis: None
-            }
+            truncated.signals.continuous.envelopes.amplitude,
+            vec![
+                amp(0.0, 0.0),
+                emp(0.5, 0.8, 1.0, 0.6),
+                amp(1.0, 0.5),
+                amp(1.5, 0.25)
+            ]
         );
+        truncated.validate().unwrap();
+    }
+
+    #[test]
+    // Truncating after a time before the frequency envelope starts removes the frequency envelope
+    fn truncate_after_empty_frequency_envelope() {
+        use crate::test_utils::amp;
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.0), amp(2.0, 1.0)];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 0.2,
+            },
+            FrequencyBreakpoint {
+                time: 2.0,
+                frequency: 0.6,
+            },
+        ]);
+
+        data.truncate_after(1.0).unwrap();
         assert_eq!(
-            serialized_signals.continuous.envelopes.amplitude[3],
-            AmplitudeBreakpoint {
-                time: 0.3,
-                amplitude: 0.5,
-                emphasis: Some(Emphasis {
-                    amplitude: 0.69,
-                    frequency: 0.7,
-                }),
-            }
+            data.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.0), amp(1.0, 0.5)]
         );
+        assert_eq!(data.signals.continuous.envelopes.frequency, None);
+    }
 
-        let freq_vec = serialized_signals.continuous.envelopes.frequency.unwrap();
+    #[test]
+    // Concatenating a clip with itself doubles the duration
+    fn concat() {
+        let mut data = latest_from_test_data("valid_v1.haptic");
+        let other = data.clone();
+        data.concat(&other, 0.0).unwrap();
+        let data = data.validate().unwrap();
+
+        let envelopes = &data.signals.continuous.envelopes;
+        let other_envelopes = &other.signals.continuous.envelopes;
+        let duration = envelopes.amplitude.last().unwrap().time;
+        let other_duration = other_envelopes.amplitude.last().unwrap().time;
+        assert!((duration - 2.0 * other_duration).abs() < 0.0001);
         assert_eq!(
-            freq_vec[0],
-            FrequencyBreakpoint {
-                time: 0.1,
-                frequency: 0.99
-            }
+            envelopes.amplitude.len(),
+            2 * other_envelopes.amplitude.len()
         );
         assert_eq!(
-            freq_vec[1],
-            FrequencyBreakpoint {
-                time: 0.2,
-                frequency: 0.54
-            }
+            envelopes.frequency.as_ref().unwrap().len(),
+            2 * other_envelopes.frequency.as_ref().unwrap().len()
         );
+    }
+
+    #[test]
+    // Splitting a clip and concatenating the two parts again reproduces the original envelopes
+    fn split_at() {
+        let data = latest_from_test_data("valid_v1.haptic");
+        let split_time = 1.234;
+        let (before, after) = data.split_at(split_time).unwrap();
+        let before = before.validate().unwrap();
+        let after = after.validate().unwrap();
+        utils::assert_near!(before.duration(), split_time, 1e-5);
+        assert_eq!(after.signals.continuous.envelopes.amplitude[0].time, 0.0);
         assert_eq!(
-            freq_vec[2],
+            before.amplitude_at(split_time),
+            after.amplitude_at(0.0),
+            "The split point is interpolated differently in both parts"
+        );
+
+        let mut joined = before;
+        joined.concat(&after, 0.0).unwrap();
+        let joined = joined.validate().unwrap();
+        utils::assert_near!(joined.duration(), data.duration(), 1e-4);
+        for index in 0..=1000 {
+            let time = data.duration() * index as f32 / 1000.0;
+            utils::assert_near!(joined.amplitude_at(time), data.amplitude_at(time), 1e-4);
+            utils::assert_near!(
+                joined.frequency_at(time).unwrap(),
+                data.frequency_at(time).unwrap(),
+                1e-4
+            );
+        }
+
+        // Test: Splitting outside of the amplitude envelope fails
+        for time in &[0.0, -1.0, data.duration(), f32::NAN] {
+            assert!(data.split_at(*time).is_err());
+        }
+    }
+
+    #[test]
+    // Concatenating with a gap, and with only one of the clips having a frequency envelope
+    fn concat_gap_and_missing_frequency_envelope() {
+        use crate::test_utils::{amp, emp};
+
+        let mut without_frequency = DataModel::default();
+        without_frequency.signals.continuous.envelopes.amplitude =
+            vec![amp(0.0, 0.0), emp(0.5, 0.5, 0.8, 0.2), amp(1.0, 0.0)];
+        let mut with_frequency = without_frequency.clone();
+        with_frequency.signals.continuous.envelopes.frequency = Some(vec![
             FrequencyBreakpoint {
-                time: 0.25,
-                frequency: 0.8
-            }
+                time: 0.0,
+                frequency: 0.2,
+            },
+            FrequencyBreakpoint {
+                time: 1.0,
+                frequency: 0.8,
+            },
+        ]);
+
+        for gap in &[-1.0, f32::NAN, f32::INFINITY] {
+            assert!(without_frequency
+                .clone()
+                .concat(&with_frequency, *gap)
+                .is_err());
+        }
+
+        let mut data = without_frequency.clone();
+        data.concat(&with_frequency, 0.5).unwrap();
+        let data = data.validate().unwrap();
+        assert_eq!(
+            data.signals.continuous.envelopes.amplitude,
+            vec![
+                amp(0.0, 0.0),
+                emp(0.5, 0.5, 0.8, 0.2),
+                amp(1.0, 0.0),
+                amp(1.5, 0.0),
+                emp(2.0, 0.5, 0.8, 0.2),
+                amp(2.5, 0.0),
+            ]
         );
         assert_eq!(
-            freq_vec[3],
-            FrequencyBreakpoint {
-                time: 0.3,
-                frequency: 0.9
-            }
+            data.signals.continuous.envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 1.5,
+                    frequency: 0.2,
+                },
+                FrequencyBreakpoint {
+                    time: 2.5,
+                    frequency: 0.8,
+                },
+            ])
         );
-    }
 
-    /// Utility function to check v0 to v1 version upgrading
-    fn check_v0_to_v1_upgrade(v0_file_name: &str, v1_file_name: &str, validate_v0: bool) {
-        let v0: crate::v0::DataModel =
-            serde_json::from_str(&load_file_from_test_data(v0_file_name)).unwrap();
+        let mut data = with_frequency.clone();
+        data.concat(&without_frequency, 0.5).unwrap();
+        let data = data.validate().unwrap();
+        assert_eq!(data.total_duration_ms(), 2500);
+        assert_eq!(
+            data.signals.continuous.envelopes.frequency,
+            with_frequency.signals.continuous.envelopes.frequency
+        );
+    }
 
-        let v0 = if validate_v0 {
-            v0.validate().unwrap()
-        } else {
-            v0
-        };
+    #[test]
+    // The gap starts after the frequency envelope and stays silent, and touching clips don't
+    // duplicate the breakpoint where they meet
+    fn concat_silent_gap() {
+        use crate::test_utils::amp;
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.5), amp(1.0, 0.5)];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.5,
+            },
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 0.5,
+            },
+        ]);
+        let mut other = DataModel::default();
+        other.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.5), amp(1.0, 0.5)];
 
-        let v1 = crate::v1::DataModel::from(v0);
+        let mut concatenated = data.clone();
+        concatenated.concat(&other, 0.5).unwrap();
+        let concatenated = concatenated.validate().unwrap();
+        assert_eq!(
+            concatenated.signals.continuous.envelopes.amplitude,
+            vec![
+                amp(0.0, 0.5),
+                amp(1.0, 0.5),
+                amp(1.0, 0.0),
+                amp(2.0, 0.0),
+                amp(2.0, 0.5),
+                amp(3.0, 0.5),
+            ]
+        );
+        assert_eq!(
+            concatenated.signals.continuous.envelopes.frequency,
+            data.signals.continuous.envelopes.frequency
+        );
+        assert_eq!(concatenated.amplitude_at(1.75), 0.0);
 
-        let v1_validation: crate::v1::DataModel =
-            serde_json::from_str(&load_file_from_test_data(v1_file_name)).unwrap();
-        assert_eq!(v1.version, v1_validation.version);
-        assert_eq!(v1.signals, v1_validation.signals);
+        let mut concatenated = other.clone();
+        concatenated.concat(&other, 0.0).unwrap();
+        let concatenated = concatenated.validate().unwrap();
+        assert_eq!(
+            concatenated.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.5), amp(1.0, 0.5), amp(2.0, 0.5)]
+        );
     }
 
-    /// unit test to check version upgrading.
     #[test]
-    fn check_version_upgrade() {
-        check_v0_to_v1_upgrade("valid_v0_conversion.vij", "valid_v1_from_v0.haptic", true);
+    // Mixing two clips sums the amplitudes, clipped to 1.0, and keeps the emphasis of both
+    fn mix() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut rumble = DataModel::default();
+        rumble.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.5), amp(2.0, 0.5)];
+
+        let mut texture = DataModel::default();
+        texture.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.0),
+            emp(0.5, 0.4, 0.6, 0.9),
+            amp(1.0, 0.8),
+            amp(1.0, 0.2),
+            amp(1.5, 0.0),
+        ];
+        texture.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.3,
+            },
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 0.7,
+            },
+        ]);
+
+        let mixed = rumble.mix(&texture).validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&mixed.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.5),
+                emp(0.5, 0.9, 0.9, 0.9),
+                amp(1.0, 1.0),
+                amp(1.0, 0.7),
+                amp(1.5, 0.5),
+                amp(2.0, 0.5),
+            ]
+        );
+        assert_eq!(
+            mixed.signals.continuous.envelopes.frequency,
+            texture.signals.continuous.envelopes.frequency
+        );
+
+        // Mixing is symmetric for the amplitude
+        let mixed_other_way = texture.mix(&rumble).validate().unwrap();
+        assert_eq!(
+            mixed_other_way.signals.continuous.envelopes.amplitude,
+            mixed.signals.continuous.envelopes.amplitude
+        );
     }
 
-    // Unit to to check v0 to v1 upgrade on a real-world file produced by the DSP code.
-    // All transients in that file are valid.
     #[test]
-    fn check_version_upgrade_v0_from_dsp() {
-        check_v0_to_v1_upgrade(
-            "valid_v0_from_dsp.vij",
-            "valid_v1_from_v0_from_dsp.haptic",
-            true,
-        );
+    // Scaling the time stretches all breakpoints, but not the emphasis
+    fn scale_time() {
+        let original = latest_from_test_data("valid_v1.haptic");
+
+        let mut data = original.clone();
+        assert!(data.scale_time(0.0).is_err());
+        assert!(data.scale_time(-1.0).is_err());
+        assert!(data.scale_time(f32::INFINITY).is_err());
+        assert!(data.scale_time(f32::NAN).is_err());
+        assert_eq!(data, original);
+
+        data.scale_time(2.0).unwrap();
+        let data = data.validate().unwrap();
+        let envelopes = &data.signals.continuous.envelopes;
+        let original_envelopes = &original.signals.continuous.envelopes;
+        for (scaled, original) in envelopes
+            .amplitude
+            .iter()
+            .zip(&original_envelopes.amplitude)
+        {
+            assert_eq!(scaled.time, original.time * 2.0);
+            assert_eq!(scaled.amplitude, original.amplitude);
+            assert_eq!(scaled.emphasis, original.emphasis);
+        }
+        for (scaled, original) in envelopes
+            .frequency
+            .as_ref()
+            .unwrap()
+            .iter()
+            .zip(original_envelopes.frequency.as_ref().unwrap())
+        {
+            assert_eq!(scaled.time, original.time * 2.0);
+            assert_eq!(scaled.frequency, original.frequency);
+        }
     }
 
-    // Unit test to check v0 to v1 upgrade. The v0 file has one valid transient
-    // and one transient without a matching amplitude breakpoint at the same timestamp.
-    // While that's a valid v0 file, we ignore that transient in the upgrade.
     #[test]
-    fn check_version_upgrade_transient_amplitude_breakpoint_mismatch() {
-        check_v0_to_v1_upgrade(
-            "valid_v0_transient_time_mismatch.vij",
-            "valid_v1_from_v0_transient_time_mismatch.haptic",
-            true,
+    // Normalizing scales the loudest breakpoint to 1.0, and leaves silent clips unchanged
+    fn normalize_amplitude() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.0),
+            emp(0.5, 0.25, 0.4, 0.7),
+            amp(1.0, 0.5),
+            emp(1.5, 0.4, 0.8, 0.1),
+            amp(2.0, 0.0),
+        ];
+        data.normalize_amplitude();
+        let data = data.validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&data.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                emp(0.5, 0.5, 0.8, 0.7),
+                amp(1.0, 1.0),
+                emp(1.5, 0.8, 1.0, 0.1),
+                amp(2.0, 0.0),
+            ]
         );
+
+        let mut silent = DataModel::default();
+        silent.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.0), amp(1.0, 0.0)];
+        let original = silent.clone();
+        silent.normalize_amplitude();
+        assert_eq!(silent, original);
     }
 
-    // unit test to check version upgrading ignoring incorrect transients.
     #[test]
-    fn check_version_upgrade_transients() {
-        check_v0_to_v1_upgrade(
-            "invalid_v0_conversions_transients.vij",
-            "valid_v1_from_invalid_v0_conversions_transients.haptic",
-            false,
+    // Limiting the energy scales a clip down to the maximum RMS amplitude, and leaves clips
+    // within the budget unchanged
+    fn limit_energy() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        // The RMS amplitude of a constant envelope is its amplitude
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude =
+            vec![amp(0.0, 0.8), emp(1.0, 0.8, 0.9, 0.3), amp(2.0, 0.8)];
+        data.limit_energy(0.4).unwrap();
+        let data = data.validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&data.signals.continuous.envelopes.amplitude),
+            vec![amp(0.0, 0.4), emp(1.0, 0.4, 0.45, 0.3), amp(2.0, 0.4)]
         );
+
+        // The RMS amplitude of a triangular envelope is 1/sqrt(3) of its peak
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude =
+            vec![amp(0.0, 0.0), amp(1.0, 0.9), amp(2.0, 0.0)];
+        data.limit_energy(0.3).unwrap();
+        utils::assert_near!(data.stats().rms_amplitude, 0.3, 1e-6);
+        utils::assert_near!(data.stats().peak_amplitude, 0.3 * 3.0_f32.sqrt(), 1e-6);
+
+        let original = data.clone();
+        data.limit_energy(0.5).unwrap();
+        assert_eq!(data, original);
+
+        data.limit_energy(-0.1).unwrap_err();
+        data.limit_energy(f32::NAN).unwrap_err();
     }
 
-    /// unit test to check version upgrading ignoring incorrect transients and frequency_envelopes.
     #[test]
-    fn check_version_upgrade_invalid() {
-        check_v0_to_v1_upgrade(
-            "invalid_v0_conversion.vij",
-            "valid_v1_from_invalid_v0_conversion.haptic",
-            false,
-        );
+    // The builder creates the same clip as building it by hand, and returns validation errors
+    fn data_model_builder() {
+        let expected = create_test_data_model();
+        let mut builder = DataModelBuilder::new().metadata(expected.metadata.clone());
+        for breakpoint in expected.signals.continuous.envelopes.amplitude.iter() {
+            builder = builder.amplitude_breakpoint(breakpoint.time, breakpoint.amplitude);
+            if let Some(emphasis) = breakpoint.emphasis {
+                builder = builder.emphasis(emphasis.amplitude, emphasis.frequency);
+            }
+        }
+        for breakpoint in expected
+            .signals
+            .continuous
+            .envelopes
+            .frequency
+            .as_ref()
+            .unwrap()
+        {
+            builder = builder.frequency_breakpoint(breakpoint.time, breakpoint.frequency);
+        }
+        assert_eq!(builder.build().unwrap(), expected);
+
+        // Test: Breakpoints out of order result in a validation error
+        let err = DataModelBuilder::new()
+            .amplitude_breakpoint(0.5, 0.2)
+            .amplitude_breakpoint(0.1, 0.2)
+            .build()
+            .unwrap_err();
+        assert!(err.contains("Breakpoint times not consecutive"), "{}", err);
+
+        // Test: Emphasis without a breakpoint is an error
+        DataModelBuilder::new()
+            .emphasis(0.5, 0.5)
+            .amplitude_breakpoint(0.0, 0.2)
+            .build()
+            .unwrap_err();
+
+        // Test: A clip without amplitude breakpoints is invalid
+        DataModelBuilder::new().build().unwrap_err();
     }
 
-    /// Unit test datamodel validation.
     #[test]
-    fn check_validation_pass() {
-        let data = load_file_from_test_data("valid_v1.haptic");
-        let data: DataModel = serde_json::from_str(&data).unwrap();
-        data.validate().unwrap();
+    // A clip built from a computed amplitude series has the current version and is valid
+    fn from_amplitude() {
+        let amplitudes: Vec<AmplitudeBreakpoint> = (0..=10)
+            .map(|i| AmplitudeBreakpoint::new(i as f32 * 0.1, i as f32 / 10.0))
+            .collect();
+        let clip = DataModel::from(Signals::from_amplitude(amplitudes.clone()))
+            .validate()
+            .unwrap();
+        assert_eq!(clip.version, DataModel::CURRENT);
+        assert_eq!(clip.signals.continuous.envelopes.amplitude, amplitudes);
+        assert_eq!(clip.signals.continuous.envelopes.frequency, None);
+        assert_eq!(Envelopes::from(amplitudes.clone()).amplitude, amplitudes);
     }
 
-    /// Unit test datamodel validation optionals.
     #[test]
-    fn check_validation_optional() {
-        let data = load_file_from_test_data("validation_v1_optionals.haptic");
-        let data: DataModel = serde_json::from_str(&data).unwrap();
-        data.validate().unwrap();
+    // The RMS amplitude of a triangular envelope is 1/sqrt(3) of its peak
+    fn stats() {
+        let clip = DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.0)
+            .amplitude_breakpoint(1.0, 0.8)
+            .emphasis(0.9, 0.5)
+            .amplitude_breakpoint(2.0, 0.0)
+            .frequency_breakpoint(0.0, 0.5)
+            .frequency_breakpoint(2.5, 0.5)
+            .build()
+            .unwrap();
+        let stats = clip.stats();
+        assert_eq!(stats.peak_amplitude, 0.8);
+        utils::assert_near!(stats.rms_amplitude, 0.8 / 3.0_f32.sqrt(), 1e-6);
+        assert_eq!(stats.duration, 2.5);
+        assert_eq!(stats.breakpoint_count, 5);
+        assert_eq!(stats.emphasis_count, 1);
+
+        // A constant envelope has the same RMS and peak amplitude
+        let clip = DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.5)
+            .amplitude_breakpoint(3.0, 0.5)
+            .build()
+            .unwrap();
+        utils::assert_near!(clip.stats().rms_amplitude, 0.5, 1e-6);
     }
 
-    /// Unit test datamodel validation amplitude range.
     #[test]
-    fn check_validation_fail_range() {
-        let data = load_file_from_test_data("validation_v1_amplitude.haptic");
-        let data: DataModel = serde_json::from_str(&data).unwrap();
-        let err = data.validate().map(|_| ()).unwrap_err();
-        assert!(
-            err.contains("Breakpoint amplitude out of range"),
-            "Failed validation at wrong point: {}",
-            err
-        );
+    // The duration covers both envelopes, and a clip is silent only if all amplitudes are 0.0
+    fn duration_and_is_silent() {
+        use crate::test_utils::{amp, emp};
+
+        let mut data = DataModel::default();
+        assert_eq!(data.duration(), 0.0);
+        assert!(data.is_silent());
+
+        data.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.0), amp(1.0, 0.0)];
+        assert_eq!(data.duration(), 1.0);
+        assert!(data.is_silent());
+
+        // Amplitudes too low to turn the motor on are silent as well
+        data.signals.continuous.envelopes.amplitude[1] = emp(1.0, 0.5 / 255.0, 0.5 / 255.0, 0.5);
+        assert!(data.is_silent());
+        data.signals.continuous.envelopes.amplitude[1] = amp(1.0, 0.0);
+
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.5,
+            },
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 0.5,
+            },
+        ]);
+        assert_eq!(data.duration(), 1.5);
+
+        data.signals.continuous.envelopes.amplitude[1] = emp(1.0, 0.0, 0.5, 0.5);
+        assert!(!data.is_silent());
+        data.signals.continuous.envelopes.amplitude[1] = amp(1.0, 0.1);
+        assert!(!data.is_silent());
     }
 
-    /// Unit test datamodel validation consecutive breakpoints.
     #[test]
-    fn check_validation_fail_sequence() {
-        let data = load_file_from_test_data("validation_v1_sequence.haptic");
-        let data: DataModel = serde_json::from_str(&data).unwrap();
-        let err = data.validate().map(|_| ()).unwrap_err();
-        assert!(
-            err.contains("Breakpoint times not consecutive"),
-            "Failed validation at wrong point: {}",
-            err
+    // A straight ramp collapses to its endpoints, while emphasis, steps and breakpoints that
+    // deviate more than the tolerance are kept
+    fn simplify() {
+        use crate::test_utils::{amp, emp};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = (0..100)
+            .map(|index| amp(index as f32 * 0.01, index as f32 / 99.0))
+            .collect();
+        data.signals.continuous.envelopes.frequency = Some(
+            data.signals
+                .continuous
+                .envelopes
+                .amplitude
+                .iter()
+                .map(|breakpoint| FrequencyBreakpoint {
+                    time: breakpoint.time,
+                    frequency: 0.5,
+                })
+                .collect(),
         );
-    }
+        let mut ramp_with_emphasis = data.clone();
 
-    #[test]
-    fn check_validation_fail_emphasis_amplitude_vs_signal_amplitude() {
-        let data = load_file_from_test_data("validation_v1_emphasis_amplitude.haptic");
-        let data: DataModel = serde_json::from_str(&data).unwrap();
-        let err = data.validate().map(|_| ()).unwrap_err();
-        assert!(
-            err.contains("Emphasis amplitude can't be lower than Envelope amplitude"),
-            "Failed validation with wrong message: {}",
-            err
+        data.simplify(0.001).unwrap();
+        assert_eq!(
+            data.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.0), amp(0.99, 1.0)]
+        );
+        assert_eq!(
+            data.signals.continuous.envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 0.0,
+                    frequency: 0.5
+                },
+                FrequencyBreakpoint {
+                    time: 0.99,
+                    frequency: 0.5
+                },
+            ])
+        );
+
+        // Test: An emphasis breakpoint in the middle of the ramp is kept
+        let emphasis_breakpoint = emp(0.5, 50.0 / 99.0, 0.9, 0.5);
+        ramp_with_emphasis.signals.continuous.envelopes.amplitude[50] = emphasis_breakpoint.clone();
+        ramp_with_emphasis.simplify(0.001).unwrap();
+        assert_eq!(
+            ramp_with_emphasis.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.0), emphasis_breakpoint, amp(0.99, 1.0)]
+        );
+
+        // Test: Peaks above the tolerance and steps are kept, small deviations are removed
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.0),
+            amp(0.1, 0.105),
+            amp(0.2, 0.2),
+            amp(0.3, 0.8),
+            amp(0.4, 0.4),
+            amp(0.5, 0.5),
+            amp(0.5, 0.1),
+            amp(0.6, 0.1),
+        ];
+        data.simplify(0.01).unwrap();
+        assert_eq!(
+            data.signals.continuous.envelopes.amplitude,
+            vec![
+                amp(0.0, 0.0),
+                amp(0.2, 0.2),
+                amp(0.3, 0.8),
+                amp(0.4, 0.4),
+                amp(0.5, 0.5),
+                amp(0.5, 0.1),
+                amp(0.6, 0.1),
+            ]
         );
+
+        data.simplify(-1.0).unwrap_err();
+        data.simplify(f32::NAN).unwrap_err();
     }
 
     #[test]
-    fn check_validation_fail_emphasis_amplitude_range() {
-        let data = load_file_from_test_data("validation_v1_emphasis_amplitude_range.haptic");
-        let haptic: DataModel = serde_json::from_str(&data).unwrap();
-        let err = haptic.validate().map(|_| ()).unwrap_err();
-        assert!(
-            err.contains("Emphasis amplitude out of range"),
-            "Failed validation with wrong message: {}",
-            err
+    // Reversing mirrors the breakpoints around the clip duration, and reversing twice restores
+    // the original clip
+    fn reverse() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.1),
+            emp(0.5, 0.2, 0.6, 0.7),
+            amp(1.0, 0.2),
+            amp(1.0, 0.8),
+            amp(2.0, 0.0),
+        ];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.5,
+            },
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 1.0,
+            },
+        ]);
+        let original = data.clone();
+
+        data.reverse();
+        let reversed = data.clone().validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&reversed.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                amp(1.0, 0.8),
+                amp(1.0, 0.2),
+                emp(1.5, 0.2, 0.6, 0.7),
+                amp(2.0, 0.1),
+            ]
+        );
+        assert_eq!(
+            reversed.signals.continuous.envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 0.5,
+                    frequency: 1.0,
+                },
+                FrequencyBreakpoint {
+                    time: 2.0,
+                    frequency: 0.5,
+                },
+            ])
         );
+
+        data.reverse();
+        assert_eq!(data.signals, original.signals);
     }
 
     #[test]
-    fn check_validation_fail_emphasis_frequency_range() {
-        let data = load_file_from_test_data("validation_v1_emphasis_frequency_range.haptic");
-        let haptic: DataModel = serde_json::from_str(&data).unwrap();
-        let err = haptic.validate().map(|_| ()).unwrap_err();
-        assert!(
-            err.contains("Emphasis frequency out of range"),
-            "Failed validation with wrong message: {}",
-            err
+    // Frequency breakpoints after the amplitude envelope are cut off when reversing
+    fn reverse_frequency_longer_than_amplitude() {
+        use crate::test_utils::amp;
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.5), amp(1.0, 0.5)];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.0,
+            },
+            FrequencyBreakpoint {
+                time: 2.0,
+                frequency: 1.0,
+            },
+        ]);
+
+        data.reverse();
+        assert_eq!(
+            data.signals.continuous.envelopes.frequency,
+            Some(vec![
+                FrequencyBreakpoint {
+                    time: 0.0,
+                    frequency: 0.5,
+                },
+                FrequencyBreakpoint {
+                    time: 1.0,
+                    frequency: 0.0,
+                },
+            ])
         );
+        data.validate().unwrap();
     }
 
     #[test]
-    fn check_valid_beta_impulses() {
-        let data: String = load_file_from_test_data("valid_beta_impulses.haptic");
-        let haptic: DataModel = serde_json::from_str(&data).unwrap();
-        haptic.validate().unwrap();
+    // Fades ramp the amplitude linearly, inserting breakpoints where the fades end, and are
+    // clamped to the clip duration
+    fn apply_fade() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 1.0),
+            emp(0.5, 1.0, 1.0, 0.5),
+            amp(2.0, 1.0),
+            amp(4.0, 0.5),
+        ];
+        let original = data.clone();
+
+        data.apply_fade(1.0, 2.0).unwrap();
+        let faded = data.validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&faded.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                emp(0.5, 0.5, 0.5, 0.5),
+                amp(1.0, 1.0),
+                amp(2.0, 1.0),
+                amp(4.0, 0.0),
+            ]
+        );
+
+        // Test: A fade longer than the clip is clamped to the clip duration
+        let mut data = original.clone();
+        data.apply_fade(10.0, 0.0).unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&data.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                emp(0.5, 0.125, 0.125, 0.5),
+                amp(2.0, 0.5),
+                amp(4.0, 0.5),
+            ]
+        );
+
+        // Test: Fades of 0 seconds leave the clip unchanged
+        let mut data = original.clone();
+        data.apply_fade(0.0, 0.0).unwrap();
+        assert_eq!(data, original);
+
+        // Test: Invalid durations are rejected
+        let mut data = original;
+        data.apply_fade(-1.0, 0.0).unwrap_err();
+        data.apply_fade(0.0, f32::NAN).unwrap_err();
     }
 
     #[test]
-    // Test that truncating before a value works as expected
-    fn truncate() {
-        let mut before_truncate = latest_from_test_data("truncate_before.haptic");
-        let after_truncate = latest_from_test_data("truncate_after.haptic");
-        before_truncate.truncate_before(2.5).unwrap();
-        assert_eq!(before_truncate.signals, after_truncate.signals);
+    // A clip with frequency modulation and emphasis is reduced to an amplitude-only clip for a
+    // device without frequency control and transients, and kept as is for a capable device
+    fn downgrade_for() {
+        let clip = latest_from_test_data("valid_v1.haptic");
+        assert_eq!(
+            clip.required_features(),
+            vec![Feature::FrequencyModulation, Feature::Emphasis]
+        );
+
+        let capable = DeviceCapabilities {
+            amplitude_control: true,
+            frequency_control: true,
+            emphasis: true,
+        };
+        assert_eq!(clip.downgrade_for(&capable), clip);
+
+        let minimal = DeviceCapabilities {
+            amplitude_control: true,
+            frequency_control: false,
+            emphasis: false,
+        };
+        let downgraded = clip.downgrade_for(&minimal);
+        assert!(downgraded.required_features().is_empty());
+        assert_eq!(downgraded.signals.continuous.envelopes.frequency, None);
+        assert_eq!(
+            downgraded.signals.continuous.envelopes.amplitude,
+            emphasize(
+                &clip.signals.continuous.envelopes.amplitude,
+                EmphasisParameters::default()
+            )
+        );
+        downgraded.validate().unwrap();
     }
 
     #[test]
-    // Test that truncating before a value after the end of the clip returns an error
-    fn truncate_after_end() {
-        let mut before_truncate = latest_from_test_data("truncate_before.haptic");
+    // Strict validation rejects clips not starting at 0 and clips with steps, which the lenient
+    // validation accepts
+    fn validate_strict() {
+        let valid = DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.2)
+            .amplitude_breakpoint(0.5, 0.4)
+            .frequency_breakpoint(0.2, 0.5)
+            .frequency_breakpoint(0.5, 0.6)
+            .build()
+            .unwrap();
+        assert_eq!(valid.clone().validate_strict().unwrap(), valid);
+
+        let late_start = DataModelBuilder::new()
+            .amplitude_breakpoint(0.1, 0.2)
+            .amplitude_breakpoint(0.5, 0.4)
+            .build()
+            .unwrap();
         assert_eq!(
-            before_truncate.truncate_before(100.0),
-            Err("No amplitude breakpoint before the specified starting time".to_string())
+            late_start.validate_strict().unwrap_err(),
+            "V1 Strict Validation Error: First amplitude breakpoint is at 0.1 instead of 0"
+        );
+
+        let amplitude_step = DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.2)
+            .amplitude_breakpoint(0.3, 0.2)
+            .amplitude_breakpoint(0.3, 0.8)
+            .build()
+            .unwrap();
+        assert_eq!(
+            amplitude_step.validate_strict().unwrap_err(),
+            "V1 Strict Validation Error: Multiple amplitude breakpoints at 0.3"
+        );
+
+        let frequency_step = DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 0.2)
+            .frequency_breakpoint(0.0, 0.2)
+            .frequency_breakpoint(0.0, 0.8)
+            .build()
+            .unwrap();
+        assert_eq!(
+            frequency_step.validate_strict().unwrap_err(),
+            "V1 Strict Validation Error: Multiple frequency breakpoints at 0"
         );
+
+        // Errors of the lenient validation are reported as well
+        let mut out_of_range = valid;
+        out_of_range.signals.continuous.envelopes.amplitude[1].amplitude = 2.0;
+        assert!(out_of_range
+            .validate_strict()
+            .unwrap_err()
+            .starts_with("V1 Validation Error"));
     }
 
     #[test]
-    // Truncating with just 2 breakpoints
-    fn truncate_2_breakpoints() {
-        let mut before_truncate = latest_from_test_data("truncate_before_2_bp.haptic");
-        let after_truncate = latest_from_test_data("truncate_after_2_bp.haptic");
-        before_truncate.truncate_before(0.5).unwrap();
-        assert_eq!(before_truncate.signals, after_truncate.signals);
+    // A gamma of 2.0 reduces mid-level amplitudes of breakpoints and emphasis, and a gamma of
+    // 1.0 leaves the clip unchanged
+    fn apply_amplitude_curve() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude =
+            vec![amp(0.0, 0.0), emp(0.1, 0.5, 0.75, 0.5), amp(0.2, 1.0)];
+        let original = data.clone();
+
+        data.apply_amplitude_curve(2.0).unwrap();
+        let curved = data.validate().unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&curved.signals.continuous.envelopes.amplitude),
+            vec![amp(0.0, 0.0), emp(0.1, 0.25, 0.5625, 0.5), amp(0.2, 1.0)]
+        );
+
+        // Test: A gamma of 1.0 leaves the clip unchanged
+        let mut data = original.clone();
+        data.apply_amplitude_curve(1.0).unwrap();
+        assert_eq!(data, original);
+
+        // Test: Invalid gammas are rejected
+        let mut data = original;
+        data.apply_amplitude_curve(0.0).unwrap_err();
+        data.apply_amplitude_curve(f32::NAN).unwrap_err();
     }
 
     #[test]
-    // Truncating with 1 breakpoint fails
-    fn truncate_1_breakpoint() {
-        let mut before_truncate = latest_from_test_data("truncate_before_1_bp.haptic");
+    // Padding shifts the breakpoints, keeps the amplitude at zero during the silence and
+    // extends the duration
+    fn pad_with_silence() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude =
+            vec![emp(0.0, 0.5, 1.0, 0.5), amp(0.5, 0.2), amp(1.0, 0.4)];
+        data.signals.continuous.envelopes.frequency = Some(vec![
+            FrequencyBreakpoint {
+                time: 0.0,
+                frequency: 0.3,
+            },
+            FrequencyBreakpoint {
+                time: 1.5,
+                frequency: 0.6,
+            },
+        ]);
+        let original = data.clone();
+
+        data.prepend_silence(0.25).unwrap();
+        data.append_silence(0.5).unwrap();
+        let padded = data.validate().unwrap();
+        assert_eq!(padded.duration(), 2.25);
         assert_eq!(
-            before_truncate.truncate_before(1.0),
-            Err("No amplitude breakpoint before the specified starting time".to_string())
+            rounded_amplitude_breakpoints(&padded.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.0),
+                amp(0.25, 0.0),
+                emp(0.25, 0.5, 1.0, 0.5),
+                amp(0.75, 0.2),
+                amp(1.25, 0.4),
+                amp(1.25, 0.0),
+                amp(2.25, 0.0),
+            ]
+        );
+        assert_eq!(
+            padded
+                .signals
+                .continuous
+                .envelopes
+                .frequency
+                .unwrap()
+                .iter()
+                .map(|breakpoint| breakpoint.time)
+                .collect::<Vec<f32>>(),
+            vec![0.25, 1.75]
+        );
+
+        // Test: A clip starting and ending silent gets only one new breakpoint on each side
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![amp(0.0, 0.0), amp(1.0, 0.0)];
+        data.prepend_silence(1.0).unwrap();
+        data.append_silence(1.0).unwrap();
+        assert_eq!(
+            data.signals.continuous.envelopes.amplitude,
+            vec![amp(0.0, 0.0), amp(1.0, 0.0), amp(2.0, 0.0), amp(3.0, 0.0)]
         );
+
+        // Test: Padding with 0 seconds leaves the clip unchanged
+        let mut data = original.clone();
+        data.prepend_silence(0.0).unwrap();
+        data.append_silence(0.0).unwrap();
+        assert_eq!(data, original);
+
+        // Test: Invalid durations are rejected
+        let mut data = original;
+        data.prepend_silence(-1.0).unwrap_err();
+        data.append_silence(f32::NAN).unwrap_err();
     }
 
     #[test]
-    // Truncating with empty frequency
-    fn truncate_empty_frequency_envelope_before() {
-        // empty frequency envelope before truncating
-        let mut before_truncate =
-            latest_from_test_data("truncate_with_empty_frequency_before.haptic");
-        let after_truncate =
-            latest_from_test_data("truncate_after_with_empty_frequency_before.haptic");
-        before_truncate.truncate_before(2.5).unwrap();
-        assert_eq!(before_truncate.signals, after_truncate.signals);
+    // The constructors create the same breakpoints as spelling out the fields
+    fn amplitude_breakpoint_constructors() {
+        assert_eq!(
+            AmplitudeBreakpoint::new(0.1, 0.5),
+            AmplitudeBreakpoint {
+                time: 0.1,
+                amplitude: 0.5,
+                emphasis: None,
+            }
+        );
+        assert_eq!(
+            AmplitudeBreakpoint::with_emphasis(0.1, 0.5, 0.8, 0.6),
+            AmplitudeBreakpoint {
+                time: 0.1,
+                amplitude: 0.5,
+                emphasis: Some(Emphasis {
+                    amplitude: 0.8,
+                    frequency: 0.6,
+                }),
+            }
+        );
     }
+
     #[test]
-    // Truncating results in a empty frequency envelope
-    fn truncate_empty_frequency_envelope_after() {
-        // empty frequency envelope before truncating
-        let mut before_truncate =
-            latest_from_test_data("truncate_with_empty_frequency_after.haptic");
-        let after_truncate =
-            latest_from_test_data("truncate_after_with_empty_frequency_after.haptic");
+    // Smoothing a zig-zag envelope reduces the peaks and troughs, keeps the mean amplitude and
+    // leaves breakpoints with emphasis untouched
+    fn smooth_amplitude() {
+        use crate::test_utils::{amp, emp, rounded_amplitude_breakpoints};
+
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = (0..=10)
+            .map(|index| amp(index as f32 * 0.1, [0.2, 0.8][index % 2]))
+            .collect();
+        let original = data.clone();
 
-        before_truncate.truncate_before(2.5).unwrap();
-        assert_eq!(before_truncate.signals, after_truncate.signals);
+        data.smooth_amplitude(0.1).unwrap();
+        let amplitudes = &data.signals.continuous.envelopes.amplitude;
+        assert_eq!(
+            rounded_amplitude_breakpoints(amplitudes),
+            (0..=10)
+                .map(|index| amp(index as f32 * 0.1, [0.35, 0.65][index % 2]))
+                .collect::<Vec<AmplitudeBreakpoint>>()
+        );
+        utils::assert_near!(average_amplitude(amplitudes, 0.0, 1.0), 0.5, 1e-5);
+
+        // Test: Breakpoints with emphasis keep their amplitude
+        let mut data = DataModel::default();
+        data.signals.continuous.envelopes.amplitude = vec![
+            amp(0.0, 0.2),
+            emp(0.1, 0.8, 1.0, 0.5),
+            amp(0.2, 0.2),
+            amp(0.3, 0.8),
+        ];
+        data.smooth_amplitude(0.1).unwrap();
+        assert_eq!(
+            rounded_amplitude_breakpoints(&data.signals.continuous.envelopes.amplitude),
+            vec![
+                amp(0.0, 0.35),
+                emp(0.1, 0.8, 1.0, 0.5),
+                amp(0.2, 0.35),
+                amp(0.3, 0.65),
+            ]
+        );
+
+        // Test: A window of 0 seconds leaves the clip unchanged
+        let mut data = original.clone();
+        data.smooth_amplitude(0.0).unwrap();
+        assert_eq!(data, original);
+
+        // Test: Invalid windows are rejected
+        let mut data = original;
+        data.smooth_amplitude(-0.1).unwrap_err();
+        data.smooth_amplitude(f32::INFINITY).unwrap_err();
     }
 }
@@ -10,15 +10,76 @@
 use serde::{Deserialize, Serialize};
 
 ///Lofelt data models versioning structure
+///
+/// The version is serialized as an object with `major`, `minor` and `patch` fields. When
+/// deserializing, a `"major.minor.patch"` string is accepted as well, as used by some third-party
+/// files.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, PartialOrd)]
+#[serde(try_from = "SerializedVersion")]
 pub struct Version {
     pub major: u32,
-    #[serde(default = "Version::default_minor_patch")]
     pub minor: u32,
-    #[serde(default = "Version::default_minor_patch")]
     pub patch: u32,
 }
 
+/// The forms a version can be deserialized from, see `Version`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedVersion {
+    Object {
+        major: u32,
+        #[serde(default = "Version::default_minor_patch")]
+        minor: u32,
+        #[serde(default = "Version::default_minor_patch")]
+        patch: u32,
+    },
+    String(String),
+}
+
+impl std::convert::TryFrom<SerializedVersion> for Version {
+    type Error = String;
+
+    fn try_from(version: SerializedVersion) -> Result<Self, Self::Error> {
+        match version {
+            SerializedVersion::Object {
+                major,
+                minor,
+                patch,
+            } => Ok(Version {
+                major,
+                minor,
+                patch,
+            }),
+            SerializedVersion::String(version) => version.parse(),
+        }
+    }
+}
+
+/// Parses a version from a `"major.minor.patch"` string.
+///
+/// Like in the object form, minor and patch can be omitted and default to 0.
+impl std::str::FromStr for Version {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let invalid_version = || format!("Invalid version \"{}\"", version);
+        let mut numbers = version
+            .split('.')
+            .map(|number| number.parse::<u32>().map_err(|_| invalid_version()));
+        let major = numbers.next().ok_or_else(invalid_version)??;
+        let minor = numbers.next().transpose()?.unwrap_or(0);
+        let patch = numbers.next().transpose()?.unwrap_or(0);
+        if numbers.next().is_some() {
+            return Err(invalid_version());
+        }
+        Ok(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
 impl Version {
     ///Default value 0 to minor and patch, when only major is deserialized
     fn default_minor_patch() -> u32 {
@@ -26,17 +87,39 @@ impl Version {
     }
 
     pub fn from_json(data: &str) -> Version {
+        Version::from_slice(data.as_bytes())
+    }
+
+    /// Like from_json(), but takes the JSON as UTF-8 encoded bytes
+    pub fn from_slice(data: &[u8]) -> Version {
         #[derive(Deserialize)]
         /// Helper struct to deserialize the version without needing the full DataModel
         pub struct VersionCheck {
             pub version: Version,
         }
 
-        match serde_json::from_str::<VersionCheck>(data) {
+        match serde_json::from_slice::<VersionCheck>(data) {
             Ok(checker) => checker.version,
             Err(_) => Version::default(),
         }
     }
+
+    /// Returns true if data of this version can be fully understood by a data model of version
+    /// `other`.
+    ///
+    /// Following semantic versioning, this is the case if both have the same major version and
+    /// this version doesn't have a higher minor version, as a higher minor version may add
+    /// features. The patch version doesn't affect compatibility.
+    pub fn is_compatible_with(&self, other: &Version) -> bool {
+        self.major == other.major && self.minor <= other.minor
+    }
+}
+
+/// Formats the version as "major.minor.patch"
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
 }
 
 /// Default version structure values
@@ -62,6 +145,63 @@ pub trait DataModelVersion {
 mod tests {
     use super::*;
 
+    fn version(major: u32, minor: u32) -> Version {
+        Version {
+            major,
+            minor,
+            patch: 0,
+        }
+    }
+
+    #[test]
+    fn version_compatibility() {
+        assert!(version(1, 0).is_compatible_with(&version(1, 1)));
+        assert!(version(1, 1).is_compatible_with(&version(1, 1)));
+        assert!(!version(1, 2).is_compatible_with(&version(1, 0)));
+        assert!(!version(2, 0).is_compatible_with(&version(1, 0)));
+        assert!(!version(1, 0).is_compatible_with(&version(2, 0)));
+
+        // The patch version is ignored
+        assert!(Version {
+            major: 1,
+            minor: 0,
+            patch: 3
+        }
+        .is_compatible_with(&version(1, 0)));
+    }
+
+    #[test]
+    // The object and the string form deserialize to the same version
+    fn version_deserialize() {
+        let expected = Version {
+            major: 1,
+            minor: 2,
+            patch: 3,
+        };
+        let object: Version =
+            serde_json::from_str(r#"{ "major": 1, "minor": 2, "patch": 3 }"#).unwrap();
+        let string: Version = serde_json::from_str(r#""1.2.3""#).unwrap();
+        assert_eq!(object, expected);
+        assert_eq!(string, expected);
+        assert_eq!(Version::from_json(r#"{ "version": "1.2.3" }"#), expected);
+
+        // Minor and patch default to 0 in both forms
+        let object: Version = serde_json::from_str(r#"{ "major": 1 }"#).unwrap();
+        let string: Version = serde_json::from_str(r#""1""#).unwrap();
+        assert_eq!(object, version(1, 0));
+        assert_eq!(string, version(1, 0));
+
+        // The version is still serialized as an object
+        assert_eq!(
+            serde_json::to_string(&expected).unwrap(),
+            r#"{"major":1,"minor":2,"patch":3}"#
+        );
+
+        for invalid in &[r#""""#, r#""1.x.0""#, r#""1.0.0.0""#, r#""-1.0.0""#] {
+            assert!(serde_json::from_str::<Version>(invalid).is_err());
+        }
+    }
+
     #[test]
     #[allow(clippy::eq_op)]
     fn version_cmp() {
@@ -1,6 +1,23 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
 use crate::v1::AmplitudeBreakpoint;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Amplitude, from 0.0 to 1.0, below which the motor is considered to be off.
+///
+/// Android amplitudes go from 0 to 255, and any amplitude below 1/255 is rounded down to 0 when
+/// converting to a Waveform, which turns the motor off. Features that need to decide whether
+/// the motor is running, like the ducking around an emphasis, should use this threshold so they
+/// all agree on what "off" means.
+pub const OFF_THRESHOLD: f32 = 1.0 / 255.0;
+
+/// Returns true if the motor is considered to be off at the given amplitude.
+///
+/// See OFF_THRESHOLD.
+pub fn is_motor_off(amplitude: f32) -> bool {
+    amplitude < OFF_THRESHOLD
+}
 
 // A Waveform is a representation of a vibration pattern.
 //
@@ -15,6 +32,9 @@ pub struct Waveform {
     pub amplitudes: Vec<i32>,
 }
 
+/// The maximum amplitude of a Waveform played on Android
+pub const ANDROID_MAX_AMPLITUDE: i32 = 255;
+
 pub struct WaveformConversionParameters {
     pub max_amplitude: i32,
 }
@@ -68,6 +88,198 @@ impl Waveform {
             amplitudes,
         }
     }
+
+    /// Returns the duration of the Waveform in milliseconds, which is the sum of all timings
+    pub fn total_duration_ms(&self) -> i64 {
+        self.timings.iter().sum()
+    }
+
+    /// Returns the number of entries of the Waveform
+    pub fn entry_count(&self) -> usize {
+        self.timings.len()
+    }
+
+    /// Returns a copy of this Waveform with every amplitude multiplied by `factor`.
+    ///
+    /// The resulting amplitudes are clamped to the range of Android amplitudes, 0 to
+    /// ANDROID_MAX_AMPLITUDE. A negative factor is invalid and returns an unmodified copy.
+    pub fn with_amplitude_multiplication(&self, factor: f32) -> Waveform {
+        if factor < 0.0 {
+            return self.clone();
+        }
+
+        Waveform {
+            timings: self.timings.clone(),
+            amplitudes: self
+                .amplitudes
+                .iter()
+                .map(|amplitude| {
+                    ((*amplitude as f32 * factor).min(ANDROID_MAX_AMPLITUDE as f32) as i32)
+                        .clamp(0, ANDROID_MAX_AMPLITUDE)
+                })
+                .collect(),
+        }
+    }
+
+    /// Merges consecutive entries with the same amplitude into one entry, without changing
+    /// how the Waveform is played.
+    pub fn coalesce(&mut self) {
+        let mut timings: Vec<i64> = Vec::with_capacity(self.timings.len());
+        let mut amplitudes: Vec<i32> = Vec::with_capacity(self.amplitudes.len());
+        for (timing, amplitude) in self.timings.iter().zip(&self.amplitudes) {
+            match (timings.last_mut(), amplitudes.last()) {
+                (Some(last_timing), Some(last_amplitude)) if last_amplitude == amplitude => {
+                    *last_timing += timing;
+                }
+                _ => {
+                    timings.push(*timing);
+                    amplitudes.push(*amplitude);
+                }
+            }
+        }
+        self.timings = timings;
+        self.amplitudes = amplitudes;
+    }
+
+    /// Merges adjacent entries until the Waveform has at most `max_entries` entries.
+    ///
+    /// The merged amplitude is the average of the two amplitudes, weighted by their durations,
+    /// so that the energy of the Waveform is preserved. The pair whose merge changes the
+    /// Waveform the least is merged first, so entries with equal amplitudes are merged before
+    /// anything else. The total duration stays unchanged.
+    ///
+    /// A `max_entries` of 0 is treated as 1.
+    pub fn decimate(&mut self, max_entries: usize) {
+        let max_entries = max_entries.max(1);
+        let len = self.timings.len();
+        if len <= max_entries {
+            return;
+        }
+
+        // The cost of merging two entries is the duration of the shorter entry times the
+        // amplitude difference, i.e. the area by which the merge changes the Waveform
+        fn merge_cost(timings: &[i64], amplitudes: &[i32], a: usize, b: usize) -> i64 {
+            timings[a].min(timings[b]) * (amplitudes[a] - amplitudes[b]).abs() as i64
+        }
+
+        // An entry is merged into its left neighbour by marking it as removed and unlinking it,
+        // so that a merge only touches the merged pair and its neighbours. The removed entries
+        // are dropped in one pass at the end.
+        let mut removed = vec![false; len];
+        let mut previous: Vec<Option<usize>> = (0..len).map(|index| index.checked_sub(1)).collect();
+        let mut next: Vec<Option<usize>> = (0..len)
+            .map(|index| Some(index + 1).filter(|next| *next < len))
+            .collect();
+        // Incremented whenever an entry changes, so that outdated heap entries can be skipped
+        let mut generations = vec![0_u32; len];
+
+        // Min-heap of (cost, index of the left entry, generation of the left entry, generation
+        // of the right entry). Pairs with the same cost are merged from left to right.
+        let mut heap: BinaryHeap<Reverse<(i64, usize, u32, u32)>> = (0..len - 1)
+            .map(|index| {
+                let cost = merge_cost(&self.timings, &self.amplitudes, index, index + 1);
+                Reverse((cost, index, 0, 0))
+            })
+            .collect();
+
+        let mut entry_count = len;
+        while entry_count > max_entries {
+            let (index, generation_a, generation_b) = match heap.pop() {
+                Some(Reverse((_, index, generation_a, generation_b))) => {
+                    (index, generation_a, generation_b)
+                }
+                None => break,
+            };
+            let next_index = match next[index] {
+                Some(next_index) if !removed[index] => next_index,
+                _ => continue,
+            };
+            if generations[index] != generation_a || generations[next_index] != generation_b {
+                continue;
+            }
+
+            let duration_a = self.timings[index];
+            let duration_b = self.timings[next_index];
+            let duration = duration_a + duration_b;
+            let amplitude = if duration > 0 {
+                let energy = self.amplitudes[index] as i64 * duration_a
+                    + self.amplitudes[next_index] as i64 * duration_b;
+                (energy as f64 / duration as f64).round() as i32
+            } else {
+                self.amplitudes[index].max(self.amplitudes[next_index])
+            };
+
+            self.timings[index] = duration;
+            self.amplitudes[index] = amplitude;
+            removed[next_index] = true;
+            next[index] = next[next_index];
+            if let Some(after) = next[index] {
+                previous[after] = Some(index);
+            }
+            generations[index] += 1;
+            entry_count -= 1;
+
+            if let Some(before) = previous[index] {
+                let cost = merge_cost(&self.timings, &self.amplitudes, before, index);
+                heap.push(Reverse((
+                    cost,
+                    before,
+                    generations[before],
+                    generations[index],
+                )));
+            }
+            if let Some(after) = next[index] {
+                let cost = merge_cost(&self.timings, &self.amplitudes, index, after);
+                heap.push(Reverse((
+                    cost,
+                    index,
+                    generations[index],
+                    generations[after],
+                )));
+            }
+        }
+
+        fn without_removed<T: Copy>(values: &[T], removed: &[bool]) -> Vec<T> {
+            values
+                .iter()
+                .zip(removed)
+                .filter(|(_, removed)| !**removed)
+                .map(|(value, _)| *value)
+                .collect()
+        }
+        self.timings = without_removed(&self.timings, &removed);
+        self.amplitudes = without_removed(&self.amplitudes, &removed);
+    }
+
+    /// Converts the Waveform to a pattern for devices that can only turn the motor on and off.
+    ///
+    /// The pattern has the shape expected by `Vibrator.vibrate(long[], int)`: Alternating
+    /// durations in milliseconds for which the motor is off and on, starting with an off
+    /// duration, which is 0 if the Waveform starts with the motor on.
+    ///
+    /// `threshold` is an amplitude from 0.0 to 1.0, relative to ANDROID_MAX_AMPLITUDE. Entries
+    /// with an amplitude at or above the threshold turn the motor on, entries below turn it off.
+    /// Entries for which `is_motor_off()` is true always turn the motor off, even with a
+    /// threshold of 0.0. Consecutive entries on the same side of the threshold are merged into
+    /// one duration.
+    pub fn to_on_off_pattern(&self, threshold: f32) -> Vec<i64> {
+        // The pattern starts with an off duration
+        let mut pattern: Vec<i64> = vec![0];
+        let mut motor_on = false;
+        for (timing, amplitude) in self.timings.iter().zip(&self.amplitudes) {
+            let amplitude = *amplitude as f32 / ANDROID_MAX_AMPLITUDE as f32;
+            let on = !is_motor_off(amplitude) && amplitude >= threshold;
+            if on != motor_on {
+                pattern.push(0);
+                motor_on = on;
+            }
+            *pattern.last_mut().unwrap() += timing;
+        }
+        if pattern == [0] {
+            pattern.clear();
+        }
+        pattern
+    }
 }
 
 #[cfg(test)]
@@ -168,6 +380,162 @@ mod tests {
         assert_eq!(expected_waveform, actual_waveform);
     }
 
+    // Tests that amplitude multiplication scales and clamps the amplitudes, but keeps the timings
+    #[test]
+    fn amplitude_multiplication() {
+        let waveform = test_utils::create_waveform(&[(10, 0), (20, 51), (30, 200), (40, 255)]);
+        assert_eq!(
+            waveform.with_amplitude_multiplication(0.5),
+            test_utils::create_waveform(&[(10, 0), (20, 25), (30, 100), (40, 127)])
+        );
+        assert_eq!(
+            waveform.with_amplitude_multiplication(2.0),
+            test_utils::create_waveform(&[(10, 0), (20, 102), (30, 255), (40, 255)])
+        );
+        assert_eq!(
+            waveform.with_amplitude_multiplication(0.0),
+            test_utils::create_waveform(&[(10, 0), (20, 0), (30, 0), (40, 0)])
+        );
+        assert_eq!(waveform.with_amplitude_multiplication(-1.0), waveform);
+    }
+
+    // Tests that amplitudes that round down to 0 in a Waveform are considered to be off
+    #[test]
+    fn motor_off_threshold() {
+        assert!(is_motor_off(0.0));
+        assert!(is_motor_off(0.9 / 255.0));
+        assert!(!is_motor_off(1.1 / 255.0));
+        assert!(!is_motor_off(1.0));
+
+        let breakpoints = [
+            amp(0.0, 0.9 / 255.0),
+            amp(0.001, 1.1 / 255.0),
+            amp(0.002, 0.0),
+        ];
+        let waveform = Waveform::from_breakpoints(
+            &breakpoints,
+            WaveformConversionParameters { max_amplitude: 255 },
+        );
+        assert_eq!(waveform.amplitudes, vec![0, 1]);
+    }
+
+    // This tests the duration and entry count of a Waveform
+    #[test]
+    fn duration_and_entry_count() {
+        let waveform = test_utils::create_waveform(&[(10, 0), (25, 100), (40, 255), (5, 0)]);
+        assert_eq!(waveform.total_duration_ms(), 80);
+        assert_eq!(waveform.entry_count(), 4);
+
+        let empty_waveform = test_utils::create_waveform(&[]);
+        assert_eq!(empty_waveform.total_duration_ms(), 0);
+        assert_eq!(empty_waveform.entry_count(), 0);
+    }
+
+    // This tests that consecutive entries with the same amplitude are merged
+    #[test]
+    fn coalesce() {
+        let mut waveform = test_utils::create_waveform(&[
+            (10, 0),
+            (25, 100),
+            (25, 100),
+            (40, 100),
+            (5, 0),
+            (5, 100),
+        ]);
+        waveform.coalesce();
+        assert_eq!(
+            waveform,
+            test_utils::create_waveform(&[(10, 0), (90, 100), (5, 0), (5, 100)])
+        );
+
+        let mut empty_waveform = test_utils::create_waveform(&[]);
+        empty_waveform.coalesce();
+        assert_eq!(empty_waveform, test_utils::create_waveform(&[]));
+    }
+
+    // This tests that decimating a long Waveform reduces the number of entries while keeping
+    // the total duration and approximately the energy
+    #[test]
+    fn decimate() {
+        let entries = (0..1000)
+            .map(|i| (10 + i % 7, (i * 37 % 256) as i32))
+            .collect::<Vec<(i64, i32)>>();
+        let mut waveform = test_utils::create_waveform(&entries);
+        let total_duration = waveform.timings.iter().sum::<i64>();
+        let energy = |waveform: &Waveform| {
+            waveform
+                .timings
+                .iter()
+                .zip(&waveform.amplitudes)
+                .map(|(timing, amplitude)| timing * *amplitude as i64)
+                .sum::<i64>()
+        };
+        let total_energy = energy(&waveform);
+
+        waveform.decimate(256);
+        assert!(waveform.timings.len() <= 256);
+        assert_eq!(waveform.timings.len(), waveform.amplitudes.len());
+        assert_eq!(waveform.timings.iter().sum::<i64>(), total_duration);
+        let energy_error = (energy(&waveform) - total_energy).abs() as f32 / total_energy as f32;
+        assert!(energy_error < 0.01, "Energy error {}", energy_error);
+
+        // Entries with the same amplitude are merged first
+        let mut waveform = test_utils::create_waveform(&[(10, 50), (20, 50), (10, 0), (5, 200)]);
+        waveform.decimate(3);
+        assert_eq!(
+            waveform,
+            test_utils::create_waveform(&[(30, 50), (10, 0), (5, 200)])
+        );
+
+        // A Waveform that is short enough is unchanged
+        let expected_waveform = waveform.clone();
+        waveform.decimate(10);
+        assert_eq!(waveform, expected_waveform);
+
+        // Merging only updates the neighbours of the merged pair, so even a very long Waveform
+        // is decimated quickly
+        let entries = (0..100_000)
+            .map(|i| (1 + i % 5, (i * 13 % 256) as i32))
+            .collect::<Vec<(i64, i32)>>();
+        let mut waveform = test_utils::create_waveform(&entries);
+        let total_duration = waveform.timings.iter().sum::<i64>();
+        waveform.decimate(256);
+        assert_eq!(waveform.timings.len(), 256);
+        assert_eq!(waveform.timings.iter().sum::<i64>(), total_duration);
+    }
+
+    // This tests that converting to an on/off pattern thresholds the amplitudes and merges
+    // consecutive entries on the same side of the threshold
+    #[test]
+    fn to_on_off_pattern() {
+        let waveform = test_utils::create_waveform(&[
+            (10, 0),
+            (20, 100),
+            (30, 200),
+            (5, 50),
+            (15, 0),
+            (40, 255),
+        ]);
+        assert_eq!(waveform.to_on_off_pattern(0.3), vec![10, 50, 20, 40]);
+
+        // Entries with an amplitude of 0 keep the motor off, even with a threshold of 0
+        assert_eq!(waveform.to_on_off_pattern(0.0), vec![10, 55, 15, 40]);
+
+        // A Waveform that starts with the motor on starts the pattern with an off duration of 0
+        assert_eq!(
+            test_utils::create_waveform(&[(20, 100), (10, 0)]).to_on_off_pattern(0.0),
+            vec![0, 20, 10]
+        );
+
+        // A threshold above the maximum amplitude keeps the motor off
+        assert_eq!(waveform.to_on_off_pattern(1.1), vec![120]);
+
+        assert_eq!(
+            test_utils::create_waveform(&[]).to_on_off_pattern(0.5),
+            Vec::<i64>::new()
+        );
+    }
+
     // This tests that breakpoints close together use proper rounding and don't
     // create waveform entries of 0ms.
     #[test]
@@ -7,17 +7,65 @@
 //! It is the "home" for data model, error handling enums, traits, etc.
 
 use clip_players::PreAuthoredClipPlayback;
+use datamodel::latest::{DataModel, Feature};
+use datamodel::Validation;
 
 pub use clip_players;
+pub use datamodel::latest::{DeviceCapabilities, EmphasisRendering};
 pub use datamodel::VersionSupport;
-pub use utils::Error;
+pub use utils::{Error, ErrorKind};
+
+/// Converts an error from loading Lofelt Data to an `Error` with a matching `ErrorKind`
+fn error_from_data_model_error(error: datamodel::DataModelError) -> Error {
+    let kind = match error {
+        datamodel::DataModelError::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+        _ => ErrorKind::InvalidData,
+    };
+    Error::with_kind(kind, &error.to_string())
+}
+
+/// A feature used by a clip that the player of a `HapticsController` can't play back
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsupportedFeature {
+    pub feature: Feature,
+    /// Name of the player that doesn't support `feature`
+    pub player_name: &'static str,
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "clip uses {}, {} doesn't support it",
+            self.feature, self.player_name
+        )
+    }
+}
 
 /// Class for playing pre-authored clips
 pub struct HapticsController {
     /// Player to which all functionality of playing back pre-authored clips is delegated to
     pub pre_authored_clip_player: Box<dyn PreAuthoredClipPlayback>,
-    /// Duration of a loaded haptic clip
+    /// Duration of a loaded haptic clip, in seconds
     clip_duration: f32,
+    /// The loaded clip before fades are applied, kept so that the clip can be loaded into the
+    /// player again without parsing it again, see `reload()`
+    original_clip: Option<DataModel>,
+    /// Duration of the fade-in, in seconds
+    fade_in: f32,
+    /// Duration of the fade-out, in seconds
+    fade_out: f32,
+    /// Amplitude multiplication set with `set_amplitude_multiplication()`, without the master
+    /// volume
+    amplitude_multiplication: f32,
+    /// Master volume set with `set_master_volume()`
+    master_volume: f32,
+    /// Frequency shift set with `set_frequency_shift()`
+    frequency_shift: f32,
+    /// Loop count set with `set_loop_count()` or `set_looping()`
+    loop_count: u32,
+    /// Gain stored in the metadata of the loaded clip, 1.0 if the clip has none
+    clip_gain: f32,
 }
 
 impl HapticsController {
@@ -25,55 +73,308 @@ impl HapticsController {
         HapticsController {
             pre_authored_clip_player,
             clip_duration: 0.0,
+            original_clip: None,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            amplitude_multiplication: 1.0,
+            master_volume: 1.0,
+            frequency_shift: 0.0,
+            loop_count: 1,
+            clip_gain: 1.0,
         }
     }
 
     /// Loads a pre-authored clip
     ///
-    /// It also sets `clip_duration` based on the last amplitude envelope breakpoint time value
+    /// It also sets `clip_duration` based on the last amplitude or frequency envelope breakpoint
+    /// time value, whichever is later
     pub fn load(&mut self, data: &str) -> Result<VersionSupport, Error> {
+        self.load_slice(data.as_bytes())
+    }
+
+    /// Loads a pre-authored clip from UTF-8 encoded bytes
+    ///
+    /// Behaves like `load()`, without requiring the data to be converted to a string first.
+    pub fn load_slice(&mut self, data: &[u8]) -> Result<VersionSupport, Error> {
         self.pre_authored_clip_player.unload()?;
+        self.original_clip = None;
         let (version_support, haptic_data) =
-            datamodel::latest_from_json(data).map_err(|string| Error::new(&string))?;
+            datamodel::latest_from_slice(data).map_err(error_from_data_model_error)?;
 
-        self.clip_duration = haptic_data
-            .signals
-            .continuous
-            .envelopes
-            .amplitude
-            .last()
-            .map_or(0.0, |amp| amp.time);
+        self.load_data_model(haptic_data)?;
+        Ok(version_support)
+    }
 
-        self.pre_authored_clip_player.load(haptic_data)?;
+    /// Loads a pre-authored clip, keeping the currently loaded clip if the new one is invalid
+    ///
+    /// Unlike `load()`, the clip is parsed and validated before the current clip is unloaded.
+    /// If parsing or validation fails, the error is returned and the currently loaded clip
+    /// stays loaded, so playback is not disturbed.
+    pub fn try_load(&mut self, data: &str) -> Result<VersionSupport, Error> {
+        let (version_support, haptic_data) =
+            datamodel::latest_from_json(data).map_err(error_from_data_model_error)?;
+
+        self.pre_authored_clip_player.unload()?;
+        self.load_data_model(haptic_data)?;
         Ok(version_support)
     }
 
+    /// Passes an already validated clip to the player and updates `clip_duration` and
+    /// `clip_gain`
+    fn load_data_model(&mut self, haptic_data: datamodel::latest::DataModel) -> Result<(), Error> {
+        self.clip_duration = haptic_data.duration();
+        self.clip_gain = haptic_data.metadata.gain.unwrap_or(1.0);
+
+        self.original_clip = Some(haptic_data.clone());
+        self.pre_authored_clip_player
+            .load(self.with_fades_applied(haptic_data)?)?;
+
+        // Like the player, a new clip starts without the runtime parameters of the previous
+        // clip
+        self.amplitude_multiplication = 1.0;
+        self.frequency_shift = 0.0;
+        self.loop_count = 1;
+        self.apply_amplitude_multiplication()
+    }
+
+    /// Passes the product of amplitude multiplication, master volume and clip gain to the
+    /// player again, after the player reset its amplitude multiplication to 1.0
+    fn apply_amplitude_multiplication(&mut self) -> Result<(), Error> {
+        let factor = self.get_amplitude_multiplication();
+        if factor != 1.0 {
+            self.pre_authored_clip_player
+                .set_amplitude_multiplication(factor)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `haptic_data` with the fade-in and fade-out applied
+    fn with_fades_applied(&self, mut haptic_data: DataModel) -> Result<DataModel, Error> {
+        haptic_data
+            .apply_fade(self.fade_in, self.fade_out)
+            .map_err(|e| Error::with_kind(ErrorKind::InvalidArgument, &e))?;
+        Ok(haptic_data)
+    }
+
+    /// Returns the loaded clip as authored, without fades or runtime parameters applied, or
+    /// None if no clip is loaded
+    pub fn clip(&self) -> Option<&DataModel> {
+        self.original_clip.as_ref()
+    }
+
+    /// Returns the loaded clip for editing, or None if no clip is loaded
+    ///
+    /// Changes to the clip are passed to the player with `reload()`.
+    pub fn clip_mut(&mut self) -> Option<&mut DataModel> {
+        self.original_clip.as_mut()
+    }
+
+    /// Loads the clip into the player again, from the clip cached in `load()`
+    ///
+    /// This passes changes made with `clip_mut()` to the player, and validates them first. The
+    /// current fades are applied to the cached clip, so this doesn't need to parse the Lofelt
+    /// Data again. Like loading a clip, this stops playback. Unlike loading a clip, the
+    /// amplitude multiplication, frequency shift and loop count are kept.
+    ///
+    /// If the edited clip is invalid, an error is returned and the player keeps playing the
+    /// previously loaded clip.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let clip = self
+            .original_clip
+            .clone()
+            .ok_or_else(|| {
+                Error::with_kind(ErrorKind::NoClipLoaded, "Unable to reload, no clip loaded.")
+            })?
+            .validate()
+            .map_err(|e| Error::with_kind(ErrorKind::InvalidData, &e))?;
+
+        self.clip_duration = clip.duration();
+        self.clip_gain = clip.metadata.gain.unwrap_or(1.0);
+        self.original_clip = Some(clip);
+        self.reload_with_fades()
+    }
+
+    /// Loads the cached original clip into the player again, with the current fades applied
+    ///
+    /// The player resets the runtime parameters when loading, so they are applied again.
+    fn reload_with_fades(&mut self) -> Result<(), Error> {
+        if let Some(original_clip) = &self.original_clip {
+            let faded_clip = self.with_fades_applied(original_clip.clone())?;
+            self.pre_authored_clip_player.unload()?;
+            self.pre_authored_clip_player.load(faded_clip)?;
+            self.apply_amplitude_multiplication()?;
+            if self.frequency_shift != 0.0 {
+                self.pre_authored_clip_player
+                    .set_frequency_shift(self.frequency_shift)?;
+            }
+            if self.loop_count != 1 {
+                self.pre_authored_clip_player
+                    .set_loop_count(self.loop_count)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the duration of a linear amplitude fade-in at the start of the clip, in seconds
+    ///
+    /// The fade is applied to the breakpoints of the clip before it is passed to the player, so
+    /// it is applied before `set_amplitude_multiplication()`. A fade longer than the clip is
+    /// clamped to the clip duration, and a duration of 0 removes the fade.
+    ///
+    /// The fade stays in effect for clips loaded later. If a clip is already loaded, it is
+    /// loaded again with the new fade, see `reload()`. This stops playback, and keeps the
+    /// amplitude multiplication, frequency shift and loop count.
+    pub fn set_fade_in(&mut self, duration: f32) -> Result<(), Error> {
+        Self::check_fade_duration(duration)?;
+        self.fade_in = duration;
+        self.reload_with_fades()
+    }
+
+    /// Sets the duration of a linear amplitude fade-out at the end of the clip, in seconds
+    ///
+    /// See `set_fade_in()` for how the fade is applied.
+    pub fn set_fade_out(&mut self, duration: f32) -> Result<(), Error> {
+        Self::check_fade_duration(duration)?;
+        self.fade_out = duration;
+        self.reload_with_fades()
+    }
+
+    fn check_fade_duration(duration: f32) -> Result<(), Error> {
+        if duration.is_nan() || duration.is_infinite() || duration < 0.0 {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to apply fade duration {}, needs to be 0 or greater",
+                    duration
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the features used by `data` that the player can't play back
+    ///
+    /// An empty list means the clip will be played back as authored.
+    pub fn unsupported_features(&self, data: &DataModel) -> Vec<UnsupportedFeature> {
+        let capabilities = self.pre_authored_clip_player.capabilities();
+        data.required_features()
+            .into_iter()
+            .filter(|feature| !capabilities.supports(*feature))
+            .map(|feature| UnsupportedFeature {
+                feature,
+                player_name: capabilities.player_name,
+            })
+            .collect()
+    }
+
+    /// Returns the features used by the loaded clip that can be rendered on a device with the
+    /// given capabilities
+    ///
+    /// Features not in the list are dropped when playing back the clip on that device. An empty
+    /// list is returned if no clip is loaded.
+    pub fn renderable_features(&self, capabilities: &DeviceCapabilities) -> Vec<Feature> {
+        self.original_clip
+            .as_ref()
+            .map(|clip| clip.required_features())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|feature| capabilities.can_render(*feature))
+            .collect()
+    }
+
     /// Plays back the pre-authored clip previously loaded with load()
     pub fn play(&mut self) -> Result<(), Error> {
         self.pre_authored_clip_player.play()
     }
 
+    /// Plays back the pre-authored clip previously loaded with load(), starting at `offset`, in
+    /// seconds
+    ///
+    /// This combines `seek()` and `play()` into one step. Like with `seek()`, an offset that is
+    /// not a finite number is rejected.
+    pub fn play_at(&mut self, offset: f32) -> Result<(), Error> {
+        if !offset.is_finite() {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!("Unable to play at {}, needs to be a finite number", offset),
+            ));
+        }
+
+        self.pre_authored_clip_player.play_at(offset)
+    }
+
     /// Stops playing back the pre-authored clip previously started with play()
     pub fn stop(&mut self) -> Result<(), Error> {
         self.pre_authored_clip_player.stop()
     }
 
-    /// Seeks to the position specified with `time`
+    /// Seeks to the position specified with `time`, in seconds
+    ///
+    /// A negative time delays the start of playback, see `PreAuthoredClipPlayback::seek()`. A
+    /// time that is not a finite number is rejected.
     pub fn seek(&mut self, time: f32) -> Result<(), Error> {
+        if !time.is_finite() {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!("Unable to seek to {}, needs to be a finite number", time),
+            ));
+        }
+
         self.pre_authored_clip_player.seek(time)
     }
 
+    /// Seeks to the position specified with `time_ms`, in milliseconds
+    pub fn seek_ms(&mut self, time_ms: i64) -> Result<(), Error> {
+        self.seek(utils::ms_to_seconds(time_ms))
+    }
+
     /// Sets the playback to repeat from the start at the end of the clip
     pub fn set_looping(&mut self, enabled: bool) -> Result<(), Error> {
-        self.pre_authored_clip_player.set_looping(enabled)
+        self.set_loop_count(if enabled { 0 } else { 1 })
+    }
+
+    /// Sets how often the clip is played, with 0 repeating it infinitely
+    pub fn set_loop_count(&mut self, count: u32) -> Result<(), Error> {
+        self.pre_authored_clip_player.set_loop_count(count)?;
+        self.loop_count = count;
+        Ok(())
     }
 
-    /// Returns duration of the loaded audio clip
+    /// Returns duration of the loaded audio clip, in seconds
     pub fn get_clip_duration(&self) -> f32 {
         self.clip_duration
     }
 
+    /// Returns duration of the loaded audio clip, in milliseconds
+    pub fn get_clip_duration_ms(&self) -> i64 {
+        utils::seconds_to_ms(self.clip_duration)
+    }
+
+    /// Returns the amplitude of the loaded clip at `time`, in seconds
+    ///
+    /// The amplitude is interpolated between the breakpoints of the clip as authored, without
+    /// fades or runtime parameters applied. Times outside of the clip are clamped to the first
+    /// or last breakpoint. Returns 0.0 if no clip is loaded.
+    pub fn amplitude_at(&self, time: f32) -> f32 {
+        self.original_clip
+            .as_ref()
+            .map_or(0.0, |clip| clip.amplitude_at(time))
+    }
+
+    /// Returns the frequency of the loaded clip at `time`, in seconds
+    ///
+    /// See `amplitude_at()`. Returns None if no clip is loaded or if the clip has no frequency
+    /// envelope.
+    pub fn frequency_at(&self, time: f32) -> Option<f32> {
+        self.original_clip
+            .as_ref()
+            .and_then(|clip| clip.frequency_at(time))
+    }
+
     /// Sets the amplitude multiplication of the loaded clip
+    ///
+    /// The factor passed to the player is the product of this factor, the master volume, see
+    /// `set_master_volume()`, and the gain stored in the metadata of the clip.
     pub fn set_amplitude_multiplication(
         &mut self,
         multiplication_factor: f32,
@@ -82,26 +383,82 @@ impl HapticsController {
             || multiplication_factor.is_infinite()
             || multiplication_factor < 0.0
         {
-            return Err(Error::new(&format!(
-                "Unable to apply amplitude multiplication factor {}, needs to be 0 or greater",
-                multiplication_factor
-            )));
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to apply amplitude multiplication factor {}, needs to be 0 or greater",
+                    multiplication_factor
+                ),
+            ));
         }
 
-        self.pre_authored_clip_player
-            .set_amplitude_multiplication(multiplication_factor)
+        self.pre_authored_clip_player.set_amplitude_multiplication(
+            multiplication_factor * self.master_volume * self.clip_gain,
+        )?;
+        self.amplitude_multiplication = multiplication_factor;
+        Ok(())
+    }
+
+    /// Sets a master volume that multiplies the amplitude of all clips
+    ///
+    /// The master volume is independent of `set_amplitude_multiplication()`, and both factors are
+    /// multiplied before being passed to the player. This allows a user-facing intensity setting
+    /// to be combined with the scaling of individual clips. Unlike the amplitude multiplication,
+    /// the master volume stays in effect when loading another clip.
+    ///
+    /// The volume needs to be 0 or greater, the default is 1.0.
+    pub fn set_master_volume(&mut self, volume: f32) -> Result<(), Error> {
+        if volume.is_nan() || volume.is_infinite() || volume < 0.0 {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to apply master volume {}, needs to be 0 or greater",
+                    volume
+                ),
+            ));
+        }
+
+        if self.original_clip.is_some() {
+            self.pre_authored_clip_player.set_amplitude_multiplication(
+                self.amplitude_multiplication * volume * self.clip_gain,
+            )?;
+        }
+        self.master_volume = volume;
+        Ok(())
+    }
+
+    /// Returns the amplitude multiplication factor passed to the player, which is the product of
+    /// the amplitude multiplication, the master volume and the clip gain
+    pub fn get_amplitude_multiplication(&self) -> f32 {
+        self.amplitude_multiplication * self.master_volume * self.clip_gain
+    }
+
+    /// Resets the amplitude multiplication and frequency shift to their defaults
+    ///
+    /// The loaded clip and the playback position are kept, so a playing clip continues at its
+    /// authored intensity and frequency.
+    pub fn reset_runtime_params(&mut self) -> Result<(), Error> {
+        self.pre_authored_clip_player.reset_runtime_params()?;
+        self.amplitude_multiplication = 1.0;
+        self.frequency_shift = 0.0;
+        self.apply_amplitude_multiplication()
     }
 
     /// Sets the frequency shift of the loaded clip
     pub fn set_frequency_shift(&mut self, shift: f32) -> Result<(), Error> {
         if shift.is_nan() || shift.is_infinite() || shift < -1.0 || shift > 1.0 {
-            return Err(Error::new(&format!(
-                "Unable to apply frequency shift {}, needs to be between -1 and 1",
-                shift
-            )));
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Unable to apply frequency shift {}, needs to be between -1 and 1",
+                    shift
+                ),
+            ));
         }
 
-        self.pre_authored_clip_player.set_frequency_shift(shift)
+        self.pre_authored_clip_player.set_frequency_shift(shift)?;
+        self.frequency_shift = shift;
+        Ok(())
     }
 }
 
@@ -141,14 +498,152 @@ mod tests {
         let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
         assert_eq!(
             haptics_controller.load(&clip).err(),
-            Some(Error::new("Unsupported version"))
+            Some(Error::with_kind(
+                ErrorKind::UnsupportedVersion,
+                "Unsupported version 2.0.0"
+            ))
         );
         assert_eq!(
             haptics_controller.play().err(),
-            Some(Error::new("Player play: no clip loaded"))
+            Some(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player play: no clip loaded"
+            ))
         );
     }
 
+    #[test]
+    /// Tests that Lofelt Data can be loaded from bytes, and that invalid UTF-8 is rejected
+    fn test_load_slice() {
+        let clip = load_test_file_valid_v1();
+        let expected_duration: f32 = 9.961_361;
+
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.load_slice(clip.as_bytes()).unwrap();
+        assert_eq!(haptics_controller.get_clip_duration(), expected_duration);
+
+        let err = haptics_controller.load_slice(&[0xff, 0xfe]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidData);
+    }
+
+    #[test]
+    /// Tests that the master volume and the amplitude multiplication are combined, and that the
+    /// master volume is kept when loading another clip
+    fn test_master_volume() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+
+        haptics_controller.set_master_volume(0.5).unwrap();
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.5);
+
+        haptics_controller
+            .set_amplitude_multiplication(1.5)
+            .unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.75);
+        haptics_controller.set_master_volume(2.0).unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 3.0);
+
+        // Test: Resetting or loading resets only the amplitude multiplication
+        haptics_controller.reset_runtime_params().unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 2.0);
+        haptics_controller
+            .set_amplitude_multiplication(1.5)
+            .unwrap();
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 2.0);
+
+        for volume in &[-1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                haptics_controller
+                    .set_master_volume(*volume)
+                    .unwrap_err()
+                    .kind,
+                ErrorKind::InvalidArgument
+            );
+        }
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 2.0);
+    }
+
+    #[test]
+    /// Tests that reload() loads the cached clip again with its edits, keeping the amplitude
+    /// multiplication, and fails without a clip or with an invalid clip
+    fn test_reload() {
+        let clip = load_test_file_valid_v1();
+        let expected_duration: f32 = 9.961_361;
+
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        assert_eq!(
+            haptics_controller.reload().unwrap_err().kind,
+            ErrorKind::NoClipLoaded
+        );
+
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller
+            .set_amplitude_multiplication(0.5)
+            .unwrap();
+        haptics_controller.play().unwrap();
+        haptics_controller.reload().unwrap();
+        assert_eq!(haptics_controller.get_clip_duration(), expected_duration);
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.5);
+        assert_eq!(
+            haptics_controller.pre_authored_clip_player.playback_state(),
+            clip_players::PlaybackState::Stopped
+        );
+        haptics_controller.play().unwrap();
+
+        // Test: Edits of the cached clip are passed to the player when reloading
+        let clip = haptics_controller.clip_mut().unwrap();
+        clip.scale_time(2.0).unwrap();
+        clip.metadata.gain = Some(0.5);
+        haptics_controller.reload().unwrap();
+        assert_near!(
+            haptics_controller.get_clip_duration(),
+            expected_duration * 2.0,
+            1e-5
+        );
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.25);
+        assert_eq!(
+            haptics_controller.clip().unwrap().duration(),
+            haptics_controller.get_clip_duration()
+        );
+
+        // Test: An invalid edit is rejected
+        haptics_controller
+            .clip_mut()
+            .unwrap()
+            .signals
+            .continuous
+            .envelopes
+            .amplitude
+            .clear();
+        assert_eq!(
+            haptics_controller.reload().unwrap_err().kind,
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    /// Tests sampling the amplitude and frequency of the loaded clip at a few known times
+    fn test_amplitude_and_frequency_at() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        assert_eq!(haptics_controller.amplitude_at(0.1), 0.0);
+        assert_eq!(haptics_controller.frequency_at(0.1), None);
+
+        haptics_controller.load(&clip).unwrap();
+        assert_near!(haptics_controller.amplitude_at(0.05), 0.25, 1e-6);
+        assert_near!(haptics_controller.amplitude_at(0.2), 0.2, 1e-6);
+        assert_near!(haptics_controller.amplitude_at(5.0), 0.5, 1e-6);
+        assert_near!(haptics_controller.frequency_at(0.015).unwrap(), 0.765, 1e-6);
+
+        // Times outside of the clip are clamped
+        assert_near!(haptics_controller.amplitude_at(-1.0), 0.2, 1e-6);
+        assert_near!(haptics_controller.amplitude_at(20.0), 0.5, 1e-6);
+        assert_near!(haptics_controller.frequency_at(0.0).unwrap(), 0.99, 1e-6);
+        assert_near!(haptics_controller.frequency_at(20.0).unwrap(), 0.9, 1e-6);
+    }
+
     #[test]
     ///Tests that old clips are unloaded
     fn test_unloading_on_invalid() {
@@ -161,12 +656,213 @@ mod tests {
 
         assert_eq!(
             haptics_controller.load(&invalid_clip).err(),
-            Some(Error::new("Unsupported version"))
+            Some(Error::with_kind(
+                ErrorKind::UnsupportedVersion,
+                "Unsupported version 2.0.0"
+            ))
         );
         assert_eq!(
             haptics_controller.play().err(),
-            Some(Error::new("Player play: no clip loaded"))
+            Some(Error::with_kind(
+                ErrorKind::NoClipLoaded,
+                "Player play: no clip loaded"
+            ))
+        );
+    }
+
+    #[test]
+    /// Tests that try_load() keeps the old clip loaded when the new clip is invalid
+    fn test_try_load_keeps_clip_on_invalid() {
+        let clip = load_test_file_valid_v1();
+        let invalid_clip = load_test_file_invalid_v1();
+        let expected_duration: f32 = 9.961_361;
+
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.try_load(&clip).unwrap();
+        haptics_controller.play().unwrap();
+
+        assert_eq!(
+            haptics_controller.try_load(&invalid_clip).err(),
+            Some(Error::with_kind(
+                ErrorKind::UnsupportedVersion,
+                "Unsupported version 2.0.0"
+            ))
+        );
+        haptics_controller.play().unwrap();
+        assert_near!(
+            expected_duration,
+            haptics_controller.get_clip_duration(),
+            f32::EPSILON
+        );
+    }
+
+    #[test]
+    /// Tests that the played events contain the product of amplitude multiplication and master
+    /// volume
+    fn test_played_events() {
+        let clip = load_test_file_valid_v1();
+        let (_, data) = datamodel::latest_from_json(&clip).unwrap();
+        let mut event_provider = clip_players::HapticEventProvider::new(data);
+        event_provider.set_amplitude_multiplication(0.25);
+        let expected_events = std::iter::from_fn(|| event_provider.get_next_event())
+            .collect::<Vec<clip_players::Event>>();
+
+        let player = null::RecordingPlayer::new().unwrap();
+        let recorded_events = player.recorded_events();
+        let mut haptics_controller = HapticsController::new(Box::new(player));
+        haptics_controller.set_master_volume(0.5).unwrap();
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller
+            .set_amplitude_multiplication(0.5)
+            .unwrap();
+        haptics_controller.play().unwrap();
+        assert_eq!(recorded_events.events(), expected_events);
+    }
+
+    #[test]
+    /// Tests that the gain stored in the metadata of a clip is applied when loading it, without
+    /// calling set_amplitude_multiplication()
+    fn test_clip_gain() {
+        let (_, mut data) = datamodel::latest_from_json(&load_test_file_valid_v1()).unwrap();
+        let mut event_provider = clip_players::HapticEventProvider::new(data.clone());
+        event_provider.set_amplitude_multiplication(0.5);
+        let expected_events = std::iter::from_fn(|| event_provider.get_next_event())
+            .collect::<Vec<clip_players::Event>>();
+
+        data.metadata.gain = Some(0.5);
+        let clip = datamodel::DataModel::V1(data).to_json().unwrap();
+        let player = null::RecordingPlayer::new().unwrap();
+        let recorded_events = player.recorded_events();
+        let mut haptics_controller = HapticsController::new(Box::new(player));
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.5);
+        haptics_controller.play().unwrap();
+        assert_eq!(recorded_events.events(), expected_events);
+
+        // Test: The gain is combined with the amplitude multiplication and kept across resets
+        haptics_controller
+            .set_amplitude_multiplication(1.5)
+            .unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.75);
+        haptics_controller.reset_runtime_params().unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 0.5);
+
+        // Test: Loading a clip without gain removes the gain again
+        haptics_controller.load(&load_test_file_valid_v1()).unwrap();
+        assert_eq!(haptics_controller.get_amplitude_multiplication(), 1.0);
+    }
+
+    #[test]
+    /// Tests that play_at() plays the events starting at the offset
+    fn test_play_at() {
+        let clip = load_test_file_valid_v1();
+        let (_, data) = datamodel::latest_from_json(&clip).unwrap();
+        let mut event_provider = clip_players::HapticEventProvider::new(data);
+        event_provider.seek(1.5);
+        let expected_events = std::iter::from_fn(|| event_provider.get_next_event())
+            .collect::<Vec<clip_players::Event>>();
+
+        let player = null::RecordingPlayer::new().unwrap();
+        let recorded_events = player.recorded_events();
+        let mut haptics_controller = HapticsController::new(Box::new(player));
+        assert_eq!(
+            haptics_controller.play_at(1.5).unwrap_err().kind,
+            ErrorKind::NoClipLoaded
+        );
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(
+            haptics_controller.play_at(f32::NAN).unwrap_err().kind,
+            ErrorKind::InvalidArgument
         );
+        haptics_controller.play_at(1.5).unwrap();
+        assert_eq!(recorded_events.events(), expected_events);
+    }
+
+    #[test]
+    /// Tests that features not supported by the player are reported
+    fn test_unsupported_features() {
+        let clip = load_test_file_valid_v1();
+        let (_, data) = datamodel::latest_from_json(&clip).unwrap();
+        assert_eq!(
+            data.required_features(),
+            vec![Feature::FrequencyModulation, Feature::Emphasis]
+        );
+
+        let haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        assert!(haptics_controller.unsupported_features(&data).is_empty());
+
+        let callbacks = clip_players::android::Callbacks::new(
+            |_: &[i64], _: &[i32], _: Option<usize>| Ok(()),
+            || Ok(()),
+            || Ok(()),
+            || Ok(()),
+            |_: &[i64], _: &[i32]| Ok(()),
+        );
+        let haptics_controller = HapticsController::new(Box::new(
+            clip_players::android::Player::new(callbacks).unwrap(),
+        ));
+        let unsupported_features = haptics_controller.unsupported_features(&data);
+        assert_eq!(
+            unsupported_features,
+            vec![UnsupportedFeature {
+                feature: Feature::FrequencyModulation,
+                player_name: "Android player",
+            }]
+        );
+        assert_eq!(
+            unsupported_features[0].to_string(),
+            "clip uses frequency modulation, Android player doesn't support it"
+        );
+    }
+
+    #[test]
+    /// Tests that emphasis is renderable with native transients or amplitude control, and
+    /// frequency modulation only with frequency control
+    fn test_renderable_features() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        let iphone = DeviceCapabilities {
+            amplitude_control: true,
+            frequency_control: true,
+            emphasis: true,
+        };
+        assert!(haptics_controller.renderable_features(&iphone).is_empty());
+
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(iphone.emphasis_rendering(), EmphasisRendering::Transients);
+        assert_eq!(
+            haptics_controller.renderable_features(&iphone),
+            vec![Feature::FrequencyModulation, Feature::Emphasis]
+        );
+
+        // Test: Without transient support, emphasis is emulated with the amplitude
+        let android = DeviceCapabilities {
+            amplitude_control: true,
+            frequency_control: false,
+            emphasis: false,
+        };
+        assert_eq!(
+            android.emphasis_rendering(),
+            EmphasisRendering::AmplitudeEmulation
+        );
+        assert_eq!(
+            haptics_controller.renderable_features(&android),
+            vec![Feature::Emphasis]
+        );
+
+        // Test: Without transients and amplitude control, emphasis can't be rendered
+        let on_off_motor = DeviceCapabilities {
+            amplitude_control: false,
+            frequency_control: false,
+            emphasis: false,
+        };
+        assert_eq!(
+            on_off_motor.emphasis_rendering(),
+            EmphasisRendering::Unsupported
+        );
+        assert!(haptics_controller
+            .renderable_features(&on_off_motor)
+            .is_empty());
     }
 
     #[test]
@@ -193,15 +889,90 @@ mod tests {
         );
     }
 
+    /// Tests that the clip duration reflects a time-scaled clip
+    #[test]
+    fn test_get_clip_duration_scaled() {
+        let (_, mut data) = datamodel::latest_from_json(&load_test_file_valid_v1()).unwrap();
+        data.scale_time(2.0).unwrap();
+        let scaled_clip = serde_json::to_string(&data).unwrap();
+
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.load(&scaled_clip).unwrap();
+        assert_near!(
+            2.0 * 9.961_361,
+            haptics_controller.get_clip_duration(),
+            f32::EPSILON * 2.0 * 9.961_361
+        );
+    }
+
+    /// Tests that the clip duration extends to the end of a frequency envelope that is longer
+    /// than the amplitude envelope
+    #[test]
+    fn test_get_clip_duration_frequency_longer() {
+        let (_, mut data) = datamodel::latest_from_json(&load_test_file_valid_v1()).unwrap();
+        data.signals
+            .continuous
+            .envelopes
+            .frequency
+            .as_mut()
+            .unwrap()
+            .push(datamodel::latest::FrequencyBreakpoint {
+                time: 12.5,
+                frequency: 0.5,
+            });
+        let clip = serde_json::to_string(&data).unwrap();
+
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.load(&clip).unwrap();
+        assert_near!(12.5, haptics_controller.get_clip_duration(), f32::EPSILON);
+    }
+
+    /// Tests the millisecond variants of seek() and get_clip_duration()
+    #[test]
+    fn test_milliseconds() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        assert_eq!(haptics_controller.get_clip_duration_ms(), 0);
+        haptics_controller.seek_ms(500).unwrap_err();
+
+        haptics_controller.load(&clip).unwrap();
+        assert_eq!(haptics_controller.get_clip_duration_ms(), 9961);
+        haptics_controller.seek_ms(500).unwrap();
+    }
+
+    /// Tests that negative seek times are accepted, and that non-finite ones are rejected
+    #[test]
+    fn test_seek() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        assert_eq!(
+            haptics_controller.seek(1.0).unwrap_err().kind,
+            ErrorKind::NoClipLoaded
+        );
+
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller.seek(-1.0).unwrap();
+        for time in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(
+                haptics_controller.seek(*time).unwrap_err().kind,
+                ErrorKind::InvalidArgument
+            );
+        }
+    }
+
     /// Tests the validity of various numbers passed to set_amplitude_multiplication()
     #[test]
     fn test_amplitude_multiplication() {
         let clip = load_test_file_valid_v1();
         let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
         haptics_controller.load(&clip).unwrap();
-        haptics_controller
-            .set_amplitude_multiplication(-2.3)
-            .unwrap_err();
+        assert_eq!(
+            haptics_controller
+                .set_amplitude_multiplication(-2.3)
+                .unwrap_err()
+                .kind,
+            ErrorKind::InvalidArgument
+        );
         haptics_controller
             .set_amplitude_multiplication(f32::NAN)
             .unwrap_err();
@@ -210,4 +981,82 @@ mod tests {
             .unwrap();
         haptics_controller.play().unwrap();
     }
+
+    /// Tests that invalid data results in an error of kind InvalidData
+    #[test]
+    fn test_invalid_data_error_kind() {
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        let error = haptics_controller.load("{ invalid json").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidData);
+        let error = haptics_controller.set_frequency_shift(2.0).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    }
+
+    /// Tests that fades can be set before and after loading a clip, and that invalid durations
+    /// are rejected
+    #[test]
+    fn test_fades() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.set_fade_in(0.5).unwrap();
+        haptics_controller.set_fade_in(-0.5).unwrap_err();
+        haptics_controller.set_fade_out(f32::NAN).unwrap_err();
+
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller.set_fade_out(100.0).unwrap();
+        haptics_controller.play().unwrap();
+        assert_near!(
+            9.961_361,
+            haptics_controller.get_clip_duration(),
+            f32::EPSILON
+        );
+
+        haptics_controller.set_fade_in(0.0).unwrap();
+        haptics_controller.set_fade_out(0.0).unwrap();
+        haptics_controller.play().unwrap();
+    }
+
+    /// Tests that changing a fade keeps the amplitude multiplication, frequency shift and loop
+    /// count, which are applied on top of the fade
+    #[test]
+    fn test_fade_keeps_runtime_params() {
+        let clip = load_test_file_valid_v1();
+        let (_, mut data) = datamodel::latest_from_json(&clip).unwrap();
+        data.apply_fade(0.5, 0.0).unwrap();
+        let mut event_provider = clip_players::HapticEventProvider::new(data);
+        event_provider.set_amplitude_multiplication(0.5);
+        event_provider.set_frequency_shift(0.1);
+        let mut expected_events = std::iter::from_fn(|| event_provider.get_next_event())
+            .collect::<Vec<clip_players::Event>>();
+        expected_events.extend(expected_events.clone());
+
+        let player = null::RecordingPlayer::new().unwrap();
+        let recorded_events = player.recorded_events();
+        let mut haptics_controller = HapticsController::new(Box::new(player));
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller
+            .set_amplitude_multiplication(0.5)
+            .unwrap();
+        haptics_controller.set_frequency_shift(0.1).unwrap();
+        haptics_controller.set_loop_count(2).unwrap();
+        haptics_controller.set_fade_in(0.5).unwrap();
+        haptics_controller.play().unwrap();
+        assert_eq!(recorded_events.events(), expected_events);
+    }
+
+    /// Tests that runtime parameters can only be reset while a clip is loaded
+    #[test]
+    fn test_reset_runtime_params() {
+        let clip = load_test_file_valid_v1();
+        let mut haptics_controller = HapticsController::new(Box::new(null::Player::new().unwrap()));
+        haptics_controller.reset_runtime_params().unwrap_err();
+
+        haptics_controller.load(&clip).unwrap();
+        haptics_controller
+            .set_amplitude_multiplication(0.5)
+            .unwrap();
+        haptics_controller.set_frequency_shift(0.5).unwrap();
+        haptics_controller.reset_runtime_params().unwrap();
+        haptics_controller.play().unwrap();
+    }
 }
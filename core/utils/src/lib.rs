@@ -1,28 +1,91 @@
 // Copyright (c) Meta Platforms, Inc. and affiliates.
 
-use std::fmt::Display;
+//! Utilities shared by the crates of the SDK.
+//!
+//! With the default `std` feature disabled, this crate only needs `alloc`. Functions that need
+//! `std`, like `seconds_to_ms()`, are not available then.
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+
+#[cfg(feature = "std")]
 pub mod test_utils;
 
+/// The reason an operation failed, so that callers can handle failures without parsing the
+/// error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Any failure that doesn't fit one of the other kinds
+    Other,
+    /// The haptic data couldn't be parsed or is invalid
+    InvalidData,
+    /// The version of the haptic data isn't supported
+    UnsupportedVersion,
+    /// The operation requires a loaded clip, but no clip is loaded
+    NoClipLoaded,
+    /// The player failed to carry out the operation
+    PlaybackFailed,
+    /// An argument passed to the operation is out of range
+    InvalidArgument,
+}
+
+impl Default for ErrorKind {
+    fn default() -> Self {
+        ErrorKind::Other
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Error {
     pub message: String,
+    pub kind: ErrorKind,
 }
 
 impl Error {
+    /// Creates an error of kind `ErrorKind::Other`
     pub fn new(message: &str) -> Error {
+        Error::with_kind(ErrorKind::Other, message)
+    }
+
+    pub fn with_kind(kind: ErrorKind, message: &str) -> Error {
         Error {
             message: message.to_string(),
+            kind,
         }
     }
 }
 
 impl Display for Error {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         formatter.write_str(&self.message)
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::new(message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error {
+            message,
+            kind: ErrorKind::Other,
+        }
+    }
+}
+
 #[cfg(target_os = "android")]
 impl From<jni::errors::Error> for Error {
     fn from(error: jni::errors::Error) -> Self {
@@ -48,6 +111,19 @@ pub fn interpolate(time_a: f32, time_b: f32, value_a: f32, value_b: f32, time: f
     value_a + value_diff * factor
 }
 
+/// Converts a time in seconds, the unit used by the data model, to milliseconds.
+///
+/// The result is rounded to the nearest millisecond.
+#[cfg(feature = "std")]
+pub fn seconds_to_ms(seconds: f32) -> i64 {
+    (seconds * 1000.0).round() as i64
+}
+
+/// Converts a time in milliseconds to seconds, the unit used by the data model.
+pub fn ms_to_seconds(ms: i64) -> f32 {
+    ms as f32 / 1000.0
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -57,4 +133,33 @@ mod tests {
         assert!((super::interpolate(0.5, 1.0, 2.0, 5.0, 0.75) - 3.5) <= f32::EPSILON);
         assert!((super::interpolate(0.5, 1.0, 2.0, 5.0, 1.0) - 5.0) <= f32::EPSILON);
     }
+
+    #[test]
+    // Test that Error can be used as a std::error::Error and created from strings
+    fn error_conversion() {
+        fn fails() -> Result<(), Box<dyn std::error::Error>> {
+            Err(super::Error::new("Failed"))?
+        }
+        let error = fails().unwrap_err();
+        assert_eq!(error.to_string(), "Failed");
+        assert!(error.source().is_none());
+
+        assert_eq!(super::Error::from("Failed"), super::Error::new("Failed"));
+        assert_eq!(
+            super::Error::from("Failed".to_string()),
+            super::Error::new("Failed")
+        );
+        assert_eq!(super::Error::new("Failed").kind, super::ErrorKind::Other);
+    }
+
+    #[test]
+    // Test conversion between seconds and milliseconds
+    fn time_unit_conversion() {
+        assert_eq!(super::seconds_to_ms(0.0), 0);
+        assert_eq!(super::seconds_to_ms(1.5), 1500);
+        assert_eq!(super::seconds_to_ms(0.0016), 2);
+        assert_eq!(super::seconds_to_ms(-0.25), -250);
+        assert!((super::ms_to_seconds(1500) - 1.5).abs() <= f32::EPSILON);
+        assert!((super::ms_to_seconds(-250) + 0.25).abs() <= f32::EPSILON);
+    }
 }
@@ -195,6 +195,34 @@ public class LofeltHapticsInstrumentedTest {
         assertEquals(exception.getMessage(), "Unable to play, no clip loaded.");
     }
 
+    // Tests that errors from setting the frequency shift are thrown as exceptions
+    @Test
+    public void setFrequencyShiftErrors() throws IOException {
+        // This is a device-only test that should not run on an emulator.
+        if (isEmulator()) {
+            return;
+        }
+
+        Context context = InstrumentationRegistry.getInstrumentation().getTargetContext();
+        LofeltHaptics lofeltHaptics = new LofeltHaptics(context);
+
+        RuntimeException exception = assertThrows(
+            RuntimeException.class,
+            () -> lofeltHaptics.setFrequencyShift(0.5f));
+        assertEquals(exception.getMessage(), "Unable to set frequency shift, no clip loaded.");
+
+        InputStream stream = context.getResources().openRawResource(R.raw.clip);
+        byte[] clip = IOUtils.toByteArray(stream);
+        lofeltHaptics.load(clip);
+
+        lofeltHaptics.setFrequencyShift(0.5f);
+        exception = assertThrows(
+            RuntimeException.class,
+            () -> lofeltHaptics.setFrequencyShift(1.5f));
+        assertEquals(exception.getMessage(),
+            "Unable to apply frequency shift 1.5, needs to be between -1 and 1");
+    }
+
     // Tests that stop() can be called when no clip is loaded
     @Test
     public void stopWithoutLoading() {
@@ -11,7 +11,13 @@ import android.util.Log;
 import androidx.annotation.ChecksSdkIntAtLeast;
 import androidx.annotation.RequiresApi;
 
+import java.util.ArrayList;
 import java.util.Arrays;
+import java.util.List;
+import java.util.concurrent.Executors;
+import java.util.concurrent.ScheduledExecutorService;
+import java.util.concurrent.ScheduledFuture;
+import java.util.concurrent.TimeUnit;
 import java.util.stream.LongStream;
 
 // Helper class used by LofeltHaptics
@@ -38,6 +44,15 @@ class Player {
     private VibrationEffect effect;
     private boolean clipLoaded = false;
     private boolean clipLoopingEnabled = false;
+    // Index of the entry from which the clip is repeated, or -1 if it isn't repeated
+    private int clipRepeatIndex = -1;
+    // The clip loaded with loadCompositionCallback(), as effects that are started at the given
+    // times. Empty if the clip was loaded with loadCallback().
+    private VibrationEffect[] compositionEffects = new VibrationEffect[0];
+    private long[] compositionStartTimesMs = new long[0];
+    // Starts the effects of the composition at their start times
+    private ScheduledExecutorService compositionScheduler;
+    private final List<ScheduledFuture<?>> scheduledCompositionEffects = new ArrayList<>();
     private final Context context;
 
     public Player(Context context) {
@@ -45,10 +60,12 @@ class Player {
     }
 
     @SuppressWarnings("SameParameterValue")
-    private void loadCallback(long[] timings, int[] amplitudes, boolean loopEnabled) {
+    private void loadCallback(long[] timings, int[] amplitudes, int repeatIndex) {
 
         clipLoaded = true;
-        clipLoopingEnabled = loopEnabled;
+        clipLoopingEnabled = repeatIndex >= 0;
+        clipRepeatIndex = repeatIndex;
+        clearComposition();
 
         if (timings.length == 0 || amplitudes.length == 0 ||
                 Arrays.stream(timings).allMatch(timing -> timing == 0) ||
@@ -71,10 +88,71 @@ class Player {
         }
     }
 
+    // Called from Rust in create(), to decide whether clips are loaded with
+    // loadCompositionCallback() instead of loadCallback()
+    @SuppressWarnings("unused")
+    private boolean supportsComposition() {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.R) {
+            return false;
+        }
+        Vibrator vibrator = (Vibrator) context.getSystemService(Context.VIBRATOR_SERVICE);
+        return vibrator != null &&
+                vibrator.areAllPrimitivesSupported(VibrationEffect.Composition.PRIMITIVE_CLICK);
+    }
+
+    // Loads a clip converted to click primitives and waveform parts. For each primitive,
+    // `startTimesMs`, `clickScales` and `entryCounts` contain its start time, its click scale and
+    // its number of waveform entries, which is 0 for clicks. `timings` and `amplitudes` contain
+    // the entries of all waveform parts, one part after the other.
+    @SuppressWarnings("unused")
+    @RequiresApi(api = Build.VERSION_CODES.R)
+    private void loadCompositionCallback(long[] startTimesMs, float[] clickScales, int[] entryCounts,
+                                         long[] timings, int[] amplitudes) {
+        clipLoaded = true;
+        clipLoopingEnabled = false;
+        clipRepeatIndex = -1;
+        effect = null;
+        vibrator = (Vibrator) context.getSystemService(Context.VIBRATOR_SERVICE);
+
+        List<VibrationEffect> effects = new ArrayList<>();
+        List<Long> effectStartTimesMs = new ArrayList<>();
+        int entryOffset = 0;
+        for (int i = 0; i < startTimesMs.length; i++) {
+            VibrationEffect primitiveEffect;
+            if (entryCounts[i] == 0) {
+                primitiveEffect = VibrationEffect.startComposition()
+                        .addPrimitive(VibrationEffect.Composition.PRIMITIVE_CLICK, clickScales[i])
+                        .compose();
+            } else {
+                int entryEnd = entryOffset + entryCounts[i];
+                long[] partTimings = Arrays.copyOfRange(timings, entryOffset, entryEnd);
+                int[] partAmplitudes = Arrays.copyOfRange(amplitudes, entryOffset, entryEnd);
+                entryOffset = entryEnd;
+
+                // VibrationEffect.createWaveform() throws for parts without any vibration, see
+                // loadCallback()
+                if (Arrays.stream(partTimings).allMatch(timing -> timing == 0) ||
+                        Arrays.stream(partAmplitudes).allMatch(amplitude -> amplitude == 0)) {
+                    continue;
+                }
+                primitiveEffect = VibrationEffect.createWaveform(partTimings, partAmplitudes, -1);
+            }
+            effects.add(primitiveEffect);
+            effectStartTimesMs.add(startTimesMs[i]);
+        }
+
+        clearComposition();
+        compositionEffects = effects.toArray(new VibrationEffect[0]);
+        compositionStartTimesMs = effectStartTimesMs.stream().mapToLong(Long::longValue).toArray();
+    }
+
     @SuppressWarnings("unused")
     private void playCallback() {
         if (!clipLoaded) {
             throw new RuntimeException("Unable to play, no clip loaded");
+        } else if (compositionEffects.length > 0) {
+            playComposition();
+            return;
         } else if (effect == null) {
             // The clip is loaded, but empty. This can for example happen when seeking beyond the
             // end of the clip or when using an amplitude multiplication of 0.0.
@@ -92,27 +170,64 @@ class Player {
     }
 
     private void stopCallback() {
+        cancelScheduledCompositionEffects();
         if (this.vibrator != null) {
             vibrator.cancel();
         }
     }
 
+    // Schedules every effect of the loaded composition to be started at its start time
+    private void playComposition() {
+        if (this.vibrator == null) {
+            throw new RuntimeException("Unable to play, Vibrator service unavailable");
+        }
+
+        stopCallback();
+        if (compositionScheduler == null) {
+            compositionScheduler = Executors.newSingleThreadScheduledExecutor();
+        }
+        final Vibrator compositionVibrator = vibrator;
+        for (int i = 0; i < compositionEffects.length; i++) {
+            final VibrationEffect compositionEffect = compositionEffects[i];
+            scheduledCompositionEffects.add(compositionScheduler.schedule(
+                    () -> compositionVibrator.vibrate(compositionEffect),
+                    compositionStartTimesMs[i], TimeUnit.MILLISECONDS));
+        }
+    }
+
+    // Cancels the effects of a playing composition that haven't been started yet
+    private void cancelScheduledCompositionEffects() {
+        for (ScheduledFuture<?> scheduledEffect : scheduledCompositionEffects) {
+            scheduledEffect.cancel(false);
+        }
+        scheduledCompositionEffects.clear();
+    }
+
+    // Forgets the loaded composition, including effects of it that haven't been started yet
+    private void clearComposition() {
+        cancelScheduledCompositionEffects();
+        compositionEffects = new VibrationEffect[0];
+        compositionStartTimesMs = new long[0];
+    }
+
     @SuppressWarnings("unused")
     private void unloadCallback() {
+        clearComposition();
         clipLoaded = false;
         clipLoopingEnabled = false;
+        clipRepeatIndex = -1;
         vibrator = null;
         effect = null;
     }
 
-    // Gets repeat value based on `clipLoopingEnabled` to be used on VibrationEffect.createWaveform()
+    // Gets repeat value based on `clipRepeatIndex` to be used on VibrationEffect.createWaveform()
     private int getRepeatValue() {
-        return clipLoopingEnabled ? 0 : -1;
+        return clipRepeatIndex;
     }
 
     @SuppressWarnings("unused")
     private void seekCallback(long[] timings, int[] amplitudes) {
-        loadCallback(timings, amplitudes, false);
+        loadCallback(timings, amplitudes, -1);
         stopCallback();
     }
 
@@ -206,6 +321,8 @@ public class LofeltHaptics {
 
     private native void setAmplitudeMultiplication(long controllerHandle, float amplitudeMultiplication);
 
+    private native void setFrequencyShift(long controllerHandle, float shift);
+
     private native void loop(long controllerHandle, boolean enable);
 
     private native float getClipDuration(long controllerHandle);
@@ -410,6 +527,26 @@ public class LofeltHaptics {
         }
     }
 
+    /**
+     * Shifts the frequency of the loaded clip.
+     * <p>
+     * The Vibrator API can't change the frequency of the motor, so the shift changes the length
+     * of the emphasis instead: a negative shift makes emphasis longer and heavier, a positive
+     * shift makes them shorter and crisper.
+     * <p>
+     * The clip needs to be loaded with {@link #load(byte[])} first. Loading a clip resets the
+     * frequency shift back to the default of 0.0.
+     *
+     * @param shift The frequency shift, between -1.0 and 1.0
+     * @throws RuntimeException if setting the frequency shift fails, for example if no clip is
+     *                          loaded or if the shift is outside of the valid range
+     */
+    public void setFrequencyShift(float shift) {
+        if (deviceMeetsMinimumRequirements()) {
+            setFrequencyShift(controllerHandle, shift);
+        }
+    }
+
     /**
      * Returns the duration of the loaded clip.
      *
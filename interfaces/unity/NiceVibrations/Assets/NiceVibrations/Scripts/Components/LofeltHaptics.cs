@@ -48,6 +48,7 @@ namespace Lofelt.NiceVibrations
         static IntPtr seekMethodId = IntPtr.Zero;
         static IntPtr loopMethodId = IntPtr.Zero;
         static IntPtr setAmplitudeMultiplicationMethodId = IntPtr.Zero;
+        static IntPtr setFrequencyShiftMethodId = IntPtr.Zero;
         static IntPtr playMaximumAmplitudePattern = IntPtr.Zero;
 
         [DllImport("lofelt_sdk")]
@@ -125,6 +126,7 @@ namespace Lofelt.NiceVibrations
                     seekMethodId = AndroidJNIHelper.GetMethodID(lofeltHaptics.GetRawClass(), "seek", "(F)V", false);
                     loopMethodId = AndroidJNIHelper.GetMethodID(lofeltHaptics.GetRawClass(), "loop", "(Z)V", false);
                     setAmplitudeMultiplicationMethodId = AndroidJNIHelper.GetMethodID(lofeltHaptics.GetRawClass(), "setAmplitudeMultiplication", "(F)V", false);
+                    setFrequencyShiftMethodId = AndroidJNIHelper.GetMethodID(lofeltHaptics.GetRawClass(), "setFrequencyShift", "(F)V", false);
                     playMaximumAmplitudePattern = AndroidJNIHelper.GetMethodID(hapticPatterns.GetRawClass(), "playMaximumAmplitudePattern", "([F)V", false);
                 }
             }
@@ -266,7 +268,9 @@ namespace Lofelt.NiceVibrations
 
         public static void SetFrequencyShift(float shift)
         {
-#if (UNITY_IOS && !UNITY_EDITOR)
+#if (UNITY_ANDROID && !UNITY_EDITOR)
+            JNIHelpers.Call(lofeltHaptics, setFrequencyShiftMethodId, shift);
+#elif (UNITY_IOS && !UNITY_EDITOR)
             lofeltHapticsSetFrequencyShiftBinding(controller, shift);
 #endif
         }
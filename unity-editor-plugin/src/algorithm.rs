@@ -4,11 +4,12 @@ use crate::GamepadRumble;
 use datamodel::{
     emphasis::emphasize,
     interpolation::{InterpolationParameters, Interpolator},
+    latest,
     v1::{AmplitudeBreakpoint, FrequencyBreakpoint},
     waveform::{Waveform, WaveformConversionParameters},
 };
 use std::cmp::Ordering;
-use utils::Error;
+use utils::{Error, ErrorKind};
 
 // Takes a list of frequency breakpoints and converts them to a list of amplitude breakpoints
 fn frequency_breakpoints_to_amplitude_breakpoints(
@@ -140,7 +141,7 @@ pub fn convert_haptic_to_gamepad_rumble_inner(data: &[u8]) -> Result<GamepadRumb
             err
         ))
     })?;
-    let (_, data) = datamodel::latest_from_json(data)
+    let data = datamodel::load_validated(data)
         .map_err(|err| Error::new(&format!("Failed to load haptic clip: {}", err)))?;
 
     // Step 2: Convert frequency envelope to list of amplitude breakpoints
@@ -249,6 +250,174 @@ fn waveforms_to_gamepad_rumble(
     Ok(gamepad_rumble)
 }
 
+// The crossover frequency used by GamepadRumble::from_data_model()
+pub const DEFAULT_CROSSOVER_FREQUENCY: f32 = 0.5;
+
+// Splits `amplitude` into the speeds of the low and high frequency motors, based on `frequency`.
+//
+// The amplitude is crossfaded linearly between the motors: At a frequency of 0.0, only the low
+// frequency motor is used, at 1.0 only the high frequency motor, and at `crossover_frequency`
+// both motors get half of the amplitude.
+fn split_motor_speeds(amplitude: f32, frequency: f32, crossover_frequency: f32) -> (f32, f32) {
+    let frequency = frequency.clamp(0.0, 1.0);
+    let high_frequency_share = if frequency <= crossover_frequency {
+        0.5 * frequency / crossover_frequency
+    } else {
+        1.0 - 0.5 * (1.0 - frequency) / (1.0 - crossover_frequency)
+    };
+    (
+        amplitude * (1.0 - high_frequency_share),
+        amplitude * high_frequency_share,
+    )
+}
+
+impl GamepadRumble {
+    // Converts a haptic clip to a GamepadRumble by sampling it at fixed intervals of `step_ms`.
+    //
+    // Unlike convert_haptic_to_gamepad_rumble_inner(), every entry of the result has a duration
+    // of `step_ms`, except for the last entry, which is shorter if the clip duration isn't a
+    // multiple of `step_ms`. The motor speeds of an entry are based on the amplitude at the start
+    // of the entry, with the emphasis rendered into the amplitude envelope.
+    //
+    // The amplitude is split between the motors with DEFAULT_CROSSOVER_FREQUENCY, see
+    // from_data_model_with_crossover().
+    pub fn from_data_model(clip: &latest::DataModel, step_ms: i32) -> Result<GamepadRumble, Error> {
+        Self::from_data_model_with_crossover(clip, step_ms, DEFAULT_CROSSOVER_FREQUENCY)
+    }
+
+    // Same as from_data_model(), but with a custom crossover frequency.
+    //
+    // If the clip has a frequency envelope, low frequencies move the amplitude towards the low
+    // frequency motor and high frequencies towards the high frequency motor, with both motors
+    // getting half of the amplitude at `crossover_frequency`. The crossover frequency is in the
+    // same normalized space as the frequency envelope, and needs to be greater than 0.0 and less
+    // than 1.0.
+    //
+    // Without a frequency envelope, both motors get the full amplitude.
+    pub fn from_data_model_with_crossover(
+        clip: &latest::DataModel,
+        step_ms: i32,
+        crossover_frequency: f32,
+    ) -> Result<GamepadRumble, Error> {
+        if !(crossover_frequency > 0.0 && crossover_frequency < 1.0) {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!(
+                    "Invalid crossover frequency {}, needs to be between 0 and 1",
+                    crossover_frequency
+                ),
+            ));
+        }
+        if step_ms <= 0 {
+            return Err(Error::with_kind(
+                ErrorKind::InvalidArgument,
+                &format!("Invalid step of {}ms, needs to be greater than 0", step_ms),
+            ));
+        }
+
+        let mut emphasized_clip = clip.clone();
+        emphasized_clip.signals.continuous.envelopes.amplitude = emphasize(
+            &clip.signals.continuous.envelopes.amplitude,
+            Default::default(),
+        );
+
+        let total_duration_ms = (clip.duration() * 1000.0).round() as i32;
+        let entry_count = ((total_duration_ms + step_ms - 1) / step_ms) as usize;
+        let mut gamepad_rumble = GamepadRumble {
+            durations_ms: Vec::with_capacity(entry_count),
+            low_frequency_motor_speeds: Vec::with_capacity(entry_count),
+            high_frequency_motor_speeds: Vec::with_capacity(entry_count),
+        };
+
+        let mut time_ms = 0;
+        while time_ms < total_duration_ms {
+            let duration_ms = step_ms.min(total_duration_ms - time_ms);
+            let time = time_ms as f32 / 1000.0;
+            let amplitude = emphasized_clip.amplitude_at(time).clamp(0.0, 1.0);
+            let (low_frequency_motor_speed, high_frequency_motor_speed) = match clip
+                .frequency_at(time)
+            {
+                Some(frequency) => split_motor_speeds(amplitude, frequency, crossover_frequency),
+                None => (amplitude, amplitude),
+            };
+            gamepad_rumble.durations_ms.push(duration_ms);
+            gamepad_rumble
+                .low_frequency_motor_speeds
+                .push(low_frequency_motor_speed);
+            gamepad_rumble
+                .high_frequency_motor_speeds
+                .push(high_frequency_motor_speed);
+            time_ms += duration_ms;
+        }
+        Ok(gamepad_rumble)
+    }
+
+    // Returns a copy of this GamepadRumble in which every entry has a duration of `step_ms`, so
+    // that a game loop polling at a fixed rate can index the entries by tick.
+    //
+    // The motor speeds of an entry are the average of the original motor speeds over the time
+    // span of the entry, weighted by how long each original speed lasts within the span.
+    //
+    // If the total duration isn't a multiple of `step_ms`, the last entry is shorter and only
+    // covers the remaining duration, so that the total duration stays the same. A `step_ms` of
+    // less than 1 is treated as 1.
+    pub fn resample(&self, step_ms: i32) -> GamepadRumble {
+        let step_ms = step_ms.max(1);
+        let total_duration_ms: i32 = self.durations_ms.iter().map(|&d| d.max(0)).sum();
+        let entry_count = ((total_duration_ms + step_ms - 1) / step_ms) as usize;
+        let mut result = GamepadRumble {
+            durations_ms: Vec::with_capacity(entry_count),
+            low_frequency_motor_speeds: Vec::with_capacity(entry_count),
+            high_frequency_motor_speeds: Vec::with_capacity(entry_count),
+        };
+
+        // Index and start time of the original entry the current step starts in
+        let mut index = 0;
+        let mut entry_start_ms = 0;
+
+        let mut step_start_ms = 0;
+        while step_start_ms < total_duration_ms {
+            let step_end_ms = (step_start_ms + step_ms).min(total_duration_ms);
+            let mut low_frequency_sum = 0.0;
+            let mut high_frequency_sum = 0.0;
+
+            // Sum up the speeds of all original entries overlapping the step
+            let mut overlap_index = index;
+            let mut overlap_start_ms = entry_start_ms;
+            while overlap_index < self.durations_ms.len() && overlap_start_ms < step_end_ms {
+                let overlap_end_ms = overlap_start_ms + self.durations_ms[overlap_index].max(0);
+                let overlap_ms =
+                    overlap_end_ms.min(step_end_ms) - overlap_start_ms.max(step_start_ms);
+                if overlap_ms > 0 {
+                    low_frequency_sum +=
+                        self.low_frequency_motor_speeds[overlap_index] * overlap_ms as f32;
+                    high_frequency_sum +=
+                        self.high_frequency_motor_speeds[overlap_index] * overlap_ms as f32;
+                }
+
+                // Entries ending within the step don't overlap with any later step
+                if overlap_end_ms <= step_end_ms {
+                    index = overlap_index + 1;
+                    entry_start_ms = overlap_end_ms;
+                }
+                overlap_index += 1;
+                overlap_start_ms = overlap_end_ms;
+            }
+
+            let duration_ms = step_end_ms - step_start_ms;
+            result.durations_ms.push(duration_ms);
+            result
+                .low_frequency_motor_speeds
+                .push(low_frequency_sum / duration_ms as f32);
+            result
+                .high_frequency_motor_speeds
+                .push(high_frequency_sum / duration_ms as f32);
+            step_start_ms = step_end_ms;
+        }
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -435,6 +604,147 @@ mod tests {
         );
     }
 
+    // Tests sampling a clip at fixed intervals
+    #[test]
+    fn from_data_model() {
+        let clip = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../core/datamodel/src/test_data/valid_v1.haptic"),
+        )
+        .unwrap();
+        let clip = datamodel::load_validated(&clip).unwrap();
+        let gamepad_rumble = GamepadRumble::from_data_model(&clip, 16).unwrap();
+
+        // The clip is 9961ms long, the last entry covers the remaining 9ms
+        let entry_count = gamepad_rumble.durations_ms.len();
+        assert_eq!(entry_count, 623);
+        assert_eq!(gamepad_rumble.low_frequency_motor_speeds.len(), entry_count);
+        assert_eq!(
+            gamepad_rumble.high_frequency_motor_speeds.len(),
+            entry_count
+        );
+        assert_eq!(gamepad_rumble.durations_ms.iter().sum::<i32>(), 9961);
+        assert!(gamepad_rumble.durations_ms[..entry_count - 1]
+            .iter()
+            .all(|&duration_ms| duration_ms == 16));
+        assert_eq!(gamepad_rumble.durations_ms[entry_count - 1], 9);
+
+        // The speeds of both motors add up to the amplitude. The first and last entries have
+        // the amplitude of the first and last breakpoint.
+        let amplitudes: Vec<f32> = gamepad_rumble
+            .low_frequency_motor_speeds
+            .iter()
+            .zip(&gamepad_rumble.high_frequency_motor_speeds)
+            .map(|(low, high)| rounded_f32(low + high, 3))
+            .collect();
+        assert_eq!(amplitudes[0], 0.2);
+        assert_eq!(amplitudes[entry_count - 1], 0.5);
+
+        // The emphasis at 200ms is rendered with the full amplitude
+        assert!(amplitudes.contains(&1.0));
+
+        GamepadRumble::from_data_model(&clip, 0).unwrap_err();
+    }
+
+    // Tests that the amplitude moves from the low to the high frequency motor when the frequency
+    // sweeps from 0 to 1
+    #[test]
+    fn from_data_model_crossover() {
+        let clip = datamodel::v1::DataModelBuilder::new()
+            .amplitude_breakpoint(0.0, 1.0)
+            .frequency_breakpoint(0.0, 0.0)
+            .amplitude_breakpoint(1.0, 1.0)
+            .frequency_breakpoint(1.0, 1.0)
+            .build()
+            .unwrap();
+
+        let gamepad_rumble =
+            rounded_gamepad_rumble(&GamepadRumble::from_data_model(&clip, 250).unwrap());
+        assert_eq!(gamepad_rumble.durations_ms, vec![250, 250, 250, 250]);
+        assert_eq!(
+            gamepad_rumble.low_frequency_motor_speeds,
+            vec![1.0, 0.75, 0.5, 0.25]
+        );
+        assert_eq!(
+            gamepad_rumble.high_frequency_motor_speeds,
+            vec![0.0, 0.25, 0.5, 0.75]
+        );
+
+        // With a lower crossover frequency, the balance shifts towards the high frequency motor
+        let gamepad_rumble = rounded_gamepad_rumble(
+            &GamepadRumble::from_data_model_with_crossover(&clip, 250, 0.25).unwrap(),
+        );
+        assert_eq!(
+            gamepad_rumble.low_frequency_motor_speeds,
+            vec![1.0, 0.5, 0.333, 0.167]
+        );
+        assert_eq!(
+            gamepad_rumble.high_frequency_motor_speeds,
+            vec![0.0, 0.5, 0.667, 0.833]
+        );
+
+        for crossover_frequency in &[0.0, 1.0, f32::NAN] {
+            GamepadRumble::from_data_model_with_crossover(&clip, 250, *crossover_frequency)
+                .unwrap_err();
+        }
+    }
+
+    // Tests resampling a GamepadRumble with variable durations to fixed steps
+    #[test]
+    fn resample() {
+        let gamepad_rumble = GamepadRumble {
+            durations_ms: vec![10, 30, 20, 45],
+            low_frequency_motor_speeds: vec![1.0, 0.5, 0.0, 0.2],
+            high_frequency_motor_speeds: vec![0.0, 0.2, 1.0, 0.4],
+        };
+        let resampled = gamepad_rumble.resample(16);
+
+        // The total duration of 105ms is preserved, with a shorter last step of 9ms
+        assert_eq!(resampled.durations_ms, vec![16, 16, 16, 16, 16, 16, 9]);
+        assert_eq!(
+            resampled.durations_ms.iter().sum::<i32>(),
+            gamepad_rumble.durations_ms.iter().sum::<i32>()
+        );
+
+        // Steps spanning multiple entries get the weighted average of their speeds
+        let expected = GamepadRumble {
+            durations_ms: vec![16, 16, 16, 16, 16, 16, 9],
+            low_frequency_motor_speeds: vec![
+                (10.0 + 6.0 * 0.5) / 16.0,
+                0.5,
+                (8.0 * 0.5) / 16.0,
+                (4.0 * 0.2) / 16.0,
+                0.2,
+                0.2,
+                0.2,
+            ],
+            high_frequency_motor_speeds: vec![
+                (6.0 * 0.2) / 16.0,
+                0.2,
+                (8.0 * 0.2 + 8.0) / 16.0,
+                (12.0 + 4.0 * 0.4) / 16.0,
+                0.4,
+                0.4,
+                0.4,
+            ],
+        };
+        assert_eq!(
+            rounded_gamepad_rumble(&resampled),
+            rounded_gamepad_rumble(&expected)
+        );
+
+        // Resampling a real clip preserves the total duration
+        let gamepad_rumble = load_from_test_data("car.vij");
+        let resampled = gamepad_rumble.resample(16);
+        assert_eq!(
+            resampled.durations_ms.iter().sum::<i32>(),
+            gamepad_rumble.durations_ms.iter().sum::<i32>()
+        );
+        assert!(resampled.durations_ms[..resampled.durations_ms.len() - 1]
+            .iter()
+            .all(|&duration_ms| duration_ms == 16));
+    }
+
     // Test that loading a complex and big haptic file doesn't panic.
     // This test doesn't compare the gamepad_rumble, as the data is too big
     // to write an `expected_gamepad_rumble` here.